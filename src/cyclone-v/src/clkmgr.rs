@@ -0,0 +1,199 @@
+//! The Clock Manager of the HPS, which configures the main, peripheral and
+//! SDRAM PLLs and the clocks derived from them.
+//! See https://www.intel.com/content/www/us/en/programmable/hps/cyclone-v/sfo1410067808053.html
+use memoffset::offset_of;
+use static_assertions::const_assert_eq;
+
+pub mod pll;
+
+/// The frequency of the `eosc1_clk` oscillator on the DE10-Nano, in Hz.
+pub const OSC1_FREQUENCY: u64 = 25_000_000;
+
+crate::declare_volatile_struct! {
+    /// Main PLL Group. The main PLL always uses `eosc1_clk` as reference.
+    #[repr(C)]
+    pub struct MainPllGroup {
+        /// Main PLL VCO Control Register
+        vco: pll::PllVco,
+        /// Main PLL VCO Advanced Control Register
+        misc: u32,
+        /// Main PLL C0 Control Register, for `mpu_clk`
+        mpuclk: pll::PllCounter,
+        /// Main PLL C1 Control Register, for `main_clk`
+        mainclk: pll::PllCounter,
+        /// Main PLL C2 Control Register, for `dbg_base_clk`
+        dbgatclk: pll::PllCounter,
+        /// Main PLL C3 Control Register, for `main_qspi_clk`
+        mainqspiclk: pll::PllCounter,
+        /// Main PLL C4 Control Register, for `main_nand_sdmmc_clk`
+        mainnandsdmmcclk: pll::PllCounter,
+        /// Main PLL C5 Control Register, for `cfg_s2f_user0_clk`
+        cfgs2fuser0clk: pll::PllCounter,
+        /// Enable Register
+        en: u32,
+        /// Main Divide Register
+        maindiv: u32,
+        /// Debug Divide Register
+        dbgdiv: u32,
+        /// Debug Trace Divide Register
+        tracediv: u32,
+        /// L4 MP SP APB Clock Source
+        l4src: u32,
+        /// Main PLL Output Counter Reset Ack Status Register
+        [readonly] stat: u32,
+        [padding] _pad_0x38_0x40: [u32; 2],
+    }
+}
+
+crate::declare_volatile_struct! {
+    /// Peripheral PLL Group.
+    #[repr(C)]
+    pub struct PeripheralPllGroup {
+        /// Peripheral PLL VCO Control Register
+        vco: pll::PllVco,
+        /// Peripheral PLL VCO Advanced Control Register
+        misc: u32,
+        /// Peripheral PLL C0 Control Register, for `emac0_clk`
+        emac0clk: pll::PllCounter,
+        /// Peripheral PLL C1 Control Register, for `emac1_clk`
+        emac1clk: pll::PllCounter,
+        /// Peripheral PLL C2 Control Register, for `periph_qspi_clk`
+        perqspiclk: pll::PllCounter,
+        /// Peripheral PLL C3 Control Register, for `periph_nand_sdmmc_clk`
+        pernandsdmmcclk: pll::PllCounter,
+        /// Peripheral PLL C4 Control Register, for `periph_base_clk`
+        perbaseclk: pll::PllCounter,
+        /// Peripheral PLL C5 Control Register, for `h2f_user1_clk`
+        s2fuser1clk: pll::PllCounter,
+        /// Enable Register
+        en: u32,
+        /// Clock Divider Register
+        div: u32,
+        /// GPIO Divider Register
+        gpiodiv: u32,
+        /// Flash Clock Source Register
+        src: u32,
+        /// Peripheral PLL Output Counter Reset Ack Status Register
+        [readonly] stat: u32,
+        [padding] _pad_0x34_0x40: [u32; 3],
+    }
+}
+
+crate::declare_volatile_struct! {
+    /// SDRAM PLL Group.
+    #[repr(C)]
+    pub struct SdramPllGroup {
+        /// SDRAM PLL VCO Control Register
+        vco: pll::PllVco,
+        /// SDRAM PLL Control Register
+        ctrl: u32,
+        /// SDRAM PLL C0 Control Register, for `ddr_dqs_clk`
+        ddrdqsclk: pll::PllCounter,
+        /// SDRAM PLL C1 Control Register, for `ddr_2x_dqs_clk`
+        ddr2xdqsclk: pll::PllCounter,
+        /// SDRAM PLL C2 Control Register, for `ddr_dq_clk`
+        ddrdqclk: pll::PllCounter,
+        /// SDRAM PLL C5 Control Register, for `h2f_user2_clk`
+        s2fuser2clk: pll::PllCounter,
+        /// Enable Register
+        en: u32,
+        /// SDRAM PLL Output Counter Reset Ack Status Register
+        [readonly] stat: u32,
+    }
+}
+
+crate::declare_volatile_struct! {
+    /// Registers in the Clock Manager module.
+    #[repr(C)]
+    pub struct ClockManager {
+        /// Control Register
+        ctrl: u32,
+        /// Bypass Register
+        bypass: pll::PllBypass,
+        /// Interrupt Status Register
+        inter: pll::PllInterrupts,
+        /// Interrupt Enable Register
+        intren: u32,
+        /// Debug Clock Control Register
+        dbctrl: u32,
+        /// Status Register
+        [readonly] stat: u32,
+        [padding] _pad_0x18_0x40: [u32; 10],
+
+        /// Main PLL Group
+        [nested] mainpllgrp: MainPllGroup,
+        /// Peripheral PLL Group
+        [nested] perpllgrp: PeripheralPllGroup,
+        /// SDRAM PLL Group
+        [nested] sdrpllgrp: SdramPllGroup,
+    }
+}
+
+const_assert_eq!(core::mem::size_of::<MainPllGroup>(), 0x40);
+const_assert_eq!(core::mem::size_of::<PeripheralPllGroup>(), 0x40);
+const_assert_eq!(core::mem::size_of::<SdramPllGroup>(), 0x20);
+const_assert_eq!(offset_of!(ClockManager, mainpllgrp), 0x40);
+const_assert_eq!(offset_of!(ClockManager, perpllgrp), 0x80);
+const_assert_eq!(offset_of!(ClockManager, sdrpllgrp), 0xC0);
+const_assert_eq!(core::mem::size_of::<ClockManager>(), 0xE0);
+
+impl ClockManager {
+    /// Whether the Clock Manager is busy updating the PLLs or counters.
+    pub fn is_busy(&self) -> bool {
+        self.stat() & 1 != 0
+    }
+
+    /// The frequency of the main PLL VCO, in Hz.
+    pub fn main_vco_frequency(&self, osc1_hz: u64) -> u64 {
+        self.mainpllgrp().vco().frequency(osc1_hz)
+    }
+
+    /// The frequency of the MPU clock, in Hz.
+    pub fn mpu_frequency(&self, osc1_hz: u64) -> u64 {
+        if self.bypass().mainpll() {
+            return osc1_hz;
+        }
+        let vco = self.main_vco_frequency(osc1_hz);
+        self.mainpllgrp().mpuclk().frequency(vco)
+    }
+
+    /// The frequency of the SDRAM DQS clock, i.e. the memory clock, in Hz.
+    /// Only valid when the SDRAM PLL uses `eosc1_clk` as reference.
+    pub fn sdram_frequency(&self, osc1_hz: u64) -> u64 {
+        if self.bypass().sdrpll() {
+            return osc1_hz;
+        }
+        let vco = self.sdrpllgrp().vco().frequency(osc1_hz);
+        self.sdrpllgrp().ddrdqsclk().frequency(vco)
+    }
+}
+
+#[test]
+fn pll_frequencies() {
+    let mut soc = crate::SocFpga::create_for_test();
+    let clkmgr = soc.clkmgr_mut();
+
+    // 1600 MHz main VCO, 800 MHz MPU.
+    clkmgr.mainpllgrp_mut().set_vco(pll::PllVco(0x0000_01FB));
+    clkmgr.mainpllgrp_mut().update_mpuclk(|c| c.set_cnt(1));
+    // 800 MHz SDRAM VCO, 400 MHz DQS.
+    clkmgr.sdrpllgrp_mut().update_vco(|vco| {
+        vco.set_numer(31);
+        vco.set_en(true);
+    });
+    clkmgr.sdrpllgrp_mut().update_ddrdqsclk(|c| c.set_cnt(1));
+
+    let clkmgr = soc.clkmgr();
+    assert!(!clkmgr.is_busy());
+    assert_eq!(clkmgr.main_vco_frequency(OSC1_FREQUENCY), 1_600_000_000);
+    assert_eq!(clkmgr.mpu_frequency(OSC1_FREQUENCY), 800_000_000);
+    assert_eq!(clkmgr.sdram_frequency(OSC1_FREQUENCY), 400_000_000);
+
+    // The register is at the right address.
+    let offset = crate::ranges::CLKMGR.start - crate::ranges::BASE.start;
+    let vco = unsafe { *(soc.base_ptr().add(offset + 0x40) as *const u32) };
+    assert_eq!(vco, 0x0000_01FB);
+
+    soc.clkmgr_mut().update_bypass(|b| b.set_mainpll(true));
+    assert_eq!(soc.clkmgr().mpu_frequency(OSC1_FREQUENCY), OSC1_FREQUENCY);
+}
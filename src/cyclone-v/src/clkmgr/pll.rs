@@ -0,0 +1,183 @@
+use bitfield::bitfield;
+
+/// The reference clock of the peripheral and SDRAM PLLs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PllSource {
+    /// The `eosc1_clk` oscillator.
+    Osc1 = 0,
+
+    /// The `eosc2_clk` oscillator.
+    Osc2 = 1,
+
+    /// The `f2s_periph_ref_clk` (peripheral PLL) or `f2s_sdram_ref_clk`
+    /// (SDRAM PLL) clock from the FPGA.
+    Fpga = 2,
+
+    /// Reserved.
+    Reserved = 3,
+}
+
+impl From<u32> for PllSource {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => PllSource::Osc1,
+            1 => PllSource::Osc2,
+            2 => PllSource::Fpga,
+            _ => PllSource::Reserved,
+        }
+    }
+}
+
+impl From<PllSource> for u32 {
+    fn from(value: PllSource) -> Self {
+        value as Self
+    }
+}
+
+bitfield! {
+    /// PLL VCO Control Register. The VCO frequency is
+    /// `reference * (numer + 1) / (denom + 1)`.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct PllVco(u32);
+    impl Debug;
+    u32;
+
+    /// Whether the VCO registers are driven by the external interface. Must be 0.
+    pub regextsel, set_regextsel: 31;
+
+    /// Reset the phase of the output counters individually, one bit per counter.
+    pub outreset, set_outreset: 30, 25;
+
+    /// Reset the phase of all output counters.
+    pub outresetall, set_outresetall: 24;
+
+    /// The reference clock of the PLL. Unused for the main PLL, which always
+    /// uses `eosc1_clk`.
+    pub from into PllSource, src, set_src: 23, 22;
+
+    /// Denominator of the VCO frequency, minus 1.
+    pub denom, set_denom: 21, 16;
+
+    /// Numerator of the VCO frequency, minus 1.
+    pub numer, set_numer: 15, 3;
+
+    /// Power down the PLL.
+    pub pwrdn, set_pwrdn: 2;
+
+    /// Enable the PLL.
+    pub en, set_en: 1;
+
+    /// Power down the analog circuitry of the PLL.
+    pub bgpwrdn, set_bgpwrdn: 0;
+}
+
+impl PllVco {
+    /// The VCO frequency, in Hz, for a reference clock of `reference_hz`.
+    pub fn frequency(&self, reference_hz: u64) -> u64 {
+        reference_hz * (self.numer() as u64 + 1) / (self.denom() as u64 + 1)
+    }
+}
+
+bitfield! {
+    /// PLL Output Counter Register. Divides the VCO frequency by `cnt + 1`.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct PllCounter(u32);
+    impl Debug;
+    u32;
+
+    /// Phase shift of the output, in units of 1/8 of the VCO period. Only
+    /// used by the SDRAM PLL.
+    pub phase, set_phase: 20, 9;
+
+    /// Divider of the VCO frequency, minus 1.
+    pub cnt, set_cnt: 8, 0;
+}
+
+impl PllCounter {
+    /// The output frequency, in Hz, for a VCO frequency of `vco_hz`.
+    pub fn frequency(&self, vco_hz: u64) -> u64 {
+        vco_hz / (self.cnt() as u64 + 1)
+    }
+}
+
+bitfield! {
+    /// Bypass Register. Each PLL can be bypassed to its reference clock, e.g.
+    /// while it is being reconfigured.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct PllBypass(u32);
+    impl Debug;
+    u32;
+
+    /// The source of the peripheral PLL when bypassed, 0 for its reference
+    /// clock and 1 for `eosc1_clk`.
+    pub perpllsrc, set_perpllsrc: 4;
+
+    /// Bypass the peripheral PLL.
+    pub perpll, set_perpll: 3;
+
+    /// The source of the SDRAM PLL when bypassed, 0 for its reference clock
+    /// and 1 for `eosc1_clk`.
+    pub sdrpllsrc, set_sdrpllsrc: 2;
+
+    /// Bypass the SDRAM PLL.
+    pub sdrpll, set_sdrpll: 1;
+
+    /// Bypass the main PLL.
+    pub mainpll, set_mainpll: 0;
+}
+
+bitfield! {
+    /// Interrupt Status Register. Holds the lock status of the PLLs.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct PllInterrupts(u32);
+    impl Debug;
+    u32;
+
+    /// Whether the SDRAM PLL is locked.
+    pub sdrpllocked, _: 8;
+
+    /// Whether the peripheral PLL is locked.
+    pub perpllocked, _: 7;
+
+    /// Whether the main PLL is locked.
+    pub mainpllocked, _: 6;
+
+    /// Set when the SDRAM PLL lost its lock. Write 1 to clear.
+    pub sdrplllost, set_sdrplllost: 5;
+
+    /// Set when the peripheral PLL lost its lock. Write 1 to clear.
+    pub perplllost, set_perplllost: 4;
+
+    /// Set when the main PLL lost its lock. Write 1 to clear.
+    pub mainplllost, set_mainplllost: 3;
+
+    /// Set when the SDRAM PLL achieved its lock. Write 1 to clear.
+    pub sdrpllachieved, set_sdrpllachieved: 2;
+
+    /// Set when the peripheral PLL achieved its lock. Write 1 to clear.
+    pub perpllachieved, set_perpllachieved: 1;
+
+    /// Set when the main PLL achieved its lock. Write 1 to clear.
+    pub mainpllachieved, set_mainpllachieved: 0;
+}
+
+#[test]
+fn vco_frequency() {
+    // The main PLL of the DE10-Nano, at 1600 MHz from the 25 MHz oscillator.
+    let vco = PllVco(0x0000_01FB);
+    assert_eq!(vco.numer(), 63);
+    assert_eq!(vco.denom(), 0);
+    assert!(vco.en());
+    assert!(vco.bgpwrdn());
+    assert_eq!(vco.frequency(25_000_000), 1_600_000_000);
+
+    let mut vco = PllVco(0);
+    vco.set_numer(79);
+    vco.set_denom(1);
+    vco.set_src(PllSource::Osc1);
+    assert_eq!(vco.frequency(25_000_000), 1_000_000_000);
+
+    let mut counter = PllCounter(0);
+    counter.set_cnt(1);
+    assert_eq!(counter.frequency(1_600_000_000), 800_000_000);
+}
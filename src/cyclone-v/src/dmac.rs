@@ -0,0 +1,277 @@
+//! The DMA Controller of the HPS, an ARM CoreLink DMA-330 (PL330).
+//! See https://www.intel.com/content/www/us/en/programmable/hps/cyclone-v/sfo1410067808053.html
+//! and the ARM DDI 0424 technical reference manual for the programming model.
+use crate::memory::MemoryMapper;
+use core::fmt;
+use memoffset::offset_of;
+use static_assertions::const_assert_eq;
+
+pub mod ccr;
+pub mod csr;
+pub mod program;
+
+/// The number of channel threads of the DMA controller.
+pub const DMA_CHANNELS: u8 = 8;
+
+/// The bit of the DMA controller in the `permodrst` register of the Reset Manager.
+pub const PERMODRST_DMA: u32 = 1 << 28;
+
+/// Errors when programming the DMA controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaError {
+    /// The channel number is out of range.
+    InvalidChannel(u8),
+
+    /// The channel is already running a program, or the debug interface of
+    /// the controller is busy.
+    Busy,
+
+    /// The addresses are not aligned, or the length is too large for a program.
+    InvalidTransfer,
+
+    /// The program does not fit in its buffer.
+    ProgramTooLarge,
+
+    /// The channel stopped on a fault. Contains its Fault Type Register.
+    Fault(u32),
+
+    /// The transfer did not complete in time.
+    Timeout,
+}
+
+impl fmt::Display for DmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DmaError::InvalidChannel(c) => write!(f, "Invalid DMA channel {c}"),
+            DmaError::Busy => f.write_str("DMA channel busy"),
+            DmaError::InvalidTransfer => f.write_str("Invalid DMA transfer"),
+            DmaError::ProgramTooLarge => f.write_str("DMA program too large"),
+            DmaError::Fault(ftr) => write!(f, "DMA channel fault (FTR = {ftr:#010X})"),
+            DmaError::Timeout => f.write_str("DMA transfer timed out"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DmaError {}
+
+crate::declare_volatile_struct! {
+    /// The registers of a DMA channel thread.
+    #[repr(C)]
+    pub struct ChannelThreadRegs {
+        /// Source Address Register
+        [readonly] sar: u32,
+        /// Destination Address Register
+        [readonly] dar: u32,
+        /// Channel Control Register
+        [readonly] ccr: ccr::ChannelControl,
+        /// Loop Counter 0 Register
+        [readonly] lc0: u32,
+        /// Loop Counter 1 Register
+        [readonly] lc1: u32,
+        [padding] _pad_0x14_0x20: [u32; 3],
+    }
+}
+
+crate::declare_volatile_struct! {
+    /// The registers of the DMA controller (non-secure interface).
+    #[repr(C)]
+    pub struct DmaController {
+        /// DMA Manager Status Register
+        [readonly] dsr: u32,
+        /// DMA Program Counter Register
+        [readonly] dpc: u32,
+        [padding] _pad_0x8_0x20: [u32; 6],
+
+        /// Interrupt Enable Register
+        inten: u32,
+        /// Event-Interrupt Raw Status Register
+        [readonly] int_event_ris: u32,
+        /// Interrupt Status Register
+        [readonly] intmis: u32,
+        /// Interrupt Clear Register
+        [writeonly] intclr: u32,
+        /// Fault Status DMA Manager Register
+        [readonly] fsrd: u32,
+        /// Fault Status DMA Channel Register
+        [readonly] fsrc: u32,
+        /// Fault Type DMA Manager Register
+        [readonly] ftrd: u32,
+        [padding] _pad_0x3c_0x40: u32,
+
+        /// Fault Type DMA Channel Registers
+        [readonly] ftr: [u32; 8],
+        [padding] _pad_0x60_0x100: [u32; 40],
+
+        /// Channel Status and Program Counter Registers
+        [nested] channel_status: [csr::ChannelStatusRegs; 8],
+        [padding] _pad_0x140_0x400: [u32; 176],
+
+        /// Channel Thread Registers
+        [nested] channel_threads: [ChannelThreadRegs; 8],
+        [padding] _pad_0x500_0xd00: [u32; 512],
+
+        /// Debug Status Register
+        [readonly] dbgstatus: u32,
+        /// Debug Command Register
+        [writeonly] dbgcmd: u32,
+        /// Debug Instruction-0 Register
+        [writeonly] dbginst0: u32,
+        /// Debug Instruction-1 Register
+        [writeonly] dbginst1: u32,
+        [padding] _pad_0xd10_0xe00: [u32; 60],
+
+        /// Configuration Registers
+        [readonly] cr0: u32,
+        [readonly] cr1: u32,
+        [readonly] cr2: u32,
+        [readonly] cr3: u32,
+        [readonly] cr4: u32,
+        /// DMA Configuration Register
+        [readonly] crd: u32,
+        [padding] _pad_0xe18_0xe80: [u32; 26],
+
+        /// Watchdog Register
+        wd: u32,
+        [padding] _pad_0xe84_0xfe0: [u32; 87],
+
+        /// Peripheral Identification Registers
+        [readonly] periph_id: [u32; 4],
+        /// Component Identification Registers
+        [readonly] pcell_id: [u32; 4],
+    }
+}
+
+const_assert_eq!(core::mem::size_of::<ChannelThreadRegs>(), 0x20);
+const_assert_eq!(offset_of!(DmaController, ftr), 0x40);
+const_assert_eq!(offset_of!(DmaController, channel_status), 0x100);
+const_assert_eq!(offset_of!(DmaController, channel_threads), 0x400);
+const_assert_eq!(offset_of!(DmaController, dbgstatus), 0xD00);
+const_assert_eq!(offset_of!(DmaController, cr0), 0xE00);
+const_assert_eq!(offset_of!(DmaController, wd), 0xE80);
+const_assert_eq!(offset_of!(DmaController, periph_id), 0xFE0);
+const_assert_eq!(core::mem::size_of::<DmaController>(), 0x1000);
+
+impl DmaController {
+    /// Whether the registers look like a PL330, from its peripheral ID.
+    pub fn is_present(&self) -> bool {
+        let id = self.periph_id();
+        (id[0] & 0xFF) == 0x30 && (id[1] & 0x0F) == 0x03
+    }
+
+    fn check_channel(channel: u8) -> Result<usize, DmaError> {
+        if channel < DMA_CHANNELS {
+            Ok(channel as usize)
+        } else {
+            Err(DmaError::InvalidChannel(channel))
+        }
+    }
+
+    /// The status of a channel thread.
+    pub fn channel_state(&self, channel: u8) -> Result<csr::ChannelState, DmaError> {
+        let channel = Self::check_channel(channel)?;
+        Ok(self.channel_status()[channel].csr().state())
+    }
+
+    /// Execute an instruction on the DMA manager thread (`channel` is `None`)
+    /// or a channel thread, through the debug interface.
+    fn execute(&mut self, channel: Option<u8>, instruction: [u8; 6]) -> Result<(), DmaError> {
+        if self.dbgstatus() & 1 != 0 {
+            return Err(DmaError::Busy);
+        }
+
+        let [i0, i1, i2, i3, i4, i5] = instruction;
+        let thread = match channel {
+            Some(channel) => ((Self::check_channel(channel)? as u32) << 8) | 1,
+            None => 0,
+        };
+        self.set_dbginst0(((i1 as u32) << 24) | ((i0 as u32) << 16) | thread);
+        self.set_dbginst1(u32::from_le_bytes([i2, i3, i4, i5]));
+        self.set_dbgcmd(0);
+        Ok(())
+    }
+
+    /// Start a program on a channel, with `DMAGO`. The program must already be
+    /// at `program_address`, a physical address the DMA controller can read.
+    pub fn start(&mut self, channel: u8, program_address: u32) -> Result<(), DmaError> {
+        if self.channel_state(channel)? != csr::ChannelState::Stopped {
+            return Err(DmaError::Busy);
+        }
+
+        // Make sure the program is written before the controller fetches it.
+        core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+
+        let [a, b, c, d] = program_address.to_le_bytes();
+        // DMAGO, in the non-secure state.
+        self.execute(None, [0xA2, channel, a, b, c, d])
+    }
+
+    /// Stop a channel, with `DMAKILL`.
+    pub fn kill(&mut self, channel: u8) -> Result<(), DmaError> {
+        self.execute(Some(channel), [0x01, 0, 0, 0, 0, 0])
+    }
+
+    /// Check whether a channel is done. Returns an error if the channel stopped
+    /// on a fault.
+    pub fn poll(&self, channel: u8) -> Result<bool, DmaError> {
+        let state = self.channel_state(channel)?;
+        if state.is_faulting() || self.fsrc() & (1u32 << channel) != 0 {
+            return Err(DmaError::Fault(self.ftr()[channel as usize]));
+        }
+        Ok(state == csr::ChannelState::Stopped)
+    }
+}
+
+impl<M: MemoryMapper> crate::SocFpga<M> {
+    /// Take the DMA controller out of reset.
+    pub fn dma_init(&mut self) {
+        self.rstmgr_mut()
+            .update_permodrst(|permodrst| *permodrst &= !PERMODRST_DMA);
+    }
+
+    /// Start copying `len` bytes between two physical addresses on a channel.
+    /// The program is written at the start of on-chip RAM, in a slot of
+    /// [`program::DMA_PROGRAM_SIZE`] bytes per channel. Use [`DmaController::poll`]
+    /// to wait for the copy to complete.
+    pub fn dma_copy(
+        &mut self,
+        channel: u8,
+        src: u32,
+        dst: u32,
+        len: usize,
+    ) -> Result<(), DmaError> {
+        let slot = DmaController::check_channel(channel)? * program::DMA_PROGRAM_SIZE;
+        let program = program::DmaProgram::copy(src, dst, len)?;
+        let bytes = program.as_bytes();
+
+        unsafe {
+            let ptr = self.ocram_ptr_mut().add(slot);
+            for (i, b) in bytes.iter().enumerate() {
+                core::ptr::write_volatile(ptr.add(i), *b);
+            }
+        }
+
+        let address = crate::ranges::OCRAM.start + slot;
+        self.dmac_mut().start(channel, address as u32)
+    }
+}
+
+#[test]
+fn start_program() {
+    let mut soc = crate::SocFpga::create_for_test();
+    soc.dma_copy(7, 0x1000, 0x2000, 0x100).unwrap();
+
+    // The program is in on-chip RAM.
+    let program = program::DmaProgram::copy(0x1000, 0x2000, 0x100).unwrap();
+    let slot = unsafe {
+        core::slice::from_raw_parts(soc.ocram_ptr().add(7 * program::DMA_PROGRAM_SIZE), 128)
+    };
+    assert_eq!(&slot[..program.as_bytes().len()], program.as_bytes());
+
+    // DMAGO with the channel and the program address.
+    let dmac = soc.dmac();
+    assert_eq!(dmac.dbginst0, 0x07A2_0000);
+    assert_eq!(dmac.dbginst1, 0xFFFF_0380);
+    assert_eq!(dmac.poll(7), Ok(true));
+    assert_eq!(dmac.channel_state(8), Err(DmaError::InvalidChannel(8)));
+}
@@ -0,0 +1,72 @@
+use bitfield::bitfield;
+
+bitfield! {
+    /// Channel Control Register. Controls the AXI transactions a DMA channel
+    /// thread performs for `DMALD` and `DMAST` instructions. It is set by the
+    /// microcode with `DMAMOV CCR`.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct ChannelControl(u32);
+    impl Debug;
+    u32;
+
+    /// Data swap of the destination, as log2 of the size in bytes (0 for no swap).
+    pub endian_swap_size, set_endian_swap_size: 30, 28;
+
+    /// The AXI cache control of destination writes.
+    pub dst_cache_ctrl, set_dst_cache_ctrl: 27, 25;
+
+    /// The AXI protection control of destination writes.
+    pub dst_prot_ctrl, set_dst_prot_ctrl: 24, 22;
+
+    /// The number of beats in a destination burst, minus one.
+    pub dst_burst_len, set_dst_burst_len: 21, 18;
+
+    /// The size of a destination beat, as log2 of the size in bytes.
+    pub dst_burst_size, set_dst_burst_size: 17, 15;
+
+    /// Increment the destination address after each beat.
+    pub dst_inc, set_dst_inc: 14;
+
+    /// The AXI cache control of source reads.
+    pub src_cache_ctrl, set_src_cache_ctrl: 13, 11;
+
+    /// The AXI protection control of source reads.
+    pub src_prot_ctrl, set_src_prot_ctrl: 10, 8;
+
+    /// The number of beats in a source burst, minus one.
+    pub src_burst_len, set_src_burst_len: 7, 4;
+
+    /// The size of a source beat, as log2 of the size in bytes.
+    pub src_burst_size, set_src_burst_size: 3, 1;
+
+    /// Increment the source address after each beat.
+    pub src_inc, set_src_inc: 0;
+}
+
+/// AXI protection for non-secure, unprivileged data accesses.
+pub const PROT_NON_SECURE: u32 = 0b010;
+
+impl ChannelControl {
+    /// A memory to memory copy with incrementing addresses, non-secure
+    /// accesses, and bursts of `burst_len` beats of `1 << beat_size` bytes.
+    pub fn copy(beat_size: u32, burst_len: u32) -> Self {
+        let mut ccr = Self(0);
+        ccr.set_src_inc(true);
+        ccr.set_src_burst_size(beat_size);
+        ccr.set_src_burst_len(burst_len - 1);
+        ccr.set_src_prot_ctrl(PROT_NON_SECURE);
+        ccr.set_dst_inc(true);
+        ccr.set_dst_burst_size(beat_size);
+        ccr.set_dst_burst_len(burst_len - 1);
+        ccr.set_dst_prot_ctrl(PROT_NON_SECURE);
+        ccr
+    }
+}
+
+#[test]
+fn channel_control_copy() {
+    // 16 beats of 8 bytes.
+    assert_eq!(ChannelControl::copy(3, 16).0, 0x00BDC2F7);
+    // Single bytes.
+    assert_eq!(ChannelControl::copy(0, 1).0, 0x00804201);
+}
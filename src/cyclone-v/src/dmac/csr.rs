@@ -0,0 +1,110 @@
+use bitfield::bitfield;
+use core::fmt;
+
+/// The state of a DMA channel thread.
+#[derive(fmt::Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelState {
+    /// 0x0 - Stopped
+    Stopped = 0x0,
+    /// 0x1 - Executing
+    Executing = 0x1,
+    /// 0x2 - Cache miss
+    CacheMiss = 0x2,
+    /// 0x3 - Updating PC
+    UpdatingPc = 0x3,
+    /// 0x4 - Waiting for event
+    WaitingForEvent = 0x4,
+    /// 0x5 - At barrier
+    AtBarrier = 0x5,
+    /// 0x7 - Waiting for peripheral
+    WaitingForPeripheral = 0x7,
+    /// 0x8 - Killing
+    Killing = 0x8,
+    /// 0x9 - Completing
+    Completing = 0x9,
+    /// 0xE - Faulting completing
+    FaultingCompleting = 0xE,
+    /// 0xF - Faulting
+    Faulting = 0xF,
+    /// Any other value, which the documentation reserves.
+    Reserved = 0x6,
+}
+
+impl ChannelState {
+    /// Whether the channel is faulting, i.e. it stopped on an error.
+    pub fn is_faulting(&self) -> bool {
+        matches!(
+            self,
+            ChannelState::Faulting | ChannelState::FaultingCompleting
+        )
+    }
+}
+
+impl From<u32> for ChannelState {
+    fn from(value: u32) -> ChannelState {
+        match value {
+            0x0 => ChannelState::Stopped,
+            0x1 => ChannelState::Executing,
+            0x2 => ChannelState::CacheMiss,
+            0x3 => ChannelState::UpdatingPc,
+            0x4 => ChannelState::WaitingForEvent,
+            0x5 => ChannelState::AtBarrier,
+            0x7 => ChannelState::WaitingForPeripheral,
+            0x8 => ChannelState::Killing,
+            0x9 => ChannelState::Completing,
+            0xE => ChannelState::FaultingCompleting,
+            0xF => ChannelState::Faulting,
+            _ => ChannelState::Reserved,
+        }
+    }
+}
+
+impl From<ChannelState> for u32 {
+    fn from(value: ChannelState) -> u32 {
+        value as u32
+    }
+}
+
+bitfield! {
+    /// Channel Status Register. The status of a DMA channel thread.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct ChannelStatus(u32);
+    impl Debug;
+    u32;
+
+    /// Whether the channel operates in the Non-secure state.
+    pub cns, _: 21;
+
+    /// Whether the channel is executing `DMAWFP` with the periph operand.
+    pub dmawfp_periph, _: 15;
+
+    /// Whether the channel is executing `DMAWFP` with the burst operand.
+    pub dmawfp_b_ns, _: 14;
+
+    /// The event or peripheral number the channel is waiting for.
+    pub wakeup_number, _: 8, 4;
+
+    /// The state of the channel.
+    pub into ChannelState, state, _: 3, 0;
+}
+
+crate::declare_volatile_struct! {
+    /// The status registers of a DMA channel thread.
+    #[repr(C)]
+    pub struct ChannelStatusRegs {
+        /// Channel Status Register
+        [readonly] csr: ChannelStatus,
+
+        /// Channel Program Counter Register
+        [readonly] cpc: u32,
+    }
+}
+
+#[test]
+fn channel_status_works() {
+    assert_eq!(ChannelStatus(0).state(), ChannelState::Stopped);
+    assert_eq!(ChannelStatus(0x0020_0001).state(), ChannelState::Executing);
+    assert!(ChannelStatus(0x0020_0001).cns());
+    assert!(ChannelStatus(0xF).state().is_faulting());
+    assert_eq!(ChannelStatus(0x6).state(), ChannelState::Reserved);
+}
@@ -0,0 +1,216 @@
+//! Microcode for the DMA controller. Channel threads fetch their instructions
+//! from memory, so a program is assembled here and then written somewhere the
+//! DMA controller can read it. Only the instructions needed to copy memory are
+//! implemented.
+use crate::dmac::ccr::ChannelControl;
+use crate::dmac::DmaError;
+use core::convert::TryFrom;
+
+/// The maximum size of a program, in bytes.
+pub const DMA_PROGRAM_SIZE: usize = 128;
+
+/// The size of a beat on the AXI bus of the DMA controller, which is 64 bits.
+pub const DMA_BEAT_SIZE: usize = 8;
+
+/// The number of beats in a burst when copying memory.
+pub const DMA_BURST_LEN: usize = 16;
+
+/// The number of bytes copied by a burst.
+pub const DMA_BURST_SIZE: usize = DMA_BEAT_SIZE * DMA_BURST_LEN;
+
+/// The largest copy a single program can do, with two nested loops of 256
+/// bursts each.
+pub const DMA_MAX_COPY: usize = DMA_BURST_SIZE * 256 * 256;
+
+/// Registers that can be set with `DMAMOV`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaRegister {
+    /// Source Address Register
+    Sar = 0,
+    /// Channel Control Register
+    Ccr = 1,
+    /// Destination Address Register
+    Dar = 2,
+}
+
+/// A loop counter, for `DMALP` and `DMALPEND`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopCounter {
+    Lc0 = 0,
+    Lc1 = 1,
+}
+
+/// A program for a DMA channel thread.
+#[derive(Clone)]
+pub struct DmaProgram {
+    bytes: [u8; DMA_PROGRAM_SIZE],
+    len: usize,
+}
+
+impl Default for DmaProgram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for DmaProgram {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.as_bytes()).finish()
+    }
+}
+
+impl DmaProgram {
+    pub fn new() -> Self {
+        Self {
+            bytes: [0; DMA_PROGRAM_SIZE],
+            len: 0,
+        }
+    }
+
+    /// The assembled instructions.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    fn push(&mut self, instruction: &[u8]) -> Result<&mut Self, DmaError> {
+        let end = self.len + instruction.len();
+        if end > DMA_PROGRAM_SIZE {
+            return Err(DmaError::ProgramTooLarge);
+        }
+        self.bytes[self.len..end].copy_from_slice(instruction);
+        self.len = end;
+        Ok(self)
+    }
+
+    /// `DMAMOV`: set a register of the channel.
+    pub fn mov(&mut self, register: DmaRegister, value: u32) -> Result<&mut Self, DmaError> {
+        let [a, b, c, d] = value.to_le_bytes();
+        self.push(&[0xBC, register as u8, a, b, c, d])
+    }
+
+    /// `DMALD`: load a burst from the source.
+    pub fn ld(&mut self) -> Result<&mut Self, DmaError> {
+        self.push(&[0x04])
+    }
+
+    /// `DMAST`: store a burst to the destination.
+    pub fn st(&mut self) -> Result<&mut Self, DmaError> {
+        self.push(&[0x08])
+    }
+
+    /// `DMAWMB`: wait for all writes to complete.
+    pub fn wmb(&mut self) -> Result<&mut Self, DmaError> {
+        self.push(&[0x13])
+    }
+
+    /// `DMAEND`: stop the channel.
+    pub fn end(&mut self) -> Result<&mut Self, DmaError> {
+        self.push(&[0x00])
+    }
+
+    /// Repeat the instructions pushed by `body` a number of times, between 1
+    /// and 256, using `DMALP` and `DMALPEND`.
+    pub fn repeat(
+        &mut self,
+        counter: LoopCounter,
+        iterations: usize,
+        body: impl FnOnce(&mut Self) -> Result<(), DmaError>,
+    ) -> Result<&mut Self, DmaError> {
+        if !(1..=256).contains(&iterations) {
+            return Err(DmaError::InvalidTransfer);
+        }
+
+        self.push(&[0x20 | ((counter as u8) << 1), (iterations - 1) as u8])?;
+        let start = self.len;
+        body(self)?;
+
+        let jump = u8::try_from(self.len - start).map_err(|_| DmaError::ProgramTooLarge)?;
+        self.push(&[0x38 | ((counter as u8) << 2), jump])
+    }
+
+    /// A program copying `len` bytes from `src` to `dst`. Both addresses must
+    /// be aligned to a beat. Full bursts are copied first, then the rest one
+    /// byte at a time.
+    pub fn copy(src: u32, dst: u32, len: usize) -> Result<Self, DmaError> {
+        if src as usize % DMA_BEAT_SIZE != 0
+            || dst as usize % DMA_BEAT_SIZE != 0
+            || len > DMA_MAX_COPY
+        {
+            return Err(DmaError::InvalidTransfer);
+        }
+
+        let bursts = len / DMA_BURST_SIZE;
+        let tail = len % DMA_BURST_SIZE;
+        let copy = |p: &mut Self| -> Result<(), DmaError> {
+            p.ld()?.st()?;
+            Ok(())
+        };
+
+        let mut program = Self::new();
+        program
+            .mov(DmaRegister::Sar, src)?
+            .mov(DmaRegister::Dar, dst)?;
+
+        if bursts > 0 {
+            program.mov(
+                DmaRegister::Ccr,
+                ChannelControl::copy(DMA_BEAT_SIZE.trailing_zeros(), DMA_BURST_LEN as u32).0,
+            )?;
+            if bursts >= 256 {
+                program.repeat(LoopCounter::Lc1, bursts / 256, |p| {
+                    p.repeat(LoopCounter::Lc0, 256, copy)?;
+                    Ok(())
+                })?;
+            }
+            if bursts % 256 > 0 {
+                program.repeat(LoopCounter::Lc0, bursts % 256, copy)?;
+            }
+        }
+        if tail > 0 {
+            program
+                .mov(DmaRegister::Ccr, ChannelControl::copy(0, 1).0)?
+                .repeat(LoopCounter::Lc0, tail, copy)?;
+        }
+
+        program.wmb()?.end()?;
+        Ok(program)
+    }
+}
+
+#[test]
+fn copy_program() {
+    let program = DmaProgram::copy(0x1000, 0x2000, 2 * DMA_BURST_SIZE + 3).unwrap();
+    #[rustfmt::skip]
+    assert_eq!(
+        program.as_bytes(),
+        &[
+            0xBC, 0x00, 0x00, 0x10, 0x00, 0x00, // DMAMOV SAR, 0x1000
+            0xBC, 0x02, 0x00, 0x20, 0x00, 0x00, // DMAMOV DAR, 0x2000
+            0xBC, 0x01, 0xF7, 0xC2, 0xBD, 0x00, // DMAMOV CCR, 16 beats of 8 bytes
+            0x20, 0x01,                         // DMALP lc0, 2
+            0x04, 0x08,                         //   DMALD, DMAST
+            0x38, 0x02,                         // DMALPEND lc0
+            0xBC, 0x01, 0x01, 0x42, 0x80, 0x00, // DMAMOV CCR, 1 beat of 1 byte
+            0x20, 0x02,                         // DMALP lc0, 3
+            0x04, 0x08,                         //   DMALD, DMAST
+            0x38, 0x02,                         // DMALPEND lc0
+            0x13, 0x00,                         // DMAWMB, DMAEND
+        ][..]
+    );
+
+    // Nested loops.
+    let program = DmaProgram::copy(0, 0, 512 * DMA_BURST_SIZE).unwrap();
+    assert_eq!(
+        &program.as_bytes()[18..],
+        &[0x22, 0x01, 0x20, 0xFF, 0x04, 0x08, 0x38, 0x02, 0x3C, 0x06, 0x13, 0x00][..]
+    );
+
+    assert_eq!(
+        DmaProgram::copy(4, 0, 8).unwrap_err(),
+        DmaError::InvalidTransfer
+    );
+    assert_eq!(
+        DmaProgram::copy(0, 0, DMA_MAX_COPY + 1).unwrap_err(),
+        DmaError::InvalidTransfer
+    );
+}
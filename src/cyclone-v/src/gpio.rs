@@ -0,0 +1,127 @@
+//! The GPIO controllers of the HPS. Each controller handles one port of up to
+//! 29 pins, numbered from 0.
+//! See https://www.intel.com/content/www/us/en/programmable/hps/cyclone-v/sfo1410067808053.html
+use memoffset::offset_of;
+use static_assertions::const_assert_eq;
+
+/// The number of pins of each GPIO controller. The last controller (`gpio2`)
+/// only has 27.
+pub const GPIO_PINS: u8 = 29;
+
+crate::declare_volatile_struct! {
+    /// Registers in a GPIO controller module.
+    #[repr(C)]
+    pub struct GpioController {
+        /// Port A Data Register
+        gpio_swporta_dr: u32,
+        /// Port A Data Direction Register. A bit set to 1 is an output.
+        gpio_swporta_ddr: u32,
+        [padding] _pad_0x08_0x30: [u32; 10],
+
+        /// Interrupt Enable Register
+        gpio_inten: u32,
+        /// Interrupt Mask Register
+        gpio_intmask: u32,
+        /// Interrupt Level Register
+        gpio_inttype_level: u32,
+        /// Interrupt Polarity Register
+        gpio_int_polarity: u32,
+        /// Interrupt Status Register
+        [readonly] gpio_intstatus: u32,
+        /// Raw Interrupt Status Register
+        [readonly] gpio_raw_intstatus: u32,
+        /// Debounce Enable Register
+        gpio_debounce: u32,
+        /// Clear Interrupt Register
+        [writeonly] gpio_porta_eoi: u32,
+        /// External Port A Register. The level of the pins.
+        [readonly] gpio_ext_porta: u32,
+        [padding] _pad_0x54_0x60: [u32; 3],
+
+        /// Synchronization Level Register
+        gpio_ls_sync: u32,
+        /// ID Code Register
+        [readonly] gpio_id_code: u32,
+        [padding] _pad_0x68_0x6c: u32,
+        /// GPIO Version Register
+        [readonly] gpio_ver_id_code: u32,
+        /// Configuration Register 2
+        [readonly] gpio_config_reg2: u32,
+        /// Configuration Register 1
+        [readonly] gpio_config_reg1: u32,
+    }
+}
+
+const_assert_eq!(offset_of!(GpioController, gpio_inten), 0x30);
+const_assert_eq!(offset_of!(GpioController, gpio_ext_porta), 0x50);
+const_assert_eq!(offset_of!(GpioController, gpio_ls_sync), 0x60);
+const_assert_eq!(core::mem::size_of::<GpioController>(), 0x78);
+
+impl GpioController {
+    fn mask(pin: u8) -> u32 {
+        assert!(pin < GPIO_PINS, "Invalid GPIO pin {}", pin);
+        1 << pin
+    }
+
+    /// Set the direction of a pin, `true` for an output.
+    pub fn set_direction(&mut self, pin: u8, output: bool) {
+        let mask = Self::mask(pin);
+        self.update_gpio_swporta_ddr(|ddr| {
+            if output {
+                *ddr |= mask;
+            } else {
+                *ddr &= !mask;
+            }
+        });
+    }
+
+    /// Whether a pin is an output.
+    pub fn is_output(&self, pin: u8) -> bool {
+        self.gpio_swporta_ddr() & Self::mask(pin) != 0
+    }
+
+    /// Drive an output pin high or low.
+    pub fn set_output(&mut self, pin: u8, high: bool) {
+        let mask = Self::mask(pin);
+        self.update_gpio_swporta_dr(|dr| {
+            if high {
+                *dr |= mask;
+            } else {
+                *dr &= !mask;
+            }
+        });
+    }
+
+    /// The level of a pin, whatever its direction.
+    pub fn input(&self, pin: u8) -> bool {
+        self.gpio_ext_porta() & Self::mask(pin) != 0
+    }
+
+    /// Clear the edge interrupts of the pins in `mask`.
+    pub fn clear_interrupts(&mut self, mask: u32) {
+        self.set_gpio_porta_eoi(mask);
+    }
+}
+
+#[test]
+fn pins() {
+    let mut soc = crate::SocFpga::create_for_test();
+    let gpio = soc.gpio1_mut();
+    gpio.set_direction(24, true);
+    gpio.set_output(24, true);
+    gpio.set_direction(3, true);
+    gpio.set_output(3, false);
+    assert!(gpio.is_output(24));
+    assert!(!gpio.is_output(25));
+    assert_eq!(gpio.gpio_swporta_ddr(), 0x0100_0008);
+    assert_eq!(gpio.gpio_swporta_dr(), 0x0100_0000);
+
+    // Inputs read the external port.
+    let offset = crate::ranges::GPIO1.start - crate::ranges::BASE.start;
+    unsafe {
+        *(soc.base_ptr_mut().add(offset + 0x50) as *mut u32) = 1 << 28;
+    }
+    assert!(soc.gpio1().input(28));
+    assert!(!soc.gpio1().input(24));
+    assert!(!soc.gpio0().input(28));
+}
@@ -0,0 +1,220 @@
+//! The I2C controllers of the HPS, Synopsys DesignWare APB I2C controllers.
+//! On the DE10-Nano, `i2c1` is connected to the HDMI transmitter and the EDID
+//! of the monitor.
+//! See https://www.intel.com/content/www/us/en/programmable/hps/cyclone-v/sfo1410067808053.html
+use memoffset::offset_of;
+use static_assertions::const_assert_eq;
+
+pub mod con;
+pub mod data_cmd;
+pub mod status;
+
+pub use con::I2cSpeed;
+
+/// The frequency of `l4_sp_clk`, the clock of the I2C controllers, as set up
+/// by the bootloader of the DE10-Nano, in Hz.
+pub const I2C_CLOCK_FREQUENCY: u64 = 100_000_000;
+
+/// The depth of the transmit and receive FIFOs.
+pub const I2C_FIFO_DEPTH: u32 = 64;
+
+crate::declare_volatile_struct! {
+    /// Registers in an I2C controller module.
+    #[repr(C)]
+    pub struct I2cController {
+        /// Control Register
+        ic_con: con::IcCon,
+        /// Target Address Register
+        ic_tar: u32,
+        /// Slave Address Register
+        ic_sar: u32,
+        [padding] _pad_0x0c_0x10: u32,
+        /// Rx/Tx Data Buffer and Command Register
+        ic_data_cmd: data_cmd::IcDataCmd,
+        /// Standard Speed Clock SCL High Count Register
+        ic_ss_scl_hcnt: u32,
+        /// Standard Speed Clock SCL Low Count Register
+        ic_ss_scl_lcnt: u32,
+        /// Fast Speed Clock SCL High Count Register
+        ic_fs_scl_hcnt: u32,
+        /// Fast Speed Clock SCL Low Count Register
+        ic_fs_scl_lcnt: u32,
+        [padding] _pad_0x24_0x2c: [u32; 2],
+
+        /// Interrupt Status Register
+        [readonly] ic_intr_stat: status::IcInterrupts,
+        /// Interrupt Mask Register
+        ic_intr_mask: status::IcInterrupts,
+        /// Raw Interrupt Status Register
+        [readonly] ic_raw_intr_stat: status::IcInterrupts,
+        /// Receive FIFO Threshold Register
+        ic_rx_tl: u32,
+        /// Transmit FIFO Threshold Register
+        ic_tx_tl: u32,
+
+        /// Clear Combined and Individual Interrupt Registers. Reading a register
+        /// clears its interrupt.
+        [readonly] ic_clr_intr: u32,
+        [readonly] ic_clr_rx_under: u32,
+        [readonly] ic_clr_rx_over: u32,
+        [readonly] ic_clr_tx_over: u32,
+        [readonly] ic_clr_rd_req: u32,
+        [readonly] ic_clr_tx_abrt: u32,
+        [readonly] ic_clr_rx_done: u32,
+        [readonly] ic_clr_activity: u32,
+        [readonly] ic_clr_stop_det: u32,
+        [readonly] ic_clr_start_det: u32,
+        [readonly] ic_clr_gen_call: u32,
+
+        /// Enable Register
+        ic_enable: u32,
+        /// Status Register
+        [readonly] ic_status: status::IcStatus,
+        /// Transmit FIFO Level Register
+        [readonly] ic_txflr: u32,
+        /// Receive FIFO Level Register
+        [readonly] ic_rxflr: u32,
+        /// SDA Hold Time Length Register
+        ic_sda_hold: u32,
+        /// Transmit Abort Source Register
+        [readonly] ic_tx_abrt_source: u32,
+        /// Generate Slave Data NACK Register
+        ic_slv_data_nack_only: u32,
+        /// DMA Control Register
+        ic_dma_cr: u32,
+        /// DMA Transmit Data Level Register
+        ic_dma_tdlr: u32,
+        /// DMA Receive Data Level Register
+        ic_dma_rdlr: u32,
+        /// SDA Setup Register
+        ic_sda_setup: u32,
+        /// ACK General Call Register
+        ic_ack_general_call: u32,
+        /// Enable Status Register
+        [readonly] ic_enable_status: u32,
+        /// SS and FS Spike Suppression Limit Register
+        ic_fs_spklen: u32,
+        [padding] _pad_0xa4_0xf4: [u32; 20],
+
+        /// Component Parameter Register 1
+        [readonly] ic_comp_param_1: u32,
+        /// Component Version Register
+        [readonly] ic_comp_version: u32,
+        /// Component Type Register
+        [readonly] ic_comp_type: u32,
+    }
+}
+
+const_assert_eq!(offset_of!(I2cController, ic_data_cmd), 0x10);
+const_assert_eq!(offset_of!(I2cController, ic_intr_stat), 0x2C);
+const_assert_eq!(offset_of!(I2cController, ic_clr_intr), 0x40);
+const_assert_eq!(offset_of!(I2cController, ic_enable), 0x6C);
+const_assert_eq!(offset_of!(I2cController, ic_tx_abrt_source), 0x80);
+const_assert_eq!(offset_of!(I2cController, ic_enable_status), 0x9C);
+const_assert_eq!(offset_of!(I2cController, ic_comp_param_1), 0xF4);
+const_assert_eq!(core::mem::size_of::<I2cController>(), 0x100);
+
+impl I2cController {
+    /// Whether the controller is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.ic_enable_status() & 1 != 0
+    }
+
+    /// Enable or disable the controller. The configuration and target address
+    /// can only be changed while disabled; poll [`Self::is_enabled`] after
+    /// disabling it.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.set_ic_enable(enabled as u32);
+    }
+
+    /// Configure the controller as a master with 7-bit addresses, for a clock
+    /// of `clock_hz`. The controller must be disabled.
+    pub fn configure_master(&mut self, speed: I2cSpeed, clock_hz: u64) {
+        let mut con = con::IcCon(0);
+        con.set_master_mode(true);
+        con.set_speed(speed);
+        con.set_ic_restart_en(true);
+        con.set_ic_slave_disable(true);
+        self.set_ic_con(con);
+
+        // Minimum high and low periods of SCL, from the I2C specification.
+        let count = |ns: u64| (clock_hz * ns).div_ceil(1_000_000_000) as u32;
+        match speed {
+            I2cSpeed::Standard => {
+                self.set_ic_ss_scl_hcnt(count(4_000));
+                self.set_ic_ss_scl_lcnt(count(4_700));
+            }
+            I2cSpeed::Fast => {
+                self.set_ic_fs_scl_hcnt(count(600));
+                self.set_ic_fs_scl_lcnt(count(1_300));
+            }
+        }
+
+        // Mask all interrupts, the controller is polled.
+        self.set_ic_intr_mask(status::IcInterrupts(0));
+    }
+
+    /// Set the 7-bit address of the slave. The controller must be disabled.
+    pub fn set_target(&mut self, address: u8) {
+        self.set_ic_tar((address & 0x7F) as u32);
+    }
+
+    /// Push a command in the transmit FIFO. Returns false if the FIFO is full.
+    pub fn push(&mut self, command: data_cmd::IcDataCmd) -> bool {
+        if !self.ic_status().tfnf() {
+            return false;
+        }
+        self.set_ic_data_cmd(command);
+        true
+    }
+
+    /// Pop a byte from the receive FIFO, if any.
+    pub fn pop(&mut self) -> Option<u8> {
+        if self.ic_status().rfne() {
+            Some(self.ic_data_cmd().dat())
+        } else {
+            None
+        }
+    }
+
+    /// The reason of the last aborted transfer, or `None` if the last
+    /// transfer was not aborted. Clears the abort.
+    pub fn take_abort(&mut self) -> Option<u32> {
+        if !self.ic_raw_intr_stat().tx_abrt() {
+            return None;
+        }
+        let source = self.ic_tx_abrt_source();
+        let _ = self.ic_clr_tx_abrt();
+        Some(source)
+    }
+}
+
+#[test]
+fn master() {
+    let mut soc = crate::SocFpga::create_for_test();
+    let i2c = soc.i2c1_mut();
+    i2c.set_enabled(false);
+    i2c.configure_master(I2cSpeed::Fast, I2C_CLOCK_FREQUENCY);
+    i2c.set_target(0x39);
+
+    assert_eq!(i2c.ic_con().0, 0x65);
+    assert_eq!(i2c.ic_tar(), 0x39);
+    assert_eq!(i2c.ic_fs_scl_hcnt(), 60);
+    assert_eq!(i2c.ic_fs_scl_lcnt(), 130);
+    assert_eq!(i2c.ic_ss_scl_hcnt(), 0);
+
+    // Nothing can be pushed while the transmit FIFO reads as full.
+    assert!(!i2c.push(data_cmd::IcDataCmd::write(0x41, false)));
+    assert_eq!(i2c.pop(), None);
+    assert_eq!(i2c.take_abort(), None);
+
+    let offset = crate::ranges::I2C1.start - crate::ranges::BASE.start;
+    unsafe {
+        // TFNF and RFNE.
+        *(soc.base_ptr_mut().add(offset + 0x70) as *mut u32) = 0b1010;
+    }
+    let i2c = soc.i2c1_mut();
+    assert!(i2c.push(data_cmd::IcDataCmd::read(true)));
+    assert_eq!(i2c.ic_data_cmd().0, 0x300);
+    assert_eq!(i2c.pop(), Some(0));
+}
@@ -0,0 +1,64 @@
+use bitfield::bitfield;
+
+/// The speed of an I2C controller in master mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum I2cSpeed {
+    /// Standard mode, up to 100 kbit/s.
+    Standard = 1,
+
+    /// Fast mode, up to 400 kbit/s.
+    Fast = 2,
+}
+
+impl From<u32> for I2cSpeed {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => I2cSpeed::Standard,
+            // Values 2 and 3 both select fast mode.
+            _ => I2cSpeed::Fast,
+        }
+    }
+}
+
+impl From<I2cSpeed> for u32 {
+    fn from(value: I2cSpeed) -> Self {
+        value as Self
+    }
+}
+
+bitfield! {
+    /// Control Register. Can only be written while the controller is disabled.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct IcCon(u32);
+    impl Debug;
+    u32;
+
+    /// Disable the slave mode.
+    pub ic_slave_disable, set_ic_slave_disable: 6;
+
+    /// Allow sending RESTART conditions in master mode.
+    pub ic_restart_en, set_ic_restart_en: 5;
+
+    /// Use 10-bit addresses in master mode.
+    pub ic_10bitaddr_master, set_ic_10bitaddr_master: 4;
+
+    /// Respond to 10-bit addresses in slave mode.
+    pub ic_10bitaddr_slave, set_ic_10bitaddr_slave: 3;
+
+    /// The speed of the controller in master mode.
+    pub from into I2cSpeed, speed, set_speed: 2, 1;
+
+    /// Enable the master mode.
+    pub master_mode, set_master_mode: 0;
+}
+
+#[test]
+fn con() {
+    let mut con = IcCon(0);
+    con.set_master_mode(true);
+    con.set_speed(I2cSpeed::Fast);
+    con.set_ic_restart_en(true);
+    con.set_ic_slave_disable(true);
+    assert_eq!(con.0, 0x65);
+    assert_eq!(IcCon(0x63).speed(), I2cSpeed::Standard);
+}
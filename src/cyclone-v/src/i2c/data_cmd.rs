@@ -0,0 +1,40 @@
+use bitfield::bitfield;
+
+bitfield! {
+    /// Rx/Tx Data Buffer and Command Register. Writing pushes a command in the
+    /// transmit FIFO; reading pops a byte from the receive FIFO.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct IcDataCmd(u32);
+    impl Debug;
+    u32;
+
+    /// Send a RESTART condition before this byte.
+    pub restart, set_restart: 10;
+
+    /// Send a STOP condition after this byte.
+    pub stop, set_stop: 9;
+
+    /// Read a byte (1) instead of writing `dat` (0).
+    pub cmd, set_cmd: 8;
+
+    /// The byte to send, or the byte received.
+    pub u8, dat, set_dat: 7, 0;
+}
+
+impl IcDataCmd {
+    /// A command writing `byte`, optionally followed by a STOP condition.
+    pub fn write(byte: u8, stop: bool) -> Self {
+        let mut value = Self(0);
+        value.set_dat(byte);
+        value.set_stop(stop);
+        value
+    }
+
+    /// A command reading a byte, optionally followed by a STOP condition.
+    pub fn read(stop: bool) -> Self {
+        let mut value = Self(0);
+        value.set_cmd(true);
+        value.set_stop(stop);
+        value
+    }
+}
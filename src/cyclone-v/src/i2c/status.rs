@@ -0,0 +1,74 @@
+use bitfield::bitfield;
+
+bitfield! {
+    /// Status Register.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct IcStatus(u32);
+    impl Debug;
+    u32;
+
+    /// The slave state machine is not idle.
+    pub slv_activity, _: 6;
+
+    /// The master state machine is not idle.
+    pub mst_activity, _: 5;
+
+    /// The receive FIFO is full.
+    pub rff, _: 4;
+
+    /// The receive FIFO is not empty.
+    pub rfne, _: 3;
+
+    /// The transmit FIFO is empty.
+    pub tfe, _: 2;
+
+    /// The transmit FIFO is not full.
+    pub tfnf, _: 1;
+
+    /// The controller is active.
+    pub activity, _: 0;
+}
+
+bitfield! {
+    /// Interrupt Status, Interrupt Mask and Raw Interrupt Status Registers.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct IcInterrupts(u32);
+    impl Debug;
+    u32;
+
+    /// A General Call address was received and acknowledged.
+    pub gen_call, set_gen_call: 11;
+
+    /// A START or RESTART condition occurred.
+    pub start_det, set_start_det: 10;
+
+    /// A STOP condition occurred.
+    pub stop_det, set_stop_det: 9;
+
+    /// The controller is active.
+    pub activity, set_activity: 8;
+
+    /// The master did not acknowledge a byte sent in slave mode.
+    pub rx_done, set_rx_done: 7;
+
+    /// The transmit was aborted. The reason is in `ic_tx_abrt_source`.
+    pub tx_abrt, set_tx_abrt: 6;
+
+    /// A master is reading from the controller in slave mode.
+    pub rd_req, set_rd_req: 5;
+
+    /// The transmit FIFO is at or below its threshold.
+    pub tx_empty, set_tx_empty: 4;
+
+    /// A command was pushed in a full transmit FIFO.
+    pub tx_over, set_tx_over: 3;
+
+    /// The receive FIFO is above its threshold.
+    pub rx_full, set_rx_full: 2;
+
+    /// A byte was received in a full receive FIFO.
+    pub rx_over, set_rx_over: 1;
+
+    /// The receive FIFO was read while empty.
+    pub rx_under, set_rx_under: 0;
+}
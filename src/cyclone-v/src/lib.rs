@@ -5,19 +5,24 @@
 //! These types are used to access the FPGA Manager registers and other Cyclone V specific
 //! registers. The code should be platform-agnostic to allow for tests. This means no
 //! assembly or architecture specific libraries.
-#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 extern crate core;
 
 use crate::memory::MemoryMapper;
 
 mod macros;
 
+pub mod clkmgr;
+pub mod dmac;
 pub mod fpgamgrregs;
+pub mod gpio;
+pub mod i2c;
 pub mod l3regs;
 pub mod memory;
 pub mod rstmgr;
 pub mod sdr;
 pub mod sysmgr;
+pub mod timer;
 
 macro_rules! declare_field {
     ($(#[$field_attr:meta])* $field_name: ident, $const_name: ident, $ty: ty [pointer]) => {
@@ -139,11 +144,35 @@ create_memory_locations! {
     /// The Reset Manager.
     rstmgr(RSTMGR):             rstmgr::ResetManager            => 0xFFD05000 .. 0xFFD050FF;
 
+    /// The Clock Manager.
+    clkmgr(CLKMGR):             clkmgr::ClockManager            => 0xFFD04000 .. 0xFFD040FF;
+
     /// Registers to control L3 interconnect settings.
     l3regs(L3_REGS):            l3regs::L3Regs                  => 0xFF800000 .. 0xFF87FFFF;
 
     /// System Manager Module
     sysmgr(SYSMGR):             sysmgr::SystemManagerModule     => 0xFFD08000 .. 0xFFD08FFF;
+
+    /// The GPIO controllers.
+    gpio0(GPIO0):               gpio::GpioController            => 0xFF708000 .. 0xFF70807F;
+    gpio1(GPIO1):               gpio::GpioController            => 0xFF709000 .. 0xFF70907F;
+    gpio2(GPIO2):               gpio::GpioController            => 0xFF70A000 .. 0xFF70A07F;
+
+    /// The I2C controllers.
+    i2c0(I2C0):                 i2c::I2cController              => 0xFFC04000 .. 0xFFC040FF;
+    i2c1(I2C1):                 i2c::I2cController              => 0xFFC05000 .. 0xFFC050FF;
+    i2c2(I2C2):                 i2c::I2cController              => 0xFFC06000 .. 0xFFC060FF;
+    i2c3(I2C3):                 i2c::I2cController              => 0xFFC07000 .. 0xFFC070FF;
+
+    /// The timers clocked by `eosc1_clk`.
+    osc1timer0(OSC1TIMER0):     timer::Timer                    => 0xFFD00000 .. 0xFFD000FF;
+    osc1timer1(OSC1TIMER1):     timer::Timer                    => 0xFFD01000 .. 0xFFD010FF;
+
+    /// The DMA Controller (non-secure registers).
+    dmac(DMAC):                 dmac::DmaController             => 0xFFE01000 .. 0xFFE01FFF;
+
+    /// The On-chip RAM.
+    ocram(OCRAM):               u8 [pointer]                    => 0xFFFF0000 .. 0xFFFFFFFF;
 }
 
 // Safety: [`SocFpga`] is safe to send across threads if its memory mapper is safe as well.
@@ -159,7 +188,22 @@ impl Default for SocFpga<memory::DevMemMemoryMapper> {
     }
 }
 
-#[cfg(feature = "std")]
+impl SocFpga<memory::RawMemoryMapper> {
+    /// Create a SoC over the physical addresses, for bare metal code (e.g. a
+    /// bootloader) running with the MMU off or an identity mapping.
+    ///
+    /// # Safety
+    /// The registers must be accessible at their physical addresses, and
+    /// nothing else must access them while this is alive.
+    pub unsafe fn physical() -> Self {
+        Self::new(memory::RawMemoryMapper::new(
+            addresses::BASE as *mut u8,
+            sizes::BASE,
+        ))
+    }
+}
+
+#[cfg(any(test, feature = "std"))]
 impl SocFpga<memory::BufferMemoryMapper> {
     pub fn create_for_test() -> Self {
         let memory = memory::BufferMemoryMapper::new(sizes::BASE);
@@ -167,3 +211,14 @@ impl SocFpga<memory::BufferMemoryMapper> {
         Self::new(memory)
     }
 }
+
+#[cfg(feature = "std")]
+impl SocFpga<memory::ReplayMemoryMapper> {
+    /// Create a SoC whose registers read as the values of a trace recorded
+    /// with [`memory::TraceRecorder`], to reproduce it in tests.
+    pub fn create_for_replay(accesses: impl IntoIterator<Item = memory::Access>) -> Self {
+        let memory = memory::ReplayMemoryMapper::new(addresses::BASE, sizes::BASE, accesses);
+
+        Self::new(memory)
+    }
+}
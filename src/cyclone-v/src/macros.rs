@@ -1,11 +1,29 @@
 #[macro_export]
 macro_rules! declare_field_accessors {
     ($(#[$fattr:meta])* $fname: ident, $ftype: ty [padding]) => {};
+    ($(#[$fattr:meta])* $fname: ident, $ftype: ty [nested]) => {
+        paste::paste! {
+            $(#[$fattr])*
+            #[inline]
+            pub fn $fname(&self) -> &$ftype {
+                &self.$fname
+            }
+
+            $(#[$fattr])*
+            #[inline]
+            pub fn [<$fname _mut>](&mut self) -> &mut $ftype {
+                &mut self.$fname
+            }
+        }
+    };
     ($(#[$fattr:meta])* $fname: ident, $ftype: ty [readonly]) => {
         $(#[$fattr])*
         #[inline]
         pub fn $fname(&self) -> $ftype {
-            unsafe { core::ptr::read_volatile(core::ptr::addr_of!(self.$fname)) }
+            unsafe {
+                let ptr = core::ptr::addr_of!(self.$fname);
+                $crate::memory::trace::on_read(ptr, core::ptr::read_volatile(ptr))
+            }
         }
     };
     ($(#[$fattr:meta])* $fname: ident, $ftype: ty [writeonly]) => {
@@ -14,7 +32,9 @@ macro_rules! declare_field_accessors {
             #[inline]
             pub fn [<set_ $fname>](&mut self, value: $ftype) {
                 unsafe {
-                    core::ptr::write_volatile(core::ptr::addr_of_mut!(self.$fname), value);
+                    let ptr = core::ptr::addr_of_mut!(self.$fname);
+                    $crate::memory::trace::on_write(ptr, &value);
+                    core::ptr::write_volatile(ptr, value);
                 }
             }
         }
@@ -24,14 +44,19 @@ macro_rules! declare_field_accessors {
             $(#[$fattr])*
             #[inline]
             pub fn $fname(&self) -> $ftype {
-                unsafe { core::ptr::read_volatile(core::ptr::addr_of!(self.$fname)) }
+                unsafe {
+                    let ptr = core::ptr::addr_of!(self.$fname);
+                    $crate::memory::trace::on_read(ptr, core::ptr::read_volatile(ptr))
+                }
             }
 
             $(#[$fattr])*
             #[inline]
             pub fn [<set_ $fname>](&mut self, value: $ftype) {
                 unsafe {
-                    core::ptr::write_volatile(core::ptr::addr_of_mut!(self.$fname), value);
+                    let ptr = core::ptr::addr_of_mut!(self.$fname);
+                    $crate::memory::trace::on_write(ptr, &value);
+                    core::ptr::write_volatile(ptr, value);
                 }
             }
 
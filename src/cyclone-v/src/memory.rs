@@ -1,13 +1,24 @@
 pub mod dev_mem;
 
+use core::ops::{Bound, RangeBounds};
 #[cfg(feature = "std")]
 pub use dev_mem::*;
-use std::ops::{Bound, RangeBounds};
 
 pub mod buffer;
-#[cfg(feature = "std")]
+#[cfg(any(test, feature = "std"))]
 pub use buffer::*;
 
+pub mod raw;
+pub use raw::*;
+
+pub mod replay;
+#[cfg(feature = "std")]
+pub use replay::*;
+
+pub mod trace;
+#[cfg(feature = "std")]
+pub use trace::*;
+
 fn clamp_range(range: impl RangeBounds<usize>, max: usize) -> (usize, usize) {
     let start = match range.start_bound() {
         Bound::Included(start) => *start,
@@ -53,13 +64,13 @@ pub trait MemoryMapper {
     /// map 0x12340000..0x12340004).
     fn as_range(&self, range: impl RangeBounds<usize>) -> &[u8] {
         let (start, len) = clamp_range(range, self.len());
-        unsafe { std::slice::from_raw_parts(self.as_ptr::<u8>().add(start), len) }
+        unsafe { core::slice::from_raw_parts(self.as_ptr::<u8>().add(start), len) }
     }
 
     /// Creates an inner mutable range of bytes.
     fn as_mut_range(&mut self, range: impl RangeBounds<usize>) -> &mut [u8] {
         let (start, len) = clamp_range(range, self.len());
-        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr::<u8>().add(start), len) }
+        unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr::<u8>().add(start), len) }
     }
 }
 
@@ -1,4 +1,4 @@
-#![cfg(feature = "std")]
+#![cfg(any(test, feature = "std"))]
 
 use crate::memory::MemoryMapper;
 use std::pin::Pin;
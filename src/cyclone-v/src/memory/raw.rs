@@ -0,0 +1,63 @@
+use crate::memory::MemoryMapper;
+
+/// A memory mapper over a raw pointer. On bare metal, with the MMU off or an
+/// identity mapping, this is the physical address itself. Available without
+/// `std`.
+#[derive(Debug)]
+pub struct RawMemoryMapper {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl RawMemoryMapper {
+    /// Map `len` bytes at `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads and writes of `len` bytes for as long as
+    /// the mapper is used.
+    pub const unsafe fn new(ptr: *mut u8, len: usize) -> Self {
+        Self { ptr, len }
+    }
+}
+
+impl MemoryMapper for RawMemoryMapper {
+    /// Map the physical address directly, which is only valid with the MMU
+    /// off or an identity mapping.
+    fn create(address: usize, size: usize) -> Result<Self, &'static str> {
+        if address == 0 {
+            return Err("Cannot map a null address");
+        }
+        if address.checked_add(size).is_none() {
+            return Err("Address range overflows");
+        }
+
+        Ok(unsafe { Self::new(address as *mut u8, size) })
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn as_ptr<T>(&self) -> *const T {
+        self.ptr as *const T
+    }
+
+    fn as_mut_ptr<T>(&mut self) -> *mut T {
+        self.ptr as *mut T
+    }
+}
+
+#[test]
+fn raw_pointer() {
+    let mut data = [0u8; 0x100];
+    let mut mapper = unsafe { RawMemoryMapper::new(data.as_mut_ptr(), data.len()) };
+    mapper
+        .as_mut_range(0x10..0x14)
+        .copy_from_slice(&[1, 2, 3, 4]);
+    assert_eq!(mapper.len(), 0x100);
+    assert_eq!(mapper.as_range(0x0F..0x15), &[0, 1, 2, 3, 4, 0]);
+    assert_eq!(data[0x12], 3);
+
+    assert!(RawMemoryMapper::create(0, 0x100).is_err());
+    assert!(RawMemoryMapper::create(usize::MAX, 2).is_err());
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "std")]
+//! Play back a trace recorded with [`crate::memory::TraceRecorder`], e.g. on a
+//! device, so the same sequence of register accesses can be reproduced in
+//! tests.
+use crate::memory::{Access, AccessHook, BufferMemoryMapper, MemoryMapper, TraceGuard};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// An access that did not match the trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The access is not the next one in the trace (`None` if the trace was
+    /// already done).
+    Mismatch {
+        expected: Option<Access>,
+        actual: Access,
+    },
+
+    /// The trace was not played entirely. Contains the number of accesses left.
+    Incomplete(usize),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Mismatch {
+                expected: Some(expected),
+                actual,
+            } => write!(f, "Expected access `{expected}`, got `{actual}`"),
+            ReplayError::Mismatch {
+                expected: None,
+                actual,
+            } => write!(f, "Unexpected access `{actual}` after the end of the trace"),
+            ReplayError::Incomplete(left) => write!(f, "{left} accesses were not replayed"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Default)]
+struct Replay {
+    accesses: VecDeque<Access>,
+    errors: Vec<ReplayError>,
+}
+
+impl AccessHook for Replay {
+    fn access(&mut self, actual: Access) -> u64 {
+        let expected = self.accesses.pop_front();
+        match expected {
+            Some(expected) if expected.matches(&actual) => expected.value,
+            expected => {
+                self.errors.push(ReplayError::Mismatch { expected, actual });
+                actual.value
+            }
+        }
+    }
+}
+
+/// A memory mapper over a buffer whose registers read as the values of a
+/// trace. Writes are checked against the trace, and mismatches are reported
+/// by [`Self::finish`].
+pub struct ReplayMemoryMapper {
+    // Detach before the buffer is freed.
+    _guard: TraceGuard,
+    buffer: BufferMemoryMapper,
+    replay: Arc<Mutex<Replay>>,
+}
+
+impl ReplayMemoryMapper {
+    /// Replay `accesses` over `size` bytes mapped at `physical_address`.
+    pub fn new(
+        physical_address: usize,
+        size: usize,
+        accesses: impl IntoIterator<Item = Access>,
+    ) -> Self {
+        let buffer = BufferMemoryMapper::new(size);
+        let replay = Arc::new(Mutex::new(Replay {
+            accesses: accesses.into_iter().collect(),
+            errors: Vec::new(),
+        }));
+
+        Self {
+            _guard: crate::memory::attach(&buffer, physical_address, replay.clone()),
+            buffer,
+            replay,
+        }
+    }
+
+    /// The number of accesses left in the trace.
+    pub fn remaining(&self) -> usize {
+        self.replay.lock().unwrap().accesses.len()
+    }
+
+    /// Check that every access matched the trace and that the whole trace was
+    /// played. Returns the first error.
+    pub fn finish(&self) -> Result<(), ReplayError> {
+        let replay = self.replay.lock().unwrap();
+        match replay.errors.first() {
+            Some(error) => Err(error.clone()),
+            None if !replay.accesses.is_empty() => {
+                Err(ReplayError::Incomplete(replay.accesses.len()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl MemoryMapper for ReplayMemoryMapper {
+    fn create(_address: usize, _size: usize) -> Result<Self, &'static str>
+    where
+        Self: Sized,
+    {
+        Err("ReplayMemoryMapper must be created with ReplayMemoryMapper::new")
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn as_ptr<T>(&self) -> *const T {
+        self.buffer.as_ptr()
+    }
+
+    fn as_mut_ptr<T>(&mut self) -> *mut T {
+        self.buffer.as_mut_ptr()
+    }
+}
+
+#[test]
+fn replay_trace() {
+    let trace = "\
+        0 W 0xFF706010 4 0x1\n\
+        10 R 0xFF706014 4 0xCAFE\n\
+        20 R 0xFF706014 4 0xBEEF\n";
+    let accesses = crate::memory::read_trace(trace.as_bytes()).unwrap();
+    let mut soc = crate::SocFpga::create_for_replay(accesses.clone());
+
+    soc.regs_mut().set_gpo(1);
+    assert_eq!(soc.regs().gpi(), 0xCAFE);
+    assert_eq!(soc.memory.remaining(), 1);
+    assert_eq!(soc.memory.finish(), Err(ReplayError::Incomplete(1)));
+    assert_eq!(soc.regs().gpi(), 0xBEEF);
+    assert_eq!(soc.memory.finish(), Ok(()));
+
+    // A different write is reported.
+    let mut soc = crate::SocFpga::create_for_replay(accesses);
+    soc.regs_mut().set_gpo(2);
+    assert!(matches!(
+        soc.memory.finish(),
+        Err(ReplayError::Mismatch {
+            expected: Some(Access { value: 1, .. }),
+            actual: Access { value: 2, .. },
+        })
+    ));
+}
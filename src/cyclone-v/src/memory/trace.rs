@@ -0,0 +1,476 @@
+//! Tracing of register accesses. The accessors generated by
+//! [`crate::declare_volatile_struct`] report every read and write to hooks
+//! attached to the memory range the register is in, which can record them
+//! (see [`TraceRecorder`]) or replace the values read (see
+//! [`crate::memory::ReplayMemoryMapper`]).
+//!
+//! Only register accessors are traced. Memory accessed through raw pointers
+//! (e.g. the FPGA manager data port or on-chip RAM) is not.
+pub use hooks::*;
+
+/// Without `std`, there is nothing to attach hooks to and accessors are not
+/// traced.
+#[cfg(not(feature = "std"))]
+mod hooks {
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn on_read<T>(_ptr: *const T, value: T) -> T {
+        value
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn on_write<T>(_ptr: *const T, _value: &T) {}
+}
+
+#[cfg(feature = "std")]
+mod hooks {
+    use crate::memory::MemoryMapper;
+    use std::collections::VecDeque;
+    use std::fmt;
+    use std::io::{self, BufRead, Write};
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// The number of accesses kept by a [`TraceRecorder`] by default.
+    pub const DEFAULT_TRACE_CAPACITY: usize = 65536;
+
+    /// Whether a register was read or written.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AccessKind {
+        Read,
+        Write,
+    }
+
+    /// A single register access.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Access {
+        /// When the access happened, since the recorder was created.
+        pub time: Duration,
+        pub kind: AccessKind,
+        /// The physical address of the register.
+        pub address: usize,
+        /// The size of the access, in bytes.
+        pub size: u8,
+        pub value: u64,
+    }
+
+    impl Access {
+        /// Whether two accesses are the same, ignoring when they happened.
+        /// Values of reads are not compared, as they come from the hardware.
+        pub fn matches(&self, other: &Access) -> bool {
+            self.kind == other.kind
+                && self.address == other.address
+                && self.size == other.size
+                && (self.kind == AccessKind::Read || self.value == other.value)
+        }
+    }
+
+    /// One access per line, e.g. `1500 W 0xFF706010 4 0x1234`, with the time
+    /// in nanoseconds.
+    impl fmt::Display for Access {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let kind = match self.kind {
+                AccessKind::Read => 'R',
+                AccessKind::Write => 'W',
+            };
+            write!(
+                f,
+                "{} {} {:#010X} {} {:#X}",
+                self.time.as_nanos(),
+                kind,
+                self.address,
+                self.size,
+                self.value
+            )
+        }
+    }
+
+    impl FromStr for Access {
+        type Err = &'static str;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            fn hex(s: &str) -> Result<u64, &'static str> {
+                let s = s
+                    .strip_prefix("0x")
+                    .or_else(|| s.strip_prefix("0X"))
+                    .unwrap_or(s);
+                u64::from_str_radix(s, 16).map_err(|_| "Invalid hexadecimal number")
+            }
+
+            let mut parts = s.split_whitespace();
+            let mut next = || parts.next().ok_or("Missing field in access");
+            let time = next()?.parse::<u64>().map_err(|_| "Invalid time")?;
+            let kind = match next()? {
+                "R" => AccessKind::Read,
+                "W" => AccessKind::Write,
+                _ => return Err("Invalid access kind"),
+            };
+            let address = hex(next()?)? as usize;
+            let size = next()?.parse::<u8>().map_err(|_| "Invalid size")?;
+            let value = hex(next()?)?;
+
+            Ok(Access {
+                time: Duration::from_nanos(time),
+                kind,
+                address,
+                size,
+                value,
+            })
+        }
+    }
+
+    /// Read a trace written by [`TraceRecorder`], skipping empty lines and
+    /// comments starting with `#`.
+    pub fn read_trace(reader: impl BufRead) -> io::Result<Vec<Access>> {
+        let mut accesses = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            accesses.push(
+                line.parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            );
+        }
+        Ok(accesses)
+    }
+
+    /// Receives the accesses to a memory range.
+    pub trait AccessHook: Send {
+        /// Called for every access. For reads, returns the value the register
+        /// should read as (normally `access.value`).
+        fn access(&mut self, access: Access) -> u64;
+    }
+
+    struct Region {
+        id: usize,
+        start: usize,
+        len: usize,
+        physical: usize,
+        hook: Arc<Mutex<dyn AccessHook>>,
+    }
+
+    static REGIONS: Mutex<Vec<Region>> = Mutex::new(Vec::new());
+    static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    /// Keeps a hook attached to a memory range. The hook is detached when
+    /// this is dropped.
+    #[derive(Debug)]
+    #[must_use = "the hook is detached when the guard is dropped"]
+    pub struct TraceGuard {
+        id: usize,
+    }
+
+    impl Drop for TraceGuard {
+        fn drop(&mut self) {
+            let mut regions = REGIONS.lock().unwrap_or_else(|e| e.into_inner());
+            let len = regions.len();
+            regions.retain(|r| r.id != self.id);
+            if regions.len() != len {
+                ACTIVE.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Attach a hook to the memory mapped by `memory`, which starts at
+    /// `physical_address`.
+    pub fn attach(
+        memory: &impl MemoryMapper,
+        physical_address: usize,
+        hook: Arc<Mutex<dyn AccessHook>>,
+    ) -> TraceGuard {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut regions = REGIONS.lock().unwrap_or_else(|e| e.into_inner());
+        regions.push(Region {
+            id,
+            start: memory.as_ptr::<u8>() as usize,
+            len: memory.len(),
+            physical: physical_address,
+            hook,
+        });
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        TraceGuard { id }
+    }
+
+    /// Call the hook of the region containing `ptr`, once per word of `bytes`
+    /// (registers larger than 8 bytes are arrays of 32-bit registers).
+    fn dispatch(kind: AccessKind, ptr: usize, bytes: &mut [u8]) {
+        let found = {
+            let regions = REGIONS.lock().unwrap_or_else(|e| e.into_inner());
+            regions
+                .iter()
+                .find(|r| ptr >= r.start && ptr < r.start + r.len)
+                .map(|r| (r.physical + (ptr - r.start), r.hook.clone()))
+        };
+        let Some((address, hook)) = found else {
+            return;
+        };
+
+        let word = if bytes.len() <= 8 { bytes.len() } else { 4 };
+        let mut hook = hook.lock().unwrap_or_else(|e| e.into_inner());
+        for (i, chunk) in bytes.chunks_mut(word).enumerate() {
+            let mut raw = [0u8; 8];
+            raw[..chunk.len()].copy_from_slice(chunk);
+            let value = hook.access(Access {
+                time: Duration::ZERO,
+                kind,
+                address: address + i * word,
+                size: chunk.len() as u8,
+                value: u64::from_le_bytes(raw),
+            });
+            if kind == AccessKind::Read {
+                chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+            }
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn on_read<T>(ptr: *const T, mut value: T) -> T {
+        if ACTIVE.load(Ordering::Relaxed) != 0 {
+            let bytes = unsafe {
+                std::slice::from_raw_parts_mut(
+                    &mut value as *mut T as *mut u8,
+                    std::mem::size_of::<T>(),
+                )
+            };
+            dispatch(AccessKind::Read, ptr as usize, bytes);
+        }
+        value
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn on_write<T>(ptr: *const T, value: &T) {
+        if ACTIVE.load(Ordering::Relaxed) != 0 {
+            // Writes are not changed, so a copy of the value is used.
+            let mut bytes = unsafe {
+                std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>())
+            }
+            .to_vec();
+            dispatch(AccessKind::Write, ptr as usize, &mut bytes);
+        }
+    }
+
+    struct Recorder {
+        start: Instant,
+        capacity: usize,
+        accesses: VecDeque<Access>,
+        writer: Option<Box<dyn Write + Send>>,
+    }
+
+    impl AccessHook for Recorder {
+        fn access(&mut self, mut access: Access) -> u64 {
+            access.time = self.start.elapsed();
+            if let Some(writer) = self.writer.as_mut() {
+                if writeln!(writer, "{access}").is_err() {
+                    // Keep recording in memory if the file cannot be written.
+                    self.writer = None;
+                }
+            }
+            if self.accesses.len() == self.capacity {
+                self.accesses.pop_front();
+            }
+            self.accesses.push_back(access);
+            access.value
+        }
+    }
+
+    impl Drop for Recorder {
+        fn drop(&mut self) {
+            if let Some(writer) = self.writer.as_mut() {
+                let _ = writer.flush();
+            }
+        }
+    }
+
+    /// Records the last accesses in a ring buffer, and optionally every access
+    /// to a writer (e.g. a file). Clones share the same recording.
+    #[derive(Clone)]
+    pub struct TraceRecorder(Arc<Mutex<Recorder>>);
+
+    impl fmt::Debug for TraceRecorder {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("TraceRecorder").finish_non_exhaustive()
+        }
+    }
+
+    impl Default for TraceRecorder {
+        fn default() -> Self {
+            Self::new(DEFAULT_TRACE_CAPACITY)
+        }
+    }
+
+    impl TraceRecorder {
+        /// A recorder keeping the last `capacity` accesses.
+        pub fn new(capacity: usize) -> Self {
+            Self(Arc::new(Mutex::new(Recorder {
+                start: Instant::now(),
+                capacity: capacity.max(1),
+                accesses: VecDeque::new(),
+                writer: None,
+            })))
+        }
+
+        /// A recorder that also writes every access to `writer`, one per line.
+        /// The writer is flushed when the last clone of the recorder is
+        /// dropped, or with [`Self::flush`].
+        pub fn with_writer(capacity: usize, writer: impl Write + Send + 'static) -> Self {
+            let this = Self::new(capacity);
+            this.lock().writer = Some(Box::new(writer));
+            this
+        }
+
+        fn lock(&self) -> std::sync::MutexGuard<'_, Recorder> {
+            self.0.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        /// Record the accesses to the memory mapped by `memory`, which starts
+        /// at `physical_address`, until the guard is dropped.
+        pub fn attach(&self, memory: &impl MemoryMapper, physical_address: usize) -> TraceGuard {
+            attach(memory, physical_address, self.0.clone())
+        }
+
+        /// The accesses in the ring buffer, oldest first.
+        pub fn accesses(&self) -> Vec<Access> {
+            self.lock().accesses.iter().copied().collect()
+        }
+
+        pub fn clear(&self) {
+            self.lock().accesses.clear();
+        }
+
+        pub fn flush(&self) -> io::Result<()> {
+            match self.lock().writer.as_mut() {
+                Some(writer) => writer.flush(),
+                None => Ok(()),
+            }
+        }
+
+        /// Write the accesses in the ring buffer, in the format read by
+        /// [`read_trace`].
+        pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+            for access in self.lock().accesses.iter() {
+                writeln!(writer, "{access}")?;
+            }
+            Ok(())
+        }
+    }
+
+    /// A memory mapper recording every register access to its memory. The
+    /// recorder can be shared with other mappers, to get a single trace.
+    pub struct TracingMemoryMapper<M: MemoryMapper> {
+        // Detach before the inner mapper is unmapped.
+        _guard: TraceGuard,
+        inner: M,
+        recorder: TraceRecorder,
+    }
+
+    impl<M: MemoryMapper> TracingMemoryMapper<M> {
+        pub fn new(inner: M, physical_address: usize, recorder: TraceRecorder) -> Self {
+            Self {
+                _guard: recorder.attach(&inner, physical_address),
+                inner,
+                recorder,
+            }
+        }
+
+        pub fn recorder(&self) -> &TraceRecorder {
+            &self.recorder
+        }
+
+        pub fn inner(&self) -> &M {
+            &self.inner
+        }
+    }
+
+    impl<M: MemoryMapper> MemoryMapper for TracingMemoryMapper<M> {
+        fn create(address: usize, size: usize) -> Result<Self, &'static str> {
+            Ok(Self::new(
+                M::create(address, size)?,
+                address,
+                TraceRecorder::default(),
+            ))
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn as_ptr<T>(&self) -> *const T {
+            self.inner.as_ptr()
+        }
+
+        fn as_mut_ptr<T>(&mut self) -> *mut T {
+            self.inner.as_mut_ptr()
+        }
+    }
+
+    #[test]
+    fn record_accesses() {
+        let memory = crate::memory::BufferMemoryMapper::new(crate::ranges::BASE.len());
+        let memory =
+            TracingMemoryMapper::new(memory, crate::ranges::BASE.start, TraceRecorder::new(2));
+        let mut soc = crate::SocFpga::new(memory);
+
+        soc.regs_mut().set_gpo(0x1234);
+        assert_eq!(soc.regs().gpo(), 0x1234);
+        soc.regs_mut().update_gpo(|gpo| *gpo |= 0x10000);
+
+        // Only the last 2 accesses are kept: the read and write of the update.
+        let accesses = soc.memory.recorder().accesses();
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(
+            (
+                accesses[0].kind,
+                accesses[0].address,
+                accesses[0].size,
+                accesses[0].value
+            ),
+            (AccessKind::Read, 0xFF706010, 4, 0x1234)
+        );
+        assert_eq!(
+            (accesses[1].kind, accesses[1].value),
+            (AccessKind::Write, 0x11234)
+        );
+
+        // Arrays are split in 32-bit registers.
+        soc.memory.recorder().clear();
+        let _ = soc.dmac().periph_id();
+        let accesses = soc.memory.recorder().accesses();
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(accesses[1].address, 0xFFE01FEC);
+
+        // Not traced once the mapper is gone.
+        let recorder = soc.memory.recorder().clone();
+        drop(soc);
+        let mut soc = crate::SocFpga::create_for_test();
+        soc.regs_mut().set_gpo(0);
+        assert_eq!(recorder.accesses().len(), 2);
+    }
+
+    #[test]
+    fn access_text_format() {
+        let access = Access {
+            time: Duration::from_nanos(1500),
+            kind: AccessKind::Write,
+            address: 0xFF706010,
+            size: 4,
+            value: 0x1234,
+        };
+        assert_eq!(access.to_string(), "1500 W 0xFF706010 4 0x1234");
+        assert_eq!(access.to_string().parse::<Access>(), Ok(access));
+
+        let trace = "# Comment\n\n1500 W 0xFF706010 4 0x1234\n2000 R 0xFF706014 4 0x0\n";
+        let accesses = read_trace(trace.as_bytes()).unwrap();
+        assert_eq!(accesses.len(), 2);
+        assert_eq!(accesses[1].kind, AccessKind::Read);
+        assert!(read_trace("1500 X 0x0 4 0x0".as_bytes()).is_err());
+    }
+}
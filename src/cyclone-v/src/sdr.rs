@@ -2,7 +2,12 @@ use memoffset::offset_of;
 use static_assertions::const_assert_eq;
 
 pub mod ctrlcfg;
+pub mod diagnostics;
+pub mod dramaddrw;
+pub mod dramintr;
+pub mod dramsts;
 pub mod fpgaportrst;
+pub mod memtest;
 
 crate::declare_volatile_struct! {
     /// Address map for the SDRAM Interface registers
@@ -34,7 +39,7 @@ crate::declare_volatile_struct! {
         [padding] _pad_0x501c_0x502c: [u32; 4],
 
         /// DRAM Address Widths Register
-        dramaddrw: u32,
+        dramaddrw: dramaddrw::DramAddrW,
 
         /// DRAM Interface Data Width Register
         dramifwidth: u32,
@@ -42,10 +47,10 @@ crate::declare_volatile_struct! {
         [padding] _pad_0x5034_0x5038: [u32; 1],
 
         /// DRAM Status Register
-        dramsts: u32,
+        [readonly] dramsts: dramsts::DramSts,
 
         /// ECC Interrupt Register
-        dramintr: u32,
+        dramintr: dramintr::DramIntr,
 
         /// ECC Single Bit Error Count Register
         sbecount: u32,
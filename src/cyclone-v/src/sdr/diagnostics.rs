@@ -0,0 +1,162 @@
+//! Reports of the DDR configuration and ECC statistics of the SDRAM
+//! controller, to diagnose faulty boards.
+use crate::sdr::ctrlcfg::MemType;
+use crate::sdr::SdramCtrl;
+
+/// The DDR configuration, as programmed by the preloader.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdramConfig {
+    /// The type of memory, or `None` if the controller is not configured.
+    pub memory_type: Option<MemType>,
+
+    /// Whether the PHY calibrated successfully.
+    pub calibrated: bool,
+
+    /// Whether ECC is enabled.
+    pub ecc: bool,
+
+    /// The width of the interface in bits, including ECC bits.
+    pub interface_width: u32,
+
+    /// The width of the data in bits, without ECC bits.
+    pub data_width: u32,
+
+    /// The number of column address bits.
+    pub column_bits: u32,
+
+    /// The number of row address bits.
+    pub row_bits: u32,
+
+    /// The number of bank address bits.
+    pub bank_bits: u32,
+
+    /// The number of chip selects.
+    pub chip_selects: u32,
+
+    /// The CAS latency, in clock cycles.
+    pub cas_latency: u32,
+
+    /// The CAS write latency, in clock cycles.
+    pub cas_write_latency: u32,
+}
+
+impl SdramConfig {
+    /// The size of the memory, in bytes.
+    pub fn size(&self) -> u64 {
+        let words = 1u64 << (self.column_bits + self.row_bits + self.bank_bits);
+        words * self.chip_selects as u64 * (self.data_width / 8) as u64
+    }
+}
+
+/// The ECC error counters. The counters saturate at 255.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EccStats {
+    /// The number of single bit errors, which were corrected.
+    pub single_bit_errors: u32,
+
+    /// The number of double bit errors, which cannot be corrected.
+    pub double_bit_errors: u32,
+
+    /// The address of the last error.
+    pub error_address: u32,
+
+    /// The number of corrections that could not be written back.
+    pub dropped_corrections: u32,
+
+    /// The address of the last dropped correction.
+    pub dropped_address: u32,
+}
+
+impl EccStats {
+    /// Whether any error was detected.
+    pub fn has_errors(&self) -> bool {
+        self.single_bit_errors != 0 || self.double_bit_errors != 0
+    }
+}
+
+impl SdramCtrl {
+    /// The DDR configuration of the controller.
+    pub fn config(&self) -> SdramConfig {
+        let ctrlcfg = self.ctrlcfg();
+        let addrw = self.dramaddrw();
+        let timing1 = self.dramtiming1();
+        let interface_width = self.dramifwidth() & 0xFF;
+        let ecc = ctrlcfg.eccen();
+
+        SdramConfig {
+            // Read the bits directly, as `MemType` does not cover unconfigured controllers.
+            memory_type: match ctrlcfg.0 & 0x7 {
+                1 => Some(MemType::Ddr2),
+                2 => Some(MemType::Ddr3),
+                4 => Some(MemType::LpDdr2),
+                _ => None,
+            },
+            calibrated: self.dramsts().calsuccess(),
+            ecc,
+            interface_width,
+            // 24 and 40 bits interfaces have 8 bits of ECC.
+            data_width: if interface_width % 16 == 8 {
+                interface_width - 8
+            } else {
+                interface_width
+            },
+            column_bits: addrw.colbits(),
+            row_bits: addrw.rowbits(),
+            bank_bits: addrw.bankbits(),
+            chip_selects: addrw.csbits() + 1,
+            cas_latency: (timing1 >> 9) & 0x1F,
+            cas_write_latency: timing1 & 0xF,
+        }
+    }
+
+    /// The ECC error counters.
+    pub fn ecc_stats(&self) -> EccStats {
+        EccStats {
+            single_bit_errors: self.sbecount() & 0xFF,
+            double_bit_errors: self.dbecount() & 0xFF,
+            error_address: self.erraddr(),
+            dropped_corrections: self.dropcount() & 0xFF,
+            dropped_address: self.dropaddr(),
+        }
+    }
+
+    /// Reset the ECC error counters and addresses.
+    pub fn clear_ecc_stats(&mut self) {
+        self.update_dramintr(|intr| intr.set_intrclr(true));
+    }
+}
+
+#[test]
+fn de10_nano_config() {
+    let mut soc = crate::SocFpga::create_for_test();
+    let sdr = soc.sdr_mut();
+
+    // The configuration of the DE10-Nano preloader: 1 GiB of DDR3, 32 bits.
+    sdr.update_ctrlcfg(|cfg| {
+        cfg.set_memtype(MemType::Ddr3);
+        cfg.set_membl(8);
+    });
+    sdr.update_dramaddrw(|addrw| {
+        addrw.set_colbits(10);
+        addrw.set_rowbits(15);
+        addrw.set_bankbits(3);
+    });
+    sdr.set_dramifwidth(32);
+    sdr.set_dramtiming1((11 << 9) | 8);
+
+    let config = soc.sdr().config();
+    assert_eq!(config.memory_type, Some(MemType::Ddr3));
+    assert!(!config.ecc);
+    assert!(!config.calibrated);
+    assert_eq!(config.data_width, 32);
+    assert_eq!(config.chip_selects, 1);
+    assert_eq!(config.cas_latency, 11);
+    assert_eq!(config.cas_write_latency, 8);
+    assert_eq!(config.size(), 1 << 30);
+
+    assert!(!soc.sdr().ecc_stats().has_errors());
+    soc.sdr_mut().set_sbecount(3);
+    assert_eq!(soc.sdr().ecc_stats().single_bit_errors, 3);
+    soc.sdr_mut().clear_ecc_stats();
+    assert!(soc.sdr().dramintr().intrclr());
+}
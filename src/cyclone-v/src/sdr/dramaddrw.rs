@@ -0,0 +1,20 @@
+use bitfield::bitfield;
+bitfield! {
+    /// This register configures the width of the various address fields of the DRAM.
+    pub struct DramAddrW(u32);
+    impl Debug;
+    u32;
+
+    /// This field defines the number of chip select address bits. Set this field to 0x0 for
+    /// single chip select and to 0x1 for two chip selects.
+    pub csbits, set_csbits: 15, 13;
+
+    /// The number of bank address bits for the memory devices in your memory interface.
+    pub bankbits, set_bankbits: 12, 10;
+
+    /// The number of row address bits for the memory devices in your memory interface.
+    pub rowbits, set_rowbits: 9, 5;
+
+    /// The number of column address bits for the memory devices in your memory interface.
+    pub colbits, set_colbits: 4, 0;
+}
@@ -0,0 +1,24 @@
+use bitfield::bitfield;
+bitfield! {
+    /// This register can enable, disable and clear the SDRAM error interrupts.
+    pub struct DramIntr(u32);
+    impl Debug;
+    u32;
+
+    /// Writing to this self-clearing bit clears the interrupt signal. Writing to this bit also
+    /// clears the error count and error address registers.
+    pub intrclr, set_intrclr: 4;
+
+    /// Set this bit to a one to mask interrupts for an ECC correction write back needing to be
+    /// dropped.
+    pub corrdropmask, set_corrdropmask: 3;
+
+    /// Mask the double bit error interrupt.
+    pub dbemask, set_dbemask: 2;
+
+    /// Mask the single bit error interrupt.
+    pub sbemask, set_sbemask: 1;
+
+    /// Enable the interrupt output.
+    pub intren, set_intren: 0;
+}
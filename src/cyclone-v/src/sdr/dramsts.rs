@@ -0,0 +1,22 @@
+use bitfield::bitfield;
+bitfield! {
+    /// This register provides the status of the calibration and ECC logic.
+    pub struct DramSts(u32);
+    impl Debug;
+    u32;
+
+    /// This bit is set to 1 when any auto-corrections have been dropped.
+    pub corrdrop, _: 4;
+
+    /// This bit is set to 1 when any ECC double bit errors are detected.
+    pub dbeerr, _: 3;
+
+    /// This bit is set to 1 when any ECC single bit errors are detected.
+    pub sbeerr, _: 2;
+
+    /// This bit is set to 1 if the PHY was unable to calibrate.
+    pub calfail, _: 1;
+
+    /// This bit will be set to 1 if the PHY was able to successfully calibrate.
+    pub calsuccess, _: 0;
+}
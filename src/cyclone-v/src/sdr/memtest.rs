@@ -0,0 +1,321 @@
+//! Memory tests, to find faulty SDRAM chips or add-ons. The tests write over
+//! the memory, so nothing else should use it while they run.
+use crate::memory::MemoryMapper;
+use core::fmt;
+use core::ops::Range;
+
+/// The number of bytes between two calls to the progress callback.
+const PROGRESS_STEP: usize = 1 << 20;
+
+/// A test of the memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryTest {
+    /// Walk a 1 through the data lines on the first word, then through the
+    /// address lines, to find lines that are stuck or shorted together.
+    WalkingOnes,
+
+    /// Write the offset of every word to itself, to find aliased addresses.
+    AddressInAddress,
+
+    /// Write pseudo-random values generated from a seed.
+    Random(u32),
+}
+
+/// The result of a failed memory test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryTestError {
+    /// A word read back differs from what was written. The offset is in bytes
+    /// from the start of the memory mapper.
+    Mismatch {
+        offset: usize,
+        expected: u32,
+        actual: u32,
+    },
+
+    /// The range is empty, not aligned on words, or outside of the mapping.
+    InvalidRange,
+
+    /// The test was cancelled by the progress callback.
+    Cancelled,
+}
+
+impl fmt::Display for MemoryTestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryTestError::Mismatch {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Memory error at offset {offset:#X}: expected {expected:#010X}, read {actual:#010X}"
+            ),
+            MemoryTestError::InvalidRange => f.write_str("Invalid memory range"),
+            MemoryTestError::Cancelled => f.write_str("Cancelled"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MemoryTestError {}
+
+/// The words under test.
+struct Words {
+    ptr: *mut u32,
+    offset: usize,
+    len: usize,
+}
+
+impl Words {
+    #[inline]
+    fn write(&mut self, i: usize, value: u32) {
+        unsafe { core::ptr::write_volatile(self.ptr.add(i), value) }
+    }
+
+    #[inline]
+    fn check(&self, i: usize, expected: u32) -> Result<(), MemoryTestError> {
+        let actual = unsafe { core::ptr::read_volatile(self.ptr.add(i)) };
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(MemoryTestError::Mismatch {
+                offset: self.offset + i * 4,
+                expected,
+                actual,
+            })
+        }
+    }
+}
+
+struct Progress<F: FnMut(usize, usize) -> bool> {
+    done: usize,
+    total: usize,
+    callback: F,
+}
+
+impl<F: FnMut(usize, usize) -> bool> Progress<F> {
+    fn report(&mut self) -> Result<(), MemoryTestError> {
+        if (self.callback)(self.done, self.total) {
+            Ok(())
+        } else {
+            Err(MemoryTestError::Cancelled)
+        }
+    }
+
+    /// Write every word with `value(i)`, then check them.
+    fn fill_and_check(
+        &mut self,
+        words: &mut Words,
+        mut value: impl FnMut(usize) -> u32,
+    ) -> Result<(), MemoryTestError> {
+        let step = PROGRESS_STEP / 4;
+        for i in 0..words.len {
+            words.write(i, value(i));
+            if i % step == step - 1 {
+                self.done += PROGRESS_STEP;
+                self.report()?;
+            }
+        }
+        self.done += (words.len % step) * 4;
+        self.report()?;
+
+        for i in 0..words.len {
+            words.check(i, value(i))?;
+            if i % step == step - 1 {
+                self.done += PROGRESS_STEP;
+                self.report()?;
+            }
+        }
+        self.done += (words.len % step) * 4;
+        self.report()
+    }
+}
+
+fn walking_ones(words: &mut Words) -> Result<(), MemoryTestError> {
+    const PATTERN: u32 = 0xAAAA_AAAA;
+    const ANTI_PATTERN: u32 = 0x5555_5555;
+
+    // Data lines.
+    for bit in 0..32 {
+        words.write(0, 1 << bit);
+        words.check(0, 1 << bit)?;
+    }
+
+    // Address lines, at every power of two.
+    let len = words.len;
+    let lines = (0..usize::BITS)
+        .map(|n| 1usize << n)
+        .take_while(move |i| *i < len);
+
+    for i in lines.clone() {
+        words.write(i, PATTERN);
+    }
+
+    // A line stuck high aliases the word with the first one.
+    words.write(0, ANTI_PATTERN);
+    for i in lines.clone() {
+        words.check(i, PATTERN)?;
+    }
+    words.write(0, PATTERN);
+
+    // A line stuck low or shorted to another aliases the word with others.
+    for i in lines.clone() {
+        words.write(i, ANTI_PATTERN);
+        words.check(0, PATTERN)?;
+        for j in lines.clone().filter(|j| *j != i) {
+            words.check(j, PATTERN)?;
+        }
+        words.write(i, PATTERN);
+    }
+
+    Ok(())
+}
+
+/// A xorshift generator, which is fast and good enough to find faulty bits.
+fn xorshift(state: &mut u32) -> u32 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    x
+}
+
+/// Run a memory test over a range of bytes of a memory mapper. `progress` is
+/// called regularly with the number of bytes tested and the total, and can
+/// return `false` to cancel the test.
+pub fn run(
+    memory: &mut impl MemoryMapper,
+    range: Range<usize>,
+    test: MemoryTest,
+    progress: impl FnMut(usize, usize) -> bool,
+) -> Result<(), MemoryTestError> {
+    if range.is_empty() || range.end > memory.len() || range.start % 4 != 0 || range.len() % 4 != 0
+    {
+        return Err(MemoryTestError::InvalidRange);
+    }
+
+    let mut words = Words {
+        ptr: unsafe { memory.as_mut_ptr::<u8>().add(range.start) as *mut u32 },
+        offset: range.start,
+        len: range.len() / 4,
+    };
+    let mut progress = Progress {
+        done: 0,
+        // Every word is written, then read.
+        total: range.len() * 2,
+        callback: progress,
+    };
+
+    match test {
+        MemoryTest::WalkingOnes => {
+            progress.total = 1;
+            progress.report()?;
+            walking_ones(&mut words)?;
+            progress.done = 1;
+            progress.report()
+        }
+        MemoryTest::AddressInAddress => {
+            let offset = range.start;
+            progress.fill_and_check(&mut words, |i| (offset + i * 4) as u32)
+        }
+        MemoryTest::Random(seed) => {
+            // The generator gets stuck on 0.
+            let seed = if seed == 0 { 1 } else { seed };
+            let mut state = seed;
+            let mut values = move |i: usize| {
+                if i == 0 {
+                    state = seed;
+                }
+                xorshift(&mut state)
+            };
+            progress.fill_and_check(&mut words, &mut values)
+        }
+    }
+}
+
+#[test]
+fn memory_tests() {
+    let mut memory = crate::memory::BufferMemoryMapper::new(3 << 20);
+    let calls = core::cell::Cell::new(0);
+    let last = core::cell::Cell::new((0, 0));
+    let mut progress = |done: usize, total: usize| {
+        calls.set(calls.get() + 1);
+        last.set((done, total));
+        true
+    };
+
+    for test in [
+        MemoryTest::WalkingOnes,
+        MemoryTest::AddressInAddress,
+        MemoryTest::Random(0),
+        MemoryTest::Random(1234),
+    ] {
+        assert_eq!(
+            run(&mut memory, 0x100..0x300000, test, &mut progress),
+            Ok(())
+        );
+        let (done, total) = last.get();
+        assert_eq!(done, total);
+    }
+    // The write and read passes of 3 MiB are reported every MiB and at their end.
+    assert_eq!(calls.get(), 2 + 3 * 6);
+
+    // The memory holds the values of the last test.
+    let mut state = 1234;
+    let bytes = memory.as_range(0x100..0x104);
+    let first = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    assert_eq!(first, xorshift(&mut state));
+
+    assert_eq!(
+        run(
+            &mut memory,
+            0x100..0x102,
+            MemoryTest::WalkingOnes,
+            |_, _| true
+        ),
+        Err(MemoryTestError::InvalidRange)
+    );
+    assert_eq!(
+        run(&mut memory, 0..0x400000, MemoryTest::WalkingOnes, |_, _| {
+            true
+        }),
+        Err(MemoryTestError::InvalidRange)
+    );
+    assert_eq!(
+        run(
+            &mut memory,
+            0..0x300000,
+            MemoryTest::AddressInAddress,
+            |done, _| done < 1 << 20
+        ),
+        Err(MemoryTestError::Cancelled)
+    );
+}
+
+#[test]
+fn find_errors() {
+    let mut memory = crate::memory::BufferMemoryMapper::new(0x1000);
+    let ptr = memory.as_mut_ptr::<u8>();
+
+    // A bit flips between the write and the read.
+    let result = run(
+        &mut memory,
+        0..0x1000,
+        MemoryTest::AddressInAddress,
+        |done, total| {
+            if done == total / 2 {
+                unsafe { *ptr.add(0x804) ^= 0x10 };
+            }
+            true
+        },
+    );
+    assert_eq!(
+        result,
+        Err(MemoryTestError::Mismatch {
+            offset: 0x804,
+            expected: 0x804,
+            actual: 0x814,
+        })
+    );
+}
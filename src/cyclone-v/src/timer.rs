@@ -0,0 +1,140 @@
+//! The timers of the HPS, Synopsys DesignWare APB timers. The OSC1 timers
+//! count down at the frequency of `eosc1_clk`.
+//! See https://www.intel.com/content/www/us/en/programmable/hps/cyclone-v/sfo1410067808053.html
+use bitfield::bitfield;
+use memoffset::offset_of;
+use static_assertions::const_assert_eq;
+
+/// How the timer restarts when it reaches zero.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimerMode {
+    /// The timer restarts from `0xFFFFFFFF`.
+    FreeRunning = 0,
+
+    /// The timer restarts from its load count.
+    UserDefined = 1,
+}
+
+impl From<u32> for TimerMode {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => TimerMode::FreeRunning,
+            1 => TimerMode::UserDefined,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<TimerMode> for u32 {
+    fn from(value: TimerMode) -> Self {
+        value as Self
+    }
+}
+
+bitfield! {
+    /// Timer1 Control Register.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct TimerControl(u32);
+    impl Debug;
+    u32;
+
+    /// Mask the interrupt of the timer.
+    pub timer1_interrupt_mask, set_timer1_interrupt_mask: 2;
+
+    /// How the timer restarts.
+    pub from into TimerMode, timer1_mode, set_timer1_mode: 1, 1;
+
+    /// Enable the timer.
+    pub timer1_enable, set_timer1_enable: 0;
+}
+
+crate::declare_volatile_struct! {
+    /// Registers in a timer module.
+    #[repr(C)]
+    pub struct Timer {
+        /// Timer1 Load Count Register
+        timer1loadcount: u32,
+        /// Timer1 Current Value Register
+        [readonly] timer1currentval: u32,
+        /// Timer1 Control Register
+        timer1controlreg: TimerControl,
+        /// Timer1 End-of-Interrupt Register. Reading it clears the interrupt.
+        [readonly] timer1eoi: u32,
+        /// Timer1 Interrupt Status Register
+        [readonly] timer1intstat: u32,
+        [padding] _pad_0x14_0xa0: [u32; 35],
+
+        /// Timers Interrupt Status Register
+        [readonly] timersintstat: u32,
+        /// Timers End-of-Interrupt Register
+        [readonly] timerseoi: u32,
+        /// Timers Raw Interrupt Status Register
+        [readonly] timersrawintstat: u32,
+        /// Timers Component Version Register
+        [readonly] timerscompversion: u32,
+    }
+}
+
+const_assert_eq!(offset_of!(Timer, timersintstat), 0xA0);
+const_assert_eq!(core::mem::size_of::<Timer>(), 0xB0);
+
+impl Timer {
+    /// Start counting down from `count`, with its interrupt masked. The load
+    /// count can only be changed while the timer is stopped, so it is stopped
+    /// first.
+    pub fn start(&mut self, count: u32, mode: TimerMode) {
+        self.stop();
+        self.set_timer1loadcount(count);
+
+        let mut control = TimerControl(0);
+        control.set_timer1_mode(mode);
+        control.set_timer1_interrupt_mask(true);
+        control.set_timer1_enable(true);
+        self.set_timer1controlreg(control);
+    }
+
+    /// Stop the timer.
+    pub fn stop(&mut self) {
+        self.update_timer1controlreg(|control| control.set_timer1_enable(false));
+    }
+
+    /// Whether the timer is running.
+    pub fn is_running(&self) -> bool {
+        self.timer1controlreg().timer1_enable()
+    }
+
+    /// The current value of the timer.
+    pub fn current(&self) -> u32 {
+        self.timer1currentval()
+    }
+
+    /// Whether the timer reached zero since the interrupt was last cleared,
+    /// clearing it.
+    pub fn take_expired(&mut self) -> bool {
+        if self.timersrawintstat() & 1 == 0 {
+            return false;
+        }
+        let _ = self.timer1eoi();
+        true
+    }
+}
+
+#[test]
+fn start_stop() {
+    let mut soc = crate::SocFpga::create_for_test();
+    let timer = soc.osc1timer1_mut();
+    timer.start(25_000_000, TimerMode::UserDefined);
+
+    assert!(timer.is_running());
+    assert_eq!(timer.timer1loadcount(), 25_000_000);
+    assert_eq!(timer.timer1controlreg().0, 0b111);
+    assert!(!timer.take_expired());
+
+    timer.stop();
+    assert!(!timer.is_running());
+    assert_eq!(
+        timer.timer1controlreg().timer1_mode(),
+        TimerMode::UserDefined
+    );
+    assert!(!soc.osc1timer0().is_running());
+}
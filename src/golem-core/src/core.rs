@@ -308,6 +308,28 @@ pub enum Error {
 
     #[error("An error occurred: {0}")]
     AnyError(#[from] Box<dyn std::error::Error>),
+
+    /// The core stopped answering, and needs to be unloaded.
+    #[error("The core stopped responding: {0}")]
+    CoreNotResponding(String),
+
+    /// The user cancelled the operation.
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// Whether the core stopped responding. Other errors can be recovered
+    /// from, but a core that is not responding should be unloaded.
+    pub fn is_core_not_responding(&self) -> bool {
+        matches!(self, Error::CoreNotResponding(_))
+    }
+}
+
+impl From<crate::progress::Cancelled> for Error {
+    fn from(_: crate::progress::Cancelled) -> Self {
+        Error::Cancelled
+    }
 }
 
 impl From<String> for Error {
@@ -489,7 +511,7 @@ impl GolemCore {
     }
 
     pub fn null() -> Self {
-        Self::new(NullCore)
+        Self::new(NullCore::default())
     }
 }
 
@@ -9,8 +9,22 @@ use crate::inputs::keyboard::ScancodeSet;
 use crate::inputs::{Button, Scancode};
 use crate::Core;
 
-/// A Golem Core that does nothing.
-pub struct NullCore;
+/// A Golem Core that does nothing. It keeps track of the keys and buttons
+/// held, so tests can check the inputs sent to a core.
+#[derive(Default)]
+pub struct NullCore {
+    keys: ScancodeSet,
+    gamepads: Vec<ButtonSet>,
+}
+
+impl NullCore {
+    fn gamepad_mut_(&mut self, index: usize) -> &mut ButtonSet {
+        if self.gamepads.len() <= index {
+            self.gamepads.resize(index + 1, ButtonSet::new());
+        }
+        &mut self.gamepads[index]
+    }
+}
 
 impl Core for NullCore {
     fn init(&mut self) -> Result<(), Error> {
@@ -57,36 +71,42 @@ impl Core for NullCore {
         Ok(())
     }
 
-    fn key_up(&mut self, _key: Scancode) -> Result<(), Error> {
+    fn key_up(&mut self, key: Scancode) -> Result<(), Error> {
+        self.keys.remove(key);
         Ok(())
     }
 
-    fn key_down(&mut self, _key: Scancode) -> Result<(), Error> {
+    fn key_down(&mut self, key: Scancode) -> Result<(), Error> {
+        self.keys.insert(key);
         Ok(())
     }
 
-    fn keys_set(&mut self, _keys: ScancodeSet) -> Result<(), Error> {
+    fn keys_set(&mut self, keys: ScancodeSet) -> Result<(), Error> {
+        self.keys = keys;
         Ok(())
     }
 
     fn keys(&self) -> Result<ScancodeSet, Error> {
-        Ok(ScancodeSet::new())
+        Ok(self.keys.clone())
     }
 
-    fn gamepad_button_up(&mut self, _index: usize, _button: Button) -> Result<(), Error> {
+    fn gamepad_button_up(&mut self, index: usize, button: Button) -> Result<(), Error> {
+        self.gamepad_mut_(index).remove(button);
         Ok(())
     }
 
-    fn gamepad_button_down(&mut self, _index: usize, _button: Button) -> Result<(), Error> {
+    fn gamepad_button_down(&mut self, index: usize, button: Button) -> Result<(), Error> {
+        self.gamepad_mut_(index).insert(button);
         Ok(())
     }
 
-    fn gamepad_buttons_set(&mut self, _index: usize, _buttons: ButtonSet) -> Result<(), Error> {
+    fn gamepad_buttons_set(&mut self, index: usize, buttons: ButtonSet) -> Result<(), Error> {
+        *self.gamepad_mut_(index) = buttons;
         Ok(())
     }
 
-    fn gamepad_buttons(&self, _index: usize) -> Result<Option<ButtonSet>, Error> {
-        Ok(None)
+    fn gamepad_buttons(&self, index: usize) -> Result<Option<ButtonSet>, Error> {
+        Ok(self.gamepads.get(index).copied())
     }
 
     fn settings(&self) -> Result<CoreSettings, Error> {
@@ -2,7 +2,7 @@ pub use core::Core;
 pub use core::GolemCore;
 
 pub mod core;
+pub mod progress;
 pub mod runner;
 
 pub mod inputs;
-
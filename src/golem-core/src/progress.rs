@@ -0,0 +1,175 @@
+//! Progress of long operations, like loading a core and its ROM, with a way
+//! for the user to cancel them.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often the listener of a [`ProgressToken`] is called at most, except for
+/// the start and end of a stage.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What is being loaded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, strum::IntoStaticStr)]
+#[strum(serialize_all = "camelCase")]
+pub enum LoadStage {
+    /// Programming the FPGA with the core.
+    #[default]
+    Core,
+
+    /// Sending the ROM to the core.
+    Rom,
+
+    /// Sending another file (e.g. a BIOS) to the core.
+    File,
+
+    /// Mounting files as SD cards of the core.
+    Mount,
+}
+
+/// The progress of a stage. `total` is 0 if the size is not known.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub stage: LoadStage,
+    pub current: usize,
+    pub total: usize,
+}
+
+/// The operation was cancelled by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Cancelled")]
+pub struct Cancelled;
+
+impl From<Cancelled> for String {
+    fn from(value: Cancelled) -> Self {
+        value.to_string()
+    }
+}
+
+/// A handle to cancel an operation from outside its progress listener.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Passed to long operations to report their progress. The listener returns
+/// `false` to cancel, in which case the operation stops at the next update
+/// with [`Cancelled`].
+#[derive(Default)]
+pub struct ProgressToken<'a> {
+    cancel: CancelHandle,
+    listener: Option<Box<dyn FnMut(&LoadProgress) -> bool + 'a>>,
+    progress: LoadProgress,
+    last_report: Option<Instant>,
+}
+
+impl std::fmt::Debug for ProgressToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressToken")
+            .field("cancel", &self.cancel)
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl<'a> ProgressToken<'a> {
+    pub fn new(listener: impl FnMut(&LoadProgress) -> bool + 'a) -> Self {
+        Self {
+            listener: Some(Box::new(listener)),
+            ..Default::default()
+        }
+    }
+
+    /// A handle that can cancel this operation, e.g. from another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// The last progress reported.
+    pub fn progress(&self) -> &LoadProgress {
+        &self.progress
+    }
+
+    /// Start a new stage. `total` is 0 if the size is not known.
+    pub fn start(&mut self, stage: LoadStage, total: usize) -> Result<(), Cancelled> {
+        self.progress = LoadProgress {
+            stage,
+            current: 0,
+            total,
+        };
+        self.report(true)
+    }
+
+    /// Update the progress of the current stage.
+    pub fn update(&mut self, current: usize) -> Result<(), Cancelled> {
+        self.progress.current = current;
+        let done = self.progress.total != 0 && current >= self.progress.total;
+        self.report(done)
+    }
+
+    /// Check whether the operation was cancelled, without reporting progress.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn report(&mut self, force: bool) -> Result<(), Cancelled> {
+        let now = Instant::now();
+        let due = self
+            .last_report
+            .map_or(true, |last| now.duration_since(last) >= PROGRESS_INTERVAL);
+
+        if let Some(listener) = self.listener.as_mut() {
+            if (force || due) && !listener(&self.progress) {
+                self.cancel.cancel();
+            }
+        }
+        if force || due {
+            self.last_report = Some(now);
+        }
+        self.check()
+    }
+}
+
+#[test]
+fn progress_token() {
+    let mut reports = Vec::new();
+    let mut token = ProgressToken::new(|p| {
+        reports.push(*p);
+        p.current < 300
+    });
+
+    token.start(LoadStage::Rom, 1000).unwrap();
+    // Too soon to be reported.
+    token.update(100).unwrap();
+    std::thread::sleep(PROGRESS_INTERVAL);
+    token.update(200).unwrap();
+    token.update(1000).unwrap_err();
+    assert!(token.is_cancelled());
+    drop(token);
+
+    assert_eq!(
+        reports.iter().map(|p| p.current).collect::<Vec<_>>(),
+        [0, 200, 1000]
+    );
+
+    // Without listener, only cancelled through its handle.
+    let mut token = ProgressToken::default();
+    token.start(LoadStage::Core, 0).unwrap();
+    token.cancel_handle().cancel();
+    assert_eq!(token.update(10), Err(Cancelled));
+}
@@ -1,7 +1,9 @@
 import { init as initBasic } from "./basic";
 import { init as initGames } from "./games";
+import { init as initMacros } from "./macros";
 
 export async function init() {
   await initBasic();
   await initGames();
+  await initMacros();
 }
@@ -0,0 +1,59 @@
+import * as core from "@:golem/core";
+import * as macros from "@:golem/macros";
+import { Commands, CoreCommandImpl, Games } from "$/services";
+
+/**
+ * The name a new recording is saved under, replacing the previous one.
+ */
+export const LAST_RECORDED_MACRO = "Last Recorded";
+
+/**
+ * The names macros are saved under for the running game. Cores launched
+ * without a game share their macros.
+ */
+export function macroOwner(golemCore: core.GolemCore): [string, string] {
+  // Names are used as file names, so they cannot contain separators.
+  const sanitize = (name: string) => name.replace(/[\/\\]/g, "_");
+  const game = Games.running();
+  return [sanitize(golemCore.name), sanitize(game?.name ?? "_")];
+}
+
+export class ToggleMacroRecordingCommand extends CoreCommandImpl {
+  key = "toggleMacroRecording";
+  label = "Start or stop recording a macro";
+  category = "Macros";
+  default = "Ctrl + 'R'";
+
+  async execute(golemCore: core.GolemCore) {
+    if (!macros.isRecording()) {
+      macros.startRecording();
+      return;
+    }
+
+    const recorded = macros.stopRecording();
+    if (recorded && recorded.events.length > 0) {
+      const [coreName, gameName] = macroOwner(golemCore);
+      macros.save(coreName, gameName, LAST_RECORDED_MACRO, recorded);
+    }
+  }
+}
+
+export class PlayLastMacroCommand extends CoreCommandImpl {
+  key = "playLastMacro";
+  label = "Play the last recorded macro";
+  category = "Macros";
+  default = "Ctrl + 'P'";
+
+  async execute(golemCore: core.GolemCore) {
+    const [coreName, gameName] = macroOwner(golemCore);
+    const recorded = macros.list(coreName, gameName)[LAST_RECORDED_MACRO];
+    if (recorded) {
+      macros.play(recorded);
+    }
+  }
+}
+
+export async function init() {
+  await Commands.register(ToggleMacroRecordingCommand);
+  await Commands.register(PlayLastMacroCommand);
+}
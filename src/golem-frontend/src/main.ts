@@ -21,6 +21,11 @@ import { settingsMenu } from "$/ui/settings";
 import { login } from "$/ui/login";
 import { downloadCenterMenu } from "$/ui/downloads";
 import { about } from "$/ui/about";
+import { autoboot } from "$/ui/autoboot";
+import { diagnosticsMenu } from "$/ui/diagnostics";
+
+// The boot core is only launched once, not every time the frontend restarts.
+let autobootChecked = false;
 
 // Polyfill for events.
 globalThis.performance = <any>{
@@ -44,6 +49,12 @@ async function debugMenu() {
           await ui.inputTester();
         },
       },
+      {
+        label: "System Diagnostics...",
+        select: async () => {
+          await diagnosticsMenu();
+        },
+      },
     ],
   });
 }
@@ -187,6 +198,17 @@ async function mainInner(): Promise<boolean> {
   ]);
   let startOn = await settings.startOn();
 
+  if (!autobootChecked) {
+    autobootChecked = true;
+    try {
+      if (await autoboot()) {
+        startOn = { kind: StartOnKind.MainMenu };
+      }
+    } catch (e) {
+      await ui.alert("Autoboot", `Could not launch the boot core: ${e}`);
+    }
+  }
+
   console.log("Starting on:", JSON.stringify(startOn));
   console.log("Date: ", new Date());
 
@@ -7,6 +7,7 @@ import { Catalog } from "./catalog";
 import { System } from "./system";
 import { sql } from "$/utils";
 import { coreOsdMenu } from "$/ui/menus/core_osd";
+import { confirmHardware } from "$/ui/hardware";
 
 export interface CoreRow extends Row {
   id: number;
@@ -207,6 +208,9 @@ export class Core {
     if (!this.rbfPath) {
       throw new Error("Core does not have an RBF path");
     }
+    if (!(await confirmHardware(this.rbfPath))) {
+      return;
+    }
 
     try {
       console.log(`Starting core: ${JSON.stringify(this)}`);
@@ -6,6 +6,8 @@ import { SqlExpression } from "@sqltags/core";
 import { User } from "../user";
 import { PickGameOptions } from "$/ui/games";
 import { Core } from "$/services/database/core";
+import { confirmHardware } from "$/ui/hardware";
+import { isCancelled, loadProgress } from "$/ui/progress";
 
 interface GamesCoreRow {
   id: number;
@@ -135,6 +137,15 @@ export class Games {
     return count;
   }
 
+  private static runningGame: Games | null = null;
+
+  /**
+   * The game running, if the core was launched from a game.
+   */
+  public static running() {
+    return Games.runningGame;
+  }
+
   public static async byId(id: number): Promise<Games> {
     const [row] = await sql<GamesCoreRow>`
             SELECT ${sql.raw(GAMES_FIELDS)}
@@ -226,6 +237,9 @@ export class Games {
   }
 
   async launch() {
+    if (!(await confirmHardware(this.row_.rbf_path))) {
+      return;
+    }
     console.log("Launching game: ", this.row_.name);
 
     // Insert last played time at.
@@ -241,12 +255,24 @@ export class Games {
 
     try {
       Core.setRunning(await Core.getById(this.row_.cores_id));
-      const core = golemCore.load({
-        core: { type: "Path", path: this.row_.rbf_path },
-        ...(this.row_.rom_path !== null
-          ? { game: { type: "RomPath", path: this.row_.rom_path } }
-          : {}),
-      });
+      Games.runningGame = this;
+      let core;
+      try {
+        core = golemCore.load(
+          {
+            core: { type: "Path", path: this.row_.rbf_path },
+            ...(this.row_.rom_path !== null
+              ? { game: { type: "RomPath", path: this.row_.rom_path } }
+              : {}),
+          },
+          loadProgress(this.name),
+        );
+      } catch (e) {
+        if (isCancelled(e)) {
+          return;
+        }
+        throw e;
+      }
 
       if (core) {
         console.log("Starting core: " + core.name);
@@ -261,6 +287,7 @@ export class Games {
       }
     } finally {
       Core.setRunning(null);
+      Games.runningGame = null;
     }
   }
 }
@@ -0,0 +1,44 @@
+import * as core from "@:golem/core";
+import * as ui from "@:golem/ui";
+import { Core } from "$/services/database/core";
+
+/**
+ * Launch the core set by the `bootcore` option of the MiSTer.ini, after a
+ * countdown the user can cancel (`bootcore_timeout`).
+ * @returns Whether a core was launched.
+ */
+export async function autoboot(): Promise<boolean> {
+  const target = core.bootTarget();
+  if (!target) {
+    return false;
+  }
+
+  if (target.timeout !== null) {
+    const game = target.game?.split("/").pop();
+    const choice = await ui.alert({
+      title: "Autoboot",
+      message: game
+        ? `Launching ${game} on ${target.name}.`
+        : `Launching ${target.name}.`,
+      choices: ["Launch Now", "Cancel"],
+      timeout: target.timeout,
+      timeout_choice: 0,
+    });
+    // Going back cancels, like the Cancel choice.
+    if (choice !== 0) {
+      return false;
+    }
+  }
+
+  console.log(`Autoboot: ${JSON.stringify(target)}`);
+  Core.setRunning(null);
+  const c = core.load({
+    core: { type: "Path", path: target.core },
+    game: target.game ? { type: "RomPath", path: target.game } : undefined,
+  });
+  c.showOsd(async () =>
+    (await import("$/ui/menus/core_osd")).coreOsdMenu(c, null),
+  );
+  c.loop();
+  return true;
+}
@@ -1,6 +1,29 @@
 import * as core from "@:golem/core";
 import * as ui from "@:golem/ui";
 import { Core } from "$/services/database/core";
+import { confirmHardware } from "$/ui/hardware";
+import { isCancelled, loadProgress } from "$/ui/progress";
+
+async function launchCoreFile(path: string) {
+  if (!(await confirmHardware(path))) {
+    return;
+  }
+
+  Core.setRunning(null);
+  let c;
+  try {
+    c = core.load({ core: { type: "Path", path } }, loadProgress("Core"));
+  } catch (e) {
+    if (isCancelled(e)) {
+      return;
+    }
+    throw e;
+  }
+  c.showOsd(async () =>
+    (await import("$/ui/menus/core_osd")).coreOsdMenu(c, null),
+  );
+  c.loop();
+}
 
 async function selectCoreFile() {
   let f = await ui.selectFile("Select Core", "/media/fat", {
@@ -9,17 +32,24 @@ async function selectCoreFile() {
   });
 
   if (f !== undefined) {
-    Core.setRunning(null);
-    let c = core.load({
-      core: { type: "Path", path: f },
-    });
-    c.showOsd(async () =>
-      (await import("$/ui/menus/core_osd")).coreOsdMenu(c, null),
-    );
-    c.loop();
+    await launchCoreFile(f);
   }
 }
 
+async function installedCoresMenu() {
+  const cores = core.scanCores();
+
+  await ui.textMenu({
+    title: "Installed Cores",
+    back: 0,
+    items: cores.map((c) => ({
+      label: c.system ?? c.name,
+      marker: c.version ?? c.dateCode ?? "",
+      select: () => launchCoreFile(c.path),
+    })),
+  });
+}
+
 export async function coresMenu() {
   const cores = await Core.list();
 
@@ -34,6 +64,7 @@ export async function coresMenu() {
         },
       })),
       "-",
+      { label: "Installed Cores...", select: installedCoresMenu },
       { label: "Select File...", select: selectCoreFile },
     ],
   });
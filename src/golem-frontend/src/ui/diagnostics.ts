@@ -0,0 +1,151 @@
+// Diagnostics of the DDR memory, to find faulty boards.
+import * as ui from "@:golem/ui";
+import * as system from "@:golem/system";
+import { isCancelled } from "$/ui/progress";
+
+const MEMORY_TESTS: { test: system.MemoryTest; label: string }[] = [
+  { test: "walkingOnes", label: "Walking Ones" },
+  { test: "addressInAddress", label: "Address in Address" },
+  { test: "random", label: "Random Patterns" },
+];
+
+function hex(value: number): string {
+  return `0x${value.toString(16).toUpperCase().padStart(8, "0")}`;
+}
+
+function sdramModule(size: number | null): string {
+  if (size === null) {
+    return "Unknown";
+  }
+  return size === 0 ? "None" : `${Math.floor(size / (1024 * 1024))} MiB`;
+}
+
+/**
+ * Run memory tests, showing their progress. The user can cancel with the
+ * back button.
+ * @returns Whether all the tests passed.
+ */
+async function runMemoryTests(tests: typeof MEMORY_TESTS): Promise<boolean> {
+  for (const { test, label } of tests) {
+    let failure: system.MemoryTestFailure | null;
+    try {
+      failure = system.testMemory(test, undefined, ({ current, total }) =>
+        ui.progress(`Memory Test - ${label}`, current, total),
+      );
+    } catch (e) {
+      if (isCancelled(e)) {
+        return false;
+      }
+      await ui.alert("Memory Test", `${label} could not run: ${e}`);
+      return false;
+    }
+
+    if (failure) {
+      await ui.alert(
+        "Memory Test Failed",
+        `${label}: the word at ${hex(failure.address)} read ${hex(failure.actual)} instead of ${hex(failure.expected)}.`,
+      );
+      return false;
+    }
+  }
+
+  await ui.alert("Memory Test", "All tests passed.");
+  return true;
+}
+
+async function memoryTestMenu() {
+  await ui.textMenu({
+    title: "Memory Test",
+    back: false,
+    items: [
+      {
+        label: "Run All Tests",
+        select: async () => {
+          await runMemoryTests(MEMORY_TESTS);
+        },
+      },
+      "-",
+      ...MEMORY_TESTS.map((t) => ({
+        label: t.label,
+        select: async () => {
+          await runMemoryTests([t]);
+        },
+      })),
+    ],
+  });
+}
+
+export async function diagnosticsMenu() {
+  let done = false;
+  while (!done) {
+    const sdram = system.sdram();
+    const ecc = sdram.eccStats;
+
+    done = await ui.textMenu({
+      title: "System Diagnostics",
+      back: true,
+      items: [
+        {
+          label: "Memory Type",
+          marker: sdram.memoryType ?? "Unknown",
+        },
+        {
+          label: "Memory Size",
+          marker: `${Math.floor(sdram.size / (1024 * 1024))} MiB`,
+        },
+        {
+          label: "Data Width",
+          marker: `${sdram.dataWidth} bits`,
+        },
+        {
+          label: "CAS Latency",
+          marker: `${sdram.casLatency} / ${sdram.casWriteLatency}`,
+        },
+        {
+          label: "Calibration",
+          marker: sdram.calibrated ? "OK" : "Failed",
+        },
+        {
+          label: "SDRAM Module",
+          marker: sdramModule(sdram.module),
+        },
+        "-",
+        {
+          label: "ECC",
+          marker: sdram.ecc ? "Enabled" : "Disabled",
+        },
+        ...(sdram.ecc
+          ? [
+              {
+                label: "Corrected Errors",
+                marker: `${ecc.singleBitErrors}`,
+              },
+              {
+                label: "Uncorrected Errors",
+                marker: `${ecc.doubleBitErrors}`,
+              },
+              {
+                label: "Last Error Address",
+                marker: hex(ecc.errorAddress),
+              },
+              {
+                label: "Clear ECC Counters",
+                select: () => {
+                  system.clearEccStats();
+                  return false;
+                },
+              },
+            ]
+          : []),
+        "-",
+        {
+          label: "Memory Test...",
+          select: async () => {
+            await memoryTestMenu();
+            return false;
+          },
+        },
+      ],
+    });
+  }
+}
@@ -0,0 +1,22 @@
+// Checks that a core can run on the hardware, before launching it.
+import * as core from "@:golem/core";
+import * as ui from "@:golem/ui";
+
+/**
+ * Warn the user if the core cannot run on this hardware (e.g. it needs an
+ * SDRAM module that is not installed).
+ * @returns Whether the core should be launched.
+ */
+export async function confirmHardware(path: string): Promise<boolean> {
+  const warning = core.checkHardware(path);
+  if (warning === null) {
+    return true;
+  }
+
+  const choice = await ui.alert({
+    title: "Missing Hardware",
+    message: `${warning}\nThe core may not work properly.`,
+    choices: ["Cancel", "Launch Anyway"],
+  });
+  return choice === 1;
+}
@@ -1,7 +1,11 @@
 import * as ui from "@:golem/ui";
 import * as core from "@:golem/core";
+import * as video from "@:golem/video";
+import * as macros from "@:golem/macros";
 import { CoreSettingPage } from "@:golem/core";
 import type { Core } from "$/services/database/core";
+import { videoModeMenu } from "$/ui/settings/video";
+import { macroOwner } from "$/commands/macros";
 
 enum SettingReturn {
   Continue,
@@ -107,6 +111,281 @@ export async function coreSettingsMenu(
     : shouldReturn;
 }
 
+/**
+ * Let the user pick a filter file of a kind, or none.
+ * @returns The selected filter (null for none), or undefined if cancelled.
+ */
+async function selectFilter(
+  title: string,
+  kind: video.FilterKind,
+  current: string | null,
+): Promise<string | null | undefined> {
+  const files = video.listFilters(kind);
+  const index = current === null ? -1 : files.indexOf(current);
+
+  const pick = await ui.textMenu<{ value: string | null } | null>({
+    title,
+    back: null,
+    highlighted: index + 1,
+    items: [
+      { label: "None", select: () => ({ value: null }) },
+      ...files.map((file) => ({
+        label: file,
+        select: () => ({ value: file }),
+      })),
+    ],
+  });
+
+  return pick === null ? undefined : pick.value;
+}
+
+const SHADOW_MASK_MODES: video.ScalerFilters["shadowMaskMode"][] = [
+  "none",
+  "enabled",
+  "enabled2x",
+];
+
+/**
+ * Ask whether video settings should be saved to the MiSTer.ini for all cores
+ * or only for this one, then save them.
+ */
+async function saveToIni(
+  coreName: string,
+  save: (section?: string) => void,
+): Promise<void> {
+  const choice = await ui.alert({
+    title: "Save to MiSTer.ini",
+    message: `Use these settings for all cores, or only for ${coreName}?`,
+    choices: ["Cancel", "All Cores", "This Core"],
+  });
+  if (choice !== 1 && choice !== 2) {
+    return;
+  }
+
+  try {
+    save(choice === 2 ? coreName : undefined);
+  } catch (e) {
+    await ui.alert("Save to MiSTer.ini", `Could not save: ${e}`);
+  }
+}
+
+async function videoFiltersMenu(coreName: string) {
+  let highlighted: number | undefined;
+  let done = false;
+
+  while (!done) {
+    const filters = video.scalerFilters();
+    const pickFilter = (
+      index: number,
+      title: string,
+      kind: video.FilterKind,
+      key: "horizontal" | "vertical" | "scanlines" | "shadowMask",
+    ) => ({
+      label: title,
+      marker: filters[key] ?? "None",
+      select: async () => {
+        const value = await selectFilter(title, kind, filters[key]);
+        if (value !== undefined) {
+          video.setScalerFilters({ ...filters, [key]: value });
+        }
+        return index;
+      },
+    });
+
+    // Menus are rebuilt after every change so markers reflect the filters
+    // actually in use by the core.
+    const result = await ui.textMenu<boolean | number>({
+      title: "Video Filters",
+      back: true,
+      highlighted,
+      items: [
+        pickFilter(0, "Horizontal", "filter", "horizontal"),
+        pickFilter(1, "Vertical", "filter", "vertical"),
+        pickFilter(2, "Scanlines", "filter", "scanlines"),
+        "-",
+        pickFilter(4, "Shadow Mask", "shadowMask", "shadowMask"),
+        {
+          label: "Shadow Mask Mode",
+          marker: filters.shadowMaskMode,
+          select: (item) => {
+            const i = SHADOW_MASK_MODES.indexOf(filters.shadowMaskMode);
+            filters.shadowMaskMode =
+              SHADOW_MASK_MODES[(i + 1) % SHADOW_MASK_MODES.length];
+            video.setScalerFilters(filters);
+            item.marker = filters.shadowMaskMode;
+          },
+        },
+        "-",
+        {
+          label: "Load Preset...",
+          select: async () => {
+            const preset = await selectFilter("Presets", "preset", null);
+            if (preset) {
+              video.setScalerFilters(video.loadPreset(preset));
+            }
+            return 7;
+          },
+        },
+        {
+          label: "Reset to Defaults",
+          select: () => {
+            video.resetScalerFilters();
+            return 8;
+          },
+        },
+        {
+          label: "Save to MiSTer.ini...",
+          select: async () => {
+            await saveToIni(coreName, video.saveScalerFiltersToIni);
+            return 9;
+          },
+        },
+      ],
+    });
+
+    if (result === true) {
+      done = true;
+    } else if (typeof result === "number") {
+      highlighted = result;
+    }
+  }
+}
+
+/**
+ * Let the user pick a number in a range.
+ * @returns The selected value, or undefined if cancelled.
+ */
+async function selectNumber(
+  title: string,
+  current: number,
+  max: number,
+  step: number,
+): Promise<number | undefined> {
+  const values = Array.from(
+    { length: Math.floor(max / step) + 1 },
+    (_, i) => i * step,
+  );
+  const pick = await ui.textMenu<number>({
+    title,
+    back: -1,
+    highlighted: Math.round(current / step),
+    items: values.map((value) => ({
+      label: `${value}`,
+      select: () => value,
+    })),
+  });
+
+  return pick === -1 ? undefined : pick;
+}
+
+async function colorControlsMenu(coreName: string) {
+  let highlighted: number | undefined;
+  let done = false;
+
+  while (!done) {
+    const controls = video.colorControls();
+    const pickNumber = (
+      index: number,
+      title: string,
+      key: "brightness" | "contrast" | "saturation" | "hue",
+      max: number,
+      step: number,
+    ) => ({
+      label: title,
+      marker: `${controls[key]}`,
+      select: async () => {
+        const value = await selectNumber(title, controls[key], max, step);
+        if (value !== undefined) {
+          video.setColorControls({ ...controls, [key]: value });
+        }
+        return index;
+      },
+    });
+
+    // Changes are applied immediately, so the user can see them on screen.
+    const result = await ui.textMenu<boolean | number>({
+      title: "Color Controls",
+      back: true,
+      highlighted,
+      items: [
+        pickNumber(0, "Brightness", "brightness", 100, 5),
+        pickNumber(1, "Contrast", "contrast", 100, 5),
+        pickNumber(2, "Saturation", "saturation", 100, 5),
+        pickNumber(3, "Hue", "hue", 345, 15),
+        "-",
+        {
+          label: "Gamma",
+          marker: controls.gamma ?? "None",
+          select: async () => {
+            const gamma = await selectFilter("Gamma", "gamma", controls.gamma);
+            if (gamma !== undefined) {
+              video.setColorControls({ ...controls, gamma });
+            }
+            return 5;
+          },
+        },
+        "-",
+        {
+          label: "Reset to Defaults",
+          select: () => {
+            video.resetColorControls();
+            return 7;
+          },
+        },
+        {
+          label: "Save to MiSTer.ini...",
+          select: async () => {
+            await saveToIni(coreName, video.saveColorControlsToIni);
+            return 8;
+          },
+        },
+      ],
+    });
+
+    if (result === true) {
+      done = true;
+    } else if (typeof result === "number") {
+      highlighted = result;
+    }
+  }
+}
+
+/**
+ * List the macros saved for the running game, to play or remove them. New
+ * macros are recorded with the "Start or stop recording a macro" shortcut.
+ * @returns Whether the OSD should be closed (a macro is playing).
+ */
+async function macrosMenu(golemCore: core.GolemCore): Promise<boolean> {
+  const [coreName, gameName] = macroOwner(golemCore);
+
+  while (true) {
+    const names = Object.keys(macros.list(coreName, gameName));
+    const result = await ui.textMenu<string | null>({
+      title: "Macros",
+      back: null,
+      items:
+        names.length > 0
+          ? names.map((name) => ({ label: name, select: () => name }))
+          : [{ label: "No macros recorded.", selectable: false }],
+    });
+    if (result === null) {
+      return false;
+    }
+
+    const choice = await ui.alert({
+      title: result,
+      message: "Play this macro?",
+      choices: ["Back", "Play", "Remove"],
+    });
+    if (choice === 1) {
+      macros.play(macros.list(coreName, gameName)[result]);
+      return true;
+    } else if (choice === 2) {
+      macros.remove(coreName, gameName, result);
+    }
+  }
+}
+
 function isKindFile(
   item: core.CoreSettingsItem,
 ): item is core.CoreSettingFileSelect {
@@ -175,6 +454,32 @@ export async function coreOsdMenu(
           }
         },
       },
+      {
+        label: "Video Mode...",
+        select: async () => {
+          await videoModeMenu();
+        },
+      },
+      {
+        label: "Video Filters...",
+        select: async () => {
+          await videoFiltersMenu(`${golemCore.name}`);
+        },
+      },
+      {
+        label: "Color Controls...",
+        select: async () => {
+          await colorControlsMenu(`${golemCore.name}`);
+        },
+      },
+      {
+        label: "Macros...",
+        select: async () => {
+          if (await macrosMenu(golemCore)) {
+            return false;
+          }
+        },
+      },
       {
         label: "Reset Core",
         select: () => {
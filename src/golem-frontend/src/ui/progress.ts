@@ -1,3 +1,4 @@
+import type { LoadProgressListener, LoadStage } from "@:golem/core";
 import * as ui from "@:golem/ui";
 import { oneLine } from "common-tags";
 
@@ -25,3 +26,38 @@ export async function partitionAndProgress<T>(
     last = +new Date();
   }
 }
+
+const LOAD_STAGE_MESSAGES: Record<LoadStage, string> = {
+  core: "Loading core...",
+  rom: "Loading game...",
+  file: "Loading file...",
+  mount: "Mounting saves...",
+};
+
+/**
+ * A listener for `core.load` showing a progress bar. The user can cancel
+ * with the back button while the ROM and files are loaded, in which case
+ * `load` throws (see `isCancelled`). Programming the FPGA cannot be
+ * cancelled.
+ */
+export function loadProgress(title: string): LoadProgressListener {
+  return ({ stage, current, total }) => {
+    // The OSD cannot be updated while the FPGA is being programmed, only
+    // before it starts, so there is no way to cancel it either.
+    if (stage === "core" && current > 0) {
+      return true;
+    }
+    return ui.progress(
+      `${title} - ${LOAD_STAGE_MESSAGES[stage]}`,
+      current,
+      total,
+    );
+  };
+}
+
+/**
+ * Whether an error thrown by `core.load` means the user cancelled it.
+ */
+export function isCancelled(e: unknown): boolean {
+  return e instanceof Error && e.message === "Cancelled";
+}
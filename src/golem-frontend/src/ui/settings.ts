@@ -12,6 +12,7 @@ import {
 } from "$/services";
 import { accountsSettingsMenu } from "$/ui/settings/accounts";
 import { networkSettingsMenu } from "./settings/network";
+import { videoModeMenu } from "./settings/video";
 
 const UPDATE_FREQUENCY_LABELS = {
   [CatalogCheckFrequency.Manually]: "Manually",
@@ -448,6 +449,10 @@ export async function settingsMenu() {
               label: "Network...",
               select: () => networkSettingsMenu(),
             },
+            {
+              label: "Video Mode...",
+              select: () => videoModeMenu(),
+            },
             {
               label: "UI...",
               select: async () => {
@@ -0,0 +1,85 @@
+import * as ui from "@:golem/ui";
+import * as video from "@:golem/video";
+
+/**
+ * Number of seconds before going back to the previous video mode.
+ */
+const REVERT_TIMEOUT = 15;
+
+const SOURCE_LABELS: { [key in video.VideoModeInfo["source"]]: string } = {
+  custom: "MiSTer.ini",
+  display: "Display",
+  default: "Default",
+};
+
+/**
+ * Apply a video mode, and ask the user to keep it. If the user does not
+ * answer (e.g. the display cannot show the mode), the previous mode is
+ * restored after a countdown.
+ * @returns Whether the mode was kept.
+ */
+async function applyVideoMode(mode: video.VideoModeInfo): Promise<boolean> {
+  try {
+    video.setMode(mode.videoMode, REVERT_TIMEOUT);
+  } catch (e) {
+    await ui.alert("Video Mode", `Could not set the video mode: ${e}`);
+    return false;
+  }
+
+  const choice = await ui.alert({
+    title: "Keep this video mode?",
+    message: `${mode.name}\nThe previous video mode will be restored automatically.`,
+    choices: ["Keep", "Revert"],
+    timeout: REVERT_TIMEOUT,
+  });
+
+  if (choice === 0) {
+    video.confirmMode();
+    return true;
+  }
+
+  video.revertMode();
+  return false;
+}
+
+export async function videoModeMenu() {
+  const modes = video.listModes();
+  let highlighted: number | undefined;
+  let current: string | undefined;
+
+  while (true) {
+    const result = await ui.textMenu<number | null>({
+      title: "Video Mode",
+      back: null,
+      highlighted,
+      items: modes.map((mode, i) => ({
+        label: mode.supported ? mode.name : `${mode.name} (!)`,
+        marker:
+          mode.videoMode === current ? "Active" : SOURCE_LABELS[mode.source],
+        select: async () => {
+          if (!mode.supported) {
+            const choice = await ui.alert({
+              title: "Unsupported Mode",
+              message:
+                "This mode is outside of the limits of the display or the scaler. Try it anyway?",
+              choices: ["Cancel", "Try"],
+            });
+            if (choice !== 1) {
+              return i;
+            }
+          }
+
+          if (await applyVideoMode(mode)) {
+            current = mode.videoMode;
+          }
+          return i;
+        },
+      })),
+    });
+
+    if (result === null) {
+      return;
+    }
+    highlighted = result;
+  }
+}
@@ -38,4 +38,11 @@ declare module "@:golem/commands" {
    * @param shortcut
    */
   export function removeShortcut(shortcut: string): void;
+
+  /**
+   * Set the shortcut that rewinds the core while held. Rewinding must be
+   * enabled in the settings. Pass `undefined` to remove the shortcut.
+   * @param shortcut The shortcut to hold for rewinding.
+   */
+  export function setRewindShortcut(shortcut?: string): void;
 }
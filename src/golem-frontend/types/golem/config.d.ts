@@ -0,0 +1,69 @@
+// Type definitions for the `golem/config` module in Golem Script.
+
+/**
+ * This module reads and edits the MiSTer.ini. Changes are written back to
+ * the file in place, keeping its comments, ordering, unknown keys and
+ * sections, so it can still be used by the stock MiSTer. Changes apply the
+ * next time a core is launched.
+ */
+declare module "@:golem/config" {
+  /**
+   * A problem found in the MiSTer.ini.
+   */
+  export interface ConfigIssue {
+    /**
+     * Errors prevent the file or an option from being loaded, warnings are
+     * options that are ignored or have no effect.
+     */
+    severity: "warning" | "error";
+    /**
+     * The line of the problem, starting at 1.
+     */
+    line: number;
+    section: string | null;
+    key: string | null;
+    message: string;
+  }
+
+  export interface ValidationReport {
+    /**
+     * The problems found, sorted by line.
+     */
+    issues: ConfigIssue[];
+  }
+
+  /**
+   * The names of the sections of the MiSTer.ini, including `MiSTer`.
+   */
+  export function sections(): string[];
+
+  /**
+   * Get the raw value of a key, as written in the file.
+   * @param key The key, e.g. `video_mode`.
+   * @param section The section, defaults to `MiSTer`.
+   * @returns The value, or undefined if the key is not in the section.
+   */
+  export function get(key: string, section?: string): string | undefined;
+
+  /**
+   * Set a key and save the file. Throws if the value is invalid, in which
+   * case the file is not changed.
+   * @param key The key, e.g. `video_mode`.
+   * @param value The value, as it would be written in the file.
+   * @param section The section, defaults to `MiSTer`. It is created if it
+   *                does not exist.
+   */
+  export function set(key: string, value: string, section?: string): void;
+
+  /**
+   * Remove a key from a section and save the file.
+   * @returns Whether the key was in the section.
+   */
+  export function remove(key: string, section?: string): boolean;
+
+  /**
+   * Check the MiSTer.ini for unknown keys, invalid values, malformed video
+   * sections and conflicting options.
+   */
+  export function validate(): ValidationReport;
+}
@@ -52,6 +52,8 @@ declare module "@:golem/core" {
 
     /**
      * The savestate file to load. If savestates are not supported, this will be ignored.
+     * The savestate is restored once the game is loaded, so the game resumes
+     * from where it was saved.
      */
     savestate?: string;
   }
@@ -132,7 +134,9 @@ declare module "@:golem/core" {
 
   /**
    * Callback for when the core wants to save a savestate.
-   * @param savestate The savestate to save (in binary format).
+   * @param savestate The savestate to save. For MiSTer cores, this is a
+   *   compressed savestate file which includes the core name and version,
+   *   so it can be validated when loaded back.
    * @param screenshot A screenshot of the current game.
    * @param slot The slot number to save the savestate to.
    */
@@ -168,6 +172,19 @@ declare module "@:golem/core" {
      */
     statusBits: number[];
 
+    /**
+     * The MiSTer.ini used by the core, after merging the sections matching the
+     * core name and its video resolution. This is meant for debugging; the
+     * `config` field is not a stable format. Undefined for non-MiSTer cores.
+     */
+    readonly effectiveConfig?: {
+      /**
+       * The sections merged on top of the `[MiSTer]` section, in order.
+       */
+      sections: string[];
+      config: string;
+    };
+
     /**
      * The core's main loop, sending any inputs to the core, and checking for
      * shortcuts. This function will return when the core is unloaded by the
@@ -230,9 +247,144 @@ declare module "@:golem/core" {
     on(event: "saveState", listener: SaveStateListener): void;
   }
 
+  /**
+   * What is being loaded when progress is reported.
+   * - `core`: programming the FPGA with the core.
+   * - `rom`: sending the game ROM to the core.
+   * - `file`: sending another file to the core.
+   * - `mount`: mounting save files as SD cards.
+   */
+  export type LoadStage = "core" | "rom" | "file" | "mount";
+
+  /**
+   * The progress of loading a core, reported while `load` runs.
+   */
+  export interface LoadProgressEvent {
+    stage: LoadStage;
+    /**
+     * Bytes (or files, for `mount`) done in this stage.
+     */
+    current: number;
+    /**
+     * Total for this stage, or 0 if it is not known.
+     */
+    total: number;
+  }
+
+  /**
+   * Called with the progress of `load`. Return `false` to cancel loading.
+   *
+   * While the FPGA is being programmed (`core` events after the first one),
+   * the OSD cannot be drawn or read, so the user has no way to cancel the
+   * launch until the ROM and files are loaded.
+   */
+  export type LoadProgressListener = (
+    event: LoadProgressEvent,
+  ) => boolean | void;
+
   /**
    * Starts a core with the given options.
    * @param options The options for the core.
+   * @param onProgress Called as the core, ROM and files are loaded. If it
+   *                   returns `false` (or throws), loading is cancelled, the
+   *                   menu core is restored and `load` throws an error with
+   *                   the message `Cancelled`. See `LoadProgressListener` for
+   *                   when a launch cannot be cancelled.
+   */
+  export function load(
+    options: RunOptions,
+    onProgress?: LoadProgressListener,
+  ): GolemCore;
+
+  /**
+   * A core to launch at startup, from the `bootcore` option of the MiSTer.ini.
+   */
+  export interface BootTarget {
+    /**
+     * The name of the core, without its version.
+     */
+    name: string;
+    /**
+     * The path of the core file.
+     */
+    core: string;
+    /**
+     * The last game played on the core, with `bootcore=lastcore` or
+     * `bootcore=exactlastcore`.
+     */
+    game: string | null;
+    /**
+     * Seconds to wait before launching (`bootcore_timeout`), so the user can
+     * cancel. If `null`, the core should be launched right away.
+     */
+    timeout: number | null;
+  }
+
+  /**
+   * The core to launch at startup, if the `bootcore` option is set and the
+   * core can be found.
+   */
+  export function bootTarget(): BootTarget | undefined;
+
+  /**
+   * A core installed, as found by `scanCores`.
+   */
+  export interface CatalogCore {
+    /**
+     * The name of the core, without its date code.
+     */
+    name: string;
+    /**
+     * The file name of the core, without its extension.
+     */
+    exactName: string;
+    /**
+     * The path of the core file.
+     */
+    path: string;
+    /**
+     * The date code in the file name, as `YYYYMMDD`.
+     */
+    dateCode: string | null;
+    /**
+     * The directory of the core, without its leading underscore (e.g.
+     * `Console` for `_Console`).
+     */
+    category: string | null;
+    /**
+     * The CRC32 of the core file, in hexadecimal.
+     */
+    hash: string;
+    /**
+     * The config string of the core, if it could be found in the bitstream.
+     */
+    configString: string | null;
+    /**
+     * The name of the system, from the config string.
+     */
+    system: string | null;
+    /**
+     * The version of the core, from the config string.
+     */
+    version: string | null;
+    /**
+     * The extensions of the files the core can load, in lower case.
+     */
+    extensions: string[];
+  }
+
+  /**
+   * Scan the cores directory for cores. Results are cached by file hash, so
+   * only new or updated cores are read. Sorted by name, most recent first.
+   */
+  export function scanCores(): CatalogCore[];
+
+  /**
+   * Check whether a core can run on this hardware, e.g. whether it needs an
+   * SDRAM module and none is installed. This is a best guess from the config
+   * string of the core; cores that were not scanned are read, which is slow.
+   * @param path The path of the core file.
+   * @returns A warning to show the user before launching the core, or `null`.
    */
-  export function load(options: RunOptions): GolemCore;
+  export function checkHardware(path: string): string | null;
 }
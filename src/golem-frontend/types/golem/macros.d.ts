@@ -0,0 +1,94 @@
+// Type definitions for the `@:golem/macros` module in Golem Script.
+
+/**
+ * This module allows recording sequences of inputs while a core is running,
+ * and replaying them later (e.g. from a shortcut created with
+ * `@:golem/commands`).
+ */
+declare module "@:golem/macros" {
+  export interface MacroKeyInput {
+    type: "key";
+    scancode: string;
+  }
+
+  export interface MacroGamepadButtonInput {
+    type: "gamepadButton";
+    gamepad: number;
+    button: string;
+  }
+
+  export type MacroInput = MacroKeyInput | MacroGamepadButtonInput;
+
+  /**
+   * A single press or release of an input, at a specific frame from the
+   * start of the macro.
+   */
+  export interface MacroEvent {
+    frame: number;
+    input: MacroInput;
+    pressed: boolean;
+  }
+
+  /**
+   * A recorded sequence of inputs.
+   */
+  export interface InputMacro {
+    events: MacroEvent[];
+  }
+
+  /**
+   * Start recording inputs sent to the core. Recording starts at the first
+   * input pressed.
+   */
+  export function startRecording(): void;
+
+  /**
+   * Stop recording and return the recorded macro, or `undefined` if no
+   * recording was in progress. Inputs that were still held when stopping
+   * (e.g. the shortcut used to stop the recording) are not part of the macro.
+   */
+  export function stopRecording(): InputMacro | undefined;
+
+  /**
+   * Whether a recording is in progress.
+   */
+  export function isRecording(): boolean;
+
+  /**
+   * Replay a macro on the running core. Replaces any macro already playing,
+   * releasing the inputs it still holds. Throws if no core is running.
+   */
+  export function play(macro: InputMacro): void;
+
+  /**
+   * Stop the macro currently playing, releasing any input it still holds.
+   */
+  export function stop(): void;
+
+  /**
+   * List the macros saved for a game, by name.
+   */
+  export function list(
+    coreName: string,
+    gameName: string,
+  ): Record<string, InputMacro>;
+
+  /**
+   * Save a macro for a game, replacing any macro with the same name.
+   */
+  export function save(
+    coreName: string,
+    gameName: string,
+    name: string,
+    macro: InputMacro,
+  ): void;
+
+  /**
+   * Remove a saved macro. Returns true if the macro existed.
+   */
+  export function remove(
+    coreName: string,
+    gameName: string,
+    name: string,
+  ): boolean;
+}
@@ -30,6 +30,27 @@ declare module "@:golem/settings" {
 
   export function setInvertToolbar(invert: boolean): void;
 
+  /**
+   * Whether rewinding is enabled for cores that support savestates.
+   */
+  export function rewindEnabled(): boolean;
+
+  export function setRewindEnabled(enabled: boolean): void;
+
+  /**
+   * The number of states kept for rewinding.
+   */
+  export function rewindDepth(): number;
+
+  export function setRewindDepth(depth: number): void;
+
+  /**
+   * The interval between two rewind states, in milliseconds.
+   */
+  export function rewindInterval(): number;
+
+  export function setRewindInterval(intervalMs: number): void;
+
   /**
    * Ping the NTP server and update the current time.
    * @param tz The timezone to use, or null to use the system timezone.
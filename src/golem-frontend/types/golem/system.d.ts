@@ -0,0 +1,113 @@
+// System diagnostics.
+
+declare module "@:golem/system" {
+  /**
+   * The ECC error counters of the SDRAM controller. Counters saturate at 255.
+   */
+  export interface EccStats {
+    singleBitErrors: number;
+    doubleBitErrors: number;
+    /**
+     * The address of the last error.
+     */
+    errorAddress: number;
+    droppedCorrections: number;
+    droppedAddress: number;
+  }
+
+  /**
+   * The DDR configuration of the SDRAM controller of the HPS.
+   */
+  export interface SdramInfo {
+    /**
+     * The type of memory, or `null` if the controller is not configured.
+     */
+    memoryType: "DDR2" | "DDR3" | "LPDDR2" | null;
+    /**
+     * Whether the PHY calibrated successfully.
+     */
+    calibrated: boolean;
+    /**
+     * The size of the memory, in bytes.
+     */
+    size: number;
+    dataWidth: number;
+    interfaceWidth: number;
+    chipSelects: number;
+    casLatency: number;
+    casWriteLatency: number;
+    ecc: boolean;
+    eccStats: EccStats;
+    /**
+     * The size of the SDRAM module of the FPGA in bytes, 0 if none is
+     * installed, or `null` if the menu core did not detect it.
+     */
+    module: number | null;
+  }
+
+  /**
+   * Read the configuration and ECC counters of the SDRAM controller.
+   */
+  export function sdram(): SdramInfo;
+
+  /**
+   * Reset the ECC counters of the SDRAM controller.
+   */
+  export function clearEccStats(): void;
+
+  /**
+   * A memory test. `walkingOnes` checks the data and address lines,
+   * `addressInAddress` and `random` write then check every word.
+   */
+  export type MemoryTest = "walkingOnes" | "addressInAddress" | "random";
+
+  export interface MemoryTestOptions {
+    /**
+     * The offset of the range to test from the start of the host memory, in
+     * bytes. Defaults to 64 MiB, after the framebuffer of the menu.
+     */
+    offset?: number;
+    /**
+     * The size of the range to test, in bytes. Defaults to the rest of the
+     * host memory.
+     */
+    size?: number;
+    /**
+     * The seed of the `random` test. Defaults to a random seed.
+     */
+    seed?: number;
+  }
+
+  /**
+   * A word that did not read back as written.
+   */
+  export interface MemoryTestFailure {
+    /**
+     * The physical address of the word.
+     */
+    address: number;
+    expected: number;
+    actual: number;
+  }
+
+  /**
+   * Called while a memory test runs, with the number of bytes tested. Return
+   * `false` to cancel the test.
+   */
+  export type MemoryTestListener = (event: {
+    current: number;
+    total: number;
+  }) => boolean | void;
+
+  /**
+   * Test the host memory shared with the FPGA. This overwrites the memory, so
+   * it throws an error unless the menu core is running.
+   * @returns `null` if the test passed, or the first failure. Throws an error
+   *          with the message "Cancelled" if the listener cancelled it.
+   */
+  export function testMemory(
+    test: MemoryTest,
+    options?: MemoryTestOptions,
+    onProgress?: MemoryTestListener,
+  ): MemoryTestFailure | null;
+}
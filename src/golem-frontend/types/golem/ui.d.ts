@@ -94,6 +94,16 @@ declare module "@:golem/ui" {
     title?: string;
     message: string;
     choices?: string[];
+    /**
+     * Dismiss the alert after this many seconds, showing a countdown. A
+     * dismissed alert returns `timeout_choice`, or `null` (like going back)
+     * if it is not set.
+     */
+    timeout?: number;
+    /**
+     * The index of the choice returned when the alert times out.
+     */
+    timeout_choice?: number;
   }): Promise<null | number>;
 
   /**
@@ -131,6 +141,17 @@ declare module "@:golem/ui" {
   export function show(message: string): void;
   export function show(title: string, message: string): void;
 
+  /**
+   * Draw a progress bar with a message, and return right away. Meant to be
+   * called repeatedly while something loads. Returns `false` if the user
+   * pressed the back button to cancel.
+   */
+  export function progress(
+    message: string,
+    current: number,
+    total: number,
+  ): boolean;
+
   /**
    * Show a message to the user, with a QR Code.
    */
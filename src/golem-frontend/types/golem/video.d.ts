@@ -1,9 +1,250 @@
 // Video management.
 
 declare module "@:golem/video" {
+  /**
+   * A video mode that can be selected.
+   */
+  export interface VideoModeInfo {
+    name: string;
+    source: "default" | "display" | "custom";
+    /**
+     * The `video_mode` string of this mode, to pass to `setMode`.
+     */
+    videoMode: string;
+    width: number;
+    height: number;
+    refresh: number;
+    /**
+     * Whether the mode can be output by the scaler and the display.
+     */
+    supported: boolean;
+  }
+
+  /**
+   * List the video modes that can be selected: the custom modes of the
+   * MiSTer.ini, the modes of the display, then the default modes.
+   */
+  export function listModes(): VideoModeInfo[];
+
   /**
    * Set the video mode.
-   * @param mode A string representing the video mode to set.
+   * @param mode A `video_mode` string (as in the MiSTer.ini) or the name of a
+   *             default video mode.
+   * @param revertAfter If set, go back to the previous video mode after this
+   *                    many seconds, unless `confirmMode` is called. Throws
+   *                    if the current video mode is unknown.
+   */
+  export function setMode(mode: string, revertAfter?: number): void;
+
+  /**
+   * Keep the video mode set by `setMode`, cancelling its revert.
+   */
+  export function confirmMode(): void;
+
+  /**
+   * Go back to the previous video mode, if the current one was not confirmed.
+   * @returns Whether the video mode was changed.
+   */
+  export function revertMode(): boolean;
+
+  /**
+   * The kind of filter files that can be listed.
+   */
+  export type FilterKind = "filter" | "shadowMask" | "preset" | "gamma";
+
+  /**
+   * Scaler filters, shadow mask and scanlines used by a core. Filters are
+   * paths relative to their root directory (e.g. `Filters/`), or `null`
+   * to disable them.
+   */
+  export interface ScalerFilters {
+    horizontal: string | null;
+    vertical: string | null;
+    scanlines: string | null;
+    shadowMask: string | null;
+    shadowMaskMode: "none" | "enabled" | "enabled2x";
+  }
+
+  /**
+   * List all the filter files of a kind, relative to their root directory.
+   * @param kind The kind of filter files to list.
+   */
+  export function listFilters(kind: FilterKind): string[];
+
+  /**
+   * Get the scaler filters used by the current core.
+   */
+  export function scalerFilters(): ScalerFilters;
+
+  /**
+   * Set the scaler filters of the current core, and save them so they are
+   * used the next time the core is launched.
+   * @param filters The filters to use.
+   */
+  export function setScalerFilters(filters: ScalerFilters): void;
+
+  /**
+   * Remove the filters saved for the current core and go back to the ones
+   * from the MiSTer.ini.
+   */
+  export function resetScalerFilters(): void;
+
+  /**
+   * Write the filters of the current core to the MiSTer.ini, keeping the rest
+   * of the file as is.
+   * @param section The section to write to, e.g. the name of the core.
+   *                Defaults to the `[MiSTer]` section.
+   */
+  export function saveScalerFiltersToIni(section?: string): void;
+
+  /**
+   * Load the filters of a preset file.
+   * @param name The path of the preset, relative to the presets directory.
+   */
+  export function loadPreset(name: string): ScalerFilters;
+
+  /**
+   * Color controls of the HDMI output and the gamma curve of the core. Values
+   * use the same ranges as the MiSTer.ini (e.g. brightness is between 0 and
+   * 100, hue between 0 and 359). The gamma curve is a path relative to the
+   * `Gamma` directory, or `null` to disable it.
+   */
+  export interface ColorControls {
+    brightness: number;
+    contrast: number;
+    saturation: number;
+    hue: number;
+    /**
+     * Gain and offset of every channel, as in the `video_gain_offset` key
+     * of the MiSTer.ini.
+     */
+    gainOffset: string;
+    gamma: string | null;
+  }
+
+  /**
+   * Get the color controls used by the current core.
+   */
+  export function colorControls(): ColorControls;
+
+  /**
+   * Set the color controls of the current core, without reloading it.
+   * @param controls The color controls to use.
+   * @param save Whether to save them so they are used the next time the core
+   *             is launched. Defaults to true.
+   */
+  export function setColorControls(
+    controls: ColorControls,
+    save?: boolean,
+  ): void;
+
+  /**
+   * Remove the color controls saved for the current core and go back to the
+   * ones from the MiSTer.ini.
+   */
+  export function resetColorControls(): void;
+
+  /**
+   * Write the color controls of the current core to the MiSTer.ini, keeping
+   * the rest of the file as is. The gamma curve is not part of the MiSTer.ini.
+   * @param section The section to write to, e.g. the name of the core.
+   *                Defaults to the `[MiSTer]` section.
+   */
+  export function saveColorControlsToIni(section?: string): void;
+
+  /**
+   * The timings of a video mode, from the EDID of the display.
+   */
+  export interface DetailedTiming {
+    pixelClockKhz: number;
+    hact: number;
+    hfp: number;
+    hs: number;
+    hbp: number;
+    vact: number;
+    vfp: number;
+    vs: number;
+    vbp: number;
+    hsyncPositive: boolean;
+    vsyncPositive: boolean;
+    interlaced: boolean;
+  }
+
+  /**
+   * The decoded EDID of the display.
+   */
+  export interface EdidInfo {
+    manufacturer: string;
+    productCode: number;
+    serialNumber: number;
+    year: number;
+    version: string;
+    name: string | null;
+    rangeLimits: {
+      minVerticalHz: number;
+      maxVerticalHz: number;
+      minHorizontalKhz: number;
+      maxHorizontalKhz: number;
+      maxPixelClockMhz: number | null;
+    } | null;
+    /**
+     * All detailed timings, the first one being the preferred mode.
+     */
+    detailedTimings: DetailedTiming[];
+    /**
+     * CEA-861 video identification codes, with whether they are native.
+     */
+    vics: [number, boolean][];
+    hdmi: boolean;
+    hdr: {
+      sdr: boolean;
+      hdr: boolean;
+      pq: boolean;
+      hlg: boolean;
+      maxLuminance: number | null;
+      maxFrameAverageLuminance: number | null;
+      minLuminance: number | null;
+    } | null;
+    vrr: {
+      protocol: "freesync" | "hdmiVrr" | "qms";
+      minFramerate: number;
+      maxFramerate: number;
+    } | null;
+  }
+
+  /**
+   * A video mode supported by the display.
+   */
+  export interface DisplayMode {
+    source: "preferred" | "detailed" | { vic: number };
+    width: number;
+    height: number;
+    refresh: number;
+    timing: DetailedTiming;
+    /**
+     * The `video_mode` string to use this mode.
+     */
+    videoMode: string;
+    /**
+     * Whether the mode can be output by the scaler.
+     */
+    supported: boolean;
+  }
+
+  /**
+   * Get the decoded EDID of the display, or `null` if it cannot be read.
+   */
+  export function edid(): EdidInfo | null;
+
+  /**
+   * List the video modes of the display, the preferred one first.
+   */
+  export function displayModes(): DisplayMode[];
+
+  /**
+   * Validate a video mode against the limits of the display and the scaler.
+   * @param mode A video mode, as in the `video_mode` key of the MiSTer.ini.
+   * @returns A list of problems, empty if the mode can be used.
    */
-  export function setMode(mode: string): void;
+  export function validateMode(mode: string): string[];
 }
@@ -6,12 +6,15 @@ use boa_macros::js_str;
 use crate::module_loader::GolemModuleLoader;
 
 mod commands;
+mod config;
 mod core;
 mod db;
 mod fs;
+mod macros;
 mod net;
 mod schema;
 mod settings;
+mod system;
 mod ui;
 mod video;
 
@@ -26,12 +29,15 @@ pub(super) fn register_modules(
 ) -> JsResult<()> {
     let modules = [
         commands::create_module,
+        config::create_module,
         core::create_module,
         db::create_module,
         fs::create_module,
+        macros::create_module,
         net::create_module,
         schema::create_module,
         settings::create_module,
+        system::create_module,
         video::create_module,
         ui::create_module,
     ];
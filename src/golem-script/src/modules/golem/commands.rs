@@ -73,6 +73,20 @@ fn remove_shortcut_(ContextData(data): ContextData<HostData>, shortcut: String)
     Ok(())
 }
 
+fn set_rewind_shortcut_(
+    ContextData(data): ContextData<HostData>,
+    shortcut: Option<String>,
+) -> JsResult<()> {
+    debug!(?shortcut, "Setting rewind shortcut");
+    let shortcut = shortcut
+        .map(|s| Shortcut::from_str(&s))
+        .transpose()
+        .map_err(|e| js_error!("Invalid shortcut: {:?}", e))?;
+
+    data.app_mut().set_rewind_shortcut(shortcut);
+    Ok(())
+}
+
 pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
     Ok((
         js_string!("commands"),
@@ -85,6 +99,10 @@ pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
                 js_string!("removeShortcut"),
                 remove_shortcut_.into_js_function_copied(context),
             ),
+            (
+                js_string!("setRewindShortcut"),
+                set_rewind_shortcut_.into_js_function_copied(context),
+            ),
         ]
         .into_js_module(context),
     ))
@@ -0,0 +1,75 @@
+use boa_engine::object::builtins::JsArray;
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
+use boa_interop::{IntoJsFunctionCopied, IntoJsModule};
+use mister_fpga::config::{ConfigFile, ValidationReport};
+
+fn open_() -> JsResult<ConfigFile> {
+    ConfigFile::base().map_err(|e| js_error!("Could not read MiSTer.ini: {}", e))
+}
+
+fn sections_(context: &mut Context) -> JsResult<JsValue> {
+    let sections = open_()?
+        .sections()
+        .into_iter()
+        .map(|s| JsString::from(s).into())
+        .collect::<Vec<JsValue>>();
+    Ok(JsArray::from_iter(sections, context).into())
+}
+
+fn get_(key: JsString, section: Option<JsString>) -> JsResult<JsValue> {
+    let section = section.map(|s| s.to_std_string_escaped());
+    Ok(open_()?
+        .get(section.as_deref(), &key.to_std_string_escaped())
+        .map(|v| JsString::from(v).into())
+        .unwrap_or_else(JsValue::undefined))
+}
+
+fn set_(key: JsString, value: JsString, section: Option<JsString>) -> JsResult<()> {
+    let section = section.map(|s| s.to_std_string_escaped());
+    let mut file = open_()?;
+    file.set(
+        section.as_deref(),
+        &key.to_std_string_escaped(),
+        value.to_std_string_escaped(),
+    )
+    .map_err(|e| js_error!("Invalid value: {}", e))?;
+    file.save()
+        .map_err(|e| js_error!("Could not save MiSTer.ini: {}", e))
+}
+
+fn remove_(key: JsString, section: Option<JsString>) -> JsResult<bool> {
+    let section = section.map(|s| s.to_std_string_escaped());
+    let mut file = open_()?;
+    if !file.remove(section.as_deref(), &key.to_std_string_escaped()) {
+        return Ok(false);
+    }
+    file.save()
+        .map_err(|e| js_error!("Could not save MiSTer.ini: {}", e))?;
+    Ok(true)
+}
+
+fn validate_(context: &mut Context) -> JsResult<JsValue> {
+    let file = open_()?;
+    let report = ValidationReport::from_ini(&file.to_string());
+    let json = serde_json::to_value(&report).map_err(JsError::from_rust)?;
+    JsValue::from_json(&json, context)
+}
+
+pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
+    Ok((
+        js_string!("config"),
+        [
+            (
+                js_string!("sections"),
+                sections_.into_js_function_copied(context),
+            ),
+            (js_string!("get"), get_.into_js_function_copied(context)),
+            (js_string!("set"), set_.into_js_function_copied(context)),
+            (
+                js_string!("remove"),
+                remove_.into_js_function_copied(context),
+            ),
+        ]
+        .into_js_module(context),
+    ))
+}
@@ -1,12 +1,16 @@
 use std::path::PathBuf;
 
 use boa_engine::class::Class;
+use boa_engine::object::builtins::JsFunction;
 use boa_engine::value::TryFromJs;
-use boa_engine::{js_string, Context, JsError, JsResult, JsString, JsValue, Module};
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
 use boa_interop::{ContextData, IntoJsFunctionCopied, IntoJsModule};
 use boa_macros::{Finalize, JsData, Trace};
+use golem_ui::data::{core_catalog, last_launch};
+use mister_fpga::config::Config;
 use one_fpga::core::Rom;
-use one_fpga::runner::CoreLaunchInfo;
+use one_fpga::progress::{Cancelled, LoadProgress, ProgressToken};
+use one_fpga::runner::{CoreLaunchInfo, Slot};
 use serde::Deserialize;
 
 use crate::modules::golem::globals::classes::JsCore;
@@ -45,6 +49,7 @@ impl TryFromJs for RunOptions {
 
 fn load_(
     options: RunOptions,
+    on_progress: Option<JsFunction>,
     host_data: ContextData<HostData>,
     context: &mut Context,
 ) -> JsResult<JsValue> {
@@ -68,16 +73,57 @@ fn load_(
         {
             core_options
                 .files
-                .insert(i, one_fpga::runner::Slot::File(PathBuf::from(file)));
+                .insert(i, Slot::File(PathBuf::from(file)));
         }
     }
 
+    if let Some(savestate) = &options.savestate {
+        core_options = core_options.with_save_state(Slot::File(PathBuf::from(savestate)));
+    }
+
+    // The listener returns `false` to cancel the launch. If it throws, the
+    // launch is cancelled and the error is rethrown.
+    let mut listener_error = None;
+    let mut progress = match &on_progress {
+        Some(listener) => ProgressToken::new(|p: &LoadProgress| {
+            let event = serde_json::json!({
+                "stage": <&str>::from(p.stage),
+                "current": p.current,
+                "total": p.total,
+            });
+            match JsValue::from_json(&event, context)
+                .and_then(|event| listener.call(&JsValue::undefined(), &[event], context))
+            {
+                Ok(result) => !matches!(result, JsValue::Boolean(false)),
+                Err(error) => {
+                    listener_error = Some(error);
+                    false
+                }
+            }
+        }),
+        None => ProgressToken::default(),
+    };
+
+    // The listener can call back into the app (e.g. `ui.progress`), so the
+    // app is not borrowed while the core launches. The core manager is
+    // taken out and put back once done.
     eprintln!("Launching core: {:?}", core_options);
-    let core = app
-        .platform_mut()
-        .core_manager_mut()
-        .launch(core_options)
-        .unwrap();
+    let mut core_manager = app.platform_mut().core_manager_mut().take();
+    let result = core_manager.launch_with_progress(core_options, &mut progress);
+    let cancelled = progress.is_cancelled();
+    drop(progress);
+    *host_data.0.app_mut().platform_mut().core_manager_mut() = core_manager;
+
+    if let Some(error) = listener_error {
+        return Err(error);
+    }
+    let core = result.map_err(|e| {
+        if cancelled {
+            js_error!("{}", Cancelled)
+        } else {
+            js_error!("Could not launch core: {}", e)
+        }
+    })?;
 
     Ok(JsValue::Object(JsCore::from_data(
         JsCore::new(core),
@@ -85,9 +131,52 @@ fn load_(
     )?))
 }
 
+fn boot_target_(context: &mut Context) -> JsResult<JsValue> {
+    let Some(target) = last_launch::boot_target(&Config::base().into_inner()) else {
+        return Ok(JsValue::undefined());
+    };
+
+    let json = serde_json::json!({
+        "name": target.core.name(),
+        "core": target.core.path().to_string_lossy(),
+        "game": target.game.map(|g| g.to_string_lossy().to_string()),
+        "timeout": target.timeout.map(|t| t.as_secs_f64()),
+    });
+    JsValue::from_json(&json, context)
+}
+
+fn scan_cores_(context: &mut Context) -> JsResult<JsValue> {
+    let cores = core_catalog::scan().map_err(|e| js_error!("{}", e))?;
+    let json = serde_json::to_value(cores).map_err(|e| js_error!("{}", e))?;
+    JsValue::from_json(&json, context)
+}
+
+fn check_hardware_(path: JsString) -> JsValue {
+    let path = PathBuf::from(path.to_std_string_escaped());
+    match core_catalog::hardware_warning(&path) {
+        Some(warning) => JsValue::from(JsString::from(warning)),
+        None => JsValue::null(),
+    }
+}
+
 pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
     Ok((
         js_string!("core"),
-        [(js_string!("load"), load_.into_js_function_copied(context))].into_js_module(context),
+        [
+            (js_string!("load"), load_.into_js_function_copied(context)),
+            (
+                js_string!("bootTarget"),
+                boot_target_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("scanCores"),
+                scan_cores_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("checkHardware"),
+                check_hardware_.into_js_function_copied(context),
+            ),
+        ]
+        .into_js_module(context),
     ))
 }
@@ -173,6 +173,18 @@ impl JsCore {
         Ok(())
     }
 
+    fn effective_config(&self, context: &mut Context) -> JsResult<JsValue> {
+        let Some(core) = self.core.as_mister_core() else {
+            return Ok(JsValue::undefined());
+        };
+        let options = core.options();
+        let json = serde_json::json!({
+            "sections": options.merged_sections(),
+            "config": format!("{:#?}", options.inner()),
+        });
+        JsValue::from_json(&json, context)
+    }
+
     fn on(&mut self, event: Events, handler: JsFunction) -> JsResult<()> {
         self.events.borrow_mut()[event].push(handler);
         Ok(())
@@ -203,6 +215,12 @@ js_class! {
             }
         }
 
+        property effective_config as "effectiveConfig" {
+            fn get(this: JsClass<JsCore>, context: &mut Context) -> JsResult<JsValue> {
+                this.borrow().effective_config(context)
+            }
+        }
+
         constructor(data: ContextData<HostData>) {
             let host_defined = data.0;
             Ok(JsCore::new(host_defined.app_mut().platform_mut().core_manager_mut().get_current_core().unwrap().clone()))
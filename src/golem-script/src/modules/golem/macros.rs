@@ -0,0 +1,134 @@
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
+use boa_interop::{ContextData, IntoJsFunctionCopied, IntoJsModule};
+use golem_ui::data::paths;
+use golem_ui::input::macros::{InputMacro, MacroSet};
+use tracing::debug;
+
+use crate::HostData;
+
+fn macro_from_js(value: JsValue, context: &mut Context) -> JsResult<InputMacro> {
+    serde_json::from_value(value.to_json(context)?)
+        .map_err(|e| js_error!("Invalid macro: {}", e.to_string()))
+}
+
+fn start_recording_(ContextData(data): ContextData<HostData>) {
+    debug!("Starting macro recording");
+    data.app_mut().macros_mut().start_recording();
+}
+
+fn stop_recording_(
+    ContextData(data): ContextData<HostData>,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    debug!("Stopping macro recording");
+    match data.app_mut().macros_mut().stop_recording() {
+        Some(m) => {
+            let json = serde_json::to_value(&m).map_err(JsError::from_rust)?;
+            JsValue::from_json(&json, context)
+        }
+        None => Ok(JsValue::undefined()),
+    }
+}
+
+fn is_recording_(ContextData(data): ContextData<HostData>) -> bool {
+    data.app().macros().is_recording()
+}
+
+fn play_(
+    ContextData(data): ContextData<HostData>,
+    value: JsValue,
+    context: &mut Context,
+) -> JsResult<()> {
+    let m = macro_from_js(value, context)?;
+    debug!(events = m.len(), "Playing macro");
+    let app = data.app_mut();
+    let Some(mut core) = app.platform_mut().core_manager_mut().get_current_core() else {
+        return Err(js_error!("No core running"));
+    };
+    app.macros_mut().play(m, &mut core);
+    Ok(())
+}
+
+fn stop_(ContextData(data): ContextData<HostData>) {
+    let app = data.app_mut();
+    if let Some(mut core) = app.platform_mut().core_manager_mut().get_current_core() {
+        app.macros_mut().stop(&mut core);
+    }
+}
+
+fn list_(core_name: JsString, game_name: JsString, context: &mut Context) -> JsResult<JsValue> {
+    let path = paths::macros_path(
+        &core_name.to_std_string_escaped(),
+        &game_name.to_std_string_escaped(),
+    )
+    .map_err(|e| js_error!("{}", e))?;
+    let set = MacroSet::load(path).map_err(|e| js_error!("Could not load macros: {}", e))?;
+
+    let json = serde_json::to_value(&set).map_err(JsError::from_rust)?;
+    JsValue::from_json(&json, context)
+}
+
+fn save_(
+    core_name: JsString,
+    game_name: JsString,
+    name: JsString,
+    value: JsValue,
+    context: &mut Context,
+) -> JsResult<()> {
+    let path = paths::macros_path(
+        &core_name.to_std_string_escaped(),
+        &game_name.to_std_string_escaped(),
+    )
+    .map_err(|e| js_error!("{}", e))?;
+    let m = macro_from_js(value, context)?;
+
+    let mut set = MacroSet::load(&path).map_err(|e| js_error!("Could not load macros: {}", e))?;
+    set.insert(name.to_std_string_escaped(), m);
+    set.save(&path)
+        .map_err(|e| js_error!("Could not save macros: {}", e))
+}
+
+fn remove_(core_name: JsString, game_name: JsString, name: JsString) -> JsResult<bool> {
+    let path = paths::macros_path(
+        &core_name.to_std_string_escaped(),
+        &game_name.to_std_string_escaped(),
+    )
+    .map_err(|e| js_error!("{}", e))?;
+
+    let mut set = MacroSet::load(&path).map_err(|e| js_error!("Could not load macros: {}", e))?;
+    let removed = set.remove(&name.to_std_string_escaped()).is_some();
+    if removed {
+        set.save(&path)
+            .map_err(|e| js_error!("Could not save macros: {}", e))?;
+    }
+    Ok(removed)
+}
+
+pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
+    Ok((
+        js_string!("macros"),
+        [
+            (
+                js_string!("startRecording"),
+                start_recording_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("stopRecording"),
+                stop_recording_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("isRecording"),
+                is_recording_.into_js_function_copied(context),
+            ),
+            (js_string!("play"), play_.into_js_function_copied(context)),
+            (js_string!("stop"), stop_.into_js_function_copied(context)),
+            (js_string!("list"), list_.into_js_function_copied(context)),
+            (js_string!("save"), save_.into_js_function_copied(context)),
+            (
+                js_string!("remove"),
+                remove_.into_js_function_copied(context),
+            ),
+        ]
+        .into_js_module(context),
+    ))
+}
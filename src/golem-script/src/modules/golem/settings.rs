@@ -126,6 +126,32 @@ fn invert_toolbar_(ContextData(data): ContextData<HostData>) -> bool {
     data.app().ui_settings().invert_toolbar()
 }
 
+fn set_rewind_enabled_(ContextData(data): ContextData<HostData>, enabled: bool) {
+    data.app_mut().ui_settings_mut().set_rewind_enabled(enabled);
+}
+
+fn rewind_enabled_(ContextData(data): ContextData<HostData>) -> bool {
+    data.app().ui_settings().rewind_enabled()
+}
+
+fn set_rewind_depth_(ContextData(data): ContextData<HostData>, depth: u32) {
+    data.app_mut().ui_settings_mut().set_rewind_depth(depth);
+}
+
+fn rewind_depth_(ContextData(data): ContextData<HostData>) -> u32 {
+    data.app().ui_settings().rewind_depth()
+}
+
+fn set_rewind_interval_(ContextData(data): ContextData<HostData>, interval_ms: u32) {
+    data.app_mut()
+        .ui_settings_mut()
+        .set_rewind_interval(std::time::Duration::from_millis(interval_ms as u64));
+}
+
+fn rewind_interval_(ContextData(data): ContextData<HostData>) -> u32 {
+    data.app().ui_settings().rewind_interval().as_millis() as u32
+}
+
 fn list_time_zones_() -> Vec<JsString> {
     let root = "/usr/share/zoneinfo/posix/";
 
@@ -282,6 +308,30 @@ pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
                 js_string!("invertToolbar"),
                 invert_toolbar_.into_js_function_copied(context),
             ),
+            (
+                js_string!("setRewindEnabled"),
+                set_rewind_enabled_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("rewindEnabled"),
+                rewind_enabled_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("setRewindDepth"),
+                set_rewind_depth_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("rewindDepth"),
+                rewind_depth_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("setRewindInterval"),
+                set_rewind_interval_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("rewindInterval"),
+                rewind_interval_.into_js_function_copied(context),
+            ),
             (
                 js_string!("updateDateTime"),
                 update_date_time_.into_js_function_copied(context),
@@ -0,0 +1,175 @@
+use std::time::SystemTime;
+
+use boa_engine::object::builtins::JsFunction;
+use boa_engine::value::TryFromJs;
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
+use boa_interop::{ContextData, IntoJsFunctionCopied, IntoJsModule};
+use boa_macros::{Finalize, JsData, Trace};
+use cyclone_v::sdr::ctrlcfg::MemType;
+use cyclone_v::sdr::memtest::{MemoryTest, MemoryTestError};
+use mister_fpga::core::AsMisterCore;
+use mister_fpga::fpga::{SdramSize, HOST_MEMORY_TEST_RANGE};
+use serde::Deserialize;
+
+use crate::HostData;
+
+#[derive(Debug, Trace, Finalize, JsData, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MemoryTestOptions {
+    offset: Option<usize>,
+    size: Option<usize>,
+    seed: Option<u32>,
+}
+
+impl TryFromJs for MemoryTestOptions {
+    fn try_from_js(value: &JsValue, context: &mut Context) -> JsResult<Self> {
+        serde_json::from_value(value.to_json(context)?)
+            .map_err(|e| JsError::from_opaque(JsString::from(e.to_string()).into()))
+    }
+}
+
+fn sdram_(ContextData(data): ContextData<HostData>, context: &mut Context) -> JsResult<JsValue> {
+    let fpga = data.app_mut().platform_mut().core_manager_mut().fpga();
+    let config = fpga.sdram_config();
+    let ecc = fpga.ecc_stats();
+
+    let json = serde_json::json!({
+        "memoryType": config.memory_type.map(|t| match t {
+            MemType::Ddr2 => "DDR2",
+            MemType::Ddr3 => "DDR3",
+            MemType::LpDdr2 => "LPDDR2",
+        }),
+        "calibrated": config.calibrated,
+        "size": config.size(),
+        "dataWidth": config.data_width,
+        "interfaceWidth": config.interface_width,
+        "chipSelects": config.chip_selects,
+        "casLatency": config.cas_latency,
+        "casWriteLatency": config.cas_write_latency,
+        "ecc": config.ecc,
+        "module": SdramSize::detect().map(|size| size.bytes()),
+        "eccStats": {
+            "singleBitErrors": ecc.single_bit_errors,
+            "doubleBitErrors": ecc.double_bit_errors,
+            "errorAddress": ecc.error_address,
+            "droppedCorrections": ecc.dropped_corrections,
+            "droppedAddress": ecc.dropped_address,
+        },
+    });
+    JsValue::from_json(&json, context)
+}
+
+fn clear_ecc_stats_(ContextData(data): ContextData<HostData>) {
+    data.app_mut()
+        .platform_mut()
+        .core_manager_mut()
+        .fpga_mut()
+        .clear_ecc_stats();
+}
+
+fn test_memory_(
+    test: JsString,
+    options: Option<MemoryTestOptions>,
+    on_progress: Option<JsFunction>,
+    ContextData(data): ContextData<HostData>,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let options = options.unwrap_or(MemoryTestOptions {
+        offset: None,
+        size: None,
+        seed: None,
+    });
+    let test = match test.to_std_string_escaped().as_str() {
+        "walkingOnes" => MemoryTest::WalkingOnes,
+        "addressInAddress" => MemoryTest::AddressInAddress,
+        "random" => MemoryTest::Random(options.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or(1)
+        })),
+        other => return Err(js_error!("Unknown memory test: {}", other)),
+    };
+    // Cores use this memory, so it can only be overwritten from the menu.
+    let is_menu = data
+        .app_mut()
+        .platform_mut()
+        .core_manager_mut()
+        .get_current_core()
+        .is_some_and(|core| core.as_menu_core().is_some());
+    if !is_menu {
+        return Err(js_error!("Memory can only be tested from the menu core"));
+    }
+
+    let start = options.offset.unwrap_or(HOST_MEMORY_TEST_RANGE.start);
+    let end = match options.size {
+        Some(size) => start
+            .checked_add(size)
+            .ok_or_else(|| js_error!("Invalid memory range"))?,
+        None => HOST_MEMORY_TEST_RANGE.end,
+    };
+
+    // The listener returns `false` to cancel the test. If it throws, the test
+    // is cancelled and the error is rethrown.
+    let mut listener_error = None;
+    let progress = |current: usize, total: usize| {
+        let Some(listener) = &on_progress else {
+            return true;
+        };
+        let event = serde_json::json!({ "current": current, "total": total });
+        match JsValue::from_json(&event, context)
+            .and_then(|event| listener.call(&JsValue::undefined(), &[event], context))
+        {
+            Ok(result) => !matches!(result, JsValue::Boolean(false)),
+            Err(error) => {
+                listener_error = Some(error);
+                false
+            }
+        }
+    };
+
+    let result = data
+        .app_mut()
+        .platform_mut()
+        .core_manager_mut()
+        .fpga_mut()
+        .test_host_memory(start..end, test, progress);
+
+    if let Some(error) = listener_error {
+        return Err(error);
+    }
+    match result {
+        Ok(()) => Ok(JsValue::null()),
+        Err(MemoryTestError::Mismatch {
+            offset,
+            expected,
+            actual,
+        }) => {
+            let json = serde_json::json!({
+                "address": offset,
+                "expected": expected,
+                "actual": actual,
+            });
+            JsValue::from_json(&json, context)
+        }
+        Err(e) => Err(js_error!("{}", e)),
+    }
+}
+
+pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
+    Ok((
+        js_string!("system"),
+        [
+            (js_string!("sdram"), sdram_.into_js_function_copied(context)),
+            (
+                js_string!("clearEccStats"),
+                clear_ecc_stats_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("testMemory"),
+                test_memory_.into_js_function_copied(context),
+            ),
+        ]
+        .into_js_module(context),
+    ))
+}
@@ -11,6 +11,7 @@ use either::Either;
 use golem_ui::application::menu;
 use golem_ui::application::panels::password::enter_password;
 use golem_ui::application::panels::prompt::prompt;
+use std::time::Duration;
 
 mod filesystem;
 
@@ -317,6 +318,8 @@ struct AlertOptions {
     message: String,
     title: Option<String>,
     choices: Option<Vec<String>>,
+    timeout: Option<f64>,
+    timeout_choice: Option<u32>,
 }
 
 fn alert_(
@@ -325,33 +328,42 @@ fn alert_(
     ContextData(host_defined): ContextData<HostData>,
     context: &mut Context,
 ) -> JsPromise {
-    let (message, title, choices) = match message {
+    let (message, title, choices, timeout, timeout_choice) = match message {
         Either::Left(message) => {
             if let Some(real_message) = title {
-                (real_message, message, vec!["OK".to_string()])
+                (real_message, message, vec!["OK".to_string()], None, None)
             } else {
-                (message, "".to_string(), vec!["OK".to_string()])
+                (message, "".to_string(), vec!["OK".to_string()], None, None)
             }
         }
         Either::Right(AlertOptions {
             message,
             title,
             choices: options,
-            ..
+            timeout,
+            timeout_choice,
         }) => (
             message,
             title.unwrap_or_default(),
             options.unwrap_or_else(|| vec!["OK".to_string()]),
+            timeout.filter(|s| s.is_finite() && *s > 0.),
+            timeout_choice.map(|i| i as usize),
         ),
     };
 
     let app = host_defined.app_mut();
-    let result = golem_ui::application::panels::alert::alert(
-        app,
-        &title,
-        &message,
-        &choices.iter().map(String::as_str).collect::<Vec<_>>(),
-    );
+    let choices = choices.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = match timeout {
+        Some(seconds) => golem_ui::application::panels::alert::alert_with_timeout(
+            app,
+            &title,
+            &message,
+            &choices,
+            Duration::from_secs_f64(seconds),
+            timeout_choice,
+        ),
+        None => golem_ui::application::panels::alert::alert(app, &title, &message, &choices),
+    };
 
     JsPromise::resolve(
         result.map_or(JsValue::null(), |n| JsValue::from(n)),
@@ -435,6 +447,21 @@ fn show_(message: String, title: Option<String>, ContextData(host_defined): Cont
     golem_ui::application::panels::alert::show(app, &title, &message);
 }
 
+fn progress_(
+    message: String,
+    current: f64,
+    total: f64,
+    ContextData(host_defined): ContextData<HostData>,
+) -> bool {
+    let app = host_defined.app_mut();
+    golem_ui::application::panels::progress::progress_frame(
+        app,
+        &message,
+        current.max(0.) as u32,
+        total.max(0.) as u32,
+    )
+}
+
 fn qr_code_(
     url: String,
     message: String,
@@ -498,6 +525,10 @@ pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
                 qr_code_.into_js_function_copied(context),
             ),
             (js_string!("show"), show_.into_js_function_copied(context)),
+            (
+                js_string!("progress"),
+                progress_.into_js_function_copied(context),
+            ),
             (
                 js_string!("textMenu"),
                 text_menu_.into_js_function_copied(context),
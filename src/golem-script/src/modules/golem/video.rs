@@ -1,44 +1,325 @@
-use boa_engine::{js_string, Context, JsError, JsResult, JsString, Module};
+use boa_engine::object::builtins::JsArray;
+use boa_engine::{js_error, js_string, Context, JsError, JsResult, JsString, JsValue, Module};
 use boa_interop::{ContextData, IntoJsFunctionCopied, IntoJsModule};
-use one_fpga::Core;
+use golem_ui::data::{color_controls, video_filters};
+use one_fpga::{Core, GolemCore};
 use std::str::FromStr;
+use std::time::Duration;
 
-use mister_fpga::config::edid::DefaultVideoMode;
-use mister_fpga::core::MisterFpgaCore;
+use mister_fpga::config::color::ColorControls;
+use mister_fpga::config::edid::{
+    get_active_edid, parse_custom_video_mode, DefaultVideoMode, EdidInfo,
+};
+use mister_fpga::config::filter::{list_filter_files, ScalerFilters};
+use mister_fpga::config::modes::available_video_modes;
+use mister_fpga::config::{Config, ConfigFile};
+use mister_fpga::core::{AsMisterCore, MisterFpgaCore};
 
 use crate::HostData;
 
-fn set_mode_(mode: String, ContextData(data): ContextData<HostData>) -> JsResult<()> {
-    let app = data.app_mut();
-    let core_manager = app.platform_mut().core_manager_mut();
-    let mut golem_core = core_manager.get_current_core().unwrap();
+fn set_mode_(
+    mode: JsString,
+    revert_after: Option<f64>,
+    ContextData(data): ContextData<HostData>,
+) -> JsResult<()> {
+    let mode = mode.to_std_string_escaped();
+    let video_mode = match DefaultVideoMode::from_str(&mode) {
+        Ok(default) => default.into(),
+        Err(_) => parse_custom_video_mode(Some(&mode)).map_err(|e| js_error!("{}", e))?,
+    };
+    let revert_after = revert_after
+        .filter(|s| s.is_finite() && *s > 0.)
+        .map(Duration::from_secs_f64);
+
+    with_mister_core(data, |core| {
+        core.set_video_mode(video_mode, revert_after)
+            .map_err(|e| js_error!("{}", e))
+    })
+}
+
+fn confirm_mode_(ContextData(data): ContextData<HostData>) -> JsResult<()> {
+    with_mister_core(data, |core| {
+        core.confirm_video_mode();
+        Ok(())
+    })
+}
+
+fn revert_mode_(ContextData(data): ContextData<HostData>) -> JsResult<bool> {
+    with_mister_core(data, |core| {
+        core.revert_video_mode().map_err(|e| js_error!("{}", e))
+    })
+}
+
+fn list_modes_(
+    ContextData(data): ContextData<HostData>,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let edid = active_edid_info().ok();
+    // Use the sections of the running core, if any.
+    let options = with_mister_core(data, |core| Ok(core.options().inner().clone()))
+        .unwrap_or_else(|_| Config::base().into_inner());
+    let modes = available_video_modes(&options, edid.as_ref())
+        .into_iter()
+        .map(|m| {
+            let json = serde_json::to_value(&m).map_err(JsError::from_rust)?;
+            JsValue::from_json(&json, context)
+        })
+        .collect::<JsResult<Vec<JsValue>>>()?;
+    Ok(JsArray::from_iter(modes, context).into())
+}
+
+fn with_mister_core<R>(
+    data: &HostData,
+    f: impl FnOnce(&mut MisterFpgaCore) -> JsResult<R>,
+) -> JsResult<R> {
+    let mut golem_core: GolemCore = data
+        .app_mut()
+        .platform_mut()
+        .core_manager_mut()
+        .get_current_core()
+        .ok_or_else(|| js_error!("No core running"))?;
+    // The menu core wraps a MiSTer core, which owns the video settings.
+    if let Some(menu) = golem_core.as_menu_core_mut() {
+        return f(menu.inner_mut());
+    }
     let core = golem_core
-        .as_any_mut()
-        .downcast_mut::<MisterFpgaCore>()
-        .unwrap();
+        .as_mister_core_mut()
+        .ok_or_else(|| js_error!("Current core is not a MiSTer core"))?;
+    f(core)
+}
+
+fn list_filters_(kind: JsString, context: &mut Context) -> JsResult<JsValue> {
+    let root = match kind.to_std_string_escaped().as_str() {
+        "filter" => Config::filters_root(),
+        "shadowMask" => Config::shadow_masks_root(),
+        "preset" => Config::presets_root(),
+        "gamma" => Config::gamma_root(),
+        other => return Err(js_error!("Unknown filter kind: {}", other)),
+    };
+
+    let files = list_filter_files(root)
+        .into_iter()
+        .map(|f| JsString::from(f).into())
+        .collect::<Vec<JsValue>>();
+    Ok(JsArray::from_iter(files, context).into())
+}
+
+fn scaler_filters_(
+    ContextData(data): ContextData<HostData>,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let filters = with_mister_core(data, |core| Ok(core.scaler_filters().clone()))?;
+    let json = serde_json::to_value(&filters).map_err(JsError::from_rust)?;
+    JsValue::from_json(&json, context)
+}
+
+fn set_scaler_filters_(
+    ContextData(data): ContextData<HostData>,
+    value: JsValue,
+    context: &mut Context,
+) -> JsResult<()> {
+    let filters: ScalerFilters = serde_json::from_value(value.to_json(context)?)
+        .map_err(|e| js_error!("Invalid filters: {}", e.to_string()))?;
+
+    with_mister_core(data, |core| {
+        core.set_scaler_filters(filters.clone())
+            .map_err(|e| js_error!("Could not set filters: {}", e))?;
+        video_filters::save(&core.config().name, &filters)
+            .map_err(|e| js_error!("Could not save filters: {}", e))
+    })
+}
+
+fn reset_scaler_filters_(ContextData(data): ContextData<HostData>) -> JsResult<()> {
+    with_mister_core(data, |core| {
+        video_filters::remove(&core.config().name)
+            .map_err(|e| js_error!("Could not remove filters: {}", e))?;
+        let filters = ScalerFilters::from_config(core.options().inner());
+        core.set_scaler_filters(filters)
+            .map_err(|e| js_error!("Could not set filters: {}", e))
+    })
+}
 
-    let video_mode = DefaultVideoMode::from_str(&mode).map_err(JsError::from_rust)?;
+fn load_preset_(name: JsString, context: &mut Context) -> JsResult<JsValue> {
+    let preset =
+        ScalerFilters::load_preset(Config::presets_root().join(name.to_std_string_escaped()))
+            .map_err(|e| js_error!("Could not load preset: {}", e))?;
+    let json = serde_json::to_value(&preset).map_err(JsError::from_rust)?;
+    JsValue::from_json(&json, context)
+}
+
+fn color_controls_(
+    ContextData(data): ContextData<HostData>,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let controls = with_mister_core(data, |core| Ok(core.color_controls().clone()))?;
+    let json = serde_json::to_value(&controls).map_err(JsError::from_rust)?;
+    JsValue::from_json(&json, context)
+}
+
+fn set_color_controls_(
+    ContextData(data): ContextData<HostData>,
+    value: JsValue,
+    save: Option<bool>,
+    context: &mut Context,
+) -> JsResult<()> {
+    let controls: ColorControls = serde_json::from_value(value.to_json(context)?)
+        .map_err(|e| js_error!("Invalid color controls: {}", e.to_string()))?;
+
+    with_mister_core(data, |core| {
+        core.set_color_controls(controls.clone())
+            .map_err(|e| js_error!("Could not set color controls: {}", e))?;
+        if save.unwrap_or(true) {
+            color_controls::save(&core.config().name, &controls)
+                .map_err(|e| js_error!("Could not save color controls: {}", e))?;
+        }
+        Ok(())
+    })
+}
+
+fn reset_color_controls_(ContextData(data): ContextData<HostData>) -> JsResult<()> {
+    with_mister_core(data, |core| {
+        color_controls::remove(&core.config().name)
+            .map_err(|e| js_error!("Could not remove color controls: {}", e))?;
+        let controls = ColorControls::from_config(core.options().inner());
+        core.set_color_controls(controls)
+            .map_err(|e| js_error!("Could not set color controls: {}", e))
+    })
+}
+
+/// Write values to the MiSTer.ini, in the `[MiSTer]` section or the given one.
+fn save_to_ini(section: Option<JsString>, values: Vec<(&str, String)>) -> JsResult<()> {
+    let section = section.map(|s| s.to_std_string_escaped());
+    let mut file = ConfigFile::base().map_err(|e| js_error!("Could not read MiSTer.ini: {}", e))?;
+    file.set_all(section.as_deref(), values)
+        .map_err(|e| js_error!("Invalid values: {}", e))?;
+    file.save()
+        .map_err(|e| js_error!("Could not save MiSTer.ini: {}", e))
+}
+
+fn save_scaler_filters_to_ini_(
+    ContextData(data): ContextData<HostData>,
+    section: Option<JsString>,
+) -> JsResult<()> {
+    let filters = with_mister_core(data, |core| Ok(core.scaler_filters().clone()))?;
+    save_to_ini(section, filters.ini_values())
+}
+
+fn save_color_controls_to_ini_(
+    ContextData(data): ContextData<HostData>,
+    section: Option<JsString>,
+) -> JsResult<()> {
+    let controls = with_mister_core(data, |core| Ok(core.color_controls().clone()))?;
+    save_to_ini(section, controls.ini_values())
+}
+
+fn active_edid_info() -> JsResult<EdidInfo> {
+    let edid = get_active_edid().map_err(|e| js_error!("{}", e))?;
+    EdidInfo::decode(&edid).map_err(|e| js_error!("{}", e))
+}
+
+fn edid_(context: &mut Context) -> JsResult<JsValue> {
+    let Ok(info) = active_edid_info() else {
+        return Ok(JsValue::null());
+    };
+    let json = serde_json::to_value(&info).map_err(JsError::from_rust)?;
+    JsValue::from_json(&json, context)
+}
+
+fn display_modes_(context: &mut Context) -> JsResult<JsValue> {
+    let modes = active_edid_info()?
+        .modes()
+        .into_iter()
+        .map(|m| {
+            let json = serde_json::to_value(&m).map_err(JsError::from_rust)?;
+            JsValue::from_json(&json, context)
+        })
+        .collect::<JsResult<Vec<JsValue>>>()?;
+    Ok(JsArray::from_iter(modes, context).into())
+}
 
-    eprintln!("Setting video mode: {:?}", video_mode);
-    mister_fpga::core::video::select_mode(
-        video_mode.into(),
-        false,
-        None,
-        None,
-        core.spi_mut(),
-        true,
-    )
-    .map_err(|e| JsError::from_opaque(JsString::from(e.to_string()).into()))?;
-    Ok(())
+fn validate_mode_(mode: JsString, context: &mut Context) -> JsResult<JsValue> {
+    // Without an EDID, only the limits of the scaler are checked.
+    let problems = match parse_custom_video_mode(Some(&mode.to_std_string_escaped())) {
+        Ok(mode) => active_edid_info().unwrap_or_default().check_mode(&mode),
+        Err(e) => vec![e],
+    };
+    let problems = problems
+        .into_iter()
+        .map(|p| JsString::from(p).into())
+        .collect::<Vec<JsValue>>();
+    Ok(JsArray::from_iter(problems, context).into())
 }
 
 pub fn create_module(context: &mut Context) -> JsResult<(JsString, Module)> {
     Ok((
         js_string!("video"),
-        [(
-            js_string!("setMode"),
-            set_mode_.into_js_function_copied(context),
-        )]
+        [
+            (
+                js_string!("setMode"),
+                set_mode_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("confirmMode"),
+                confirm_mode_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("revertMode"),
+                revert_mode_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("listModes"),
+                list_modes_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("listFilters"),
+                list_filters_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("scalerFilters"),
+                scaler_filters_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("setScalerFilters"),
+                set_scaler_filters_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("resetScalerFilters"),
+                reset_scaler_filters_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("loadPreset"),
+                load_preset_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("saveScalerFiltersToIni"),
+                save_scaler_filters_to_ini_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("colorControls"),
+                color_controls_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("setColorControls"),
+                set_color_controls_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("resetColorControls"),
+                reset_color_controls_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("saveColorControlsToIni"),
+                save_color_controls_to_ini_.into_js_function_copied(context),
+            ),
+            (js_string!("edid"), edid_.into_js_function_copied(context)),
+            (
+                js_string!("displayModes"),
+                display_modes_.into_js_function_copied(context),
+            ),
+            (
+                js_string!("validateMode"),
+                validate_mode_.into_js_function_copied(context),
+            ),
+        ]
         .into_js_module(context),
     ))
 }
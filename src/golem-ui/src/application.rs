@@ -1,6 +1,7 @@
 use crate::application::toolbar::Toolbar;
 use crate::data::settings::UiSettings;
 use crate::input::commands::CommandId;
+use crate::input::macros::MacroState;
 use crate::input::shortcut::Shortcut;
 use crate::input::InputState;
 use crate::macguiver::application::EventLoopState;
@@ -35,6 +36,8 @@ pub struct GoLEmApp {
 
     input_state: InputState,
     shortcuts: HashMap<Shortcut, CommandId>,
+    macros: MacroState,
+    rewind_shortcut: Option<Shortcut>,
 
     ui_settings: UiSettings,
 }
@@ -69,6 +72,8 @@ impl GoLEmApp {
             osd_buffer: DrawBuffer::new(osd_size),
             input_state: InputState::default(),
             shortcuts: Default::default(),
+            macros: MacroState::default(),
+            rewind_shortcut: None,
             ui_settings: UiSettings::default(),
         }
     }
@@ -81,6 +86,29 @@ impl GoLEmApp {
         self.shortcuts.remove(shortcut)
     }
 
+    pub fn rewind_shortcut(&self) -> Option<&Shortcut> {
+        self.rewind_shortcut.as_ref()
+    }
+
+    pub fn set_rewind_shortcut(&mut self, shortcut: Option<Shortcut>) {
+        self.rewind_shortcut = shortcut;
+    }
+
+    /// Whether the rewind shortcut is currently held.
+    pub fn is_rewind_held(&self) -> bool {
+        self.rewind_shortcut
+            .as_ref()
+            .is_some_and(|s| s.matches(&self.input_state))
+    }
+
+    pub fn macros(&self) -> &MacroState {
+        &self.macros
+    }
+
+    pub fn macros_mut(&mut self) -> &mut MacroState {
+        &mut self.macros
+    }
+
     pub fn init_platform(&mut self) {
         self.platform.init();
     }
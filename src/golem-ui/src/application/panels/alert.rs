@@ -9,7 +9,7 @@ use embedded_graphics::mono_font::MonoTextStyle;
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{Line, PrimitiveStyle};
-use embedded_graphics::text::Text;
+use embedded_graphics::text::{Alignment, Text};
 use embedded_layout::layout::linear::{spacing, LinearLayout};
 use embedded_layout::prelude::*;
 use embedded_menu::items::menu_item::SelectValue;
@@ -18,6 +18,7 @@ use embedded_menu::Menu;
 use embedded_text::style::{HeightMode, TextBoxStyleBuilder};
 use embedded_text::TextBox;
 use std::convert::identity;
+use std::time::{Duration, Instant};
 use tracing::error;
 use url::Url;
 
@@ -141,6 +142,37 @@ pub fn show(app: &mut GoLEmApp, title: &str, message: &str) {
 }
 
 pub fn alert(app: &mut GoLEmApp, title: &str, message: &str, choices: &[&str]) -> Option<usize> {
+    alert_(app, title, message, choices, None)
+}
+
+/// Show an alert that is dismissed after `timeout`, showing the remaining
+/// seconds. Returns `timeout_choice` when it times out, so callers can tell it
+/// apart from the user going back (`None`).
+pub fn alert_with_timeout(
+    app: &mut GoLEmApp,
+    title: &str,
+    message: &str,
+    choices: &[&str],
+    timeout: Duration,
+    timeout_choice: Option<usize>,
+) -> Option<usize> {
+    let deadline = Instant::now() + timeout;
+    alert_(
+        app,
+        title,
+        message,
+        choices,
+        Some((deadline, timeout_choice)),
+    )
+}
+
+fn alert_(
+    app: &mut GoLEmApp,
+    title: &str,
+    message: &str,
+    choices: &[&str],
+    deadline: Option<(Instant, Option<usize>)>,
+) -> Option<usize> {
     let display_area = app.main_buffer().bounding_box();
 
     let mut choices = choices
@@ -199,6 +231,23 @@ pub fn alert(app: &mut GoLEmApp, title: &str, message: &str, choices: &[&str]) -
         let _ = buffer.clear(BinaryColor::Off);
         let _ = layout.draw(buffer);
 
+        if let Some((deadline, timeout_choice)) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Some(timeout_choice);
+            }
+
+            let countdown = format!("{}s", remaining.as_secs() + 1);
+            let corner = buffer.bounding_box().bottom_right().unwrap_or_default();
+            let _ = Text::with_alignment(
+                &countdown,
+                corner - Point::new(2, 2),
+                MonoTextStyle::new(&ascii::FONT_6X10, BinaryColor::On),
+                Alignment::Right,
+            )
+            .draw(buffer);
+        }
+
         let menu = &mut layout.object;
         for ev in state.events() {
             match menu.interact(ev.clone()) {
@@ -1,11 +1,28 @@
+use crate::application::panels::alert::alert;
 use crate::application::GoLEmApp;
 use crate::input::commands::CommandId;
+use crate::input::macros::MacroInput;
 use image::DynamicImage;
+use mister_fpga::core::AsMisterCore;
+use one_fpga::core::Error as CoreError;
 use one_fpga::{Core, GolemCore};
 use sdl3::event::Event;
 use std::fmt::Debug;
-use std::time::Instant;
-use tracing::{debug, error, info, trace};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, trace, warn};
+
+/// How often to step back while the rewind shortcut is held.
+const REWIND_STEP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Keep the error of an input sent to the core if the core stopped responding.
+/// Other errors are ignored, the next input will be sent anyway.
+fn check_responding(result: Result<(), CoreError>, not_responding: &mut Option<CoreError>) {
+    if let Err(error) = result {
+        if error.is_core_not_responding() && not_responding.is_none() {
+            *not_responding = Some(error);
+        }
+    }
+}
 
 fn core_loop<C, E: Debug>(
     app: &mut GoLEmApp,
@@ -20,10 +37,28 @@ fn core_loop<C, E: Debug>(
         &[u8],
         &mut C,
     ) -> Result<(), E>,
+    not_responding: &mut Option<CoreError>,
 ) -> Result<(), E> {
     let mut should_check_savestates = matches!(core.save_state(0), Ok(Some(_)));
     let mut i = 0;
 
+    // Macros are timed using the core's frame rate, if known.
+    let frame_duration = core
+        .as_mister_core_mut()
+        .and_then(|c| c.video_info().ok())
+        .map(|info| info.vtime());
+    app.macros_mut().set_frame_duration(frame_duration);
+
+    if app.ui_settings().rewind_enabled() {
+        let options = app.ui_settings().rewind_options();
+        if let Some(c) = core.as_mister_core_mut() {
+            if let Err(error) = c.enable_rewind(options) {
+                warn!(?error, "Could not enable rewind");
+            }
+        }
+    }
+    let mut last_rewind_step = Instant::now();
+
     // This is a special loop that forwards everything to the core,
     // except for the menu button(s).
     app.event_loop(move |app, state| {
@@ -37,20 +72,48 @@ fn core_loop<C, E: Debug>(
                     ..
                 } => {
                     if !repeat {
-                        let _ = core.key_down((*scancode).into());
+                        check_responding(core.key_down((*scancode).into()), not_responding);
+                        app.macros_mut().record(
+                            MacroInput::Key {
+                                scancode: (*scancode).into(),
+                            },
+                            true,
+                        );
                     }
                 }
                 Event::KeyUp {
                     scancode: Some(scancode),
                     ..
                 } => {
-                    let _ = core.key_up((*scancode).into());
+                    check_responding(core.key_up((*scancode).into()), not_responding);
+                    app.macros_mut().record(
+                        MacroInput::Key {
+                            scancode: (*scancode).into(),
+                        },
+                        false,
+                    );
                 }
                 Event::ControllerButtonDown { which, button, .. } => {
-                    let _ = core.gamepad_button_down((which - 1) as usize, (*button).into());
+                    let result = core.gamepad_button_down((which - 1) as usize, (*button).into());
+                    check_responding(result, not_responding);
+                    app.macros_mut().record(
+                        MacroInput::GamepadButton {
+                            gamepad: (which - 1) as usize,
+                            button: (*button).into(),
+                        },
+                        true,
+                    );
                 }
                 Event::ControllerButtonUp { which, button, .. } => {
-                    let _ = core.gamepad_button_up((which - 1) as usize, (*button).into());
+                    let result = core.gamepad_button_up((which - 1) as usize, (*button).into());
+                    check_responding(result, not_responding);
+                    app.macros_mut().record(
+                        MacroInput::GamepadButton {
+                            gamepad: (which - 1) as usize,
+                            button: (*button).into(),
+                        },
+                        false,
+                    );
                 }
                 // TODO: this.
                 // Event::ControllerAxisMotion {
@@ -62,6 +125,13 @@ fn core_loop<C, E: Debug>(
             }
         }
 
+        if not_responding.is_some() {
+            return Some(Ok(()));
+        }
+
+        // Replay any macro currently playing.
+        app.macros_mut().poll(core);
+
         // Check if any action needs to be taken.
         for id in state.shortcuts() {
             if let Err(e) = shortcut_handler(app, core, id, context) {
@@ -69,6 +139,56 @@ fn core_loop<C, E: Debug>(
             }
         }
 
+        // Restore the savestate the game was launched with, once the core runs.
+        if let Some(c) = core
+            .as_mister_core_mut()
+            .filter(|c| c.has_pending_restore())
+        {
+            match c.poll_pending_restore() {
+                Ok(true) => info!("Restored savestate"),
+                Ok(false) => {}
+                Err(error) => error!(?error, "Could not restore savestate"),
+            }
+        }
+
+        // Rewind needs to be polled before checking savestates, so its own slot
+        // is never reported as dirty.
+        if let Some(c) = core.as_mister_core_mut().filter(|c| c.is_rewind_enabled()) {
+            if app.is_rewind_held() {
+                if last_rewind_step.elapsed() >= REWIND_STEP_INTERVAL {
+                    last_rewind_step = Instant::now();
+                    if let Err(error) = c.rewind_step() {
+                        error!(?error, "Could not rewind");
+                    }
+                }
+            } else if let Err(error) = c.poll_rewind() {
+                error!(?error, "Error capturing rewind state. Disabling rewind.");
+                c.disable_rewind();
+            }
+        }
+
+        // Serve SD card reads and writes (e.g. battery saves), and follow the
+        // core switching between PAL and NTSC.
+        if i % 5 == 0 {
+            if let Some(c) = core.as_mister_core_mut() {
+                // Reading the status bits is cheap, and tells whether the core
+                // still answers even if no input is sent.
+                if let Err(error) = c.read_status_bits() {
+                    if error.is_timeout() {
+                        *not_responding = Some(error.into());
+                        return Some(Ok(()));
+                    }
+                    warn!(?error, "Could not read the status bits");
+                }
+                if let Err(error) = c.poll_mounts() {
+                    error!(?error, "Error accessing SD card");
+                }
+                if let Err(error) = c.poll_video_mode() {
+                    error!(?error, "Error switching video mode");
+                }
+            }
+        }
+
         // Check Savestates and SD Card every 5 loop. This should still be under every
         // frame, since we approximate 600fps.
         if should_check_savestates && i % 5 == 0 {
@@ -101,6 +221,24 @@ fn core_loop<C, E: Debug>(
                                     break;
                                 }
 
+                                // MiSTer cores save states with the core and game information.
+                                if let Some(c) = core.as_mister_core() {
+                                    match c
+                                        .create_save_state_file(buffer, screenshot.as_ref())
+                                        .and_then(|f| f.to_vec().map_err(Into::into))
+                                    {
+                                        Ok(file) => buffer = file,
+                                        Err(err) => {
+                                            error!(
+                                                ?err,
+                                                "Error saving savestate. Will stop trying."
+                                            );
+                                            should_check_savestates = false;
+                                            break;
+                                        }
+                                    }
+                                }
+
                                 if let Err(err) = savestate_handler(
                                     app,
                                     core,
@@ -154,7 +292,55 @@ pub fn run_core_loop<C, E: Debug>(
     app.hide_toolbar();
     app.platform_mut().core_manager_mut().hide_osd();
 
-    let result = core_loop(app, core, context, shortcut_handler, savestate_handler);
+    let mut not_responding = None;
+    let result = core_loop(
+        app,
+        core,
+        context,
+        shortcut_handler,
+        savestate_handler,
+        &mut not_responding,
+    );
+
+    if let Some(error) = not_responding {
+        // Nothing can be sent to the core anymore, not even the inputs held by a
+        // macro. Go back to the menu and tell the user.
+        app.macros_mut().abandon();
+        let _ = app.macros_mut().stop_recording();
+        // Writes the core already sent (e.g. battery saves) are only on the host.
+        if let Some(c) = core.as_mister_core_mut() {
+            if let Err(error) = c.flush_mounts() {
+                error!(?error, "Could not flush SD cards");
+            }
+        }
+        if let Err(error) = app
+            .platform_mut()
+            .core_manager_mut()
+            .recover_from_hang(&error)
+        {
+            error!(?error, "Could not reload the menu");
+        }
+        app.show_toolbar();
+        alert(
+            app,
+            "Core Not Responding",
+            "The core stopped responding and was unloaded. It might not be compatible \
+             with this version, or the core file might be corrupted.",
+            &["Back"],
+        );
+        return result;
+    }
+
+    if let Some(c) = core.as_mister_core_mut() {
+        c.disable_rewind();
+        if let Err(error) = c.flush_mounts() {
+            error!(?error, "Could not flush SD cards");
+        }
+    }
+
+    // Make sure no macro outlives the core.
+    app.macros_mut().stop(core);
+    let _ = app.macros_mut().stop_recording();
 
     debug!("Core loop ended");
     info!("Loading Main Menu");
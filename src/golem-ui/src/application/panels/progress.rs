@@ -1,5 +1,5 @@
 use crate::application::GoLEmApp;
-use embedded_graphics::draw_target::{DrawTarget, DrawTargetExt};
+use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::geometry::{Dimensions, Point, Size};
 use embedded_graphics::mono_font::{ascii, MonoTextStyle};
 use embedded_graphics::pixelcolor::BinaryColor;
@@ -12,7 +12,11 @@ use embedded_graphics::Drawable;
 use embedded_layout::align::horizontal;
 use embedded_layout::layout::linear::{spacing, LinearLayout};
 use embedded_layout::object_chain::Chain;
+use sdl3::event::Event;
+use sdl3::gamepad::Button;
+use sdl3::keyboard::Keycode;
 
+#[derive(Clone, Copy)]
 struct ProgressBar {
     width: u32,
     total: u32,
@@ -93,7 +97,7 @@ impl ProgressBar {
         self.bar.primitive.rectangle.size.width = if self.total == 0 {
             0
         } else {
-            current * self.width / self.total
+            (current.min(self.total) as u64 * self.width as u64 / self.total as u64) as u32
         };
     }
 }
@@ -108,22 +112,15 @@ pub enum ProgressBarUpdate {
     Idle,
 }
 
-pub fn progress_bar(
-    app: &mut GoLEmApp,
-    message: &str,
-    total: u32,
-    mut update_callback: impl FnMut() -> ProgressBarUpdate,
-) -> bool {
-    let display_area = app.main_buffer().bounding_box();
+/// Draw the message and a progress bar on the OSD.
+fn draw_progress(app: &mut GoLEmApp, message: &str, bar: ProgressBar) {
+    let display_area = app.osd_buffer().bounding_box();
 
-    let bar = ProgressBar::new(display_area.size.width * 3 / 4, total, 0);
-    let message = message.to_string();
-
-    let mut layout = LinearLayout::vertical(
+    let layout = LinearLayout::vertical(
         Chain::new(
             LinearLayout::vertical(
                 Chain::new(Text::new(
-                    &message,
+                    message,
                     Point::zero(),
                     MonoTextStyle::new(&ascii::FONT_8X13_BOLD, BinaryColor::On),
                 ))
@@ -143,12 +140,26 @@ pub fn progress_bar(
     .with_spacing(spacing::DistributeFill(display_area.size.height - 32))
     .arrange();
 
+    let buffer = app.osd_buffer();
+    let _ = buffer.clear(BinaryColor::Off);
+    let _ = layout.draw(buffer);
+}
+
+pub fn progress_bar(
+    app: &mut GoLEmApp,
+    message: &str,
+    total: u32,
+    mut update_callback: impl FnMut() -> ProgressBarUpdate,
+) -> bool {
+    let display_area = app.osd_buffer().bounding_box();
+
+    let mut bar = ProgressBar::new(display_area.size.width * 3 / 4, total, 0);
+    let message = message.to_string();
+
     let mut last_update = std::time::Instant::now();
 
     app.draw_loop(|app, _state| {
-        let mut buffer = app.main_buffer().color_converted();
-        let _ = buffer.clear(BinaryColor::Off);
-        let _ = layout.draw(&mut buffer);
+        draw_progress(app, &message, bar);
 
         let now = std::time::Instant::now();
         let elapsed = now - last_update;
@@ -157,11 +168,9 @@ pub fn progress_bar(
 
             match update_callback() {
                 ProgressBarUpdate::UpdateBar(current) => {
-                    let bar = &mut layout.inner_mut().object;
                     bar.set_progress(current, None);
                 }
                 ProgressBarUpdate::UpdateBarTotal(current, total) => {
-                    let bar = &mut layout.inner_mut().object;
                     bar.set_progress(current, Some(total));
                 }
                 ProgressBarUpdate::UpdateMessage(_new_message) => {
@@ -181,3 +190,32 @@ pub fn progress_bar(
         None
     })
 }
+
+/// Draw a single frame of a progress bar, for operations that run on the main
+/// thread and report their progress as they go (e.g. loading a core). Unlike
+/// [`progress_bar`], this returns right away. The OSD is shown, as a core
+/// being loaded starts with it hidden. Returns `false` if the user pressed
+/// Escape or the B button to cancel.
+pub fn progress_frame(app: &mut GoLEmApp, message: &str, current: u32, total: u32) -> bool {
+    let display_area = app.osd_buffer().bounding_box();
+    let bar = ProgressBar::new(display_area.size.width * 3 / 4, total, current);
+    app.platform_mut().core_manager_mut().show_osd();
+
+    app.draw_loop(|app, state| {
+        draw_progress(app, message, bar);
+
+        let cancelled = state.events().any(|event| {
+            matches!(
+                event,
+                Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } | Event::ControllerButtonDown {
+                    button: Button::B,
+                    ..
+                }
+            )
+        });
+        Some(!cancelled)
+    })
+}
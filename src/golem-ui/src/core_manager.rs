@@ -1,16 +1,18 @@
 use std::path::Path;
 use std::time::SystemTime;
 
-use byteorder::{LittleEndian, ReadBytesExt};
-use tracing::info;
+use tracing::{info, warn};
 
 use mister_fpga::core::file::SdCard;
 use mister_fpga::core::{MenuCore, MisterFpgaCore};
-use mister_fpga::fpga::MisterFpga;
-use one_fpga::core::SaveState;
+use mister_fpga::fpga::{MisterFpga, Program, Rbf, RbfFile};
+use one_fpga::core::Rom;
+use one_fpga::progress::{Cancelled, LoadStage, ProgressToken};
 use one_fpga::runner::{CoreLaunchInfo, CoreType, Slot};
 use one_fpga::{Core, GolemCore};
 
+use crate::data::{color_controls, last_launch, saves, video_filters};
+
 pub struct CoreManager {
     fpga: MisterFpga,
     current_core: Option<GolemCore>,
@@ -52,18 +54,19 @@ impl CoreManager {
         Ok(core)
     }
 
-    pub fn load(&mut self, program: &[u8], is_menu: bool) -> Result<GolemCore, String> {
-        let program = if &program[..6] != b"MiSTer" {
-            program
-        } else {
-            let start = 16;
-            let size = (&program[12..]).read_u32::<LittleEndian>().unwrap() as usize;
-            &program[start..start + size]
-        };
+    pub fn load(&mut self, program: impl Program, is_menu: bool) -> Result<GolemCore, String> {
+        self.load_with_progress(program, is_menu, &mut ProgressToken::default())
+    }
 
+    fn load_with_progress(
+        &mut self,
+        program: impl Program,
+        is_menu: bool,
+        progress: &mut ProgressToken,
+    ) -> Result<GolemCore, String> {
         self.fpga.wait_for_ready();
         self.fpga
-            .load(program)
+            .load_with_progress(program, progress)
             .map_err(|e| format!("Could not load program: {e:?}"))?;
         self.fpga.core_reset();
 
@@ -73,8 +76,9 @@ impl CoreManager {
     pub fn load_menu(&mut self) -> Result<GolemCore, String> {
         info!("Loading menu");
         let bytes = include_bytes!("../assets/menu.rbf");
+        let rbf = Rbf::parse(bytes).map_err(|e| format!("Invalid menu core: {e}"))?;
 
-        let mut core = self.load(bytes, true)?;
+        let mut core = self.load(rbf, true)?;
 
         if let Some(core) = core.as_any_mut().downcast_mut::<MenuCore>() {
             // Send the logo to the framebuffer.
@@ -96,17 +100,62 @@ impl CoreManager {
     }
 
     pub fn load_core(&mut self, path: impl AsRef<Path>) -> Result<GolemCore, String> {
+        self.load_core_with_progress(path, &mut ProgressToken::default())
+    }
+
+    fn load_core_with_progress(
+        &mut self,
+        path: impl AsRef<Path>,
+        progress: &mut ProgressToken,
+    ) -> Result<GolemCore, String> {
         info!("Loading core from: {:?}", path.as_ref().display());
-        let bytes = std::fs::read(path.as_ref()).map_err(|e| e.to_string())?;
-        let core = self.load(&bytes, false)?;
+        // Check the file before resetting the FPGA, so an invalid core does not
+        // unload the current one.
+        let file = RbfFile::open(path.as_ref()).map_err(|e| format!("Invalid core file: {e}"))?;
+        let core = self.load_with_progress(file, false, progress)?;
         Ok(core)
     }
 
     pub fn launch(&mut self, info: CoreLaunchInfo<()>) -> Result<GolemCore, String> {
+        self.launch_with_progress(info, &mut ProgressToken::default())
+    }
+
+    /// Launch a core like [`Self::launch`], reporting the progress of loading
+    /// the core, its ROM and mounting its files. If the token is cancelled,
+    /// the menu core is loaded back (unless the current core was used) and
+    /// [`Cancelled`] is returned as the error.
+    pub fn launch_with_progress(
+        &mut self,
+        info: CoreLaunchInfo<()>,
+        progress: &mut ProgressToken,
+    ) -> Result<GolemCore, String> {
+        let is_current = matches!(info.core, CoreType::Current);
+        match self.launch_(info, progress) {
+            Err(error) if progress.is_cancelled() => {
+                info!(?error, "Launch cancelled");
+                if !is_current {
+                    self.load_menu()?;
+                }
+                Err(Cancelled.into())
+            }
+            result => result,
+        }
+    }
+
+    fn launch_(
+        &mut self,
+        info: CoreLaunchInfo<()>,
+        progress: &mut ProgressToken,
+    ) -> Result<GolemCore, String> {
+        let mut rbf_path = None;
         let mut golem_core = match info.core {
             CoreType::Current => self.get_current_core().ok_or("No core running")?,
             CoreType::Menu => self.load_menu()?,
-            CoreType::RbfFile(path) => self.load_core(path)?,
+            CoreType::RbfFile(path) => {
+                let core = self.load_core_with_progress(&path, progress)?;
+                rbf_path = Some(path);
+                core
+            }
         };
 
         let mister_core = golem_core
@@ -114,49 +163,150 @@ impl CoreManager {
             .downcast_mut::<MisterFpgaCore>()
             .unwrap();
 
-        if let Some(rom) = &info.rom {
-            mister_core
+        match &info.rom {
+            Some(Rom::File(path)) => mister_core.send_rom_with_progress(path, progress)?,
+            Some(rom) => mister_core
                 .send_rom(rom.clone())
-                .map_err(|e| e.to_string())?;
+                .map_err(|e| e.to_string())?,
+            None => {}
         }
 
-        if !info.files.is_empty() {
-            let should_sav = mister_core
-                .menu_options()
-                .iter()
-                .filter_map(|x| x.as_load_file_info())
-                .any(|i| i.save_support);
+        let mut files = info.files;
+        let should_sav = mister_core
+            .menu_options()
+            .iter()
+            .filter_map(|x| x.as_load_file_info())
+            .any(|i| i.save_support);
 
+        // Cores that support saves get the game's SAV file mounted, unless
+        // one was provided. Only that file is limited in size; images provided
+        // by the caller (e.g. hard drives) can be much larger.
+        let mut sav_slot = None;
+        if should_sav && !files.contains_key(&0) {
+            if let Some(Rom::File(rom_path)) = &info.rom {
+                let sav_path = saves::sav_file_path(&mister_core.config().name, rom_path);
+                if let Err(error) = saves::backup(&sav_path, saves::DEFAULT_BACKUP_COUNT) {
+                    warn!(?error, ?sav_path, "Could not back up SAV file");
+                }
+                files.insert(0, Slot::File(sav_path));
+                sav_slot = Some(0);
+            }
+        }
+
+        if !files.is_empty() {
             if should_sav {
-                for (idx, f) in info.files {
+                progress.start(LoadStage::Mount, files.len())?;
+                for (i, (idx, f)) in files.into_iter().enumerate() {
                     if let Slot::File(ref path) = f {
-                        mister_core.mount(SdCard::from_path(path)?, idx as u8)?;
+                        let mut card = SdCard::from_path(path)?;
+                        if sav_slot == Some(idx) {
+                            card = card.with_max_size(saves::MAX_SAV_SIZE);
+                        }
+                        mister_core.mount(card, idx as u8)?;
                     }
+                    progress.update(i + 1)?;
                 }
             }
             mister_core.end_send_file()?;
-            while mister_core.poll_mounts()? {}
+            while mister_core.poll_mounts()? {
+                progress.check()?;
+            }
         } else {
             mister_core.end_send_file()?;
         }
 
-        // Load all savestates.
-        if let Some(savestate_manager) = mister_core.save_states_mut() {
-            for (slot, state) in savestate_manager.slots_mut().iter_mut().enumerate() {
-                if let Some(Slot::File(path)) = info.save_state.get(slot) {
-                    let mut f = std::fs::File::open(path).map_err(|e| e.to_string())?;
-                    state.load(&mut f).map_err(|e| e.to_string())?;
+        // Use the scaler filters and colors the user picked for this core, if any.
+        match video_filters::load(&mister_core.config().name) {
+            Ok(Some(filters)) => {
+                if let Err(error) = mister_core.set_scaler_filters(filters) {
+                    warn!(?error, "Could not set scaler filters");
+                }
+            }
+            Ok(None) => {}
+            Err(error) => warn!(?error, "Could not load scaler filters"),
+        }
+        match color_controls::load(&mister_core.config().name) {
+            Ok(Some(controls)) => {
+                if let Err(error) = mister_core.set_color_controls(controls) {
+                    warn!(?error, "Could not set color controls");
+                }
+            }
+            Ok(None) => {}
+            Err(error) => warn!(?error, "Could not load color controls"),
+        }
+
+        // Load all savestates, then restore the first one so the game continues
+        // from where it was saved. The core loop restores it once the core runs.
+        if !info.save_state.is_empty() && mister_core.save_states().is_none() {
+            warn!("Core does not support savestates, ignoring.");
+        } else {
+            // A savestate that cannot be loaded is skipped, so the game can still
+            // be started.
+            let mut restore_first = !info.save_state.is_empty();
+            for (slot, state) in info.save_state.iter().enumerate() {
+                let data = match state {
+                    Slot::File(path) => std::fs::read(path)
+                        .map_err(|e| format!("Could not read savestate {path:?}: {e}")),
+                    Slot::Memory(_, data) => Ok(data.clone()),
+                };
+                let result = data.and_then(|data| {
+                    mister_core
+                        .load_save_state(slot, &data)
+                        .map_err(|e| e.to_string())
+                });
+                if let Err(error) = result {
+                    warn!(slot, ?error, "Could not load savestate, skipping");
+                    restore_first &= slot != 0;
+                }
+            }
+            if restore_first {
+                if let Err(error) = mister_core.restore_save_state_when_running(0) {
+                    warn!(?error, "Could not restore savestate");
                 }
             }
         }
 
+        // Remember the core and game, for `bootcore=lastcore`.
+        if let Some(rbf_path) = rbf_path {
+            let game = match &info.rom {
+                Some(Rom::File(path)) => Some(path.as_path()),
+                _ => None,
+            };
+            if let Err(error) = last_launch::save(&rbf_path, game) {
+                warn!(?error, "Could not save the last core launched");
+            }
+        }
+
         Ok(golem_core)
     }
 
+    /// Unload a core that stopped responding and go back to the menu. Nothing is
+    /// sent to the core as every command would time out, so its SD cards should
+    /// be flushed before calling this.
+    pub fn recover_from_hang(
+        &mut self,
+        error: &one_fpga::core::Error,
+    ) -> Result<GolemCore, String> {
+        warn!(?error, "Core stopped responding, reloading the menu");
+        if let Some(mut core) = self.current_core.take() {
+            core.quit();
+        }
+        self.fpga.core_reset();
+        self.load_menu()
+    }
+
     pub fn get_current_core(&mut self) -> Option<GolemCore> {
         self.current_core.clone()
     }
 
+    /// Take this manager's state, leaving one without a current core that
+    /// shares the same FPGA in its place. Used to launch a core while the
+    /// progress listener may still use the manager left in the application.
+    pub fn take(&mut self) -> CoreManager {
+        let empty = CoreManager::new(self.fpga.clone());
+        std::mem::replace(self, empty)
+    }
+
     pub fn show_osd(&mut self) {
         self.fpga_mut().osd_enable();
     }
@@ -1,2 +1,7 @@
+pub mod color_controls;
+pub mod core_catalog;
+pub mod last_launch;
 pub mod paths;
+pub mod saves;
 pub mod settings;
+pub mod video_filters;
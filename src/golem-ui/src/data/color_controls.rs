@@ -0,0 +1,30 @@
+//! Color controls and gamma curve picked by the user for a core, replacing the
+//! defaults from the MiSTer.ini.
+use crate::data::paths;
+use mister_fpga::config::color::ColorControls;
+
+/// Load the color controls saved for a core, if any.
+pub fn load(core_name: &str) -> Result<Option<ColorControls>, String> {
+    let path = paths::color_controls_path(core_name);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid color controls in {path:?}: {e}"))
+}
+
+/// Save the color controls for a core, to be applied every time it is launched.
+pub fn save(core_name: &str, controls: &ColorControls) -> Result<(), String> {
+    let content = serde_json::to_string(controls).map_err(|e| e.to_string())?;
+    std::fs::write(paths::color_controls_path(core_name), content).map_err(|e| e.to_string())
+}
+
+/// Remove the color controls saved for a core, so the defaults are used.
+pub fn remove(core_name: &str) -> Result<(), String> {
+    let path = paths::color_controls_path(core_name);
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! The index of cores installed, cached between runs so only new or updated
+//! cores have to be read.
+use crate::data::paths;
+use mister_fpga::config::Config;
+use mister_fpga::core_info::{CatalogCore, CoreCatalog, RbfMetadata};
+use mister_fpga::fpga::SdramSize;
+use std::path::Path;
+use tracing::warn;
+
+/// Load the cached catalog. An invalid cache is ignored, it will be rebuilt
+/// on the next scan.
+pub fn load() -> CoreCatalog {
+    let path = paths::core_catalog_path();
+    if !path.exists() {
+        return CoreCatalog::default();
+    }
+
+    std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            warn!(?path, "Invalid core catalog, ignoring: {e}");
+            CoreCatalog::default()
+        })
+}
+
+pub fn save(catalog: &CoreCatalog) -> Result<(), String> {
+    let content = serde_json::to_string(catalog).map_err(|e| e.to_string())?;
+    std::fs::write(paths::core_catalog_path(), content).map_err(|e| e.to_string())
+}
+
+/// Scan the cores root for cores and update the cache. Returns the cores
+/// found, sorted by name and most recent first.
+pub fn scan() -> Result<Vec<CatalogCore>, String> {
+    let mut catalog = load();
+    let cores = catalog
+        .scan(Config::cores_root())
+        .map_err(|e| format!("Could not scan cores: {e}"))?
+        .to_vec();
+    save(&catalog)?;
+    Ok(cores)
+}
+
+/// A warning to show before launching a core that cannot run on this
+/// hardware, e.g. a core that needs an SDRAM module when none is installed.
+/// Cores that were not scanned yet are read, which is slow.
+pub fn hardware_warning(path: &Path) -> Option<String> {
+    let requirement = match load().metadata_of(path) {
+        Some(metadata) => metadata.sdram_requirement(),
+        None => std::fs::read(path)
+            .map(|bytes| RbfMetadata::from_bytes(&bytes).sdram_requirement())
+            .unwrap_or_default(),
+    };
+    requirement.check(SdramSize::detect())
+}
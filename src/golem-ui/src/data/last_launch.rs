@@ -0,0 +1,80 @@
+//! The last core and game launched, to boot straight into them on startup with
+//! the `bootcore` option of the MiSTer.ini.
+use crate::data::paths;
+use mister_fpga::config::{Config, MisterConfig};
+use mister_fpga::core_info::CoreInfo;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastLaunch {
+    /// The path of the RBF file.
+    pub core: PathBuf,
+
+    /// The path of the ROM loaded, if any.
+    pub game: Option<PathBuf>,
+}
+
+/// A core (and game) to launch at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootTarget {
+    pub core: CoreInfo,
+    pub game: Option<PathBuf>,
+
+    /// How long to wait before launching, or `None` to launch right away.
+    pub timeout: Option<Duration>,
+}
+
+/// Load the last core launched, if any.
+pub fn load() -> Result<Option<LastLaunch>, String> {
+    let path = paths::last_launch_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid last launch in {path:?}: {e}"))
+}
+
+/// Record a core being launched. The name of the core is also written to the
+/// `lastcore.dat` file of the MiSTer.
+pub fn save(core: &Path, game: Option<&Path>) -> Result<(), String> {
+    if let Some(info) = CoreInfo::from_path(core) {
+        Config::set_last_core_data(info.exact_name()).map_err(|e| e.to_string())?;
+    }
+
+    let launch = LastLaunch {
+        core: core.to_path_buf(),
+        game: game.map(Path::to_path_buf),
+    };
+    let content = serde_json::to_string(&launch).map_err(|e| e.to_string())?;
+    std::fs::write(paths::last_launch_path(), content).map_err(|e| e.to_string())
+}
+
+/// The core to launch at startup, following the `bootcore` option. With
+/// `lastcore` and `exactlastcore`, the last game is launched too if it was
+/// played on that core.
+pub fn boot_target(config: &MisterConfig) -> Option<BootTarget> {
+    let bootcore = config.bootcore();
+    let is_last_core = bootcore.is_last_core();
+    let core = Option::<CoreInfo>::from(bootcore)?;
+
+    let game = if is_last_core {
+        load()
+            .ok()
+            .flatten()
+            .filter(|last| last.core.as_path() == core.path())
+            .and_then(|last| last.game)
+            .filter(|game| game.exists())
+    } else {
+        None
+    };
+
+    Some(BootTarget {
+        core,
+        game,
+        timeout: config.bootcore_timeout(),
+    })
+}
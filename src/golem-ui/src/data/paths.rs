@@ -50,6 +50,30 @@ pub fn sav_root_path() -> PathBuf {
     p
 }
 
+pub fn macros_root_path() -> PathBuf {
+    let p = config_root_path().join("macros");
+    if !p.exists() {
+        std::fs::create_dir_all(&p).unwrap();
+    }
+    p
+}
+
+pub fn video_filters_root_path() -> PathBuf {
+    let p = config_root_path().join("video_filters");
+    if !p.exists() {
+        std::fs::create_dir_all(&p).unwrap();
+    }
+    p
+}
+
+pub fn color_controls_root_path() -> PathBuf {
+    let p = config_root_path().join("color_controls");
+    if !p.exists() {
+        std::fs::create_dir_all(&p).unwrap();
+    }
+    p
+}
+
 pub fn savestates_path(core_name: &str) -> PathBuf {
     savestates_root_path().join(core_name)
 }
@@ -58,6 +82,41 @@ pub fn sav_path(core_name: &str) -> PathBuf {
     sav_root_path().join(core_name)
 }
 
+/// The path of the macros of a game, `<macros>/<core>/<game>.json`. The names
+/// come from scripts, so they cannot contain a path.
+pub fn macros_path(core_name: &str, game_name: &str) -> Result<PathBuf, String> {
+    check_file_name(core_name)?;
+    check_file_name(game_name)?;
+    Ok(macros_root_path()
+        .join(core_name)
+        .join(format!("{game_name}.json")))
+}
+
+/// Check that a name can be used as a single file name, e.g. that it does not
+/// contain a path separator or go up a directory.
+fn check_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+        return Err(format!("Invalid name: {name:?}"));
+    }
+    Ok(())
+}
+
+pub fn video_filters_path(core_name: &str) -> PathBuf {
+    video_filters_root_path().join(format!("{core_name}.json"))
+}
+
+pub fn color_controls_path(core_name: &str) -> PathBuf {
+    color_controls_root_path().join(format!("{core_name}.json"))
+}
+
+pub fn last_launch_path() -> PathBuf {
+    config_root_path().join("last_launch.json")
+}
+
+pub fn core_catalog_path() -> PathBuf {
+    config_root_path().join("core_catalog.json")
+}
+
 pub fn settings_path() -> PathBuf {
     config_root_path().join("settings.json5")
 }
@@ -91,3 +150,13 @@ pub fn all_settings_paths() -> Vec<PathBuf> {
         .filter(|p| p.exists())
         .collect()
 }
+
+#[test]
+fn file_names() {
+    assert!(check_file_name("NES").is_ok());
+    assert!(check_file_name("Super Mario Bros. (World)").is_ok());
+    assert!(check_file_name("").is_err());
+    assert!(check_file_name("..").is_err());
+    assert!(check_file_name("../../x").is_err());
+    assert!(check_file_name("a\\b").is_err());
+}
@@ -0,0 +1,71 @@
+//! Battery saves (SAV files) of games.
+use crate::data::paths;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Number of backups kept for every SAV file.
+pub const DEFAULT_BACKUP_COUNT: usize = 5;
+
+/// Maximum size a SAV file can grow to, to protect against a core writing
+/// past the end of its save.
+pub const MAX_SAV_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The path of the SAV file for a game, `<saves>/<core>/<game>.sav`.
+pub fn sav_file_path(core_name: &str, rom_path: &Path) -> PathBuf {
+    let game_name = rom_path
+        .file_stem()
+        .map_or_else(|| "game".into(), |s| s.to_string_lossy());
+    paths::sav_path(core_name).join(format!("{game_name}.sav"))
+}
+
+fn backup_path(sav_path: &Path, index: usize) -> PathBuf {
+    let file_name = sav_path.file_name().unwrap_or_default().to_string_lossy();
+    sav_path
+        .with_file_name("backups")
+        .join(format!("{file_name}.{index}"))
+}
+
+/// Make a backup of a SAV file, rotating older backups so only the `count`
+/// most recent are kept (`.1` being the most recent). Returns the path of
+/// the new backup, or `None` if there was nothing to back up.
+pub fn backup(sav_path: &Path, count: usize) -> Result<Option<PathBuf>, String> {
+    let is_empty = std::fs::metadata(sav_path).map_or(true, |m| m.len() == 0);
+    if count == 0 || is_empty {
+        return Ok(None);
+    }
+
+    let oldest = backup_path(sav_path, count);
+    if oldest.exists() {
+        std::fs::remove_file(&oldest).map_err(|e| e.to_string())?;
+    }
+    for i in (1..count).rev() {
+        let from = backup_path(sav_path, i);
+        if from.exists() {
+            std::fs::rename(&from, backup_path(sav_path, i + 1)).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let path = backup_path(sav_path, 1);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::copy(sav_path, &path).map_err(|e| e.to_string())?;
+    debug!(?path, "Backed up SAV file");
+    Ok(Some(path))
+}
+
+#[test]
+fn backup_rotates() {
+    let dir = tempdir::TempDir::new("saves").unwrap();
+    let sav = dir.path().join("game.sav");
+
+    assert_eq!(backup(&sav, 2).unwrap(), None);
+    for i in 1..=3u8 {
+        std::fs::write(&sav, [i]).unwrap();
+        backup(&sav, 2).unwrap();
+    }
+
+    assert_eq!(std::fs::read(backup_path(&sav, 1)).unwrap(), [3]);
+    assert_eq!(std::fs::read(backup_path(&sav, 2)).unwrap(), [2]);
+    assert!(!backup_path(&sav, 3).exists());
+}
@@ -1,6 +1,10 @@
 use crate::application::menu::style::{MenuStyleFontSize, MenuStyleOptions};
+use mister_fpga::savestate::rewind::{
+    RewindOptions, DEFAULT_REWIND_DEPTH, DEFAULT_REWIND_INTERVAL,
+};
 use serde::{Deserialize, Serialize};
 use std::hash::Hash;
+use std::time::Duration;
 use strum::Display;
 
 #[derive(Default, Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, Display)]
@@ -44,6 +48,14 @@ pub struct UiSettings {
     toolbar_datetime_format: Option<DateTimeFormat>,
 
     menu_font_size: Option<MenuStyleFontSize>,
+
+    rewind_enabled: Option<bool>,
+
+    /// Number of states kept for rewinding.
+    rewind_depth: Option<u32>,
+
+    /// Interval between rewind states, in milliseconds.
+    rewind_interval: Option<u32>,
 }
 
 impl UiSettings {
@@ -80,6 +92,39 @@ impl UiSettings {
         self.menu_font_size = Some(menu_font_size);
     }
 
+    pub fn rewind_enabled(&self) -> bool {
+        self.rewind_enabled.unwrap_or(false)
+    }
+
+    pub fn set_rewind_enabled(&mut self, rewind_enabled: bool) {
+        self.rewind_enabled = Some(rewind_enabled);
+    }
+
+    pub fn rewind_depth(&self) -> u32 {
+        self.rewind_depth.unwrap_or(DEFAULT_REWIND_DEPTH as u32)
+    }
+
+    pub fn set_rewind_depth(&mut self, rewind_depth: u32) {
+        self.rewind_depth = Some(rewind_depth.max(1));
+    }
+
+    pub fn rewind_interval(&self) -> Duration {
+        self.rewind_interval.map_or(DEFAULT_REWIND_INTERVAL, |ms| {
+            Duration::from_millis(ms as u64)
+        })
+    }
+
+    pub fn set_rewind_interval(&mut self, rewind_interval: Duration) {
+        self.rewind_interval = Some(rewind_interval.as_millis().clamp(16, u32::MAX as u128) as u32);
+    }
+
+    pub fn rewind_options(&self) -> RewindOptions {
+        RewindOptions {
+            depth: self.rewind_depth() as usize,
+            interval: self.rewind_interval(),
+        }
+    }
+
     pub fn menu_style_options(&self) -> MenuStyleOptions {
         MenuStyleOptions {
             font_size: self.menu_font_size(),
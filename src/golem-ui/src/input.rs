@@ -6,6 +6,7 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 
 pub mod commands;
+pub mod macros;
 pub mod password;
 pub mod shortcut;
 
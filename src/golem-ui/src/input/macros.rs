@@ -0,0 +1,406 @@
+use one_fpga::inputs::{Button, Scancode};
+use one_fpga::Core;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// The default frame duration used when the core does not report its refresh rate.
+pub const DEFAULT_FRAME_DURATION: Duration = Duration::from_nanos(16_666_667);
+
+/// A single input that can be part of a macro.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MacroInput {
+    Key {
+        #[serde_as(as = "DisplayFromStr")]
+        scancode: Scancode,
+    },
+    GamepadButton {
+        gamepad: usize,
+        button: Button,
+    },
+}
+
+impl MacroInput {
+    fn send(&self, core: &mut impl Core, pressed: bool) -> Result<(), one_fpga::core::Error> {
+        match (self, pressed) {
+            (MacroInput::Key { scancode }, true) => core.key_down(*scancode),
+            (MacroInput::Key { scancode }, false) => core.key_up(*scancode),
+            (MacroInput::GamepadButton { gamepad, button }, true) => {
+                core.gamepad_button_down(*gamepad, *button)
+            }
+            (MacroInput::GamepadButton { gamepad, button }, false) => {
+                core.gamepad_button_up(*gamepad, *button)
+            }
+        }
+    }
+}
+
+/// An input press or release, happening at a specific frame from the start of the macro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroEvent {
+    pub frame: u32,
+    pub input: MacroInput,
+    pub pressed: bool,
+}
+
+/// A recorded sequence of inputs, with frame timing.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputMacro {
+    events: Vec<MacroEvent>,
+}
+
+impl InputMacro {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> &[MacroEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The number of frames this macro lasts.
+    pub fn frames(&self) -> u32 {
+        self.events.last().map_or(0, |e| e.frame)
+    }
+}
+
+/// All macros saved for a single game, by name.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroSet {
+    #[serde(flatten)]
+    macros: BTreeMap<String, InputMacro>,
+}
+
+impl MacroSet {
+    /// Load a macro set from a file. If the file does not exist, returns an empty set.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, content).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InputMacro> {
+        self.macros.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl ToString, m: InputMacro) {
+        self.macros.insert(name.to_string(), m);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<InputMacro> {
+        self.macros.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &InputMacro)> {
+        self.macros.iter()
+    }
+}
+
+/// Records inputs sent to a core, with their timing.
+#[derive(Debug)]
+pub struct MacroRecorder {
+    start: Instant,
+    frame_duration: Duration,
+    events: Vec<MacroEvent>,
+}
+
+impl MacroRecorder {
+    pub fn new(frame_duration: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            frame_duration,
+            events: Vec::new(),
+        }
+    }
+
+    fn current_frame(&self) -> u32 {
+        (self.start.elapsed().as_nanos() / self.frame_duration.as_nanos().max(1)) as u32
+    }
+
+    pub fn record(&mut self, input: MacroInput, pressed: bool) {
+        // Wait for the first input before starting the clock, so the macro
+        // does not start with a long pause.
+        if self.events.is_empty() {
+            if !pressed {
+                return;
+            }
+            self.start = Instant::now();
+        }
+
+        let frame = self.current_frame();
+        self.events.push(MacroEvent {
+            frame,
+            input,
+            pressed,
+        });
+    }
+
+    /// Finish the recording. Inputs that were pressed but never released are
+    /// removed, as those are most likely the shortcut used to stop the recording.
+    pub fn finish(self) -> InputMacro {
+        let mut events = self.events;
+        let mut held: Vec<MacroInput> = Vec::new();
+        for e in events.iter() {
+            if e.pressed {
+                held.push(e.input);
+            } else {
+                held.retain(|i| i != &e.input);
+            }
+        }
+
+        events.retain(|e| !held.contains(&e.input));
+        if let Some(first) = events.first().map(|e| e.frame) {
+            for e in events.iter_mut() {
+                e.frame -= first;
+            }
+        }
+
+        debug!(events = events.len(), "Macro recorded");
+        InputMacro { events }
+    }
+}
+
+/// Replays a macro on a core.
+#[derive(Debug)]
+pub struct MacroPlayer {
+    start: Instant,
+    frame_duration: Duration,
+    input_macro: InputMacro,
+    next: usize,
+    held: Vec<MacroInput>,
+}
+
+impl MacroPlayer {
+    pub fn new(input_macro: InputMacro, frame_duration: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            frame_duration,
+            input_macro,
+            next: 0,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.input_macro.events.len()
+    }
+
+    /// Send all events that are due to the core. Returns true when the macro
+    /// has finished playing.
+    pub fn poll(&mut self, core: &mut impl Core) -> bool {
+        let frame =
+            (self.start.elapsed().as_nanos() / self.frame_duration.as_nanos().max(1)) as u32;
+
+        while let Some(event) = self.input_macro.events.get(self.next) {
+            if event.frame > frame {
+                break;
+            }
+
+            if let Err(error) = event.input.send(core, event.pressed) {
+                warn!(?error, ?event, "Could not send macro input to core");
+            }
+            if event.pressed {
+                self.held.push(event.input);
+            } else {
+                self.held.retain(|i| i != &event.input);
+            }
+            self.next += 1;
+        }
+
+        self.is_done()
+    }
+
+    /// Stop playing, releasing any input still held by the macro.
+    pub fn stop(mut self, core: &mut impl Core) {
+        for input in self.held.drain(..) {
+            let _ = input.send(core, false);
+        }
+    }
+}
+
+/// The macro state of the application; at most one recording and one playback
+/// can happen at the same time.
+#[derive(Debug, Default)]
+pub struct MacroState {
+    frame_duration: Option<Duration>,
+    recorder: Option<MacroRecorder>,
+    player: Option<MacroPlayer>,
+}
+
+impl MacroState {
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration.unwrap_or(DEFAULT_FRAME_DURATION)
+    }
+
+    pub fn set_frame_duration(&mut self, frame_duration: Option<Duration>) {
+        self.frame_duration = frame_duration.filter(|d| !d.is_zero());
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.player.is_some()
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(MacroRecorder::new(self.frame_duration()));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<InputMacro> {
+        self.recorder.take().map(MacroRecorder::finish)
+    }
+
+    pub fn record(&mut self, input: MacroInput, pressed: bool) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(input, pressed);
+        }
+    }
+
+    /// Play a macro, replacing the one playing. Inputs held by the macro
+    /// replaced are released first, so they don't stay stuck in the core.
+    pub fn play(&mut self, input_macro: InputMacro, core: &mut impl Core) {
+        self.stop(core);
+        self.player = Some(MacroPlayer::new(input_macro, self.frame_duration()));
+    }
+
+    /// Play the current macro, if any.
+    pub fn poll(&mut self, core: &mut impl Core) {
+        if let Some(player) = self.player.as_mut() {
+            if player.poll(core) {
+                self.player = None;
+            }
+        }
+    }
+
+    pub fn stop(&mut self, core: &mut impl Core) {
+        if let Some(player) = self.player.take() {
+            player.stop(core);
+        }
+    }
+
+    /// Drop the current macro without releasing its inputs, e.g. when the core
+    /// stopped responding and would not receive them anyway.
+    pub fn abandon(&mut self) {
+        self.player = None;
+    }
+}
+
+#[test]
+fn recorder_drops_unreleased_inputs() {
+    let a = MacroInput::GamepadButton {
+        gamepad: 0,
+        button: Button::A,
+    };
+    let guide = MacroInput::GamepadButton {
+        gamepad: 0,
+        button: Button::Guide,
+    };
+
+    let mut recorder = MacroRecorder::new(DEFAULT_FRAME_DURATION);
+    // Release before any press is ignored.
+    recorder.record(a, false);
+    recorder.record(a, true);
+    recorder.record(a, false);
+    recorder.record(guide, true);
+
+    let m = recorder.finish();
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.events()[0].frame, 0);
+    assert!(m.events().iter().all(|e| e.input == a));
+}
+
+/// The buttons of the first gamepad held by a core.
+#[cfg(test)]
+fn held_buttons(core: &impl Core) -> one_fpga::inputs::gamepad::ButtonSet {
+    core.gamepad_buttons(0).unwrap().unwrap_or_default()
+}
+
+#[test]
+fn play_releases_inputs_of_replaced_macro() {
+    use one_fpga::core::NullCore;
+    use one_fpga::inputs::gamepad::ButtonSet;
+
+    let press = |button| MacroEvent {
+        frame: 0,
+        input: MacroInput::GamepadButton { gamepad: 0, button },
+        pressed: true,
+    };
+    let hold_a = InputMacro {
+        events: vec![press(Button::A)],
+    };
+    let hold_b = InputMacro {
+        events: vec![press(Button::B)],
+    };
+
+    let mut core = NullCore::default();
+    let mut state = MacroState::default();
+    state.play(hold_a, &mut core);
+    state.poll(&mut core);
+    assert!(held_buttons(&core).contains(Button::A));
+
+    // Replacing the macro releases A before B is pressed.
+    state.play(hold_b, &mut core);
+    assert_eq!(held_buttons(&core), ButtonSet::new());
+    state.poll(&mut core);
+    assert!(held_buttons(&core).contains(Button::B));
+    assert!(!held_buttons(&core).contains(Button::A));
+
+    state.stop(&mut core);
+    assert_eq!(held_buttons(&core), ButtonSet::new());
+    assert!(!state.is_playing());
+}
+
+#[test]
+fn abandon_sends_nothing() {
+    use one_fpga::core::NullCore;
+
+    let hold_a = InputMacro {
+        events: vec![MacroEvent {
+            frame: 0,
+            input: MacroInput::GamepadButton {
+                gamepad: 0,
+                button: Button::A,
+            },
+            pressed: true,
+        }],
+    };
+
+    let mut core = NullCore::default();
+    let mut state = MacroState::default();
+    state.play(hold_a, &mut core);
+    state.poll(&mut core);
+
+    // A hung core would not receive the release, so it is not sent.
+    state.abandon();
+    assert!(!state.is_playing());
+    assert!(held_buttons(&core).contains(Button::A));
+}
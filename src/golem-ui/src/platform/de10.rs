@@ -74,12 +74,18 @@ impl De10Platform {
     }
 
     pub fn update_toolbar(&mut self, buffer: &DrawBuffer<BinaryColor>) {
-        self.title_display
-            .send(self.core_manager.fpga_mut(), buffer);
+        if let Err(error) = self
+            .title_display
+            .send(self.core_manager.fpga_mut(), buffer)
+        {
+            error!(?error, "Could not update the toolbar");
+        }
     }
 
     pub fn update_osd(&mut self, buffer: &DrawBuffer<BinaryColor>) {
-        self.osd_display.send(self.core_manager.fpga_mut(), buffer);
+        if let Err(error) = self.osd_display.send(self.core_manager.fpga_mut(), buffer) {
+            error!(?error, "Could not update the OSD");
+        }
     }
 
     pub fn update_menu_framebuffer(&mut self) {
@@ -1,10 +1,10 @@
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 use clap_verbosity_flag::Level as VerbosityLevel;
 use clap_verbosity_flag::{LogLevel, Verbosity};
-use tracing::{info, warn, Level};
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::fmt::Subscriber;
 
 use golem_ui::application;
@@ -33,6 +33,33 @@ pub struct Flags {
     /// This is useful for testing. A prompt will ensure that this is what you want.
     #[clap(long, default_value = "false")]
     pub reset: bool,
+
+    /// Check a MiSTer.ini for problems, print them and exit. The exit code is 1 if
+    /// any error was found. Defaults to the MiSTer.ini on the SD card.
+    #[clap(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "/media/fat/MiSTer.ini")]
+    pub check_config: Option<PathBuf>,
+
+    /// Record every register access of the SoC to a file, one per line, to
+    /// debug hardware issues. The trace can be replayed in tests.
+    #[clap(long, value_name = "PATH")]
+    pub trace_registers: Option<PathBuf>,
+}
+
+fn check_config(path: &Path) -> ! {
+    let report = match mister_fpga::config::ValidationReport::from_file(path) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    };
+
+    if report.is_empty() {
+        println!("{}: no problems found.", path.display());
+    } else {
+        print!("{}", report);
+    }
+    std::process::exit(if report.has_errors() { 1 } else { 0 });
 }
 
 fn main() {
@@ -51,6 +78,10 @@ fn main() {
     println!(include_str!("../assets/header.txt"), v = v);
 
     let opts = Flags::parse();
+    if let Some(path) = opts.check_config.as_ref() {
+        check_config(path);
+    }
+
     // Initialize tracing.
     let subscriber = Subscriber::builder();
     let subscriber = match opts.verbose.log_level() {
@@ -86,8 +117,30 @@ fn main() {
     // Create the application and run it.
     let start = std::time::Instant::now();
     info!("Starting application...");
-    golem_script::run(opts.script.as_ref(), application::GoLEmApp::new())
-        .expect("Failed to run golem");
+    let mut app = application::GoLEmApp::new();
+
+    // Written line by line so the trace is complete even if GoLEm crashes
+    // or exits without returning.
+    let _trace = opts.trace_registers.as_ref().map(|path| {
+        info!(?path, "Tracing register accesses");
+        let file = match std::fs::File::create(path) {
+            Ok(file) => file,
+            Err(error) => {
+                error!(?path, ?error, "Could not create the trace file");
+                std::process::exit(2);
+            }
+        };
+        let recorder = cyclone_v::memory::TraceRecorder::with_writer(
+            cyclone_v::memory::DEFAULT_TRACE_CAPACITY,
+            std::io::LineWriter::new(file),
+        );
+        app.platform_mut()
+            .core_manager_mut()
+            .fpga()
+            .trace_registers(&recorder)
+    });
+
+    golem_script::run(opts.script.as_ref(), app).expect("Failed to run golem");
     let elapsed = start.elapsed();
     info!(?elapsed, "Done");
 }
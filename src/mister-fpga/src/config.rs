@@ -9,19 +9,21 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 use thiserror::Error;
-use tracing::info;
+use tracing::{info, warn};
 use validator::Validate;
 use video::aspect::AspectRatio;
 use video::resolution::Resolution;
 
 mod bootcore;
 mod fb_size;
+mod file;
 mod hdmi_limited;
 mod hdr;
 mod ini; // Internal module.
 mod ntsc_mode;
 mod osd_rotate;
 mod reset_combo;
+mod validation;
 mod vga_mode;
 pub mod video;
 mod vrr_mode;
@@ -30,11 +32,13 @@ mod vsync_adjust;
 
 pub use bootcore::*;
 pub use fb_size::*;
+pub use file::*;
 pub use hdmi_limited::*;
 pub use hdr::*;
 pub use ntsc_mode::*;
 pub use osd_rotate::*;
 pub use reset_combo::*;
+pub use validation::*;
 pub use vga_mode::*;
 pub use video::*;
 pub use vrr_mode::*;
@@ -877,6 +881,12 @@ impl MisterConfig {
         self.direct_video.unwrap_or_default()
     }
 
+    /// Whether specific video modes are set for PAL or NTSC cores.
+    #[inline]
+    pub fn has_pal_ntsc_video_modes(&self) -> bool {
+        !self.direct_video() && (self.video_conf_pal.is_some() || self.video_conf_ntsc.is_some())
+    }
+
     /// Whether to use vsync adjust.
     #[inline]
     pub fn vsync_adjust(&self) -> VsyncAdjustConfig {
@@ -887,6 +897,23 @@ impl MisterConfig {
         }
     }
 
+    /// The range of core refresh rates `vsync_adjust` is applied to. Zero means no limit.
+    #[inline]
+    pub fn vsync_adjust_refresh_range(&self) -> (f32, f32) {
+        let max = self.refresh_max.filter(|max| *max > 0.).unwrap_or(f32::MAX);
+        (self.refresh_min.unwrap_or_default(), max)
+    }
+
+    /// The Variable Refresh Rate mode.
+    #[inline]
+    pub fn vrr_mode(&self) -> VrrModeConfig {
+        if self.direct_video() {
+            VrrModeConfig::Disabled
+        } else {
+            self.vrr_mode.unwrap_or_default()
+        }
+    }
+
     /// Whether to use PAL in the menu.
     #[inline]
     pub fn menu_pal(&self) -> bool {
@@ -898,6 +925,19 @@ impl MisterConfig {
     pub fn forced_scandoubler(&self) -> bool {
         self.forced_scandoubler.unwrap_or_default()
     }
+
+    /// The core to launch at startup.
+    #[inline]
+    pub fn bootcore(&self) -> BootCoreConfig {
+        self.bootcore.clone().unwrap_or_default()
+    }
+
+    /// How long to wait before launching the boot core, giving the user a
+    /// chance to cancel. Without a timeout, the core is launched right away.
+    #[inline]
+    pub fn bootcore_timeout(&self) -> Option<Duration> {
+        self.bootcore_timeout.filter(|d| !d.is_zero())
+    }
 }
 
 #[cfg(test)]
@@ -925,6 +965,38 @@ pub struct Config {
     #[serde(flatten)]
     #[merge(strategy = merge::hashmap::recurse)]
     overrides: HashMap<String, MisterConfig>,
+
+    /// The override sections merged into the MiSTer section, in order.
+    #[serde(skip)]
+    #[merge(skip)]
+    merged_sections: Vec<String>,
+}
+
+/// The priority of a section for a core, or `None` if it does not apply to it.
+/// Like the MiSTer, names are case insensitive, a section can list multiple
+/// cores separated by `+`, and a name ending with `*` matches all cores starting
+/// with it. Sections naming only the core come last, so they take precedence.
+fn core_section_priority(section: &str, corename: &str) -> Option<u8> {
+    if section.to_ascii_lowercase().starts_with("video=") {
+        return None;
+    }
+
+    let corename = corename.to_ascii_lowercase();
+    let names = section
+        .split('+')
+        .map(|n| n.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    if names.iter().any(|n| *n == corename) {
+        Some(if names.len() == 1 { 2 } else { 1 })
+    } else if names
+        .iter()
+        .filter_map(|n| n.strip_suffix('*'))
+        .any(|prefix| corename.starts_with(prefix))
+    {
+        Some(0)
+    } else {
+        None
+    }
 }
 
 impl Config {
@@ -954,8 +1026,12 @@ impl Config {
 
     pub fn base() -> Self {
         let path = Self::root().join("MiSTer.ini");
-        Self::load(&path).unwrap_or_else(|_| {
-            info!(?path, "Failed to load MiSTer.ini, using defaults.");
+        Self::load(&path).unwrap_or_else(|error| {
+            warn!(
+                ?path,
+                %error,
+                "Failed to load MiSTer.ini, using defaults. Run `golem --check-config` for details."
+            );
             let mut c = Self::default();
             c.mister.set_defaults();
             c
@@ -970,24 +1046,119 @@ impl Config {
         Self::root().join("config")
     }
 
+    pub fn filters_root() -> PathBuf {
+        Self::root().join("Filters")
+    }
+
+    pub fn shadow_masks_root() -> PathBuf {
+        Self::root().join("Shadow_Masks")
+    }
+
+    pub fn presets_root() -> PathBuf {
+        Self::root().join("Presets")
+    }
+
+    pub fn gamma_root() -> PathBuf {
+        Self::root().join("Gamma")
+    }
+
     pub fn last_core_data() -> Option<String> {
-        std::fs::read_to_string(Self::config_root().join("lastcore.dat")).ok()
+        std::fs::read_to_string(Self::config_root().join("lastcore.dat"))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Record the last core launched, with its version (e.g. `SNES_20240101`),
+    /// in the same file as the MiSTer so `bootcore=lastcore` works with both.
+    pub fn set_last_core_data(exact_name: &str) -> io::Result<()> {
+        let root = Self::config_root();
+        std::fs::create_dir_all(&root)?;
+        std::fs::write(root.join("lastcore.dat"), exact_name)
     }
 
+    /// Merge the sections of a core into the MiSTer section. Sections using
+    /// wildcards are merged first, then the ones listing multiple cores, so the
+    /// section naming only the core takes precedence.
     pub fn merge_core_override(&mut self, corename: &str) {
-        if let Some(o) = self.overrides.get(corename) {
-            self.mister.merge(o.clone());
+        let mut sections = self
+            .overrides
+            .keys()
+            .filter_map(|s| core_section_priority(s, corename).map(|p| (p, s.clone())))
+            .collect::<Vec<_>>();
+        sections.sort();
+        for (_, section) in sections {
+            self.merge_section_(section);
         }
     }
 
-    pub fn merge_video_override(&mut self, resolution: Resolution) {
-        // Try to get `123x456@78` format first.
-        let video_str = format!("video={}", resolution);
-        if let Some(o) = self.overrides.get(&video_str) {
+    /// Merge the section of a video resolution into the MiSTer section, with
+    /// the refresh rate (e.g. `[video=1920x1080@60]`) or without it.
+    pub fn merge_video_override(&mut self, resolution: Resolution, refresh: Option<f64>) {
+        let without_refresh = format!("video={}", resolution);
+        let with_refresh = refresh.map(|r| format!("{without_refresh}@{}", r.round()));
+
+        let find = |name: &str| {
+            self.overrides
+                .keys()
+                .find(|k| k.eq_ignore_ascii_case(name))
+                .cloned()
+        };
+        let section = with_refresh
+            .and_then(|name| find(&name))
+            .or_else(|| find(&without_refresh));
+        if let Some(section) = section {
+            self.merge_section_(section);
+        }
+    }
+
+    fn merge_section_(&mut self, section: String) {
+        if let Some(o) = self.overrides.get(&section) {
             self.mister.merge(o.clone());
+            self.merged_sections.push(section);
         }
     }
 
+    /// The override sections merged into the MiSTer section, in order.
+    pub fn merged_sections(&self) -> &[String] {
+        &self.merged_sections
+    }
+
+    pub fn inner(&self) -> &MisterConfig {
+        &self.mister
+    }
+
+    /// The configuration of a core (or the menu, without a name): the MiSTer
+    /// section, then the sections of the core, then the section of the
+    /// resolution of its video mode.
+    pub fn for_core(corename: Option<&str>) -> Self {
+        let mut config = Self::base();
+        if let Some(corename) = corename {
+            config.merge_core_override(corename);
+        }
+
+        // Selecting the video mode can read the EDID, which is slow.
+        let has_video_sections = config
+            .overrides
+            .keys()
+            .any(|k| k.to_ascii_lowercase().starts_with("video="));
+        if has_video_sections {
+            if let Some(mode) = video::edid::select_video_mode(&config.mister)
+                .ok()
+                .and_then(|m| m.vmode_def)
+            {
+                let resolution = Resolution::new(
+                    (mode.param.hact << mode.param.pr) as u16,
+                    mode.param.vact as u16,
+                );
+                config.merge_video_override(resolution, Some(mode.frame_rate()));
+            }
+        }
+
+        info!(sections = ?config.merged_sections, "Loaded MiSTer.ini");
+        config
+    }
+
     /// Read INI config using our custom parser, then output the JSON, then parse that into
     /// the config struct. This is surprisingly fast, solid and byte compatible with the
     /// original CPP code (checked manually on various files).
@@ -999,7 +1170,7 @@ impl Config {
             return Ok(Default::default());
         }
 
-        let json = ini::parse(&s).unwrap().to_json_string(
+        let json = ini::parse(&s)?.to_json_string(
             |name, value: &str| match name {
                 "mouse_throttle"
                 | "video_info"
@@ -1253,6 +1424,41 @@ fn works_with_empty_file() {
     }
 }
 
+#[test]
+fn merge_override_sections() {
+    let ini = "\
+        [MiSTer]\n\
+        vscale_border=1\n\
+        [snes+NES]\n\
+        vsync_adjust=1\n\
+        [SNES]\n\
+        vsync_adjust=2\n\
+        [genesis*]\n\
+        vscale_border=4\n\
+        [video=1920x1080@60]\n\
+        vscale_border=8\n\
+        [video=640x480]\n\
+        vscale_border=16\n\
+    ";
+
+    let mut config = Config::from_ini(ini.as_bytes()).unwrap();
+    config.merge_core_override("SNES");
+    assert_eq!(config.merged_sections(), ["snes+NES", "SNES"]);
+    assert_eq!(config.inner().vsync_adjust(), VsyncAdjustConfig::LowLatency);
+
+    let mut config = Config::from_ini(ini.as_bytes()).unwrap();
+    config.merge_core_override("Genesis32X");
+    config.merge_video_override(Resolution::new(1920, 1080), Some(59.94));
+    assert_eq!(config.merged_sections(), ["genesis*", "video=1920x1080@60"]);
+    assert_eq!(config.inner().vscale_border, Some(8));
+
+    let mut config = Config::from_ini(ini.as_bytes()).unwrap();
+    config.merge_core_override("NES");
+    config.merge_video_override(Resolution::new(640, 480), Some(60.));
+    assert_eq!(config.merged_sections(), ["snes+NES", "video=640x480"]);
+    assert_eq!(config.inner().vscale_border, Some(16));
+}
+
 #[cfg(test)]
 mod examples {
     use crate::config::*;
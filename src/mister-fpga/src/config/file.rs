@@ -0,0 +1,154 @@
+//! Editing the MiSTer.ini in place. Only the edited entries are changed; the
+//! comments, ordering, unknown keys and override sections are kept, so the file
+//! can still be used by the stock MiSTer.
+use crate::config::ini::IniDocument;
+use crate::config::{Config, ConfigError};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The name of the main section of the MiSTer.ini.
+pub const MISTER_SECTION: &str = "MiSTer";
+
+/// A MiSTer.ini file being edited. Sections are the `[MiSTer]` section when
+/// `None`, otherwise a core or a video resolution override section.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+    document: IniDocument,
+}
+
+impl ConfigFile {
+    /// Open a config file. A missing file is considered empty, and will be
+    /// created when saving.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            document: IniDocument::new(&content),
+        })
+    }
+
+    /// Open the MiSTer.ini at the root of the SD card.
+    pub fn base() -> Result<Self, ConfigError> {
+        Self::open(Config::root().join("MiSTer.ini"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The names of the sections in the file, including `MiSTer`.
+    pub fn sections(&self) -> Vec<String> {
+        self.document.sections().map(str::to_string).collect()
+    }
+
+    /// The raw value of a key, as written in the file.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        self.document
+            .get(Some(section.unwrap_or(MISTER_SECTION)), key)
+    }
+
+    /// Set a key. The file must still be a valid config after the change, or
+    /// it is left unchanged and an error is returned.
+    pub fn set(
+        &mut self,
+        section: Option<&str>,
+        key: &str,
+        value: impl ToString,
+    ) -> Result<(), ConfigError> {
+        self.set_all(section, [(key, value.to_string())])
+    }
+
+    /// Set multiple keys of a section at once. Either all changes are applied,
+    /// or none if the resulting config is invalid.
+    pub fn set_all<'a>(
+        &mut self,
+        section: Option<&str>,
+        values: impl IntoIterator<Item = (&'a str, String)>,
+    ) -> Result<(), ConfigError> {
+        let section = Some(section.unwrap_or(MISTER_SECTION));
+        let previous = self.document.clone();
+        for (key, value) in values {
+            self.document.set(section, key, value.trim());
+        }
+
+        if let Err(e) = self.config() {
+            self.document = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove a key, so its default (or the value of the `[MiSTer]` section,
+    /// for overrides) is used. Returns whether the key was in the section.
+    pub fn remove(&mut self, section: Option<&str>, key: &str) -> bool {
+        self.document
+            .remove(Some(section.unwrap_or(MISTER_SECTION)), key)
+    }
+
+    /// Parse the file as edited.
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        Config::from_ini(self.document.to_string().as_bytes())
+    }
+
+    /// Write the file back. The content is written to a temporary file first,
+    /// so a power loss cannot leave a truncated MiSTer.ini behind.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let tmp = self.path.with_extension("ini.tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(self.document.to_string().as_bytes())?;
+        // The content must be on disk before the rename is.
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.document.fmt(f)
+    }
+}
+
+#[test]
+fn edit_config_file() {
+    use crate::config::color::ColorControls;
+
+    let dir = tempdir::TempDir::new("golem").unwrap();
+    let path = dir.path().join("MiSTer.ini");
+    std::fs::write(
+        &path,
+        "[MiSTer]\n; Comment.\nvscale_border=2\nsome_future_key=abc\n\n[snes]\nvsync_adjust=1\n",
+    )
+    .unwrap();
+
+    let mut file = ConfigFile::open(&path).unwrap();
+    file.set(None, "vscale_border", 4).unwrap();
+    file.set(Some("snes"), "vsync_adjust", 2).unwrap();
+    assert!(file.set(None, "vscale_border", "abc").is_err());
+    file.set_all(None, ColorControls::default().ini_values())
+        .unwrap();
+    assert_eq!(file.get(None, "vscale_border"), Some("4"));
+    file.save().unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[MiSTer]\n; Comment.\nvscale_border=4\nsome_future_key=abc\n\
+         video_brightness=50\nvideo_contrast=50\nvideo_saturation=100\nvideo_hue=0\n\
+         video_gain_offset=1, 0, 1, 0, 1, 0\n\n[snes]\nvsync_adjust=2\n",
+    );
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.inner().vscale_border, Some(4));
+    assert_eq!(
+        config.into_inner_with_overrides(&["snes"]).vsync_adjust(),
+        crate::config::VsyncAdjustConfig::LowLatency
+    );
+}
@@ -1,7 +1,11 @@
 //! The goal of this module is to load the configuration from an INI file, then
 //! convert it to a JSON file. This way we can use serde_json which is a better
 //! supported library to deserialize stuff.
+//!
+//! [`IniDocument`] keeps the lines of the file instead, so it can be edited
+//! and written back without losing comments, ordering or unknown keys.
 use std::collections::BTreeMap;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -196,3 +200,285 @@ pub fn parse(input: &str) -> Result<Ini, Error> {
         sections,
     })
 }
+
+/// A line of an [`IniDocument`]. The original text is kept so unchanged lines
+/// are written back as they were.
+#[derive(Debug, Clone, PartialEq)]
+enum Line {
+    /// An empty line, a comment or a line that could not be parsed.
+    Other(String),
+    Section {
+        raw: String,
+        name: String,
+    },
+    Entry {
+        raw: String,
+        key: String,
+    },
+}
+
+/// Split the code of a line from its comment, if any.
+fn split_comment(line: &str) -> (&str, &str) {
+    match line.find(';') {
+        Some(i) => line.split_at(i),
+        None => (line, ""),
+    }
+}
+
+fn entry_value(raw: &str) -> &str {
+    let (code, _) = split_comment(raw);
+    code.split_once('=').map_or("", |(_, value)| value.trim())
+}
+
+/// An entry of an [`IniDocument`]. Line numbers start at 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DocumentEntry<'a> {
+    pub line: usize,
+    pub section: Option<&'a str>,
+    pub key: &'a str,
+    pub value: &'a str,
+}
+
+/// An INI file that can be edited in place.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct IniDocument {
+    lines: Vec<Line>,
+    newline: &'static str,
+    trailing_newline: bool,
+}
+
+impl IniDocument {
+    pub fn new(input: &str) -> Self {
+        let lines = input
+            .lines()
+            .map(|raw| {
+                let (code, _) = split_comment(raw);
+                let code = code.trim();
+                if let Some(name) = code.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                    Line::Section {
+                        raw: raw.to_string(),
+                        name: name.trim().to_string(),
+                    }
+                } else if let Some((key, _)) = code.split_once('=') {
+                    Line::Entry {
+                        raw: raw.to_string(),
+                        key: key.trim().to_string(),
+                    }
+                } else {
+                    Line::Other(raw.to_string())
+                }
+            })
+            .collect();
+
+        Self {
+            lines,
+            newline: if input.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: input.is_empty() || input.ends_with('\n'),
+        }
+    }
+
+    /// The names of all sections, in order. Entries before the first section
+    /// are not part of any.
+    pub fn sections(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|l| match l {
+            Line::Section { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the lines with the name of the section they are in. Section
+    /// names are case insensitive, like on the MiSTer.
+    fn lines_in<'a>(
+        &'a self,
+        section: Option<&'a str>,
+    ) -> impl Iterator<Item = (usize, &'a Line)> + 'a {
+        let mut current: Option<&str> = None;
+        self.lines.iter().enumerate().filter(move |&(_, line)| {
+            if let Line::Section { name, .. } = line {
+                current = Some(name.as_str());
+                return false;
+            }
+            match (current, section) {
+                (None, None) => true,
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                _ => false,
+            }
+        })
+    }
+
+    fn find_entries(&self, section: Option<&str>, key: &str) -> Vec<usize> {
+        self.lines_in(section)
+            .filter(|(_, line)| matches!(line, Line::Entry { key: k, .. } if k == key))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The value of a key in a section. If the key is repeated, the last one
+    /// wins.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        let i = *self.find_entries(section, key).last()?;
+        match &self.lines[i] {
+            Line::Entry { raw, .. } => Some(entry_value(raw)),
+            _ => None,
+        }
+    }
+
+    /// All entries of the document, in order, with their line numbers.
+    pub fn entries(&self) -> impl Iterator<Item = DocumentEntry<'_>> {
+        let mut section = None;
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, line)| match line {
+                Line::Section { name, .. } => {
+                    section = Some(name.as_str());
+                    None
+                }
+                Line::Entry { raw, key } => Some(DocumentEntry {
+                    line: i + 1,
+                    section,
+                    key: key.as_str(),
+                    value: entry_value(raw),
+                }),
+                Line::Other(_) => None,
+            })
+    }
+
+    /// The line numbers of the headers of the sections.
+    pub fn section_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines.iter().enumerate().filter_map(|(i, l)| match l {
+            Line::Section { name, .. } => Some((i + 1, name.as_str())),
+            _ => None,
+        })
+    }
+
+    /// The lines that are neither empty, a comment, a section or an entry,
+    /// with their line numbers.
+    pub fn invalid_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines.iter().enumerate().filter_map(|(i, l)| match l {
+            Line::Other(raw) if !split_comment(raw).0.trim().is_empty() => {
+                Some((i + 1, raw.as_str()))
+            }
+            _ => None,
+        })
+    }
+
+    /// Set the value of a key. The last entry of the key is replaced, keeping
+    /// its indentation and comment. A new key is added after the last entry of
+    /// the section, and a new section is added at the end of the file.
+    pub fn set(&mut self, section: Option<&str>, key: &str, value: &str) {
+        if let Some(&i) = self.find_entries(section, key).last() {
+            if let Line::Entry { raw, .. } = &mut self.lines[i] {
+                let (code, comment) = split_comment(raw);
+                let (prefix, old_value) = code.split_once('=').unwrap_or((code, ""));
+                let padding = &old_value[old_value.trim_end().len()..];
+                let spacing = &old_value[..old_value.len() - old_value.trim_start().len()];
+                let padding = if comment.is_empty() { "" } else { padding };
+                *raw = format!("{prefix}={spacing}{value}{padding}{comment}");
+            }
+            return;
+        }
+
+        let entry = Line::Entry {
+            raw: format!("{key}={value}"),
+            key: key.to_string(),
+        };
+
+        let header = self.lines.iter().position(|l| {
+            matches!((l, section), (Line::Section { name, .. }, Some(s)) if name.eq_ignore_ascii_case(s))
+        });
+        let last_entry = self
+            .lines_in(section)
+            .filter(|(_, l)| matches!(l, Line::Entry { .. }))
+            .map(|(i, _)| i)
+            .last();
+
+        match (section, last_entry, header) {
+            (_, Some(i), _) => self.lines.insert(i + 1, entry),
+            (Some(_), None, Some(h)) => self.lines.insert(h + 1, entry),
+            (None, None, _) => self.lines.insert(0, entry),
+            (Some(name), None, None) => {
+                if matches!(self.lines.last(), Some(l) if *l != Line::Other(String::new())) {
+                    self.lines.push(Line::Other(String::new()));
+                }
+                self.lines.push(Line::Section {
+                    raw: format!("[{name}]"),
+                    name: name.to_string(),
+                });
+                self.lines.push(entry);
+            }
+        }
+    }
+
+    /// Remove all entries of a key in a section. Returns whether any entry was
+    /// removed.
+    pub fn remove(&mut self, section: Option<&str>, key: &str) -> bool {
+        let entries = self.find_entries(section, key);
+        for i in entries.iter().rev() {
+            self.lines.remove(*i);
+        }
+        !entries.is_empty()
+    }
+}
+
+impl fmt::Display for IniDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.newline)?;
+            }
+            match line {
+                Line::Other(raw) | Line::Section { raw, .. } | Line::Entry { raw, .. } => {
+                    f.write_str(raw)?
+                }
+            }
+        }
+        if self.trailing_newline && !self.lines.is_empty() {
+            f.write_str(self.newline)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn edit_document() {
+    let input = "\
+        ; Main options.\r\n\
+        [MiSTer]\r\n\
+        video_mode = 8   ; 1080p\r\n\
+        unknown_key=1\r\n\
+        \r\n\
+        [snes]\r\n\
+        vsync_adjust=1\r\n";
+
+    let mut doc = IniDocument::new(input);
+    assert_eq!(doc.to_string(), input);
+    assert_eq!(doc.get(Some("mister"), "video_mode"), Some("8"));
+    assert_eq!(doc.get(Some("MiSTer"), "vsync_adjust"), None);
+
+    doc.set(Some("MiSTer"), "video_mode", "0");
+    doc.set(Some("MiSTer"), "vscale_mode", "1");
+    doc.set(Some("SNES"), "vsync_adjust", "2");
+    doc.set(Some("video=640x480"), "vscale_border", "16");
+    assert!(doc.remove(Some("snes"), "vsync_adjust"));
+    assert!(!doc.remove(Some("snes"), "vsync_adjust"));
+
+    assert_eq!(
+        doc.to_string(),
+        "\
+        ; Main options.\r\n\
+        [MiSTer]\r\n\
+        video_mode = 0   ; 1080p\r\n\
+        unknown_key=1\r\n\
+        vscale_mode=1\r\n\
+        \r\n\
+        [snes]\r\n\
+        \r\n\
+        [video=640x480]\r\n\
+        vscale_border=16\r\n"
+    );
+    assert_eq!(
+        doc.sections().collect::<Vec<_>>(),
+        ["MiSTer", "snes", "video=640x480"]
+    );
+}
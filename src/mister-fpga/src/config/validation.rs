@@ -0,0 +1,323 @@
+//! Checks of a MiSTer.ini, reporting unknown keys, invalid values, malformed
+//! video sections and conflicting options with the line they are on.
+use crate::config::edid::parse_custom_video_mode;
+use crate::config::ini::IniDocument;
+use crate::config::video::resolution::Resolution;
+use crate::config::{Config, HdrConfig, MisterConfig, MISTER_SECTION};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// The option is ignored or has no effect.
+    Warning,
+
+    /// The file (or section) cannot be loaded.
+    Error,
+}
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigIssue {
+    pub severity: Severity,
+
+    /// The line of the problem, starting at 1.
+    pub line: usize,
+    pub section: Option<String>,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}: line {}", self.line)?;
+        if let Some(section) = &self.section {
+            write!(f, " [{section}]")?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, " {key}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// The result of validating a config file. Issues are sorted by line.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A deserializer that only records the fields of the struct it is asked for.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("expected a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// The keys of a section of the MiSTer.ini, including aliases.
+pub fn known_keys() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = MisterConfig::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Options that cannot be used together. The second option is reported.
+fn conflicts(config: &MisterConfig) -> Vec<(&'static str, Severity, String)> {
+    let mut conflicts = Vec::new();
+    if config.direct_video() && config.vga_scaler.unwrap_or_default() {
+        conflicts.push((
+            "vga_scaler",
+            Severity::Warning,
+            "vga_scaler is ignored when direct_video is enabled.".to_string(),
+        ));
+    }
+    if config.dvi_mode() && config.hdmi_audio_96k.unwrap_or_default() {
+        conflicts.push((
+            "hdmi_audio_96k",
+            Severity::Warning,
+            "There is no audio over HDMI when dvi_mode is enabled.".to_string(),
+        ));
+    }
+    if config.dvi_mode() && !matches!(config.hdr(), HdrConfig::None) {
+        conflicts.push((
+            "hdr",
+            Severity::Warning,
+            "HDR cannot be signaled to the display when dvi_mode is enabled.".to_string(),
+        ));
+    }
+    if let (Some(min), Some(max)) = (config.refresh_min, config.refresh_max) {
+        if min > max {
+            conflicts.push((
+                "refresh_max",
+                Severity::Error,
+                format!("refresh_max ({max}) is lower than refresh_min ({min})."),
+            ));
+        }
+    }
+    if let (Some(min), Some(max)) = (config.vrr_min_framerate, config.vrr_max_framerate) {
+        if min > max {
+            conflicts.push((
+                "vrr_max_framerate",
+                Severity::Error,
+                format!("vrr_max_framerate ({max}) is lower than vrr_min_framerate ({min})."),
+            ));
+        }
+    }
+    conflicts
+}
+
+/// Check a `[video=WIDTHxHEIGHT@REFRESH]` section name.
+fn check_video_section(name: &str) -> Result<(), String> {
+    let (resolution, refresh) = match name.split_once('@') {
+        Some((resolution, refresh)) => (resolution, Some(refresh)),
+        None => (name, None),
+    };
+    Resolution::from_str(resolution.trim())?;
+    match refresh.map(|r| r.trim().parse::<f64>()) {
+        Some(Ok(r)) if r > 0. => Ok(()),
+        Some(_) => Err(format!("Invalid refresh rate {:?}", refresh.unwrap_or(""))),
+        None => Ok(()),
+    }
+}
+
+impl ValidationReport {
+    /// Validate the content of a MiSTer.ini.
+    pub fn from_ini(content: &str) -> Self {
+        let document = IniDocument::new(content);
+        let known_keys = known_keys();
+        let mut issues = Vec::new();
+        let mut issue = |severity, line, section: Option<&str>, key: Option<&str>, message| {
+            issues.push(ConfigIssue {
+                severity,
+                line,
+                section: section.map(str::to_string),
+                key: key.map(str::to_string),
+                message,
+            })
+        };
+
+        for (line, text) in document.invalid_lines() {
+            let message = format!(
+                "Invalid line {:?}, expected a section or a key=value.",
+                text
+            );
+            issue(Severity::Error, line, None, None, message);
+        }
+
+        for (line, name) in document.section_lines() {
+            let video = name.get(..6).filter(|p| p.eq_ignore_ascii_case("video="));
+            if video.is_some() {
+                if let Err(e) = check_video_section(&name[6..]) {
+                    issue(Severity::Error, line, Some(name), None, e);
+                }
+            }
+        }
+
+        // The entries without errors, to check the options together.
+        let mut valid = String::new();
+        let mut valid_section = None;
+
+        for entry in document.entries() {
+            let (section, key) = (entry.section, Some(entry.key));
+            if !known_keys.contains(&entry.key) {
+                let message = "Unknown key, it will be ignored.".to_string();
+                issue(Severity::Warning, entry.line, section, key, message);
+                continue;
+            }
+
+            // Load the key on its own, so errors can be traced back to its line.
+            let single = format!("[{MISTER_SECTION}]\n{}={}\n", entry.key, entry.value);
+            let config = match Config::from_ini(single.as_bytes()) {
+                Ok(config) => config.into_inner(),
+                Err(e) => {
+                    issue(Severity::Error, entry.line, section, key, e.to_string());
+                    continue;
+                }
+            };
+            if let Err(e) = config.validate() {
+                let message = e.to_string().trim().to_string();
+                issue(Severity::Error, entry.line, section, key, message);
+                continue;
+            }
+
+            let is_video_mode = matches!(
+                entry.key,
+                "video_mode" | "video_mode_ntsc" | "video_mode_pal"
+            );
+            if is_video_mode && !entry.value.is_empty() {
+                if let Err(e) = parse_custom_video_mode(Some(entry.value)) {
+                    issue(Severity::Error, entry.line, section, key, e);
+                    continue;
+                }
+            }
+
+            // Entries before the first section are ignored by the MiSTer.
+            if let Some(name) = section {
+                if valid_section != Some(name) {
+                    valid.push_str(&format!("[{name}]\n"));
+                    valid_section = Some(name);
+                }
+                valid.push_str(&format!("{}={}\n", entry.key, entry.value));
+            }
+        }
+
+        // Conflicts are checked with the options a section inherits from the
+        // MiSTer section, but only reported in sections setting the option.
+        if let Ok(config) = Config::from_ini(valid.as_bytes()) {
+            let mut sections = vec![MISTER_SECTION.to_string()];
+            sections.extend(config.overrides.keys().cloned());
+            for name in sections {
+                let effective = config.clone().into_inner_with_overrides(&[name.as_str()]);
+                for (key, severity, message) in conflicts(&effective) {
+                    let entry = document
+                        .entries()
+                        .filter(|e| e.key == key)
+                        .filter(|e| e.section.is_some_and(|s| s.eq_ignore_ascii_case(&name)))
+                        .last();
+                    if let Some(entry) = entry {
+                        issue(severity, entry.line, entry.section, Some(key), message);
+                    }
+                }
+            }
+        }
+
+        issues.sort_by_key(|i| i.line);
+        Self { issues }
+    }
+
+    /// Validate a MiSTer.ini file.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, std::io::Error> {
+        Ok(Self::from_ini(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[test]
+fn validate_config() {
+    let ini = "\
+        [MiSTer]\n\
+        direct_video=1\n\
+        vga_scaler=1\n\
+        not_a_key=1\n\
+        vscale_border=abc\n\
+        video_brightness=200\n\
+        refresh_min=60\n\
+        refresh_max=50\n\
+        this is not valid\n\
+        [video=640xabc]\n\
+        [snes]\n\
+        video_mode=abc\n\
+        dvi_mode=1\n\
+        hdmi_audio_96k=1\n\
+    ";
+
+    let report = ValidationReport::from_ini(ini);
+    let issues = report
+        .issues
+        .iter()
+        .map(|i| (i.line, i.severity, i.key.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        issues,
+        [
+            (3, Severity::Warning, Some("vga_scaler")),
+            (4, Severity::Warning, Some("not_a_key")),
+            (5, Severity::Error, Some("vscale_border")),
+            (6, Severity::Error, Some("video_brightness")),
+            (8, Severity::Error, Some("refresh_max")),
+            (9, Severity::Error, None),
+            (10, Severity::Error, None),
+            (12, Severity::Error, Some("video_mode")),
+            (14, Severity::Warning, Some("hdmi_audio_96k")),
+        ]
+    );
+    assert!(report.has_errors());
+
+    assert!(ValidationReport::from_ini("[MiSTer]\nvideo_mode=8\nypbpr=1\n").is_empty());
+}
@@ -5,8 +5,12 @@ use std::fmt;
 use std::str::FromStr;
 
 pub mod aspect;
+pub mod color;
 pub mod edid;
+pub mod filter;
+pub mod modes;
 pub mod resolution;
+pub mod vrr;
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct VideoGainOffsets {
@@ -24,7 +28,7 @@ impl Serialize for VideoGainOffsets {
         S: Serializer,
     {
         if serializer.is_human_readable() {
-            // Serialize into a string separated by spaces.
+            // Serialize into a string separated by commas, like the MiSTer.ini.
             serializer.serialize_str(&self.to_string())
         } else {
             let mut seq = serializer.serialize_seq(Some(6))?;
@@ -145,7 +149,7 @@ impl fmt::Display for VideoGainOffsets {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} {} {} {} {} {}",
+            "{}, {}, {}, {}, {}, {}",
             self.gain_red,
             self.offset_red,
             self.gain_green,
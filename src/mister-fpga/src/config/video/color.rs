@@ -0,0 +1,181 @@
+//! Color controls and gamma curves, as found in the `Gamma` folder of a MiSTer
+//! SD card.
+use crate::config::video::VideoGainOffsets;
+use crate::config::{Config, MisterConfig};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Number of entries in a gamma curve, one per input level.
+pub const GAMMA_CURVE_SIZE: usize = 256;
+
+/// A gamma look-up table, with an output level for every red, green and blue
+/// input level. Files contain one entry per line, either a single value used
+/// for all channels or three comma separated values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GammaCurve(Vec<(u8, u8, u8)>);
+
+impl GammaCurve {
+    /// A curve that does not change any color.
+    pub fn identity() -> Self {
+        Self((0..=255).map(|v| (v, v, v)).collect())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read gamma curve {path:?}: {e}"))?
+            .parse()
+            .map_err(|e| format!("Invalid gamma curve {path:?}: {e}"))
+    }
+
+    pub fn entries(&self) -> &[(u8, u8, u8)] {
+        &self.0
+    }
+}
+
+impl FromStr for GammaCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_value(v: &str) -> Result<u8, String> {
+            let v = v.trim();
+            match v.strip_prefix("0x").or_else(|| v.strip_prefix("0X")) {
+                Some(hex) => u8::from_str_radix(hex, 16),
+                None => v.parse(),
+            }
+            .map_err(|e| format!("Invalid value {v:?}: {e}"))
+        }
+
+        let entries = s
+            .lines()
+            .map(|l| l.split(['#', ';']).next().unwrap_or("").trim())
+            .filter(|l| !l.is_empty())
+            .map(|line| {
+                let values = line
+                    .split([',', ' ', '\t'])
+                    .filter(|v| !v.is_empty())
+                    .map(parse_value)
+                    .collect::<Result<Vec<_>, _>>()?;
+                match values.as_slice() {
+                    [v] => Ok((*v, *v, *v)),
+                    [r, g, b] => Ok((*r, *g, *b)),
+                    _ => Err(format!("Expected 1 or 3 values, got {line:?}")),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if entries.len() != GAMMA_CURVE_SIZE {
+            return Err(format!(
+                "Expected {GAMMA_CURVE_SIZE} entries, got {}",
+                entries.len()
+            ));
+        }
+        Ok(Self(entries))
+    }
+}
+
+/// The color controls applied to the HDMI output. Values use the same ranges
+/// as the MiSTer.ini (e.g. `video_brightness` is between 0 and 100). The gamma
+/// curve is a path relative to the `Gamma` folder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorControls {
+    pub brightness: u8,
+    pub contrast: u8,
+    pub saturation: u8,
+    pub hue: u16,
+    pub gain_offset: VideoGainOffsets,
+    #[serde(default)]
+    pub gamma: Option<String>,
+}
+
+impl Default for ColorControls {
+    fn default() -> Self {
+        Self::from_config(&MisterConfig::new_defaults())
+    }
+}
+
+impl ColorControls {
+    /// The color controls from the MiSTer.ini, without a gamma curve.
+    pub fn from_config(config: &MisterConfig) -> Self {
+        Self {
+            brightness: config.video_brightness.unwrap_or(50),
+            contrast: config.video_contrast.unwrap_or(50),
+            saturation: config.video_saturation.unwrap_or(100),
+            hue: config.video_hue.unwrap_or_default(),
+            gain_offset: config.video_gain_offset(),
+            gamma: None,
+        }
+    }
+
+    /// Replace the color controls of a config, clamping values to their
+    /// valid range.
+    pub fn apply_to(&self, config: &mut MisterConfig) {
+        config.video_brightness = Some(self.brightness.min(100));
+        config.video_contrast = Some(self.contrast.min(100));
+        config.video_saturation = Some(self.saturation.min(100));
+        config.video_hue = Some(self.hue % 360);
+        config.video_gain_offset = Some(self.gain_offset);
+    }
+
+    /// The MiSTer.ini keys of these controls, to write them back with
+    /// [`ConfigFile::set_all`](crate::config::ConfigFile::set_all). The gamma
+    /// curve is not part of the MiSTer.ini.
+    pub fn ini_values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("video_brightness", self.brightness.min(100).to_string()),
+            ("video_contrast", self.contrast.min(100).to_string()),
+            ("video_saturation", self.saturation.min(100).to_string()),
+            ("video_hue", (self.hue % 360).to_string()),
+            ("video_gain_offset", self.gain_offset.to_string()),
+        ]
+    }
+
+    /// Load the gamma curve, if any.
+    pub fn load_gamma(&self) -> Result<Option<GammaCurve>, String> {
+        match self.gamma.as_deref() {
+            Some(name) if !name.trim().is_empty() => {
+                GammaCurve::load(Config::gamma_root().join(name)).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[test]
+fn parse_gamma_curve() {
+    let content = (0..256)
+        .map(|i| {
+            if i % 2 == 0 {
+                format!("{i}")
+            } else {
+                format!("{i}, 0x{:02X}, 0 # comment", 255 - i)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let curve: GammaCurve = content.parse().unwrap();
+
+    assert_eq!(curve.entries().len(), GAMMA_CURVE_SIZE);
+    assert_eq!(curve.entries()[2], (2, 2, 2));
+    assert_eq!(curve.entries()[3], (3, 252, 0));
+    assert_eq!(GammaCurve::identity().entries()[255], (255, 255, 255));
+
+    assert!("1,2,3\n".parse::<GammaCurve>().is_err());
+    assert!("1,2\n".repeat(256).parse::<GammaCurve>().is_err());
+    assert!("256\n".repeat(256).parse::<GammaCurve>().is_err());
+}
+
+#[test]
+fn color_controls_round_trip() {
+    let mut config = MisterConfig::new_defaults();
+    let mut controls = ColorControls::from_config(&config);
+    assert_eq!(controls, ColorControls::default());
+
+    controls.brightness = 150;
+    controls.hue = 400;
+    controls.apply_to(&mut config);
+    assert_eq!(config.video_brightness(), 0.5);
+    assert_eq!(config.video_hue, Some(40));
+}
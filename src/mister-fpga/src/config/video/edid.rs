@@ -11,6 +11,12 @@ use crate::config::MisterConfig;
 use crate::fpga::user_io::SetVideoMode;
 use crate::fpga::Spi;
 
+mod info;
+pub use info::*;
+
+/// Maximum pixel clock the scaler can output, in MHz.
+pub const MAX_PIXEL_CLOCK_MHZ: f64 = 210.;
+
 pub struct Edid {
     inner: [u8; 256],
 }
@@ -90,7 +96,8 @@ fn create_i2c(
     Ok(i2c)
 }
 
-fn get_active_edid_() -> Result<[u8; 256], String> {
+/// Read the EDID of the connected display.
+pub fn get_active_edid() -> Result<[u8; 256], String> {
     #[cfg(target_os = "linux")]
     {
         Edid::from_i2c().map(|edid| edid.into_inner())
@@ -103,7 +110,7 @@ fn get_active_edid_() -> Result<[u8; 256], String> {
 }
 
 fn get_edid_vmode_(options: &MisterConfig) -> Option<CustomVideoMode> {
-    let edid = match get_active_edid_() {
+    let edid = match get_active_edid() {
         Ok(edid) => edid,
         Err(e) => {
             warn!("EDID Err while getting active edid: {}\n", e);
@@ -251,7 +258,7 @@ fn parse_edid_vmode_(options: &MisterConfig, edid: &[u8]) -> Result<CustomVideoM
         hact, vact, frame_rate, f_pix
     );
 
-    if f_pix > 210. {
+    if f_pix > MAX_PIXEL_CLOCK_MHZ {
         warn!(
             "EDID: Preferred mode has too high pixel clock ({:.3}MHz).",
             f_pix
@@ -267,21 +274,21 @@ fn parse_edid_vmode_(options: &MisterConfig, edid: &[u8]) -> Result<CustomVideoM
         } else if frame_rate > 60. {
             let f_pix =
                 60. * (((hact + hfp + hbp + hsync) * (vact + vfp + vbp + vsync)) as f64) / 1000000.;
-            if f_pix <= 210. {
+            if f_pix <= MAX_PIXEL_CLOCK_MHZ {
                 warn!(
                     "EDID: Reducing frame rate to 60Hz with new pixel clock {:.3}MHz.",
                     f_pix
                 );
                 v.f_pix = f_pix;
             } else {
-                return Err(
-                    "EDID: Pixel rate too high, falling back to default video mode.".to_string(),
-                );
+                v = best_edid_vmode_(edid).ok_or_else(|| {
+                    "EDID: Pixel rate too high, falling back to default video mode.".to_string()
+                })?;
             }
         } else {
-            return Err(
-                "EDID: Frame rate too high. Falling back to default video mode.".to_string(),
-            );
+            v = best_edid_vmode_(edid).ok_or_else(|| {
+                "EDID: Frame rate too high. Falling back to default video mode.".to_string()
+            })?;
         }
     }
 
@@ -290,6 +297,31 @@ fn parse_edid_vmode_(options: &MisterConfig, edid: &[u8]) -> Result<CustomVideoM
     Ok(v)
 }
 
+/// The largest mode of the display that the scaler can output, preferring
+/// higher refresh rates.
+fn best_edid_vmode_(edid: &[u8]) -> Option<CustomVideoMode> {
+    let mode = EdidInfo::decode(edid)
+        .ok()?
+        .modes()
+        .into_iter()
+        .filter(|m| m.supported)
+        .max_by(|a, b| {
+            (a.width * a.height)
+                .cmp(&(b.width * b.height))
+                .then(a.refresh.total_cmp(&b.refresh))
+        })?;
+
+    warn!(
+        "EDID: Using {}x{}@{:.1} from the display modes instead.",
+        mode.width, mode.height, mode.refresh
+    );
+    let mut v = CustomVideoMode::from(&mode.timing);
+    if let DisplayModeSource::Vic(vic) = mode.source {
+        v.param.vic = vic as u32;
+    }
+    Some(v)
+}
+
 fn hexdump(data: &[u8]) -> String {
     let mut n = 0;
     let mut size = data.len();
@@ -488,6 +520,8 @@ impl DefaultVideoMode {
 impl From<DefaultVideoMode> for CustomVideoModeParam {
     fn from(mode: DefaultVideoMode) -> Self {
         let vpar = mode.v_param();
+        // 2560x1440 is output with pixel repetition.
+        let pr = matches!(mode, DefaultVideoMode::V2560x1440r60) as u32;
         let mode = mode as u8 as u32;
         CustomVideoModeParam {
             mode,
@@ -504,7 +538,7 @@ impl From<DefaultVideoMode> for CustomVideoModeParam {
             vpol: 0,
             vic: 0,
             rb: 0,
-            pr: 0,
+            pr,
         }
     }
 }
@@ -579,6 +613,23 @@ impl CustomVideoMode {
         Ok(())
     }
 
+    /// This mode with its pixel clock changed so it refreshes at `refresh` Hz,
+    /// as used by `vsync_adjust`. Returns `None` if the pixel clock would be
+    /// out of the range of the PLL.
+    pub fn with_refresh(&self, refresh: f64) -> Option<Self> {
+        let p = &self.param;
+        let h_total = p.hact + p.hfp + p.hs + p.hbp;
+        let v_total = p.vact + p.vfp + p.vs + p.vbp;
+        let f_pix = (h_total * v_total) as f64 * refresh / 1_000_000.;
+        if !(2. ..=300.).contains(&f_pix) {
+            return None;
+        }
+
+        let mut mode = *self;
+        mode.set_pll(f_pix);
+        Some(mode)
+    }
+
     pub fn set_pll(&mut self, f_out: f64) {
         trace!("Calculate PLL for {:.4} MHz", f_out);
 
@@ -644,6 +695,9 @@ impl From<DefaultVideoMode> for CustomVideoMode {
     }
 }
 
+/// Refresh rate under which a core is considered PAL.
+const PAL_REFRESH_THRESHOLD: f64 = 55.;
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct VideoModeDef {
     pub vmode_def: Option<CustomVideoMode>,
@@ -651,16 +705,169 @@ pub struct VideoModeDef {
     pub vmode_ntsc: Option<CustomVideoMode>,
 }
 
-fn parse_custom_video_mode(video_mode: Option<&str>) -> CustomVideoMode {
-    if video_mode.is_none() || matches!(video_mode, Some("auto")) || matches!(video_mode, Some(""))
-    {
-        return DefaultVideoMode::V1920x1080r60.into();
-        // return DefaultVideoMode::V1280x720r60.into();
-        // return DefaultVideoMode::V640x480r60.into();
+impl VideoModeDef {
+    /// Whether there are specific video modes for PAL or NTSC cores.
+    pub fn has_pal_ntsc_modes(&self) -> bool {
+        self.vmode_pal.is_some() || self.vmode_ntsc.is_some()
+    }
+
+    /// Select the video mode for a core running at `refresh` Hz, falling back
+    /// to the default mode. Returns whether the mode is a PAL mode.
+    pub fn select(&self, refresh: f64) -> Option<(CustomVideoMode, bool)> {
+        if refresh > 0. && refresh < PAL_REFRESH_THRESHOLD {
+            self.vmode_pal.or(self.vmode_def).map(|m| (m, true))
+        } else {
+            self.vmode_ntsc.or(self.vmode_def).map(|m| (m, false))
+        }
+    }
+}
+
+/// Calculate the timings of a mode using the CVT reduced blanking formula (v1).
+fn video_calculate_cvt_(hact: u32, vact: u32, refresh: f64) -> Result<CustomVideoMode, String> {
+    const RB_MIN_V_BLANK: f64 = 460.; // us
+    const RB_H_FPORCH: u32 = 48;
+    const RB_H_SYNC: u32 = 32;
+    const RB_H_BPORCH: u32 = 80;
+    const RB_V_FPORCH: u32 = 3;
+    const MIN_V_BPORCH: u32 = 6;
+    const CLOCK_STEP: f64 = 0.25; // MHz
+
+    if hact == 0 || vact == 0 || !(1. ..=240.).contains(&refresh) {
+        return Err(format!("Invalid video mode {hact}x{vact}@{refresh}"));
+    }
+
+    // The vsync width encodes the aspect ratio.
+    let ratio = hact as f64 / vact as f64;
+    let is_ratio = |w: f64, h: f64| (ratio - w / h).abs() < 0.01;
+    let vs = if is_ratio(4., 3.) {
+        4
+    } else if is_ratio(16., 9.) {
+        5
+    } else if is_ratio(16., 10.) {
+        6
+    } else if is_ratio(5., 4.) || is_ratio(15., 9.) {
+        7
+    } else {
+        10
+    };
+
+    let h_period = (1000000. / refresh - RB_MIN_V_BLANK) / vact as f64;
+    if h_period <= 0. {
+        return Err(format!("Invalid video mode {hact}x{vact}@{refresh}"));
+    }
+    let vbi_lines = ((RB_MIN_V_BLANK / h_period) as u32 + 1).max(RB_V_FPORCH + vs + MIN_V_BPORCH);
+
+    let h_total = hact + RB_H_FPORCH + RB_H_SYNC + RB_H_BPORCH;
+    let v_total = vact + vbi_lines;
+    let f_pix =
+        CLOCK_STEP * ((refresh * (h_total * v_total) as f64 / 1000000.) / CLOCK_STEP).floor();
+
+    let mut v = CustomVideoMode::default();
+    v.param.hact = hact;
+    v.param.hfp = RB_H_FPORCH;
+    v.param.hs = RB_H_SYNC;
+    v.param.hbp = RB_H_BPORCH;
+    v.param.vact = vact;
+    v.param.vfp = RB_V_FPORCH;
+    v.param.vs = vs;
+    v.param.vbp = vbi_lines - RB_V_FPORCH - vs;
+    v.param.rb = 1;
+    v.f_pix = f_pix;
+    Ok(v)
+}
+
+/// Parse a `video_mode` string from the MiSTer.ini. This supports the following forms:
+///   - `auto` or an empty string, which is the default video mode.
+///   - A preset index (e.g. `8` for 1920x1080@60).
+///   - `WxH@R` (e.g. `1920x1080@60`), which uses CVT reduced blanking timings.
+///   - Raw timings `hact,hfp,hs,hbp,vact,vfp,vs,vbp`, optionally followed by the
+///     pixel clock in kHz, then the hsync and vsync polarities (1 for negative).
+///     Without a pixel clock, one is computed for 60Hz.
+pub fn parse_custom_video_mode(video_mode: Option<&str>) -> Result<CustomVideoMode, String> {
+    let video_mode = video_mode.map(str::trim).unwrap_or_default();
+    if video_mode.is_empty() || video_mode.eq_ignore_ascii_case("auto") {
+        return Ok(DefaultVideoMode::V1920x1080r60.into());
+    }
+
+    if let Some((res, refresh)) = video_mode.split_once('@') {
+        let (w, h) = res
+            .split_once(['x', 'X'])
+            .ok_or_else(|| format!("Invalid video mode {video_mode:?}"))?;
+        let invalid = |_| format!("Invalid video mode {video_mode:?}");
+        let mut v = video_calculate_cvt_(
+            w.trim().parse().map_err(invalid)?,
+            h.trim().parse().map_err(invalid)?,
+            refresh
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid video mode {video_mode:?}"))?,
+        )?;
+        v.set_pll(v.f_pix);
+        return Ok(v);
     }
-    DefaultVideoMode::V1920x1080r60.into()
 
-    // todo!("parse_custom_video_mode")
+    let values = video_mode
+        .split(',')
+        .map(|v| v.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Invalid video mode {video_mode:?}"))?;
+
+    match values.as_slice() {
+        [index] => {
+            let mode = u8::try_from(*index)
+                .ok()
+                .and_then(DefaultVideoMode::from_repr)
+                .filter(|m| (*m as u8) < DefaultVideoMode::Ntsc15K as u8)
+                .ok_or_else(|| format!("Invalid video mode index {index}"))?;
+            let mut v: CustomVideoMode = mode.into();
+            v.param.vic = mode.vic_mode();
+            Ok(v)
+        }
+        [hact, hfp, hs, hbp, vact, vfp, vs, vbp, rest @ ..] if matches!(rest.len(), 0 | 1 | 3) => {
+            let mut v = CustomVideoMode::default();
+            v.param.hact = *hact;
+            v.param.hfp = *hfp;
+            v.param.hs = *hs;
+            v.param.hbp = *hbp;
+            v.param.vact = *vact;
+            v.param.vfp = *vfp;
+            v.param.vs = *vs;
+            v.param.vbp = *vbp;
+            if v.param.hact == 0 || v.param.vact == 0 {
+                return Err(format!("Invalid video mode {video_mode:?}"));
+            }
+
+            v.f_pix = match rest.first() {
+                Some(khz) => *khz as f64 / 1000.,
+                None => 60. * ((hact + hfp + hs + hbp) * (vact + vfp + vs + vbp)) as f64 / 1000000.,
+            };
+            if !(1. ..=MAX_PIXEL_CLOCK_MHZ).contains(&v.f_pix) {
+                return Err(format!(
+                    "Invalid pixel clock {:.3}MHz in video mode {video_mode:?}",
+                    v.f_pix
+                ));
+            }
+            if let [_, hpol, vpol] = rest {
+                v.param.hpol = (*hpol != 0) as u32;
+                v.param.vpol = (*vpol != 0) as u32;
+            }
+
+            v.set_pll(v.f_pix);
+            Ok(v)
+        }
+        _ => Err(format!("Invalid video mode {video_mode:?}")),
+    }
+}
+
+fn parse_optional_video_mode_(name: &str, video_mode: Option<&str>) -> Option<CustomVideoMode> {
+    video_mode?;
+    match parse_custom_video_mode(video_mode) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            warn!("{}: {}. Ignoring.", name, e);
+            None
+        }
+    }
 }
 
 pub fn select_video_mode(options: &MisterConfig) -> Result<VideoModeDef, String> {
@@ -681,12 +888,12 @@ pub fn select_video_mode(options: &MisterConfig) -> Result<VideoModeDef, String>
             vmode_ntsc: None,
         })
     } else {
-        eprintln!("select_video_mode: conf: {:?}", options.video_conf);
-        // return Ok(VideoModeDef {
-        //     vmode_def: Some(DefaultVideoMode::V640x480r60.into()),
-        //     vmode_pal: None,
-        //     vmode_ntsc: None,
-        // });
+        debug!(
+            video_conf = ?options.video_conf,
+            video_conf_pal = ?options.video_conf_pal,
+            video_conf_ntsc = ?options.video_conf_ntsc,
+            "select_video_mode"
+        );
 
         if options.video_conf.is_none()
             && options.video_conf_pal.is_none()
@@ -701,17 +908,86 @@ pub fn select_video_mode(options: &MisterConfig) -> Result<VideoModeDef, String>
             }
         }
 
-        let def = parse_custom_video_mode(options.video_conf.as_deref());
+        let def = parse_custom_video_mode(options.video_conf.as_deref()).unwrap_or_else(|e| {
+            warn!("video_mode: {}. Using default video mode.", e);
+            DefaultVideoMode::V1920x1080r60.into()
+        });
 
         Ok(VideoModeDef {
             vmode_def: Some(def),
-            vmode_pal: None,
-            vmode_ntsc: None,
+            vmode_pal: parse_optional_video_mode_(
+                "video_mode_pal",
+                options.video_conf_pal.as_deref(),
+            ),
+            vmode_ntsc: parse_optional_video_mode_(
+                "video_mode_ntsc",
+                options.video_conf_ntsc.as_deref(),
+            ),
         })
     }
 }
 
-#[ignore]
+#[test]
+fn parse_video_mode_preset() {
+    let v = parse_custom_video_mode(Some("8")).unwrap();
+    assert_eq!((v.param.hact, v.param.vact), (1920, 1080));
+    assert_eq!(v.param.vic, 6);
+
+    assert!(parse_custom_video_mode(Some("15")).is_err());
+    assert!(parse_custom_video_mode(Some("300")).is_err());
+}
+
+#[test]
+fn parse_video_mode_cvt() {
+    let v = parse_custom_video_mode(Some("1920x1080@60")).unwrap();
+    assert_eq!((v.param.hfp, v.param.hs, v.param.hbp), (48, 32, 80));
+    assert_eq!((v.param.vfp, v.param.vs, v.param.vbp), (3, 5, 23));
+    assert!((v.f_pix - 138.5).abs() < 0.01);
+    assert!((v.frame_rate() - 60.).abs() < 0.1);
+}
+
+#[test]
+fn parse_video_mode_raw() {
+    let v = parse_custom_video_mode(Some("1280,110,40,220,720,5,5,20,74250")).unwrap();
+    assert_eq!(v.param.hact, 1280);
+    assert_eq!(v.param.vbp, 20);
+    assert!((v.f_pix - 74.25).abs() < 0.01);
+
+    let v = parse_custom_video_mode(Some("1280,110,40,220,720,5,5,20,74250,1,1")).unwrap();
+    assert_eq!((v.param.hpol, v.param.vpol), (1, 1));
+
+    let v = parse_custom_video_mode(Some("1280,110,40,220,720,5,5,20")).unwrap();
+    assert!((v.frame_rate() - 60.).abs() < 0.1);
+
+    assert!(parse_custom_video_mode(Some("1280,110,40,220,720,5,5,20,74250,1")).is_err());
+    assert!(parse_custom_video_mode(Some("1280x720")).is_err());
+}
+
+#[test]
+fn select_pal_ntsc() {
+    let modes = VideoModeDef {
+        vmode_def: Some(DefaultVideoMode::V1920x1080r60.into()),
+        vmode_pal: Some(DefaultVideoMode::V1920x1080r50.into()),
+        vmode_ntsc: None,
+    };
+
+    let (pal, is_pal) = modes.select(50.).unwrap();
+    assert!(is_pal);
+    assert_eq!(pal.param.hfp, 528);
+    let (ntsc, is_pal) = modes.select(59.94).unwrap();
+    assert!(!is_pal);
+    assert_eq!(ntsc.param.hfp, 88);
+}
+
+#[test]
+fn vsync_adjust_pixel_clock() {
+    let mode: CustomVideoMode = DefaultVideoMode::V1920x1080r60.into();
+    let adjusted = mode.with_refresh(59.94).unwrap();
+    assert!((adjusted.f_pix - 148.3515).abs() < 0.001);
+    assert_eq!(adjusted.param.hact, mode.param.hact);
+    assert!(mode.with_refresh(1000.).is_none());
+}
+
 #[test]
 fn parse_4k_hdmi_edid() {
     // This is the EDID from my monitor (VESA 4K).
@@ -738,8 +1014,28 @@ fn parse_4k_hdmi_edid() {
     )
     .unwrap();
 
+    let info = EdidInfo::decode(&edid).unwrap();
+    assert_eq!(info.manufacturer, "EGA");
+    assert_eq!(info.name.as_deref(), Some("HD60 S+"));
+    assert!(info.hdmi);
+    assert_eq!(info.vrr, None);
+    assert!(info.hdr.unwrap().pq);
+    assert_eq!(info.range_limits.unwrap().max_pixel_clock_mhz, Some(600));
+
+    let modes = info.modes();
+    let preferred = &modes[0];
+    assert_eq!(preferred.source, DisplayModeSource::Preferred);
+    assert_eq!((preferred.width, preferred.height), (3840, 2160));
+    assert_eq!(preferred.timing.pixel_clock_khz, 594_000);
+    assert!(!preferred.supported);
+    assert!(modes
+        .iter()
+        .any(|m| m.source == DisplayModeSource::Vic(16) && m.supported));
+
+    // The preferred mode is too fast for the scaler, so the best supported
+    // mode of the display is used instead.
     let vmode = parse_edid_vmode_(&MisterConfig::new_defaults(), &edid).unwrap();
-    assert_eq!(vmode.param.hact, 3840);
-    assert_eq!(vmode.param.vact, 2160);
-    assert_eq!(vmode.frame_rate(), 60.);
+    assert_eq!(vmode.param.hact, 1920);
+    assert_eq!(vmode.param.vact, 1080);
+    assert!((vmode.frame_rate() - 60.).abs() < 0.01);
 }
@@ -0,0 +1,484 @@
+//! Decoding of the EDID of a display (base block and CEA-861 extensions).
+use super::{CustomVideoMode, MAX_PIXEL_CLOCK_MHZ};
+use crate::config::vrr::{VrrProtocol, VrrSupport};
+use serde::Serialize;
+
+/// IEEE OUIs of the vendor specific data blocks of a CEA-861 extension.
+const HDMI_OUI: [u8; 3] = [0x03, 0x0C, 0x00];
+const AMD_OUI: [u8; 3] = [0x1A, 0x00, 0x00];
+const HDMI_FORUM_OUI: [u8; 3] = [0xD8, 0x5D, 0xC4];
+
+/// The timings of a video mode, as found in the detailed timing descriptors
+/// of an EDID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailedTiming {
+    pub pixel_clock_khz: u32,
+    pub hact: u32,
+    pub hfp: u32,
+    pub hs: u32,
+    pub hbp: u32,
+    pub vact: u32,
+    pub vfp: u32,
+    pub vs: u32,
+    pub vbp: u32,
+    pub hsync_positive: bool,
+    pub vsync_positive: bool,
+    pub interlaced: bool,
+}
+
+impl DetailedTiming {
+    fn parse(x: &[u8]) -> Option<Self> {
+        let pixel_clock_khz = (x[0] as u32 + ((x[1] as u32) << 8)) * 10;
+        if pixel_clock_khz == 0 {
+            return None;
+        }
+
+        let hact = x[2] as u32 + ((x[4] as u32 & 0xf0) << 4);
+        let hbl = x[3] as u32 + ((x[4] as u32 & 0x0f) << 8);
+        let hfp = x[8] as u32 + ((x[11] as u32 & 0xc0) << 2);
+        let hs = x[9] as u32 + ((x[11] as u32 & 0x30) << 4);
+        let vact = x[5] as u32 + ((x[7] as u32 & 0xf0) << 4);
+        let vbl = x[6] as u32 + ((x[7] as u32 & 0x0f) << 8);
+        let vfp = (x[10] as u32 >> 4) + ((x[11] as u32 & 0x0c) << 2);
+        let vs = (x[10] as u32 & 0x0f) + ((x[11] as u32 & 0x03) << 4);
+        let flags = x[17];
+        // Polarities are only defined for digital separate sync.
+        let separate_sync = flags & 0x18 == 0x18;
+
+        Some(Self {
+            pixel_clock_khz,
+            hact,
+            hfp,
+            hs,
+            hbp: hbl.checked_sub(hs + hfp)?,
+            vact,
+            vfp,
+            vs,
+            vbp: vbl.checked_sub(vs + vfp)?,
+            hsync_positive: separate_sync && flags & 0x02 != 0,
+            vsync_positive: separate_sync && flags & 0x04 != 0,
+            interlaced: flags & 0x80 != 0,
+        })
+    }
+
+    pub fn h_total(&self) -> u32 {
+        self.hact + self.hfp + self.hs + self.hbp
+    }
+
+    pub fn v_total(&self) -> u32 {
+        self.vact + self.vfp + self.vs + self.vbp
+    }
+
+    /// The refresh rate, in Hz.
+    pub fn refresh(&self) -> f64 {
+        self.pixel_clock_khz as f64 * 1000. / (self.h_total() * self.v_total()) as f64
+    }
+
+    /// The timings as a `video_mode` string for the MiSTer.ini.
+    pub fn to_video_mode_string(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.hact,
+            self.hfp,
+            self.hs,
+            self.hbp,
+            self.vact,
+            self.vfp,
+            self.vs,
+            self.vbp,
+            self.pixel_clock_khz,
+            !self.hsync_positive as u8,
+            !self.vsync_positive as u8,
+        )
+    }
+}
+
+impl From<&CustomVideoMode> for DetailedTiming {
+    fn from(mode: &CustomVideoMode) -> Self {
+        let p = &mode.param;
+        Self {
+            pixel_clock_khz: (mode.f_pix * 1000.).round() as u32,
+            hact: p.hact,
+            hfp: p.hfp,
+            hs: p.hs,
+            hbp: p.hbp,
+            vact: p.vact,
+            vfp: p.vfp,
+            vs: p.vs,
+            vbp: p.vbp,
+            hsync_positive: p.hpol == 0,
+            vsync_positive: p.vpol == 0,
+            interlaced: false,
+        }
+    }
+}
+
+impl From<&DetailedTiming> for CustomVideoMode {
+    fn from(timing: &DetailedTiming) -> Self {
+        let mut mode = CustomVideoMode::default();
+        mode.param.hact = timing.hact;
+        mode.param.hfp = timing.hfp;
+        mode.param.hs = timing.hs;
+        mode.param.hbp = timing.hbp;
+        mode.param.vact = timing.vact;
+        mode.param.vfp = timing.vfp;
+        mode.param.vs = timing.vs;
+        mode.param.vbp = timing.vbp;
+        mode.param.hpol = !timing.hsync_positive as u32;
+        mode.param.vpol = !timing.vsync_positive as u32;
+        mode.set_pll(timing.pixel_clock_khz as f64 / 1000.);
+        mode
+    }
+}
+
+/// Timings of the CEA-861 video identification codes (VICs) that can be
+/// output, as `(vic, [hact, hfp, hs, hbp, vact, vfp, vs, vbp], pixel clock in kHz)`.
+#[rustfmt::skip]
+const CEA_VIC_TIMINGS: &[(u8, [u32; 8], u32)] = &[
+    (1,  [640, 16, 96, 48, 480, 10, 2, 33],     25_175),
+    (2,  [720, 16, 62, 60, 480, 9, 6, 30],      27_000),
+    (3,  [720, 16, 62, 60, 480, 9, 6, 30],      27_000),
+    (4,  [1280, 110, 40, 220, 720, 5, 5, 20],   74_250),
+    (16, [1920, 88, 44, 148, 1080, 4, 5, 36],   148_500),
+    (17, [720, 12, 64, 68, 576, 5, 5, 39],      27_000),
+    (18, [720, 12, 64, 68, 576, 5, 5, 39],      27_000),
+    (19, [1280, 440, 40, 220, 720, 5, 5, 20],   74_250),
+    (31, [1920, 528, 44, 148, 1080, 4, 5, 36],  148_500),
+    (32, [1920, 638, 44, 148, 1080, 4, 5, 36],  74_250),
+    (33, [1920, 528, 44, 148, 1080, 4, 5, 36],  74_250),
+    (34, [1920, 88, 44, 148, 1080, 4, 5, 36],   74_250),
+];
+
+fn vic_timing(vic: u8) -> Option<DetailedTiming> {
+    let (_, t, pixel_clock_khz) = CEA_VIC_TIMINGS.iter().find(|(v, _, _)| *v == vic)?;
+    // Modes up to 576p use negative syncs.
+    let positive = t[4] > 576;
+    Some(DetailedTiming {
+        pixel_clock_khz: *pixel_clock_khz,
+        hact: t[0],
+        hfp: t[1],
+        hs: t[2],
+        hbp: t[3],
+        vact: t[4],
+        vfp: t[5],
+        vs: t[6],
+        vbp: t[7],
+        hsync_positive: positive,
+        vsync_positive: positive,
+        interlaced: false,
+    })
+}
+
+/// The limits of the display, from its range limits descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeLimits {
+    pub min_vertical_hz: u16,
+    pub max_vertical_hz: u16,
+    pub min_horizontal_khz: u16,
+    pub max_horizontal_khz: u16,
+    pub max_pixel_clock_mhz: Option<u16>,
+}
+
+/// The HDR static metadata data block of a CEA-861 extension.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HdrStaticMetadata {
+    pub sdr: bool,
+    pub hdr: bool,
+    pub pq: bool,
+    pub hlg: bool,
+    /// Luminances, in cd/m².
+    pub max_luminance: Option<f64>,
+    pub max_frame_average_luminance: Option<f64>,
+    pub min_luminance: Option<f64>,
+}
+
+impl HdrStaticMetadata {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let eotf = *data.first()?;
+        let luminance = |cv: Option<&u8>| {
+            cv.filter(|v| **v > 0)
+                .map(|v| 50. * 2f64.powf(*v as f64 / 32.))
+        };
+        let max_luminance = luminance(data.get(2));
+        let min_luminance = data
+            .get(4)
+            .zip(max_luminance)
+            .map(|(cv, max)| max * (*cv as f64 / 255.).powi(2) / 100.);
+
+        Some(Self {
+            sdr: eotf & 0x01 != 0,
+            hdr: eotf & 0x02 != 0,
+            pq: eotf & 0x04 != 0,
+            hlg: eotf & 0x08 != 0,
+            max_luminance,
+            max_frame_average_luminance: luminance(data.get(3)),
+            min_luminance,
+        })
+    }
+}
+
+/// Where a display mode comes from in the EDID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DisplayModeSource {
+    /// The first detailed timing, the native mode of the display.
+    Preferred,
+    Detailed,
+    Vic(u8),
+}
+
+/// A video mode supported by the display.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayMode {
+    pub source: DisplayModeSource,
+    pub width: u32,
+    pub height: u32,
+    pub refresh: f64,
+    pub timing: DetailedTiming,
+
+    /// The `video_mode` string to use this mode.
+    pub video_mode: String,
+
+    /// Whether the mode can be output by the scaler.
+    pub supported: bool,
+}
+
+/// The decoded EDID of a display. The default has no information, so only the
+/// limits of the scaler are checked.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EdidInfo {
+    pub manufacturer: String,
+    pub product_code: u16,
+    pub serial_number: u32,
+    pub year: u16,
+    pub version: String,
+    pub name: Option<String>,
+    pub range_limits: Option<RangeLimits>,
+
+    /// All detailed timings, from the base block then the extensions. The
+    /// first one is the preferred mode.
+    pub detailed_timings: Vec<DetailedTiming>,
+
+    /// Video identification codes of the CEA-861 extensions, with whether
+    /// they are a native mode.
+    pub vics: Vec<(u8, bool)>,
+
+    /// Whether the display supports HDMI (instead of DVI only).
+    pub hdmi: bool,
+    pub hdr: Option<HdrStaticMetadata>,
+    pub vrr: Option<VrrSupport>,
+}
+
+/// Iterate over the data blocks of a CEA-861 extension, including their header.
+fn cea_data_blocks(ext: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let end = (ext[2] as usize).clamp(4, 127);
+    let mut blocks = &ext[4..end];
+    std::iter::from_fn(move || {
+        let (header, rest) = blocks.split_first()?;
+        let len = (*header & 0x1F) as usize;
+        if len > rest.len() {
+            return None;
+        }
+        let block = &blocks[..=len];
+        blocks = &rest[len..];
+        Some(block)
+    })
+}
+
+impl EdidInfo {
+    pub fn decode(edid: &[u8]) -> Result<Self, String> {
+        if edid.len() < 128 || edid[..8] != [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00] {
+            return Err("Invalid EDID header".to_string());
+        }
+
+        let id = u16::from_be_bytes([edid[8], edid[9]]);
+        let manufacturer = [10, 5, 0]
+            .iter()
+            .map(|shift| (b'A' - 1 + ((id >> shift) & 0x1F) as u8) as char)
+            .collect();
+
+        let mut info = Self {
+            manufacturer,
+            product_code: u16::from_le_bytes([edid[10], edid[11]]),
+            serial_number: u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]),
+            year: 1990 + edid[17] as u16,
+            version: format!("{}.{}", edid[18], edid[19]),
+            name: None,
+            range_limits: None,
+            detailed_timings: Vec::new(),
+            vics: Vec::new(),
+            hdmi: false,
+            hdr: None,
+            vrr: None,
+        };
+
+        for d in edid[54..126].chunks_exact(18) {
+            if d[0] != 0 || d[1] != 0 {
+                info.detailed_timings.extend(DetailedTiming::parse(d));
+                continue;
+            }
+
+            match d[3] {
+                0xFC => {
+                    let name = String::from_utf8_lossy(&d[5..])
+                        .split('\n')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string();
+                    info.name = Some(name);
+                }
+                0xFD => {
+                    let offset = |bit: u8| if d[4] & bit != 0 { 255 } else { 0 };
+                    info.range_limits = Some(RangeLimits {
+                        min_vertical_hz: d[5] as u16 + offset(0x01),
+                        max_vertical_hz: d[6] as u16 + offset(0x02),
+                        min_horizontal_khz: d[7] as u16 + offset(0x04),
+                        max_horizontal_khz: d[8] as u16 + offset(0x08),
+                        max_pixel_clock_mhz: Some(d[9] as u16 * 10).filter(|c| *c > 0),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for ext in edid[128..].chunks_exact(128).filter(|ext| ext[0] == 0x02) {
+            info.decode_cea_extension(ext);
+        }
+
+        Ok(info)
+    }
+
+    fn decode_cea_extension(&mut self, ext: &[u8]) {
+        for block in cea_data_blocks(ext) {
+            let data = &block[1..];
+            match block[0] >> 5 {
+                // Video data block.
+                2 => self.vics.extend(data.iter().map(|svd| match svd {
+                    1..=64 | 129..=192 => (svd & 0x7F, svd & 0x80 != 0),
+                    _ => (*svd, false),
+                })),
+                // Vendor specific data block.
+                3 if data.len() >= 3 => match [data[0], data[1], data[2]] {
+                    HDMI_OUI => self.hdmi = true,
+                    AMD_OUI if data.len() >= 7 => {
+                        self.vrr = Some(VrrSupport {
+                            protocol: VrrProtocol::Freesync,
+                            min_framerate: data[5] as u16,
+                            max_framerate: data[6] as u16,
+                        });
+                    }
+                    HDMI_FORUM_OUI if data.len() >= 11 && data[9] & 0x3F != 0 => {
+                        // FreeSync is preferred when both are supported.
+                        if self.vrr.is_none() {
+                            self.vrr = Some(VrrSupport {
+                                protocol: VrrProtocol::HdmiVrr,
+                                min_framerate: (data[9] & 0x3F) as u16,
+                                max_framerate: (((data[9] & 0xC0) as u16) << 2) | data[10] as u16,
+                            });
+                        }
+                    }
+                    _ => {}
+                },
+                // Extended tag, HDR static metadata data block.
+                7 if data.first() == Some(&6) => self.hdr = HdrStaticMetadata::parse(&data[1..]),
+                _ => {}
+            }
+        }
+
+        let dtd_start = ext[2] as usize;
+        if (4..127).contains(&dtd_start) {
+            self.detailed_timings.extend(
+                ext[dtd_start..127]
+                    .chunks_exact(18)
+                    .map_while(DetailedTiming::parse),
+            );
+        }
+    }
+
+    /// All the video modes of the display, the preferred one first. Duplicates
+    /// are removed.
+    pub fn modes(&self) -> Vec<DisplayMode> {
+        let detailed = self.detailed_timings.iter().enumerate().map(|(i, t)| {
+            let source = if i == 0 {
+                DisplayModeSource::Preferred
+            } else {
+                DisplayModeSource::Detailed
+            };
+            (source, *t)
+        });
+        let vics = self
+            .vics
+            .iter()
+            .filter_map(|(vic, _)| Some((DisplayModeSource::Vic(*vic), vic_timing(*vic)?)));
+
+        let mut modes: Vec<DisplayMode> = Vec::new();
+        for (source, timing) in detailed.chain(vics) {
+            if modes.iter().any(|m| m.timing == timing) {
+                continue;
+            }
+            modes.push(DisplayMode {
+                source,
+                width: timing.hact,
+                height: timing.vact,
+                refresh: timing.refresh(),
+                timing,
+                video_mode: timing.to_video_mode_string(),
+                supported: self.check_timing(&timing).is_empty(),
+            });
+        }
+        modes
+    }
+
+    /// Check a video mode against the limits of the display and the scaler.
+    /// Returns a list of problems, empty if the mode can be used.
+    pub fn check_mode(&self, mode: &CustomVideoMode) -> Vec<String> {
+        self.check_timing(&DetailedTiming::from(mode))
+    }
+
+    fn check_timing(&self, timing: &DetailedTiming) -> Vec<String> {
+        let mut problems = Vec::new();
+        let pixel_clock = timing.pixel_clock_khz as f64 / 1000.;
+        let refresh = timing.refresh();
+        let horizontal_khz = timing.pixel_clock_khz as f64 / timing.h_total() as f64;
+
+        if timing.interlaced {
+            problems.push("Interlaced modes are not supported".to_string());
+        }
+        if pixel_clock > MAX_PIXEL_CLOCK_MHZ {
+            problems.push(format!(
+                "Pixel clock {pixel_clock:.3}MHz is above the maximum of the scaler ({MAX_PIXEL_CLOCK_MHZ}MHz)"
+            ));
+        }
+
+        if let Some(limits) = self.range_limits {
+            if let Some(max) = limits
+                .max_pixel_clock_mhz
+                .filter(|max| pixel_clock > *max as f64)
+            {
+                problems.push(format!(
+                    "Pixel clock {pixel_clock:.3}MHz is above the maximum of the display ({max}MHz)"
+                ));
+            }
+            let (min, max) = (limits.min_vertical_hz, limits.max_vertical_hz);
+            if refresh < min as f64 - 0.5 || refresh > max as f64 + 0.5 {
+                problems.push(format!(
+                    "Refresh rate {refresh:.2}Hz is outside of the range of the display ({min}-{max}Hz)"
+                ));
+            }
+            let (min, max) = (limits.min_horizontal_khz, limits.max_horizontal_khz);
+            if horizontal_khz < min as f64 - 0.5 || horizontal_khz > max as f64 + 0.5 {
+                problems.push(format!(
+                    "Horizontal frequency {horizontal_khz:.2}kHz is outside of the range of the display ({min}-{max}kHz)"
+                ));
+            }
+        }
+
+        problems
+    }
+}
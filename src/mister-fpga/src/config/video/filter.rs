@@ -0,0 +1,385 @@
+//! Scaler filters, shadow masks and presets, as found in the `Filters`,
+//! `Shadow_Masks` and `Presets` folders of a MiSTer SD card.
+use crate::config::{Config, MisterConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::warn;
+
+/// Number of phases of a polyphase filter.
+pub const FILTER_PHASES: usize = 16;
+
+/// Number of taps of every phase of a polyphase filter.
+pub const FILTER_TAPS: usize = 4;
+
+/// Maximum width and height of a shadow mask.
+pub const SHADOW_MASK_MAX_SIZE: usize = 16;
+
+fn content_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(|l| l.split(['#', ';']).next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty())
+}
+
+/// The coefficients of a polyphase filter.
+pub type FilterPhases = [[i16; FILTER_TAPS]; FILTER_PHASES];
+
+/// A polyphase filter for the scaler. Files contain 16 phases of 4 coefficients
+/// (one phase per line), used for both directions, or 32 phases where the first
+/// 16 are horizontal and the last 16 are vertical.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolyphaseFilter {
+    pub horizontal: FilterPhases,
+    pub vertical: FilterPhases,
+}
+
+impl PolyphaseFilter {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read filter {path:?}: {e}"))?
+            .parse()
+            .map_err(|e| format!("Invalid filter {path:?}: {e}"))
+    }
+}
+
+impl FromStr for PolyphaseFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let phases = content_lines(s)
+            .map(|line| {
+                let taps = line
+                    .split(',')
+                    .map(|c| c.trim().parse::<i16>().map_err(|e| e.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(c) = taps.iter().find(|c| !(-256..256).contains(*c)) {
+                    return Err(format!("Coefficient {c} out of range"));
+                }
+                <[i16; FILTER_TAPS]>::try_from(taps)
+                    .map_err(|t| format!("Expected {FILTER_TAPS} coefficients, found {}", t.len()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let as_phases = |p: &[[i16; FILTER_TAPS]]| -> FilterPhases { p.try_into().unwrap() };
+        match phases.len() {
+            FILTER_PHASES => Ok(Self {
+                horizontal: as_phases(&phases),
+                vertical: as_phases(&phases),
+            }),
+            n if n == FILTER_PHASES * 2 => Ok(Self {
+                horizontal: as_phases(&phases[..FILTER_PHASES]),
+                vertical: as_phases(&phases[FILTER_PHASES..]),
+            }),
+            n => Err(format!(
+                "Expected {} or {} phases, found {n}",
+                FILTER_PHASES,
+                FILTER_PHASES * 2
+            )),
+        }
+    }
+}
+
+/// How a shadow mask is applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShadowMaskMode {
+    #[default]
+    None,
+    Enabled,
+    /// Every pixel of the mask covers 2x2 pixels of the output.
+    Enabled2x,
+}
+
+impl ShadowMaskMode {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::None,
+            1 => Self::Enabled,
+            _ => Self::Enabled2x,
+        }
+    }
+}
+
+/// A shadow mask. Files start with an optional version line (`v1` or `v2`),
+/// then the `width,height` of the mask, followed by one line per row of
+/// comma separated hexadecimal values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowMask {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u16>,
+}
+
+impl ShadowMask {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read shadow mask {path:?}: {e}"))?
+            .parse()
+            .map_err(|e| format!("Invalid shadow mask {path:?}: {e}"))
+    }
+}
+
+impl FromStr for ShadowMask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = content_lines(s).peekable();
+        if lines
+            .peek()
+            .is_some_and(|l| l.eq_ignore_ascii_case("v1") || l.eq_ignore_ascii_case("v2"))
+        {
+            lines.next();
+        }
+
+        let (width, height) = lines
+            .next()
+            .and_then(|l| l.split_once(','))
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+            .ok_or("Missing shadow mask size")?;
+        if !(1..=SHADOW_MASK_MAX_SIZE).contains(&width)
+            || !(1..=SHADOW_MASK_MAX_SIZE).contains(&height)
+        {
+            return Err(format!("Invalid shadow mask size {width}x{height}"));
+        }
+
+        let mut data = Vec::with_capacity(width * height);
+        for row in lines.by_ref().take(height) {
+            let values = row
+                .split(',')
+                .map(|v| {
+                    let v = v.trim();
+                    let v = v.strip_prefix("0x").unwrap_or(v);
+                    u16::from_str_radix(v, 16)
+                        .ok()
+                        .filter(|v| *v <= 0xFFF)
+                        .ok_or_else(|| format!("Invalid shadow mask value {v:?}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.len() != width {
+                return Err(format!("Expected {width} values, found {}", values.len()));
+            }
+            data.extend(values);
+        }
+        if data.len() != width * height {
+            return Err(format!("Expected {height} rows"));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+}
+
+/// The filters used by the scaler. Filter and shadow mask names are paths relative
+/// to their folder on the SD card (e.g. `Scanlines/Scanlines_25.txt`).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScalerFilters {
+    pub horizontal: Option<String>,
+    pub vertical: Option<String>,
+    pub scanlines: Option<String>,
+    pub shadow_mask: Option<String>,
+    #[serde(default)]
+    pub shadow_mask_mode: ShadowMaskMode,
+}
+
+impl ScalerFilters {
+    /// The default filters from the MiSTer.ini. Values in the `preset_default`
+    /// preset are used for any filter not set explicitly.
+    pub fn from_config(config: &MisterConfig) -> Self {
+        let non_empty = |v: &Option<String>| v.clone().filter(|v| !v.trim().is_empty());
+        let mut filters = Self {
+            horizontal: non_empty(&config.vfilter_default),
+            vertical: non_empty(&config.vfilter_vertical_default),
+            scanlines: non_empty(&config.vfilter_scanlines_default),
+            shadow_mask: non_empty(&config.shmask_default),
+            shadow_mask_mode: ShadowMaskMode::from_u8(config.shmask_mode_default.unwrap_or(0)),
+        };
+
+        if let Some(preset) = non_empty(&config.preset_default) {
+            match Self::load_preset(Config::presets_root().join(preset)) {
+                Ok(preset) => {
+                    filters.horizontal = filters.horizontal.or(preset.horizontal);
+                    filters.vertical = filters.vertical.or(preset.vertical);
+                    filters.scanlines = filters.scanlines.or(preset.scanlines);
+                    filters.shadow_mask = filters.shadow_mask.or(preset.shadow_mask);
+                    if config.shmask_mode_default.is_none() {
+                        filters.shadow_mask_mode = preset.shadow_mask_mode;
+                    }
+                }
+                Err(e) => warn!("Could not load preset: {}", e),
+            }
+        }
+
+        filters
+    }
+
+    /// Load a preset file, which uses the same keys as the MiSTer.ini.
+    pub fn load_preset(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read preset {path:?}: {e}"))?
+            .parse()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// The MiSTer.ini keys of these filters, to write them back with
+    /// [`ConfigFile::set_all`](crate::config::ConfigFile::set_all). The preset
+    /// is cleared, as the filters already include its values.
+    pub fn ini_values(&self) -> Vec<(&'static str, String)> {
+        let name = |v: &Option<String>| v.clone().unwrap_or_default();
+        vec![
+            ("vfilter_default", name(&self.horizontal)),
+            ("vfilter_vertical_default", name(&self.vertical)),
+            ("vfilter_scanlines_default", name(&self.scanlines)),
+            ("shmask_default", name(&self.shadow_mask)),
+            (
+                "shmask_mode_default",
+                (self.shadow_mask_mode as u8).to_string(),
+            ),
+            ("preset_default", String::new()),
+        ]
+    }
+
+    /// Load the filter files. Returns the horizontal and vertical filters (the
+    /// scanlines filter replaces the vertical one), and the shadow mask.
+    pub fn load(&self) -> Result<(Option<PolyphaseFilter>, Option<ShadowMask>), String> {
+        let load_filter = |name: &Option<String>| {
+            name.as_ref()
+                .map(|n| PolyphaseFilter::load(Config::filters_root().join(n)))
+                .transpose()
+        };
+
+        let horizontal = load_filter(&self.horizontal)?;
+        let vertical = match load_filter(&self.scanlines)? {
+            Some(f) => Some(f),
+            None => load_filter(&self.vertical)?,
+        };
+        let filter = match (horizontal, vertical) {
+            (Some(h), Some(v)) => Some(PolyphaseFilter {
+                horizontal: h.horizontal,
+                vertical: v.vertical,
+            }),
+            (h, v) => h.or(v),
+        };
+
+        let shadow_mask = match self.shadow_mask_mode {
+            ShadowMaskMode::None => None,
+            _ => self
+                .shadow_mask
+                .as_ref()
+                .map(|n| ShadowMask::load(Config::shadow_masks_root().join(n)))
+                .transpose()?,
+        };
+
+        Ok((filter, shadow_mask))
+    }
+}
+
+impl FromStr for ScalerFilters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filters = Self::default();
+        for line in content_lines(s).filter(|l| !l.starts_with('[')) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            match key.trim() {
+                "vfilter_default" => filters.horizontal = value,
+                "vfilter_vertical_default" => filters.vertical = value,
+                "vfilter_scanlines_default" => filters.scanlines = value,
+                "shmask_default" => filters.shadow_mask = value,
+                "shmask_mode_default" => {
+                    filters.shadow_mask_mode = ShadowMaskMode::from_u8(
+                        value
+                            .as_deref()
+                            .unwrap_or("0")
+                            .parse()
+                            .map_err(|_| format!("Invalid shmask_mode_default {value:?}"))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+        Ok(filters)
+    }
+}
+
+/// List the `.txt` files in a folder and its subfolders, relative to it.
+pub fn list_filter_files(root: impl AsRef<Path>) -> Vec<String> {
+    fn visit(root: &Path, dir: &Path, out: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.is_dir() {
+                visit(root, &path, out);
+            } else if path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("txt"))
+            {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    out.push(relative.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+
+    let root: PathBuf = root.as_ref().into();
+    let mut out = Vec::new();
+    visit(&root, &root, &mut out);
+    out.sort();
+    out
+}
+
+#[test]
+fn parse_filter() {
+    let phases = (0..16).map(|i| format!("{}, {}, 0, -{}\n", 128 - i, i, i % 3));
+    let content = format!("# Test filter\n{}", phases.collect::<String>());
+
+    let filter: PolyphaseFilter = content.parse().unwrap();
+    assert_eq!(filter.horizontal, filter.vertical);
+    assert_eq!(filter.horizontal[2], [126, 2, 0, -2]);
+
+    let both = format!("{content}{content}");
+    assert!(both.parse::<PolyphaseFilter>().is_ok());
+    assert!("1,2,3\n".parse::<PolyphaseFilter>().is_err());
+    assert!(content
+        .replace("128", "512")
+        .parse::<PolyphaseFilter>()
+        .is_err());
+}
+
+#[test]
+fn parse_shadow_mask() {
+    let mask: ShadowMask = "# Aperture grille\nv2\n3,2\n0x0F0,000,F00\n00F,0F0,000\n"
+        .parse()
+        .unwrap();
+    assert_eq!((mask.width, mask.height), (3, 2));
+    assert_eq!(mask.data, vec![0x0F0, 0, 0xF00, 0x00F, 0x0F0, 0]);
+
+    assert!("2,2\n0,0\n".parse::<ShadowMask>().is_err());
+    assert!("17,1\n0\n".parse::<ShadowMask>().is_err());
+}
+
+#[test]
+fn parse_preset() {
+    let preset: ScalerFilters = "[MiSTer]\nvfilter_default=Lanczos/lanczos2.txt\n\
+        shmask_default=Masks/grille.txt ; comment\nshmask_mode_default=2\n"
+        .parse()
+        .unwrap();
+    assert_eq!(preset.horizontal.as_deref(), Some("Lanczos/lanczos2.txt"));
+    assert_eq!(preset.vertical, None);
+    assert_eq!(preset.shadow_mask.as_deref(), Some("Masks/grille.txt"));
+    assert_eq!(preset.shadow_mask_mode, ShadowMaskMode::Enabled2x);
+}
@@ -0,0 +1,148 @@
+//! The video modes that can be selected from the UI: the default modes of the
+//! scaler, the modes of the display and the custom modes of the MiSTer.ini.
+use crate::config::edid::{
+    parse_custom_video_mode, CustomVideoMode, DefaultVideoMode, DisplayModeSource, EdidInfo,
+};
+use crate::config::MisterConfig;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VideoModeSource {
+    /// One of the default modes of the scaler.
+    Default,
+
+    /// A mode from the EDID of the display.
+    Display,
+
+    /// A mode from the `video_mode` keys of the MiSTer.ini.
+    Custom,
+}
+
+/// A video mode that can be selected.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoModeInfo {
+    pub name: String,
+    pub source: VideoModeSource,
+
+    /// The `video_mode` string of this mode, which can be passed to
+    /// [`parse_custom_video_mode`].
+    pub video_mode: String,
+    pub width: u32,
+    pub height: u32,
+    pub refresh: f64,
+
+    /// Whether the mode can be output by the scaler and the display.
+    pub supported: bool,
+}
+
+impl VideoModeInfo {
+    fn new(
+        name: String,
+        source: VideoModeSource,
+        video_mode: String,
+        mode: &CustomVideoMode,
+        edid: &EdidInfo,
+    ) -> Self {
+        Self {
+            name,
+            source,
+            video_mode,
+            // Pixel repetition doubles the horizontal resolution.
+            width: mode.param.hact << mode.param.pr,
+            height: mode.param.vact,
+            refresh: mode.frame_rate(),
+            supported: edid.check_mode(mode).is_empty(),
+        }
+    }
+}
+
+/// List the video modes that can be selected, the custom modes of the config
+/// first, then the ones of the display and the defaults. Without an EDID, only
+/// the limits of the scaler are checked.
+pub fn available_video_modes(config: &MisterConfig, edid: Option<&EdidInfo>) -> Vec<VideoModeInfo> {
+    let no_edid = EdidInfo::default();
+    let edid = edid.unwrap_or(&no_edid);
+    let mut modes = Vec::new();
+
+    let custom = [
+        ("video_mode", &config.video_conf),
+        ("video_mode_ntsc", &config.video_conf_ntsc),
+        ("video_mode_pal", &config.video_conf_pal),
+    ];
+    for (key, video_mode) in custom {
+        let Some(video_mode) = video_mode.as_deref().map(str::trim) else {
+            continue;
+        };
+        if let Ok(mode) = parse_custom_video_mode(Some(video_mode)) {
+            modes.push(VideoModeInfo::new(
+                format!("{key}={video_mode}"),
+                VideoModeSource::Custom,
+                video_mode.to_string(),
+                &mode,
+                edid,
+            ));
+        }
+    }
+
+    for m in edid.modes() {
+        let name = match m.source {
+            DisplayModeSource::Preferred => {
+                format!("{}x{}@{:.2} (native)", m.width, m.height, m.refresh)
+            }
+            _ => format!("{}x{}@{:.2}", m.width, m.height, m.refresh),
+        };
+        let mode = CustomVideoMode::from(&m.timing);
+        modes.push(VideoModeInfo::new(
+            name,
+            VideoModeSource::Display,
+            m.video_mode,
+            &mode,
+            edid,
+        ));
+    }
+
+    // TV modes are only used with direct video, and cannot be selected.
+    for index in 0..DefaultVideoMode::Ntsc15K as u8 {
+        let video_mode = index.to_string();
+        let Ok(mode) = parse_custom_video_mode(Some(&video_mode)) else {
+            continue;
+        };
+        let name = format!(
+            "{}x{}@{:.0}",
+            mode.param.hact << mode.param.pr,
+            mode.param.vact,
+            mode.frame_rate()
+        );
+        modes.push(VideoModeInfo::new(
+            name,
+            VideoModeSource::Default,
+            video_mode,
+            &mode,
+            edid,
+        ));
+    }
+
+    modes
+}
+
+#[test]
+fn list_video_modes() {
+    let mut config = MisterConfig::new_defaults();
+    config.video_conf = Some("1280x720@50".to_string());
+    let modes = available_video_modes(&config, None);
+
+    assert_eq!(modes[0].source, VideoModeSource::Custom);
+    assert_eq!((modes[0].width, modes[0].height), (1280, 720));
+
+    let defaults = modes
+        .iter()
+        .filter(|m| m.source == VideoModeSource::Default)
+        .collect::<Vec<_>>();
+    assert_eq!(defaults.len(), DefaultVideoMode::Ntsc15K as usize);
+    assert!(defaults.iter().all(|m| m.supported));
+    assert!(defaults
+        .iter()
+        .any(|m| m.name == "1920x1080@60" && m.video_mode == "8"));
+}
@@ -0,0 +1,225 @@
+//! Variable refresh rate (FreeSync, HDMI VRR and QMS) and `vsync_adjust`, which
+//! make the HDMI output follow the refresh rate of the core.
+use crate::config::edid::{CustomVideoMode, EdidInfo};
+use crate::config::{MisterConfig, VrrModeConfig, VsyncAdjustConfig};
+use serde::Serialize;
+use tracing::warn;
+
+/// Refresh range used when neither the MiSTer.ini nor the display set one.
+pub const DEFAULT_VRR_MIN_FRAMERATE: u16 = 48;
+pub const DEFAULT_VRR_MAX_FRAMERATE: u16 = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VrrProtocol {
+    /// AMD FreeSync, signalled with an SPD infoframe.
+    Freesync,
+
+    /// HDMI Forum VRR, signalled with a VTEM packet.
+    HdmiVrr,
+
+    /// HDMI Quick Media Switching, signalled with a VTEM packet.
+    Qms,
+}
+
+/// The variable refresh rate protocol and range to use with a display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VrrSupport {
+    pub protocol: VrrProtocol,
+    pub min_framerate: u16,
+    pub max_framerate: u16,
+}
+
+impl VrrSupport {
+    /// Detect the variable refresh rate support of a display from its EDID,
+    /// preferring FreeSync.
+    pub fn from_edid(edid: &[u8]) -> Option<Self> {
+        EdidInfo::decode(edid).ok()?.vrr
+    }
+
+    /// The variable refresh rate to use from the MiSTer.ini, using the EDID of
+    /// the display to detect support when `vrr_mode` is auto.
+    pub fn from_config(config: &MisterConfig, edid: Option<&[u8]>) -> Option<Self> {
+        let detected = edid.and_then(Self::from_edid);
+        let protocol = match config.vrr_mode() {
+            VrrModeConfig::Disabled => return None,
+            VrrModeConfig::Auto => detected?.protocol,
+            VrrModeConfig::Freesync => VrrProtocol::Freesync,
+            VrrModeConfig::HdmiVrr => VrrProtocol::HdmiVrr,
+            VrrModeConfig::Qms => VrrProtocol::Qms,
+        };
+
+        let non_zero = |v: Option<u8>| v.filter(|v| *v > 0).map(u16::from);
+        Some(Self {
+            protocol,
+            min_framerate: non_zero(config.vrr_min_framerate)
+                .or(detected.map(|d| d.min_framerate))
+                .unwrap_or(DEFAULT_VRR_MIN_FRAMERATE),
+            max_framerate: non_zero(config.vrr_max_framerate)
+                .or(detected.map(|d| d.max_framerate))
+                .unwrap_or(DEFAULT_VRR_MAX_FRAMERATE),
+        })
+    }
+
+    /// The FreeSync SPD infoframe, with its header and checksum.
+    pub fn freesync_infoframe(&self) -> [u8; 12] {
+        #[rustfmt::skip]
+        let mut data = [
+            0x83, 0x01, 0x08, // SPD infoframe, version 1, 8 bytes.
+            0x00,             // Checksum, calculated later.
+            0x1A, 0x00, 0x00, // AMD OUI.
+            0x00, 0x00,
+            0x07,             // FreeSync supported, enabled and active.
+            self.min_framerate.min(255) as u8,
+            self.max_framerate.min(255) as u8,
+        ];
+
+        data[3] = 0u8.wrapping_sub(data.iter().fold(0u8, |a, i| a.wrapping_add(*i)));
+        data
+    }
+
+    /// The HDMI video timing extended metadata packet (VTEM) for a mode at
+    /// the `base_refresh` rate.
+    pub fn vtem_packet(&self, mode: &CustomVideoMode, base_refresh: u16) -> [u8; 14] {
+        let flags = match self.protocol {
+            VrrProtocol::Qms => 0b110, // M_CONST and QMS_EN.
+            _ => 0b001,                // VRR_EN.
+        };
+        let base_refresh = base_refresh.min(0x3FF);
+
+        #[rustfmt::skip]
+        let data = [
+            0x7F, 0xC0, 0x00, // Extended metadata packet, first and last, sequence 0.
+            0xC4,             // New, end, variable frame rate.
+            0x00,
+            0x01,             // Organization ID (HDMI Forum).
+            0x00, 0x01,       // Data set tag (VTEM).
+            0x00, 0x04,       // Data set length.
+            flags,
+            mode.param.vfp.min(255) as u8,
+            ((mode.param.rb as u8 & 1) << 2) | (base_refresh >> 8) as u8,
+            base_refresh as u8,
+        ];
+        data
+    }
+}
+
+/// How the HDMI output follows the refresh rate of the core.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RefreshSync {
+    pub vsync_adjust: VsyncAdjustConfig,
+    pub vrr: Option<VrrSupport>,
+
+    /// The base refresh rate sent with HDMI VRR, instead of the core's.
+    pub vesa_framerate: Option<u16>,
+    refresh_min: f64,
+    refresh_max: f64,
+}
+
+impl RefreshSync {
+    pub fn from_config(config: &MisterConfig, edid: Option<&[u8]>) -> Self {
+        let (refresh_min, refresh_max) = config.vsync_adjust_refresh_range();
+        Self {
+            vsync_adjust: config.vsync_adjust(),
+            vrr: VrrSupport::from_config(config, edid),
+            vesa_framerate: config.vrr_vesa_framerate.filter(|v| *v > 0).map(u16::from),
+            refresh_min: refresh_min as f64,
+            refresh_max: refresh_max as f64,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.vrr.is_some() || self.vsync_adjust != VsyncAdjustConfig::Disabled
+    }
+
+    /// Whether the scaler should use a single buffer to lower latency.
+    pub fn is_low_latency(&self) -> bool {
+        self.vrr.is_none() && self.vsync_adjust == VsyncAdjustConfig::LowLatency
+    }
+
+    /// The base refresh rate of HDMI VRR for a core running at `refresh` Hz.
+    pub fn base_refresh(&self, refresh: f64) -> u16 {
+        self.vesa_framerate.unwrap_or(refresh.round() as u16)
+    }
+
+    /// Adjust a video mode for a core running at `refresh` Hz. With VRR the
+    /// core drives the refresh rate, otherwise with `vsync_adjust` the pixel
+    /// clock is changed so both refresh rates match.
+    pub fn adjust(&self, mode: CustomVideoMode, refresh: f64) -> CustomVideoMode {
+        if self.vrr.is_some() {
+            return CustomVideoMode { vrr: true, ..mode };
+        }
+        if self.vsync_adjust == VsyncAdjustConfig::Disabled {
+            return mode;
+        }
+        if !(self.refresh_min..=self.refresh_max).contains(&refresh) {
+            warn!(
+                refresh,
+                "Refresh rate outside of the range for vsync_adjust"
+            );
+            return mode;
+        }
+
+        mode.with_refresh(refresh).unwrap_or_else(|| {
+            warn!(refresh, "Pixel clock out of range for vsync_adjust");
+            mode
+        })
+    }
+}
+
+#[cfg(test)]
+fn test_edid(vendor_block: &[u8]) -> Vec<u8> {
+    let mut edid = vec![0u8; 256];
+    edid[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+    edid[128] = 0x02;
+    edid[129] = 0x03;
+    // An unrelated video data block, then the vendor block.
+    edid[132..135].copy_from_slice(&[0x42, 0x10, 0x04]);
+    edid[135..135 + vendor_block.len()].copy_from_slice(vendor_block);
+    edid[130] = (135 + vendor_block.len() - 128) as u8;
+    edid
+}
+
+#[test]
+fn detect_vrr() {
+    let freesync = test_edid(&[0x67, 0x1A, 0x00, 0x00, 0x01, 0x01, 48, 144]);
+    assert_eq!(
+        VrrSupport::from_edid(&freesync),
+        Some(VrrSupport {
+            protocol: VrrProtocol::Freesync,
+            min_framerate: 48,
+            max_framerate: 144,
+        })
+    );
+
+    let hdmi = [
+        0x6B, 0xD8, 0x5D, 0xC4, 0x01, 0x78, 0x00, 0x00, 0x00, 0x00, 0x58, 0x20,
+    ];
+    assert_eq!(
+        VrrSupport::from_edid(&test_edid(&hdmi)),
+        Some(VrrSupport {
+            protocol: VrrProtocol::HdmiVrr,
+            min_framerate: 24,
+            max_framerate: 288,
+        })
+    );
+
+    assert_eq!(
+        VrrSupport::from_edid(&test_edid(&[0x63, 0x03, 0x0C, 0x00])),
+        None
+    );
+    assert_eq!(VrrSupport::from_edid(&[0u8; 128]), None);
+}
+
+#[test]
+fn freesync_infoframe_checksum() {
+    let vrr = VrrSupport {
+        protocol: VrrProtocol::Freesync,
+        min_framerate: 48,
+        max_framerate: 75,
+    };
+    let data = vrr.freesync_infoframe();
+    assert_eq!(data.iter().fold(0u8, |a, i| a.wrapping_add(*i)), 0);
+    assert_eq!(&data[10..], &[48, 75]);
+}
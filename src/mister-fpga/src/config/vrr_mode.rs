@@ -5,6 +5,7 @@ use serde::Deserialize;
 /// 1 - Auto Detect VRR from display EDID.
 /// 2 - Force Enable Freesync
 /// 3 - Force Enable Vesa HDMI Forum VRR
+/// 4 - Force Enable HDMI Quick Media Switching (QMS-VRR)
 #[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
 pub enum VrrModeConfig {
     #[default]
@@ -19,4 +20,7 @@ pub enum VrrModeConfig {
 
     #[serde(alias = "3")]
     HdmiVrr = 3,
+
+    #[serde(alias = "4")]
+    Qms = 4,
 }
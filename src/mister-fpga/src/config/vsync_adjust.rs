@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-#[derive(Default, Debug, Copy, Clone, Deserialize)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum VsyncAdjustConfig {
     #[default]
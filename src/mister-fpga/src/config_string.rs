@@ -19,7 +19,7 @@ use tracing::{debug, warn};
 use one_fpga::core::{CoreSettingItem, CoreSettings, SettingId};
 pub use types::*;
 
-use crate::fpga::user_io;
+use crate::fpga::{user_io, SdramRequirement};
 use crate::types::StatusBitMap;
 
 pub mod midi;
@@ -459,6 +459,53 @@ impl Config {
         None
     }
 
+    /// The option used to select the savestate slot, with its status bits and
+    /// choices (one per slot), if the core has one.
+    pub fn savestate_slot_option(&self) -> Option<(&Range<u8>, &[String])> {
+        self.menu
+            .iter()
+            .filter_map(ConfigMenu::as_option)
+            .find_map(|item| match item {
+                ConfigMenu::Option {
+                    bits,
+                    label,
+                    choices,
+                } => {
+                    let label = label.to_lowercase();
+                    (label.contains("savestate slot") || label.contains("save state slot"))
+                        .then_some((bits, choices.as_slice()))
+                }
+                _ => None,
+            })
+    }
+
+    /// Whether the core needs an SDRAM module, inferred from its config
+    /// string. Cores named after SDRAM (e.g. `JTSDRAM`, a memory test) need
+    /// it, and cores with an option to select SDRAM can run without it.
+    /// Otherwise, cores known to need SDRAM (e.g. `NES`) are looked up by name.
+    /// Cores using dual SDRAM are only known from their core type, once loaded.
+    pub fn sdram_requirement(&self) -> SdramRequirement {
+        if self.name.to_uppercase().contains("SDRAM") {
+            return SdramRequirement::Required;
+        }
+
+        let has_sdram_choice = self
+            .menu
+            .iter()
+            .filter_map(ConfigMenu::as_option)
+            .any(|item| match item {
+                ConfigMenu::Option { choices, .. } => {
+                    choices.iter().any(|c| c.eq_ignore_ascii_case("SDRAM"))
+                }
+                _ => false,
+            });
+        if has_sdram_choice {
+            SdramRequirement::Optional
+        } else {
+            SdramRequirement::of_known_core(&self.name).unwrap_or_default()
+        }
+    }
+
     pub fn as_core_settings(&self, bits: &StatusBitMap) -> CoreSettings {
         let it = self.menu.iter().flat_map(|item| {
             item.as_core_menu_item(bits)
@@ -597,6 +644,14 @@ fn config_string_nes() {
     assert!(config.is_ok(), "{:?}", config);
 }
 
+#[test]
+fn config_string_nes_savestate_slots() {
+    let config = Config::from_str(CONFIG_STRING_NES).unwrap();
+    let (bits, choices) = config.savestate_slot_option().unwrap();
+    assert_eq!(bits, &(45..47));
+    assert_eq!(choices.len(), 4);
+}
+
 #[test]
 fn config_string_nes_menu() {
     let config = Config::from_str(CONFIG_STRING_NES).unwrap();
@@ -3,6 +3,16 @@ use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use tracing::trace;
 
+/// Create a file for reading and writing, truncating it if it exists.
+fn create_file(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
+
 #[derive(Debug)]
 enum SdMountFileInner {
     /// A memory based sd card.
@@ -54,6 +64,11 @@ impl Write for SdMountFileInner {
             } => {
                 if f.stream_position()? + buf.len() as u64 > *max_size {
                     let end = (*max_size).saturating_sub(f.stream_position()?) as usize;
+                    if end == 0 {
+                        // Drop anything past the maximum size.
+                        trace!(len = buf.len(), "Write past max size, ignoring.");
+                        return Ok(buf.len());
+                    }
                     f.write(&buf[..end])
                 } else {
                     f.write(buf)
@@ -74,7 +89,7 @@ impl Write for SdMountFileInner {
                 }
 
                 trace!("Creating {:?}", p);
-                let mut f = File::create(&p)?;
+                let mut f = create_file(p)?;
                 let result = f.write(buf);
                 *self = SdMountFileInner::File {
                     f: Some(f),
@@ -99,6 +114,19 @@ impl Write for SdMountFileInner {
     }
 }
 
+impl SdMountFileInner {
+    /// Make sure all writes are persisted to the filesystem.
+    fn sync(&mut self) -> std::io::Result<()> {
+        match self {
+            SdMountFileInner::File { f: Some(f), .. } => {
+                f.flush()?;
+                f.sync_data()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl Seek for SdMountFileInner {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
         match self {
@@ -110,7 +138,7 @@ impl Seek for SdMountFileInner {
                 ..
             } => {
                 trace!("Creating {:?}", p);
-                let mut f = File::create(&p)?;
+                let mut f = create_file(p)?;
                 let result = f.seek(pos);
                 *self = SdMountFileInner::File {
                     f: Some(f),
@@ -134,6 +162,9 @@ impl Seek for SdMountFileInner {
 pub struct SdCard {
     writeable: bool,
 
+    /// Whether the core wrote to the card since the last flush.
+    dirty: bool,
+
     inner: SdMountFileInner,
 }
 
@@ -157,6 +188,7 @@ impl SdCard {
 
         Ok(Self {
             writeable,
+            dirty: false,
             inner: SdMountFileInner::File {
                 f: file,
                 path: Some(path),
@@ -168,10 +200,28 @@ impl SdCard {
     pub fn from_memory(data: Vec<u8>) -> Self {
         Self {
             writeable: true,
+            dirty: false,
             inner: SdMountFileInner::Memory(Cursor::new(data)),
         }
     }
 
+    /// Limit the size the file can grow to. Writes past this size are truncated.
+    /// This has no effect on memory cards.
+    pub fn with_max_size(mut self, size: u64) -> Self {
+        if let SdMountFileInner::File { max_size, .. } = &mut self.inner {
+            *max_size = Some(size);
+        }
+        self
+    }
+
+    /// The path of the file on the filesystem, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.inner {
+            SdMountFileInner::File { path, .. } => path.as_deref(),
+            SdMountFileInner::Memory(_) => None,
+        }
+    }
+
     pub fn writeable(&self) -> bool {
         self.writeable
     }
@@ -192,6 +242,42 @@ impl SdCard {
         self.size() == 0
     }
 
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Write `data` at `offset`, marking the card as dirty.
+    pub fn write_at(&mut self, offset: u64, data: &[u8]) -> std::io::Result<()> {
+        self.dirty = true;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        self.inner.write_all(data)
+    }
+
+    /// Read into `buffer` from `offset`. Anything past the end of the file is
+    /// read as zeroes, since cores read whole blocks.
+    pub fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> std::io::Result<()> {
+        buffer.fill(0);
+        self.inner.seek(SeekFrom::Start(offset))?;
+
+        let mut total = 0;
+        while total < buffer.len() {
+            match self.inner.read(&mut buffer[total..])? {
+                0 => break,
+                n => total += n,
+            }
+        }
+        Ok(())
+    }
+
+    /// Persist any write done by the core to the filesystem.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if self.dirty {
+            self.inner.sync()?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
     pub fn as_io(&mut self) -> &'_ mut (impl Read + Write + Seek) {
         &mut self.inner
     }
@@ -200,3 +286,22 @@ impl SdCard {
         &mut self.inner
     }
 }
+
+#[test]
+fn sd_card_max_size_and_short_reads() {
+    let dir = tempdir::TempDir::new("sdcard").unwrap();
+    let path = dir.path().join("game.sav");
+
+    let mut card = SdCard::from_path(&path).unwrap().with_max_size(1024);
+    assert!(!card.is_dirty());
+    card.write_at(512, &[0xAA; 1024]).unwrap();
+    assert!(card.is_dirty());
+    card.flush().unwrap();
+    assert!(!card.is_dirty());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 1024);
+
+    let mut buffer = [0xFF; 512];
+    card.read_at(768, &mut buffer).unwrap();
+    assert_eq!(&buffer[..256], &[0xAA; 256]);
+    assert_eq!(&buffer[256..], &[0; 256]);
+}
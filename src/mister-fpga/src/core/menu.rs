@@ -33,6 +33,16 @@ impl MenuCore {
         })
     }
 
+    /// The MiSTer core running the menu, for settings shared with other cores
+    /// (e.g. the video mode).
+    pub fn inner(&self) -> &MisterFpgaCore {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut MisterFpgaCore {
+        &mut self.inner
+    }
+
     pub fn send_to_framebuffer(&mut self, image: &image::RgbImage) -> Result<(), String> {
         let menu_fb_size = self.inner.video_info()?.fb_resolution();
 
@@ -72,7 +82,7 @@ impl Core for MenuCore {
     }
 
     fn reset(&mut self) -> Result<(), Error> {
-        self.inner.soft_reset();
+        self.inner.soft_reset()?;
         Ok(())
     }
 
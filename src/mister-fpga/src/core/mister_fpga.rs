@@ -2,21 +2,27 @@ use std::any::Any;
 use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::Read;
+use std::ops::Range;
 use std::path::Path;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 use image::DynamicImage;
-use tracing::{debug, info, trace};
+use tracing::{debug, info, trace, warn};
 
 use cyclone_v::memory::{DevMemMemoryMapper, MemoryMapper};
 use one_fpga::core::{Bios, CoreSettings, Error, MountedFile, Rom, SaveState, SettingId};
 use one_fpga::inputs::gamepad::ButtonSet;
 use one_fpga::inputs::keyboard::ScancodeSet;
 use one_fpga::inputs::{Button, Scancode};
+use one_fpga::progress::{LoadStage, ProgressToken};
 use one_fpga::Core;
 
-use crate::config::{Config, HdmiLimitedConfig, VgaMode};
+use crate::config::color::ColorControls;
+use crate::config::edid::{get_active_edid, select_video_mode, CustomVideoMode, VideoModeDef};
+use crate::config::filter::ScalerFilters;
+use crate::config::vrr::RefreshSync;
+use crate::config::{Config, HdmiLimitedConfig, MisterConfig, VgaMode, VrrModeConfig};
 use crate::config_string;
 use crate::config_string::{ConfigMenu, FpgaRamMemoryAddress, LoadFileInfo};
 use crate::core::buttons::ButtonMap;
@@ -28,15 +34,23 @@ use crate::fpga::file_io::{
     FileExtension, FileIndex, FileTxData16Bits, FileTxData8Bits, FileTxDisabled, FileTxEnabled,
 };
 use crate::fpga::user_io::{
-    ButtonSwitches, GetSdStat, GetStatusBits, SdRead, SdStatOutput, SdWrite, SetSdConf, SetSdInfo,
-    SetSdStat, SetStatusBits, UserIoButtonSwitch, UserIoJoystick, UserIoKeyboardKeyDown,
+    ButtonSwitches, DisableGamma, EnableGamma, GetSdStat, GetStatusBits, IsGammaSupported, SdRead,
+    SdStatOutput, SdWrite, SetScalerFilter, SetSdConf, SetSdInfo, SetSdStat, SetShadowMask,
+    SetStatusBits, SetVsyncLine, UserIoButtonSwitch, UserIoJoystick, UserIoKeyboardKeyDown,
     UserIoKeyboardKeyUp, UserIoRtc,
 };
-use crate::fpga::{user_io, CoreInterfaceType, CoreType, MisterFpga};
+use crate::fpga::{
+    user_io, CoreInterfaceType, CoreType, MisterFpga, SdramRequirement, SpiError, DMA_CHUNK_SIZE,
+};
 use crate::keyboard::Ps2Scancode;
+use crate::savestate::file::SaveStateFile;
+use crate::savestate::rewind::{Rewind, RewindOptions, SaveStateTriggers};
 use crate::savestate::SaveStateManager;
 use crate::types::StatusBitMap;
 
+/// Files sent to memory from this size are copied by the DMA controller.
+const DMA_MIN_FILE_SIZE: usize = 1024 * 1024;
+
 #[derive(Debug)]
 pub enum MisterFpgaSendFileInfo {
     Memory {
@@ -85,6 +99,11 @@ pub struct MisterFpgaCore {
     cards: Box<[Option<SdCard>; 16]>,
 
     save_states: Option<SaveStateManager<DevMemMemoryMapper>>,
+    rewind: Option<Rewind>,
+
+    // The savestate slot to restore once the core is running.
+    pending_restore: Option<usize>,
+
     gamepads: [ButtonMap; 6],
 
     status: StatusBitMap,
@@ -95,6 +114,33 @@ pub struct MisterFpgaCore {
     // A cache for the video_info.
     video_info: Option<VideoInfo>,
 
+    // The MiSTer.ini, with the sections of this core merged in.
+    options: Config,
+
+    // The scaler filters currently set.
+    scaler_filters: ScalerFilters,
+
+    // The color controls and gamma curve currently set.
+    color_controls: ColorControls,
+
+    // The PAL and NTSC video modes to switch between, if configured.
+    video_modes: Option<VideoModeDef>,
+
+    // Whether the PAL video mode is currently selected.
+    video_is_pal: Option<bool>,
+
+    // The video mode last selected, if known.
+    video_mode: Option<CustomVideoMode>,
+
+    // The video mode to go back to, and when, if the current one is not confirmed.
+    video_mode_revert: Option<(CustomVideoMode, Instant)>,
+
+    // How the video output follows the refresh rate of the core.
+    refresh_sync: RefreshSync,
+
+    // The CRC32 of the last ROM sent to the core, used to identify the game.
+    game_crc: Option<u32>,
+
     // Whether we should quit.
     should_quit: bool,
 }
@@ -139,11 +185,22 @@ impl MisterFpgaCore {
             config,
             cards: Box::new([NONE; 16]),
             save_states,
+            rewind: None,
+            pending_restore: None,
             gamepads: [map; 6],
             status: Default::default(),
             status_counter: 0,
             framebuffer: crate::framebuffer::FpgaFramebuffer::default(),
             video_info: None,
+            options: Config::default(),
+            scaler_filters: ScalerFilters::default(),
+            color_controls: ColorControls::default(),
+            video_modes: None,
+            video_is_pal: None,
+            video_mode: None,
+            video_mode_revert: None,
+            refresh_sync: RefreshSync::default(),
+            game_crc: None,
             should_quit: false,
         })
     }
@@ -153,12 +210,12 @@ impl MisterFpgaCore {
     }
 
     /// Perform a soft reset.
-    pub fn soft_reset(&mut self) {
-        self.read_status_bits();
+    pub fn soft_reset(&mut self) -> Result<(), SpiError> {
+        self.read_status_bits()?;
         self.status.set(0, true);
-        self.send_status_bits(self.status);
+        self.send_status_bits(self.status)?;
         self.status.set(0, false);
-        self.send_status_bits(self.status);
+        self.send_status_bits(self.status)
     }
 
     /// Send the Real Time Clock to the core.
@@ -185,6 +242,40 @@ impl MisterFpgaCore {
         path: &Path,
         file_info: Option<LoadFileInfo>,
     ) -> Result<(), String> {
+        self.load_file_with_progress(path, file_info, &mut ProgressToken::default())
+    }
+
+    /// Send a file to the core like [`Self::load_file`], reporting the number
+    /// of bytes sent. If the token is cancelled, the transfer is stopped and
+    /// the core only received part of the file.
+    pub fn load_file_with_progress(
+        &mut self,
+        path: &Path,
+        file_info: Option<LoadFileInfo>,
+        progress: &mut ProgressToken,
+    ) -> Result<(), String> {
+        self.load_file_(path, file_info, LoadStage::File, progress)
+            .map(|_| ())
+    }
+
+    /// Send a ROM file to the core, reporting the number of bytes sent.
+    pub fn send_rom_with_progress(
+        &mut self,
+        path: &Path,
+        progress: &mut ProgressToken,
+    ) -> Result<(), String> {
+        self.game_crc = Some(self.load_file_(path, None, LoadStage::Rom, progress)?);
+        Ok(())
+    }
+
+    /// Send a file to the core, returning its CRC32.
+    fn load_file_(
+        &mut self,
+        path: &Path,
+        file_info: Option<LoadFileInfo>,
+        stage: LoadStage,
+        progress: &mut ProgressToken,
+    ) -> Result<u32, String> {
         info!(?path, ?file_info, "Loading file");
         let info = file_info.map_or_else(
             || MisterFpgaSendFileInfo::from_path(path, self),
@@ -205,37 +296,48 @@ impl MisterFpgaCore {
         let file = File::open(path).map_err(|e| e.to_string())?;
         let size = file.metadata().map_err(|e| e.to_string())?.len() as u32;
 
+        progress.start(stage, size as usize)?;
         self.start_send_file(info.index(), &ext, size)?;
-        match info {
+        let crc = match info {
             MisterFpgaSendFileInfo::Memory { index, address } => {
                 trace!(?index, ?address, ?ext, ?size, "File info (memory)");
-                self.send_file_to_sdram_(size, address, file)?;
+                self.send_file_to_sdram_(size, address, file, progress)
             }
             MisterFpgaSendFileInfo::Buffered { index } => {
                 trace!(?index, ?ext, ?size, "File info (buffered)");
-                self.send_file_to_buffer_(size, file)?;
+                self.send_file_to_buffer_(size, file, progress)
             }
-        }
-        self.read_status_bits();
+        };
+        let crc = match crc {
+            Ok(crc) => crc,
+            Err(error) => {
+                // Do not leave the core waiting for the rest of the file.
+                self.end_send_file()?;
+                return Err(error);
+            }
+        };
+        self.read_status_bits()?;
 
         self.status.set(0, false);
-        self.send_status_bits(self.status);
+        self.send_status_bits(self.status)?;
 
         // self.end_send_file()?;
         debug!("Done in {}ms", now.elapsed().as_millis());
 
-        Ok(())
+        Ok(crc)
     }
 
     fn start_send_file(&mut self, index: u8, ext: &str, size: u32) -> Result<(), String> {
         self.fpga.spi_mut().execute(FileIndex::from(index))?;
         self.fpga.spi_mut().execute(FileExtension(ext))?;
-        self.fpga.spi_mut().execute(FileTxEnabled(Some(size)))
+        self.fpga.spi_mut().execute(FileTxEnabled(Some(size)))?;
+        Ok(())
     }
 
     pub fn end_send_file(&mut self) -> Result<(), String> {
         // Disable download.
-        self.fpga.spi_mut().execute(FileTxDisabled)
+        self.fpga.spi_mut().execute(FileTxDisabled)?;
+        Ok(())
     }
 
     /// Return the core parsed config structure.
@@ -243,6 +345,15 @@ impl MisterFpgaCore {
         &self.config
     }
 
+    /// Whether the core needs an SDRAM module, from its core type or config string.
+    pub fn sdram_requirement(&self) -> SdramRequirement {
+        if self.core_type.is_dual_sdram() {
+            SdramRequirement::Dual
+        } else {
+            self.config.sdram_requirement()
+        }
+    }
+
     /// Return the video info of the core.
     pub fn video_info(&mut self) -> Result<VideoInfo, String> {
         if let Some(video_info) = self.video_info {
@@ -258,13 +369,13 @@ impl MisterFpgaCore {
         self.config().status_bit_map_mask()
     }
 
-    pub fn status_pulse(&mut self, bit: usize) {
+    pub fn status_pulse(&mut self, bit: usize) -> Result<(), SpiError> {
         let mut bits = *self.status_bits();
         bits.set(bit, true);
-        self.send_status_bits(bits);
+        self.send_status_bits(bits)?;
 
         bits.set(bit, false);
-        self.send_status_bits(bits);
+        self.send_status_bits(bits)
     }
 
     /// Return the core status bits. This is an internal cache of the
@@ -275,19 +386,19 @@ impl MisterFpgaCore {
     }
 
     /// Update the internal cache and return it.
-    pub fn read_status_bits(&mut self) -> &StatusBitMap {
+    pub fn read_status_bits(&mut self) -> Result<&StatusBitMap, SpiError> {
         self.fpga
             .spi_mut()
-            .execute(GetStatusBits(&mut self.status, &mut self.status_counter))
-            .unwrap();
-        &self.status
+            .execute(GetStatusBits(&mut self.status, &mut self.status_counter))?;
+        Ok(&self.status)
     }
 
     /// Send status bits to the core.
-    pub fn send_status_bits(&mut self, bits: StatusBitMap) {
+    pub fn send_status_bits(&mut self, bits: StatusBitMap) -> Result<(), SpiError> {
         debug!(?bits, "Setting status bits");
-        self.fpga.spi_mut().execute(SetStatusBits(&bits)).unwrap();
+        self.fpga.spi_mut().execute(SetStatusBits(&bits))?;
         self.status = bits;
+        Ok(())
     }
 
     pub fn menu_options(&self) -> &[ConfigMenu] {
@@ -295,27 +406,33 @@ impl MisterFpgaCore {
     }
 
     /// Notify the core of a keyboard key down event.
-    pub fn key_down(&mut self, keycode: impl Into<Ps2Scancode> + Debug + Copy) {
+    pub fn key_down(
+        &mut self,
+        keycode: impl Into<Ps2Scancode> + Debug + Copy,
+    ) -> Result<(), SpiError> {
         let scancode = keycode.into();
         debug!(?keycode, ?scancode, "Keydown");
         if scancode != Ps2Scancode::None {
             self.fpga
                 .spi_mut()
-                .execute(UserIoKeyboardKeyDown::from(scancode))
-                .unwrap();
+                .execute(UserIoKeyboardKeyDown::from(scancode))?;
         }
+        Ok(())
     }
 
     /// Notify the core of a keyboard key up event.
-    pub fn key_up(&mut self, keycode: impl Into<Ps2Scancode> + Debug + Copy) {
+    pub fn key_up(
+        &mut self,
+        keycode: impl Into<Ps2Scancode> + Debug + Copy,
+    ) -> Result<(), SpiError> {
         let scancode = keycode.into();
         debug!(?keycode, ?scancode, "Keyup");
         if scancode != Ps2Scancode::None {
             self.fpga
                 .spi_mut()
-                .execute(UserIoKeyboardKeyUp::from(scancode))
-                .unwrap();
+                .execute(UserIoKeyboardKeyUp::from(scancode))?;
         }
+        Ok(())
     }
 
     pub fn gamepad(&self, idx: u8) -> Option<&ButtonMap> {
@@ -326,38 +443,38 @@ impl MisterFpgaCore {
         self.gamepads.get_mut(idx as usize)
     }
 
-    pub fn send_gamepad(&mut self, idx: u8, map: ButtonMap) {
+    pub fn send_gamepad(&mut self, idx: u8, map: ButtonMap) -> Result<(), SpiError> {
         if idx > 5 {
-            return;
+            return Ok(());
         }
 
         self.fpga
             .spi_mut()
-            .execute(UserIoJoystick::from_joystick_index(idx, &map))
-            .unwrap();
+            .execute(UserIoJoystick::from_joystick_index(idx, &map))?;
         self.gamepads[idx as usize] = map;
+        Ok(())
     }
 
     /// Notify the core of a gamepad button down event.
-    pub fn gamepad_button_down(&mut self, joystick_idx: u8, button: u8) {
+    pub fn gamepad_button_down(&mut self, joystick_idx: u8, button: u8) -> Result<(), SpiError> {
         let g = &mut self.gamepads[joystick_idx as usize];
         g.down(button);
 
         self.fpga
             .spi_mut()
-            .execute(UserIoJoystick::from_joystick_index(joystick_idx, g))
-            .unwrap();
+            .execute(UserIoJoystick::from_joystick_index(joystick_idx, g))?;
+        Ok(())
     }
 
     /// Notify the core of a gamepad button up event.
-    pub fn gamepad_button_up(&mut self, joystick_idx: u8, button: u8) {
+    pub fn gamepad_button_up(&mut self, joystick_idx: u8, button: u8) -> Result<(), SpiError> {
         let g = &mut self.gamepads[joystick_idx as usize];
         g.up(button);
 
         self.fpga
             .spi_mut()
-            .execute(UserIoJoystick::from_joystick_index(joystick_idx, g))
-            .unwrap();
+            .execute(UserIoJoystick::from_joystick_index(joystick_idx, g))?;
+        Ok(())
     }
 
     /// Access the internal save state manager, in readonly.
@@ -370,6 +487,319 @@ impl MisterFpgaCore {
         self.save_states.as_mut()
     }
 
+    /// The number of savestate slots available to the user. The last slot is
+    /// reserved while rewinding is enabled.
+    pub fn user_save_state_slots(&self) -> usize {
+        let nb_slots = self.save_states().map_or(0, |m| m.nb_slots());
+        if self.rewind.is_some() {
+            nb_slots - 1
+        } else {
+            nb_slots
+        }
+    }
+
+    fn check_user_save_state_slot_(&self, slot: usize) -> Result<(), Error> {
+        if self.rewind_slot() == Some(slot) {
+            return Err(Error::Message(format!(
+                "Savestate slot {slot} is reserved for rewinding."
+            )));
+        }
+        Ok(())
+    }
+
+    /// The number of choices selectable for an option. The savestate slot
+    /// option cannot select the slot reserved for rewinding.
+    fn option_choices_len_(&self, bits: &Range<u8>, choices: &[String]) -> usize {
+        let is_slot_option = self
+            .config
+            .savestate_slot_option()
+            .is_some_and(|(slot_bits, _)| slot_bits == bits);
+        if is_slot_option && self.rewind.is_some() {
+            choices.len().min(self.user_save_state_slots()).max(1)
+        } else {
+            choices.len()
+        }
+    }
+
+    /// Wrap a savestate (as saved from a slot) into a file with the core and game
+    /// information, ready to be written to disk.
+    pub fn create_save_state_file(
+        &self,
+        data: Vec<u8>,
+        screenshot: Option<&DynamicImage>,
+    ) -> Result<SaveStateFile, Error> {
+        let file = SaveStateFile::new(
+            self.config.name.as_str(),
+            self.config.version(),
+            self.game_crc,
+            data,
+        );
+
+        match screenshot {
+            Some(screenshot) => Ok(file.with_thumbnail(screenshot)?),
+            None => Ok(file),
+        }
+    }
+
+    /// Load a savestate file into a slot. This validates that the savestate
+    /// was made with this core before touching the core memory.
+    pub fn load_save_state_file(
+        &mut self,
+        slot: usize,
+        mut reader: impl Read,
+    ) -> Result<(), Error> {
+        self.check_user_save_state_slot_(slot)?;
+        let file = SaveStateFile::read(&mut reader)?;
+        let manager = self
+            .save_states
+            .as_mut()
+            .ok_or_else(|| Error::Message("Core does not support savestates.".to_string()))?;
+
+        file.validate(
+            self.config.name.as_str(),
+            self.config.version(),
+            manager.slot_size(),
+        )?;
+        if let Some(crc) = file.header().game_crc.filter(|_| self.game_crc.is_some()) {
+            if Some(crc) != self.game_crc {
+                warn!(crc, game_crc = ?self.game_crc, "Savestate was made with a different game.");
+            }
+        }
+
+        let ss = manager
+            .slots_mut()
+            .get_mut(slot)
+            .ok_or_else(|| Error::Message(format!("Invalid savestate slot {slot}.")))?;
+        SaveState::load(ss, &mut std::io::Cursor::new(file.data()))
+    }
+
+    /// Load a savestate into a slot, either from a savestate file or from raw data
+    /// (as saved from the core memory).
+    pub fn load_save_state(&mut self, slot: usize, data: &[u8]) -> Result<(), Error> {
+        self.check_user_save_state_slot_(slot)?;
+        if SaveStateFile::is_save_state_file(data) {
+            return self.load_save_state_file(slot, data);
+        }
+
+        let ss = self
+            .save_states
+            .as_mut()
+            .ok_or_else(|| Error::Message("Core does not support savestates.".to_string()))?
+            .slots_mut()
+            .get_mut(slot)
+            .ok_or_else(|| Error::Message(format!("Invalid savestate slot {slot}.")))?;
+        SaveState::load(ss, &mut std::io::Cursor::new(data))
+    }
+
+    /// Ask the core to restore the savestate in a slot, by selecting the slot and
+    /// triggering the core's restore state menu item.
+    pub fn restore_save_state(&mut self, slot: usize) -> Result<(), Error> {
+        self.check_user_save_state_slot_(slot)?;
+        let triggers = SaveStateTriggers::from_config(&self.config).ok_or_else(|| {
+            Error::Message("Core does not have a restore state option.".to_string())
+        })?;
+
+        if let Some(range) = triggers.slot {
+            let mut bits = *self.status_bits();
+            bits.set_range(range, slot as u32);
+            self.send_status_bits(bits)?;
+        } else if slot != 0 {
+            return Err(Error::Message(format!("Invalid savestate slot {slot}.")));
+        }
+
+        debug!(slot, "Restoring savestate");
+        self.status_pulse(triggers.restore as usize)?;
+        Ok(())
+    }
+
+    /// Restore the savestate in a slot once the core is running. Cores ignore
+    /// the restore trigger while they are still starting, e.g. right after their
+    /// ROM was loaded. See [Self::poll_pending_restore].
+    pub fn restore_save_state_when_running(&mut self, slot: usize) -> Result<(), Error> {
+        self.check_user_save_state_slot_(slot)?;
+        if SaveStateTriggers::from_config(&self.config).is_none() {
+            return Err(Error::Message(
+                "Core does not have a restore state option.".to_string(),
+            ));
+        }
+
+        self.pending_restore = Some(slot);
+        Ok(())
+    }
+
+    /// Whether a savestate is waiting for the core to run to be restored.
+    pub fn has_pending_restore(&self) -> bool {
+        self.pending_restore.is_some()
+    }
+
+    /// Restore the savestate set by [Self::restore_save_state_when_running] if the
+    /// core is running, which is when it outputs video at a known refresh rate.
+    /// Returns true if the savestate was restored.
+    pub fn poll_pending_restore(&mut self) -> Result<bool, Error> {
+        let Some(slot) = self.pending_restore else {
+            return Ok(false);
+        };
+
+        let info = VideoInfo::create(self.spi_mut()).map_err(Error::Message)?;
+        if info.refresh_rate().is_none() {
+            return Ok(false);
+        }
+
+        self.pending_restore = None;
+        self.restore_save_state(slot)?;
+        Ok(true)
+    }
+
+    /// Enable rewinding. This reserves the last savestate slot of the core, which
+    /// will be saved periodically by [Self::poll_rewind]. The slot is hidden from
+    /// the user (see [Self::user_save_state_slots]) until rewinding is disabled,
+    /// so it cannot be enabled on cores with a single slot, or while the user has
+    /// the last slot selected.
+    pub fn enable_rewind(&mut self, options: RewindOptions) -> Result<(), String> {
+        let nb_slots = self.save_states().map_or(0, |m| m.nb_slots());
+        if nb_slots == 0 {
+            return Err("Core does not support savestates.".to_string());
+        }
+        if nb_slots == 1 {
+            return Err("Core has a single savestate slot, which cannot be reserved.".to_string());
+        }
+        let triggers = SaveStateTriggers::from_config(&self.config)
+            .ok_or("Core does not have savestate triggers.")?;
+        if let Some(range) = triggers.slot.clone() {
+            if self.status_bits().get_range(range) as usize == nb_slots - 1 {
+                return Err("The last savestate slot is selected by the user.".to_string());
+            }
+        }
+
+        debug!(?options, ?triggers, slot = nb_slots - 1, "Enabling rewind");
+        self.rewind = Some(Rewind::new(options, triggers, nb_slots - 1));
+        Ok(())
+    }
+
+    pub fn disable_rewind(&mut self) {
+        if let Some(mut rewind) = self.rewind.take() {
+            if let Err(error) = self.restore_user_savestate_slot_(&mut rewind) {
+                warn!(?error, "Could not restore the savestate slot");
+            }
+        }
+    }
+
+    pub fn is_rewind_enabled(&self) -> bool {
+        self.rewind.is_some()
+    }
+
+    /// The savestate slot reserved for rewinding, if enabled.
+    pub fn rewind_slot(&self) -> Option<usize> {
+        self.rewind.as_ref().map(|r| r.slot)
+    }
+
+    /// The number of states that can be rewound.
+    pub fn rewind_len(&self) -> usize {
+        self.rewind.as_ref().map_or(0, |r| r.buffer.len())
+    }
+
+    fn select_rewind_savestate_slot_(&mut self, rewind: &mut Rewind) -> Result<(), SpiError> {
+        if let Some(range) = rewind.triggers.slot.clone() {
+            let mut bits = *self.status_bits();
+            if rewind.user_slot.is_none() {
+                rewind.user_slot = Some(bits.get_range(range.clone()));
+            }
+            bits.set_range(range, rewind.slot as u32);
+            self.send_status_bits(bits)?;
+        }
+        Ok(())
+    }
+
+    fn restore_user_savestate_slot_(&mut self, rewind: &mut Rewind) -> Result<(), SpiError> {
+        if let (Some(range), Some(slot)) = (rewind.triggers.slot.clone(), rewind.user_slot.take()) {
+            let mut bits = *self.status_bits();
+            bits.set_range(range, slot);
+            self.send_status_bits(bits)?;
+        }
+        Ok(())
+    }
+
+    /// Capture a new rewind state if it's time to. This should be called often (at
+    /// least every frame) and before checking savestates for changes, so the rewind
+    /// slot is never seen as dirty.
+    pub fn poll_rewind(&mut self) -> Result<(), String> {
+        let mut rewind = match self.rewind.take() {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+
+        let result = self.poll_rewind_(&mut rewind);
+        self.rewind = Some(rewind);
+        result
+    }
+
+    fn poll_rewind_(&mut self, rewind: &mut Rewind) -> Result<(), String> {
+        if rewind.pending {
+            let manager = self
+                .save_states
+                .as_mut()
+                .ok_or("Core does not support savestates.")?;
+            let ss = &mut manager.slots_mut()[rewind.slot];
+
+            if !ss.is_dirty() {
+                // Do not wait forever if the core ignored the trigger.
+                if rewind.last_capture.elapsed() > rewind.options.interval * 4 {
+                    warn!("Core did not save the rewind state in time.");
+                    rewind.pending = false;
+                }
+                return Ok(());
+            }
+
+            let mut buffer = Vec::new();
+            SaveState::save(ss, &mut buffer).map_err(|e| e.to_string())?;
+            rewind.buffer.push(buffer);
+            rewind.pending = false;
+            self.restore_user_savestate_slot_(rewind)?;
+        } else if rewind.last_capture.elapsed() >= rewind.options.interval {
+            self.select_rewind_savestate_slot_(rewind)?;
+            self.status_pulse(rewind.triggers.save as usize)?;
+            rewind.last_capture = Instant::now();
+            rewind.pending = true;
+        }
+
+        Ok(())
+    }
+
+    /// Go back one state in the rewind buffer and restore it in the core.
+    /// Returns false if there is no state to go back to.
+    pub fn rewind_step(&mut self) -> Result<bool, String> {
+        let mut rewind = match self.rewind.take() {
+            Some(r) => r,
+            None => return Ok(false),
+        };
+
+        let result = self.rewind_step_(&mut rewind);
+        self.rewind = Some(rewind);
+        result
+    }
+
+    fn rewind_step_(&mut self, rewind: &mut Rewind) -> Result<bool, String> {
+        let state = match rewind.buffer.step_back()? {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+
+        let manager = self
+            .save_states
+            .as_mut()
+            .ok_or("Core does not support savestates.")?;
+        let ss = &mut manager.slots_mut()[rewind.slot];
+        SaveState::load(ss, &mut std::io::Cursor::new(state)).map_err(|e| e.to_string())?;
+
+        self.select_rewind_savestate_slot_(rewind)?;
+        self.status_pulse(rewind.triggers.restore as usize)?;
+
+        // Do not capture the state we just restored right away.
+        rewind.last_capture = Instant::now();
+        rewind.pending = false;
+        Ok(true)
+    }
+
     /// Take a screenshot and return the image in memory.
     pub fn take_screenshot(&self) -> Result<DynamicImage, String> {
         self.framebuffer.take_screenshot()
@@ -379,6 +809,180 @@ impl MisterFpgaCore {
         &self.framebuffer
     }
 
+    /// Select the video mode for the refresh rate of the core when it changes.
+    /// This switches between the PAL and NTSC video modes (`video_mode_pal` and
+    /// `video_mode_ntsc` in the MiSTer.ini), and applies VRR or `vsync_adjust`.
+    /// Returns true if the video mode was changed.
+    pub fn poll_video_mode(&mut self) -> Result<bool, String> {
+        if matches!(self.video_mode_revert, Some((_, deadline)) if Instant::now() >= deadline) {
+            warn!("Video mode was not confirmed in time, reverting");
+            return self.revert_video_mode();
+        }
+
+        let Some(modes) = self.video_modes else {
+            return Ok(false);
+        };
+
+        let info = VideoInfo::create(self.spi_mut())?;
+        let changed = self
+            .video_info
+            .map_or(true, |previous| info.has_changed(&previous));
+        self.video_info = Some(info);
+        if !changed {
+            return Ok(false);
+        }
+
+        let Some(refresh) = info.refresh_rate() else {
+            return Ok(false);
+        };
+        let Some((mode, is_pal)) = modes.select(refresh) else {
+            return Ok(false);
+        };
+        // Without VRR or vsync_adjust, the mode only changes between PAL and NTSC.
+        let sync = self.refresh_sync;
+        if !sync.is_enabled() && self.video_is_pal == Some(is_pal) {
+            return Ok(false);
+        }
+
+        let mode = sync.adjust(mode, refresh);
+        info!(
+            refresh,
+            is_pal,
+            f_pix = mode.f_pix,
+            vrr = mode.vrr,
+            "Switching video mode"
+        );
+        // Keep the custom aspect ratios the core was started with.
+        let options = self.options.inner().clone();
+        self.select_video_mode_(mode, &options)?;
+        video::set_vrr(&sync, &mode, refresh)?;
+        self.spi_mut()
+            .execute(SetVsyncLine(if sync.is_low_latency() { 1 } else { 0 }))?;
+        self.video_is_pal = Some(is_pal);
+        Ok(true)
+    }
+
+    /// Select a video mode. If `revert_after` is set, the previous mode is
+    /// selected again after that delay unless [`Self::confirm_video_mode`] is
+    /// called, so a mode the display cannot show does not leave it blank. This
+    /// fails if the current mode is unknown, as there would be nothing to revert to.
+    pub fn set_video_mode(
+        &mut self,
+        mode: CustomVideoMode,
+        revert_after: Option<Duration>,
+    ) -> Result<(), String> {
+        let options = self.options.inner().clone();
+        if options.direct_video() {
+            return Err("Video modes cannot be changed with direct video".to_string());
+        }
+
+        // Keep the mode to revert to if a previous change was not confirmed.
+        let previous = match self.video_mode_revert {
+            Some((previous, _)) => Some(previous),
+            None => self
+                .video_mode
+                .or_else(|| select_video_mode(&options).ok()?.vmode_def),
+        };
+        if revert_after.is_some() && previous.is_none() {
+            return Err("The current video mode is unknown and could not be reverted".to_string());
+        }
+
+        info!(f_pix = mode.f_pix, ?revert_after, "Selecting video mode");
+        self.select_video_mode_(mode, &options)?;
+        self.video_mode_revert = previous
+            .zip(revert_after)
+            .map(|(previous, delay)| (previous, Instant::now() + delay));
+        Ok(())
+    }
+
+    /// Keep the current video mode, cancelling its revert.
+    pub fn confirm_video_mode(&mut self) {
+        self.video_mode_revert = None;
+    }
+
+    /// Go back to the previous video mode if the current one was not confirmed.
+    /// Returns true if the video mode was changed.
+    pub fn revert_video_mode(&mut self) -> Result<bool, String> {
+        let Some((previous, _)) = self.video_mode_revert.take() else {
+            return Ok(false);
+        };
+
+        info!(f_pix = previous.f_pix, "Reverting video mode");
+        let options = self.options.inner().clone();
+        self.select_video_mode_(previous, &options)?;
+        Ok(true)
+    }
+
+    fn select_video_mode_(
+        &mut self,
+        mode: CustomVideoMode,
+        options: &MisterConfig,
+    ) -> Result<(), String> {
+        let aspect_ratios = options.custom_aspect_ratio();
+        let is_menu = self.is_menu;
+        video::select_mode(
+            mode,
+            false,
+            aspect_ratios.first().cloned(),
+            aspect_ratios.get(1).cloned(),
+            self.spi_mut(),
+            is_menu,
+        )?;
+        self.video_mode = Some(mode);
+        Ok(())
+    }
+
+    /// The MiSTer.ini used by this core, with the sections of the core and its
+    /// video resolution merged in.
+    pub fn options(&self) -> &Config {
+        &self.options
+    }
+
+    pub fn scaler_filters(&self) -> &ScalerFilters {
+        &self.scaler_filters
+    }
+
+    /// Load the scaler filters and shadow mask, and send them to the core.
+    pub fn set_scaler_filters(&mut self, filters: ScalerFilters) -> Result<(), String> {
+        let (filter, shadow_mask) = filters.load()?;
+        debug!(?filters, "Setting scaler filters");
+
+        self.spi_mut().execute(SetScalerFilter(filter.as_ref()))?;
+        self.spi_mut().execute(SetShadowMask(
+            shadow_mask.as_ref(),
+            filters.shadow_mask_mode,
+        ))?;
+        self.scaler_filters = filters;
+        Ok(())
+    }
+
+    pub fn color_controls(&self) -> &ColorControls {
+        &self.color_controls
+    }
+
+    /// Apply color controls to the HDMI output and send the gamma curve to the
+    /// core, without reloading it.
+    pub fn set_color_controls(&mut self, controls: ColorControls) -> Result<(), String> {
+        let gamma = controls.load_gamma()?;
+        debug!(?controls, "Setting color controls");
+
+        let mut options = self.options.inner().clone();
+        controls.apply_to(&mut options);
+        video::set_color_controls(&options)?;
+
+        let mut has_gamma = false;
+        self.spi_mut().execute(IsGammaSupported(&mut has_gamma))?;
+        match gamma {
+            Some(curve) if has_gamma => self.spi_mut().execute(EnableGamma(curve.entries()))?,
+            Some(_) => warn!("Core does not support gamma curves"),
+            None if has_gamma => self.spi_mut().execute(DisableGamma)?,
+            None => {}
+        }
+
+        self.color_controls = controls;
+        Ok(())
+    }
+
     /// Mount an SD card to the core.
     pub fn mount(&mut self, file: SdCard, index: u8) -> Result<(), String> {
         self.fpga.spi_mut().execute(
@@ -430,16 +1034,12 @@ impl MisterFpgaCore {
                 ))?;
 
                 let addr = stat.lba * stat.block_size as u64;
-                let io = card.as_io();
-                io.seek(SeekFrom::Start(addr)).map_err(|e| e.to_string())?;
-                io.write_all(&buffer).map_err(|e| e.to_string())?;
+                card.write_at(addr, &buffer).map_err(|e| e.to_string())?;
             } else if stat.op.is_read() {
                 result = true;
                 let mut buffer = vec![0; stat.size];
                 let addr = stat.lba * stat.block_size as u64;
-                let io = card.as_io();
-                io.seek(SeekFrom::Start(addr)).map_err(|e| e.to_string())?;
-                io.read_exact(&mut buffer).map_err(|e| e.to_string())?;
+                card.read_at(addr, &mut buffer).map_err(|e| e.to_string())?;
 
                 // Blocks are now in memory, send them to the core.
                 self.fpga.spi_mut().execute(SdRead::new(
@@ -449,39 +1049,78 @@ impl MisterFpgaCore {
                 ))?;
             }
         }
+
+        // Once the core is done writing, persist its writes.
+        if !result {
+            self.flush_mounts()?;
+        }
         Ok(result)
     }
 
+    /// Persist all writes to mounted SD cards to the filesystem.
+    pub fn flush_mounts(&mut self) -> Result<(), String> {
+        for card in self.cards.iter_mut().flatten() {
+            if card.is_dirty() {
+                debug!(path = ?card.path(), "Flushing SD card");
+                card.flush().map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
     fn send_file_to_sdram_(
         &mut self,
         size: u32,
         address: FpgaRamMemoryAddress,
         mut reader: impl Read,
-    ) -> Result<(), String> {
+        progress: &mut ProgressToken,
+    ) -> Result<u32, String> {
         // Verify invariants.
         if size >= 0x2000_0000 {
             return Err("File too large.".to_string());
         }
+        let size = size as usize;
+
+        // Large files are copied by the DMA controller, so the CPU is free to
+        // read the next chunk of the file in the meantime.
+        if size >= DMA_MIN_FILE_SIZE && address.as_u32() % 8 == 0 && self.fpga.dma_ready() {
+            debug!("Sending file with DMA");
+            let crc = self
+                .fpga
+                .dma_write(address.as_u32(), size, reader, progress)?;
+            debug!("CRC: {:08X}", crc);
+            return Ok(crc);
+        }
+
         let mut crc = crc32fast::Hasher::new();
-        let mut mem = DevMemMemoryMapper::create(address.as_usize(), size as usize)?;
+        let mut mem = DevMemMemoryMapper::create(address.as_usize(), size)?;
 
-        let mut bytes2send = size;
-        while bytes2send > 0 {
+        let mut sent = 0;
+        while sent < size {
+            let end = size.min(sent + DMA_CHUNK_SIZE);
             let sz = reader
-                .read(mem.as_mut_range(..))
+                .read(mem.as_mut_range(sent..end))
                 .map_err(|e| e.to_string())?;
+            if sz == 0 {
+                return Err(format!("File truncated at {sent} bytes."));
+            }
 
-            // crc.update(mem.as_range(start..start + len));
-            bytes2send -= sz as u32;
+            crc.update(mem.as_range(sent..sent + sz));
+            sent += sz;
+            progress.update(sent)?;
         }
-        crc.update(mem.as_range(..));
 
         let crc = crc.finalize();
         debug!("CRC: {:08X}", crc);
-        Ok(())
+        Ok(crc)
     }
 
-    fn send_file_to_buffer_(&mut self, size: u32, mut reader: impl Read) -> Result<(), String> {
+    fn send_file_to_buffer_(
+        &mut self,
+        size: u32,
+        mut reader: impl Read,
+        progress: &mut ProgressToken,
+    ) -> Result<u32, String> {
         // Verify invariants.
         if size >= 0x2000_0000 {
             return Err("File too large.".to_string());
@@ -491,6 +1130,7 @@ impl MisterFpgaCore {
         let now = std::time::Instant::now();
 
         let mut buffer = [0u8; 4096];
+        let mut sent = 0;
         loop {
             match reader.read(&mut buffer).map_err(|e| e.to_string()) {
                 Ok(0) => break,
@@ -516,6 +1156,9 @@ impl MisterFpgaCore {
                                 .execute(FileTxData16Bits(&buf16[..size / 2]))?;
                         }
                     }
+
+                    sent += size;
+                    progress.update(sent)?;
                 }
                 Err(e) => {
                     return Err(e);
@@ -528,7 +1171,7 @@ impl MisterFpgaCore {
         let crc = crc.finalize();
         debug!("CRC: {:08X}", crc);
 
-        Ok(())
+        Ok(crc)
     }
 
     pub fn trigger_menu(&mut self, menu: &ConfigMenu) -> Result<bool, String> {
@@ -549,15 +1192,15 @@ impl MisterFpgaCore {
             }
             ConfigMenu::Option { bits, choices, .. } => {
                 let (from, to) = (bits.start, bits.end);
+                let max = self.option_choices_len_(bits, choices);
                 let mut bits = *self.status_bits();
-                let max = choices.len();
                 let value = bits.get_range(from..to) as usize;
                 bits.set_range(from..to, ((value + 1) % max) as u32);
-                self.send_status_bits(bits);
+                self.send_status_bits(bits)?;
                 Ok(true)
             }
             ConfigMenu::Trigger { index, .. } => {
-                self.status_pulse(*index as usize);
+                self.status_pulse(*index as usize)?;
                 Ok(true)
             }
             ConfigMenu::PageItem(_, sub) => self.trigger_menu(sub),
@@ -570,21 +1213,22 @@ impl MisterFpgaCore {
 
 impl Core for MisterFpgaCore {
     fn init(&mut self) -> Result<(), Error> {
-        self.soft_reset();
-        self.fpga
-            .spi_mut()
-            .execute(user_io::SetMemorySize::from_fpga().unwrap())
-            .map_err(Error::Message)?;
+        self.soft_reset()?;
+        let memory_size = user_io::SetMemorySize::from_fpga().unwrap();
+        if let Some(warning) = self.sdram_requirement().check(memory_size.sdram_size()) {
+            warn!("{warning}");
+        }
+        self.fpga.spi_mut().execute(memory_size)?;
 
         // Initialize the framebuffer.
         if !self.is_menu {
-            self.fpga
-                .spi_mut()
-                .execute(user_io::SetFramebufferToCore)
-                .map_err(Error::Message)?;
+            self.fpga.spi_mut().execute(user_io::SetFramebufferToCore)?;
         }
 
-        let options = Config::base().into_inner();
+        // The menu only uses the MiSTer section, and the video section.
+        let corename = (!self.is_menu).then_some(self.config.name.as_str());
+        self.options = Config::for_core(corename);
+        let options = self.options.inner().clone();
 
         let mut switches = UserIoButtonSwitch::new();
         if options.vga_scaler == Some(true) {
@@ -617,10 +1261,30 @@ impl Core for MisterFpgaCore {
             switches |= ButtonSwitches::DirectVideo;
         }
 
-        self.spi_mut().execute(switches).unwrap();
+        self.spi_mut().execute(switches)?;
 
         video::init(&options);
         video::init_mode(&options, self, self.is_menu);
+        self.color_controls = ColorControls::from_config(&options);
+        if let Err(error) = self.set_scaler_filters(ScalerFilters::from_config(&options)) {
+            warn!(?error, "Could not set the default scaler filters");
+        }
+        if !self.is_menu {
+            // Only read the EDID if needed to detect VRR support, as it is slow.
+            let edid = if options.vrr_mode() == VrrModeConfig::Auto {
+                get_active_edid()
+                    .inspect_err(|error| warn!(?error, "Could not read EDID for VRR"))
+                    .ok()
+            } else {
+                None
+            };
+            self.refresh_sync = RefreshSync::from_config(&options, edid.as_ref().map(|e| &e[..]));
+            debug!(sync = ?self.refresh_sync, "Refresh rate sync");
+
+            if options.has_pal_ntsc_video_modes() || self.refresh_sync.is_enabled() {
+                self.video_modes = select_video_mode(&options).ok();
+            }
+        }
         self.framebuffer.update_type_from_core();
 
         Ok(())
@@ -631,7 +1295,7 @@ impl Core for MisterFpgaCore {
     }
 
     fn reset(&mut self) -> Result<(), Error> {
-        self.soft_reset();
+        self.soft_reset()?;
         Ok(())
     }
 
@@ -642,10 +1306,7 @@ impl Core for MisterFpgaCore {
     }
 
     fn set_rtc(&mut self, time: SystemTime) -> Result<(), Error> {
-        self.fpga
-            .spi_mut()
-            .execute(UserIoRtc::from(time))
-            .map_err(Error::Message)?;
+        self.fpga.spi_mut().execute(UserIoRtc::from(time))?;
         Ok(())
     }
 
@@ -654,10 +1315,11 @@ impl Core for MisterFpgaCore {
     }
 
     fn save_state_mut(&mut self, slot: usize) -> Result<Option<&mut dyn SaveState>, Error> {
+        let user_slots = self.user_save_state_slots();
         let manager = self.save_states_mut();
         if let Some(manager) = manager {
             let slots = manager.slots_mut();
-            if slot >= slots.len() {
+            if slot >= user_slots {
                 Ok(None)
             } else {
                 Ok(Some(&mut slots[slot]))
@@ -671,7 +1333,7 @@ impl Core for MisterFpgaCore {
         let manager = self.save_states();
         if let Some(manager) = manager {
             let slots = manager.slots();
-            if slot >= slots.len() {
+            if slot >= self.user_save_state_slots() {
                 Ok(None)
             } else {
                 Ok(Some(&slots[slot]))
@@ -693,7 +1355,9 @@ impl Core for MisterFpgaCore {
             Rom::Memory(_, _) => Err(Error::Message(
                 "Memory ROMs are not supported yet.".to_string(),
             )),
-            Rom::File(path) => self.load_file(&path, None).map_err(Error::Message),
+            Rom::File(path) => self
+                .send_rom_with_progress(&path, &mut ProgressToken::default())
+                .map_err(Error::Message),
         }
     }
 
@@ -702,12 +1366,12 @@ impl Core for MisterFpgaCore {
     }
 
     fn key_up(&mut self, key: Scancode) -> Result<(), Error> {
-        self.key_up(key);
+        self.key_up(key)?;
         Ok(())
     }
 
     fn key_down(&mut self, key: Scancode) -> Result<(), Error> {
-        self.key_down(key);
+        self.key_down(key)?;
         Ok(())
     }
 
@@ -720,12 +1384,12 @@ impl Core for MisterFpgaCore {
     }
 
     fn gamepad_button_up(&mut self, index: usize, button: Button) -> Result<(), Error> {
-        self.gamepad_button_up(index as u8, button as u8);
+        self.gamepad_button_up(index as u8, button as u8)?;
         Ok(())
     }
 
     fn gamepad_button_down(&mut self, index: usize, button: Button) -> Result<(), Error> {
-        self.gamepad_button_down(index as u8, button as u8);
+        self.gamepad_button_down(index as u8, button as u8)?;
         Ok(())
     }
 
@@ -748,7 +1412,7 @@ impl Core for MisterFpgaCore {
             .filter_map(ConfigMenu::as_trigger)
             .find(|item| item.setting_id() == Some(id))
         {
-            self.status_pulse(*index as usize);
+            self.status_pulse(*index as usize)?;
         }
 
         Ok(())
@@ -778,11 +1442,11 @@ impl Core for MisterFpgaCore {
             .find(|item| item.setting_id() == Some(id))
         {
             let (from, to) = (bits.start, bits.end);
+            let max = self.option_choices_len_(bits, choices);
             let mut bits = *self.status_bits();
-            let max = choices.len();
             bits.set_range(from..to, (value as usize % max) as u32);
             let new_value = bits.get_range(from..to);
-            self.send_status_bits(bits);
+            self.send_status_bits(bits)?;
             Ok(new_value)
         } else {
             Ok(0)
@@ -800,7 +1464,7 @@ impl Core for MisterFpgaCore {
             let mut bits = *self.status_bits();
             bits.set_range(from..to, if value { 1 } else { 0 });
             let new_value = bits.get_range(from..to) != 0;
-            self.send_status_bits(bits);
+            self.send_status_bits(bits)?;
             Ok(new_value)
         } else {
             Ok(false)
@@ -816,6 +1480,9 @@ impl Core for MisterFpgaCore {
     }
 
     fn quit(&mut self) {
+        if let Err(error) = self.flush_mounts() {
+            warn!(?error, "Could not flush SD cards");
+        }
         self.should_quit = true;
     }
 
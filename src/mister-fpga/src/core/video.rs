@@ -10,6 +10,7 @@ use crate::config;
 use crate::config::aspect::AspectRatio;
 use crate::config::edid::CustomVideoMode;
 use crate::config::resolution::Resolution;
+use crate::config::vrr::RefreshSync;
 use crate::fpga::user_io::UserIoCommands;
 use crate::fpga::Spi;
 
@@ -25,6 +26,7 @@ mod private {
     use crate::config;
     use crate::config::aspect::AspectRatio;
     use crate::config::edid::CustomVideoMode;
+    use crate::config::vrr::RefreshSync;
     use crate::fpga::Spi;
 
     pub fn hdmi_config_init(config: &config::MisterConfig) -> Result<(), String> {
@@ -32,6 +34,22 @@ mod private {
         Ok(())
     }
 
+    pub fn hdmi_config_set_color(config: &config::MisterConfig) -> Result<(), String> {
+        debug!(
+            ?config,
+            "HDMI color controls not supported on this platform"
+        );
+        Ok(())
+    }
+
+    pub fn hdmi_config_set_vrr(
+        _sync: &RefreshSync,
+        _mode: &CustomVideoMode,
+        _refresh: f64,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
     pub fn init_mode(
         options: &config::MisterConfig,
         _core: &mut crate::core::MisterFpgaCore,
@@ -65,6 +83,18 @@ pub fn init(options: &config::MisterConfig) {
     }
 }
 
+/// Apply the color controls of the config to the HDMI output, without
+/// reinitializing it.
+pub fn set_color_controls(options: &config::MisterConfig) -> Result<(), String> {
+    private::hdmi_config_set_color(options)
+}
+
+/// Signal the variable refresh rate of a mode to the display. This must be
+/// called after the mode is selected, as selecting a mode resets infoframes.
+pub fn set_vrr(sync: &RefreshSync, mode: &CustomVideoMode, refresh: f64) -> Result<(), String> {
+    private::hdmi_config_set_vrr(sync, mode, refresh)
+}
+
 pub fn select_mode(
     mode: CustomVideoMode,
     direct_video: bool,
@@ -196,4 +226,18 @@ impl VideoInfo {
     pub fn vtime(&self) -> Duration {
         Duration::from_nanos(self.vtime_ms as u64 * 10)
     }
+
+    /// The refresh rate of the core, in Hz, if known.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        if self.vtime_ms == 0 {
+            None
+        } else {
+            Some(100_000_000. / self.vtime_ms as f64)
+        }
+    }
+
+    /// Whether the core changed its video output since `other` was read.
+    pub(crate) fn has_changed(&self, other: &VideoInfo) -> bool {
+        self.res != other.res
+    }
 }
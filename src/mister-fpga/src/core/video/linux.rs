@@ -9,6 +9,7 @@ use crate::config;
 use crate::config::{HdmiLimitedConfig, HdrConfig, MisterConfig, VgaMode, video};
 use crate::config::aspect::AspectRatio;
 use crate::config::edid::CustomVideoMode;
+use crate::config::vrr::{RefreshSync, VrrProtocol};
 use crate::fpga::Spi;
 
 mod video_mode;
@@ -231,6 +232,13 @@ pub fn hdmi_config_init(options: &MisterConfig) -> Result<(), String> {
     Ok(())
 }
 
+/// Recompute the color space conversion matrix of the HDMI output from the color
+/// controls of the config (brightness, contrast, saturation, hue, gain and offset).
+pub fn hdmi_config_set_color(options: &MisterConfig) -> Result<(), String> {
+    let mut i2c = create_i2c(0x39)?;
+    hdmi_config_set_csc(&mut i2c, options)
+}
+
 fn hdmi_config_set_csc(device: &mut LinuxI2CDevice, options: &MisterConfig) -> Result<(), String> {
     // default color conversion matrices
     // for the original hexadecimal versions please refer
@@ -562,6 +570,54 @@ fn hdmi_config_set_hdr(device: &mut LinuxI2CDevice, options: &MisterConfig) -> R
     })
 }
 
+/// Send the variable refresh rate infoframe for a mode to the display, or stop
+/// sending it if VRR is disabled.
+pub fn hdmi_config_set_vrr(
+    sync: &RefreshSync,
+    mode: &CustomVideoMode,
+    refresh: f64,
+) -> Result<(), String> {
+    // FreeSync uses the SPD packet (bit 6), HDMI VRR and QMS use the spare
+    // packet 1 (bit 0).
+    const SPD_ENABLE: u8 = 0b0100_0000;
+    const SPARE_1_ENABLE: u8 = 0b0000_0001;
+
+    let mut i2c = create_i2c(0x39)?;
+    let mut packets = i2c.smbus_read_byte_data(0x40).map_err(|error| {
+        error!(?error, "i2c: vrr: read error (0x40)");
+        error.to_string()
+    })?;
+    packets &= !(SPD_ENABLE | SPARE_1_ENABLE);
+
+    let Some(vrr) = sync.vrr else {
+        return send_to_i2c(&mut i2c, &[(0x40, packets)]);
+    };
+
+    // The base address in the packet memory, its update register and data.
+    let (base, update, data, enable) = match vrr.protocol {
+        VrrProtocol::Freesync => (0x00, 0x1F, vrr.freesync_infoframe().to_vec(), SPD_ENABLE),
+        VrrProtocol::HdmiVrr | VrrProtocol::Qms => (
+            0xC0,
+            0xDF,
+            vrr.vtem_packet(mode, sync.base_refresh(refresh)).to_vec(),
+            SPARE_1_ENABLE,
+        ),
+    };
+    debug!(?vrr, ?data, "Setting VRR infoframe");
+
+    let mut packet_i2c = create_i2c(0x38)?;
+    send_to_i2c(&mut packet_i2c, &[(update, 0b10000000)])?;
+    let data = data
+        .into_iter()
+        .enumerate()
+        .map(|(i, val)| (base + i as u8, val))
+        .collect::<Vec<_>>();
+    send_to_i2c(&mut packet_i2c, &data)?;
+    send_to_i2c(&mut packet_i2c, &[(update, 0x00)])?;
+
+    send_to_i2c(&mut i2c, &[(0x40, packets | enable)])
+}
+
 pub fn init_mode(
     options: &config::MisterConfig,
     core: &mut crate::core::MisterFpgaCore,
@@ -7,39 +7,9 @@ use crate::config;
 use crate::config::aspect::AspectRatio;
 use crate::config::edid::CustomVideoMode;
 use crate::config::FramebufferSizeConfig;
-use crate::fpga::user_io::{
-    DisableGamma, EnableGamma, IsGammaSupported, SetCustomAspectRatio, SetFramebufferToCore,
-    SetFramebufferToLinux,
-};
+use crate::fpga::user_io::{SetCustomAspectRatio, SetFramebufferToCore, SetFramebufferToLinux};
 use crate::fpga::Spi;
 
-pub struct GammaConfiguration(Vec<(u8, u8, u8)>);
-
-#[allow(dead_code)]
-impl GammaConfiguration {
-    pub fn new() -> Self {
-        Self(Vec::new())
-    }
-
-    pub fn add(&mut self, r: u8, g: u8, b: u8) {
-        self.0.push((r, g, b));
-    }
-
-    pub fn add_grayscale(&mut self, v: u8) {
-        self.0.push((v, v, v));
-    }
-
-    pub fn set(&self, spi: &mut Spi<impl MemoryMapper>) -> Result<(), String> {
-        if self.0.is_empty() {
-            spi.execute(DisableGamma)?;
-        } else {
-            spi.execute(EnableGamma(self.0.as_slice()))?;
-        }
-
-        Ok(())
-    }
-}
-
 fn video_fb_config(
     mode: &CustomVideoMode,
     fb_size: FramebufferSizeConfig,
@@ -132,18 +102,6 @@ pub fn select_mode(
     spi: &mut Spi<impl MemoryMapper>,
     is_menu: bool,
 ) -> Result<(), String> {
-    let mut has_gamma = false;
-    spi.execute(IsGammaSupported(&mut has_gamma))?;
-
-    if has_gamma {
-        let mut gamma = GammaConfiguration::new();
-        // TODO: get gamma configuration from options/database.
-        gamma.add_grayscale(0);
-        gamma.add_grayscale(0x7F);
-        gamma.add_grayscale(0xFF);
-        gamma.set(spi)?;
-    }
-
     if aspect_ratio_1.or(aspect_ratio_2).is_some() {
         let first = aspect_ratio_1.unwrap_or_else(AspectRatio::zero);
         let second = aspect_ratio_2.unwrap_or_else(AspectRatio::zero);
@@ -151,8 +109,9 @@ pub fn select_mode(
         spi.execute(SetCustomAspectRatio(first.into(), second.into()))?;
     }
 
-    // TODO: set scaler filter.
-    // TODO: set VRR.
+    // Scaler filters and gamma are set per core, see `MisterFpgaCore::set_scaler_filters`
+    // and `MisterFpgaCore::set_color_controls`. VRR and vsync_adjust follow the
+    // refresh rate of the core, see `MisterFpgaCore::poll_video_mode`.
 
     mode.send_to_core(direct_video, spi, is_menu)?;
     if is_menu {
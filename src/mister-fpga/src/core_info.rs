@@ -2,6 +2,9 @@ use crate::config;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
+mod catalog;
+pub use catalog::*;
+
 fn strip_version(name: &str) -> &str {
     name.rsplit_once('_').map(|(name, _)| name).unwrap_or(name)
 }
@@ -249,4 +252,11 @@ fn from_bootcore_config() {
     let core = x.unwrap();
     assert_eq!(core.name, "Core");
     assert_eq!(core.path, root.join("_Cores/Core_12345678.rbf"));
+
+    // The exact name must match for `exactlastcore`.
+    let exact = Option::<CoreInfo>::from(config::BootCoreConfig::ExactLastCore);
+    assert_eq!(exact, None);
+    config::Config::set_last_core_data(core.exact_name()).unwrap();
+    let exact = Option::<CoreInfo>::from(config::BootCoreConfig::ExactLastCore).unwrap();
+    assert_eq!(exact.path, root.join("_Cores/Core_12345678.rbf"));
 }
@@ -0,0 +1,388 @@
+//! An index of all the cores (RBF files) installed, with the metadata found
+//! in their file name and bitstream. Reading a bitstream is slow on the
+//! DE10-Nano, so the metadata is cached by file hash, and files that did not
+//! change since the last scan are not hashed again.
+use super::{core_name_of_, exact_core_name_of_, strip_version, CoreInfo};
+use crate::config_string::Config;
+use crate::fpga::{Rbf, RbfCompression, RbfFile, SdramRequirement};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsStr;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+/// The minimum length of a run of text in a bitstream to be considered a
+/// config string. The shortest useful config strings are a name and a
+/// version, e.g. `Core;;V,v123456`.
+const MIN_CONFIG_STRING_LEN: usize = 16;
+
+/// The metadata found in the content of an RBF file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RbfMetadata {
+    /// The config string embedded in the core, if it could be found.
+    pub config_string: Option<String>,
+
+    /// The name of the system, from the config string.
+    pub system: Option<String>,
+
+    /// The version of the core, from the config string.
+    pub version: Option<String>,
+
+    /// The extensions of the files the core can load, in lower case.
+    pub extensions: Vec<String>,
+}
+
+impl RbfMetadata {
+    /// Extract the metadata of an RBF file, compressed or not. A file that
+    /// cannot be read as an RBF results in empty metadata.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        if RbfCompression::detect(bytes) != RbfCompression::None {
+            let mut program = Vec::new();
+            let result = RbfFile::from_reader(Cursor::new(bytes.to_vec()))
+                .and_then(|mut file| Ok(file.read_to_end(&mut program)?));
+            return match result {
+                Ok(_) => Self::from_program(&program, &[]),
+                Err(e) => {
+                    warn!(?e, "Could not decompress RBF");
+                    Self::default()
+                }
+            };
+        }
+
+        match Rbf::parse(bytes) {
+            Ok(rbf) => Self::from_program(rbf.program(), rbf.metadata()),
+            Err(e) => {
+                warn!(?e, "Invalid RBF");
+                Self::default()
+            }
+        }
+    }
+
+    fn from_program(program: &[u8], metadata: &[u8]) -> Self {
+        let config_string = std::str::from_utf8(metadata)
+            .ok()
+            .map(|s| s.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+            .filter(|s| s.parse::<Config>().is_ok())
+            .map(str::to_string)
+            .or_else(|| find_config_string(program));
+
+        let Some(config_string) = config_string else {
+            return Self::default();
+        };
+        let Ok(config) = config_string.parse::<Config>() else {
+            return Self::default();
+        };
+
+        let extensions: BTreeSet<String> = config
+            .menu
+            .iter()
+            .filter_map(|item| item.as_load_file()?.as_load_file_info())
+            .flat_map(|info| info.extensions.iter())
+            .map(|ext| ext.as_str().to_lowercase())
+            .collect();
+
+        Self {
+            system: Some(config.name.clone()).filter(|name| !name.is_empty()),
+            version: config.version().map(str::to_string),
+            extensions: extensions.into_iter().collect(),
+            config_string: Some(config_string),
+        }
+    }
+
+    /// Whether the core needs an SDRAM module, from its config string.
+    pub fn sdram_requirement(&self) -> SdramRequirement {
+        self.config_string
+            .as_deref()
+            .and_then(|s| s.parse::<Config>().ok())
+            .map(|config| config.sdram_requirement())
+            .unwrap_or_default()
+    }
+}
+
+/// Find a config string in a bitstream. Some cores keep it as plain text in
+/// their ROM initialization data; most don't, in which case `None` is returned.
+fn find_config_string(program: &[u8]) -> Option<String> {
+    program
+        .split(|b| !(0x20..0x7F).contains(b))
+        .filter(|run| run.len() >= MIN_CONFIG_STRING_LEN && run.contains(&b';'))
+        .filter_map(|run| std::str::from_utf8(run).ok())
+        .filter(|s| {
+            s.split(';').next().is_some_and(|name| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            })
+        })
+        .find(|s| s.parse::<Config>().is_ok())
+        .map(str::to_string)
+}
+
+/// Extract the date code of a core from its file name, e.g. `20240131` for
+/// `SNES_20240131.rbf`.
+fn date_code_of_(path: &Path) -> Option<String> {
+    let (_, code) = exact_core_name_of_(path)?.rsplit_once('_')?;
+    chrono::NaiveDate::parse_from_str(code, "%Y%m%d").ok()?;
+    Some(code.to_string())
+}
+
+/// A core found when scanning the cores directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogCore {
+    /// The name of the core, without its date code.
+    pub name: String,
+
+    /// The file name of the core, without its extension.
+    pub exact_name: String,
+
+    /// The path of the RBF file.
+    pub path: PathBuf,
+
+    /// The date code in the file name, as `YYYYMMDD`.
+    pub date_code: Option<String>,
+
+    /// The directory the core is in, without its leading underscore, e.g.
+    /// `Console` for `_Console/`.
+    pub category: Option<String>,
+
+    /// The CRC32 of the file, in hexadecimal.
+    pub hash: String,
+
+    #[serde(flatten)]
+    pub metadata: RbfMetadata,
+}
+
+impl From<&CatalogCore> for CoreInfo {
+    fn from(core: &CatalogCore) -> Self {
+        CoreInfo::new(&core.name, &core.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    size: u64,
+    modified: u64,
+    hash: String,
+}
+
+/// The size and modification time (in seconds) of a file.
+fn size_and_modified_(path: &Path) -> Option<(u64, u64)> {
+    let metadata = path.metadata().ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((metadata.len(), modified))
+}
+
+/// The index of cores, which can be serialized to keep it between runs.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoreCatalog {
+    /// The metadata of RBF files, by hash.
+    metadata: BTreeMap<String, RbfMetadata>,
+
+    /// The files found in the last scan.
+    files: BTreeMap<PathBuf, FileStamp>,
+
+    #[serde(skip)]
+    cores: Vec<CatalogCore>,
+}
+
+impl CoreCatalog {
+    /// Scan the cores under `root`, in the same directories `CoreInfo::from_name`
+    /// looks into. Files already indexed are only read if they changed.
+    pub fn scan(&mut self, root: impl AsRef<Path>) -> Result<&[CatalogCore], std::io::Error> {
+        let mut paths = Vec::new();
+        list_cores_(root.as_ref(), &mut paths)?;
+
+        let mut files = BTreeMap::new();
+        let mut cores = Vec::with_capacity(paths.len());
+        for path in paths {
+            let Some((size, modified)) = size_and_modified_(&path) else {
+                continue;
+            };
+
+            let hash = match self.files.get(&path) {
+                Some(stamp) if stamp.size == size && stamp.modified == modified => {
+                    stamp.hash.clone()
+                }
+                _ => {
+                    let bytes = std::fs::read(&path)?;
+                    let hash = format!("{:08x}", crc32fast::hash(&bytes));
+                    if !self.metadata.contains_key(&hash) {
+                        debug!(?path, %hash, "Reading core metadata");
+                        self.metadata
+                            .insert(hash.clone(), RbfMetadata::from_bytes(&bytes));
+                    }
+                    hash
+                }
+            };
+
+            let metadata = self.metadata.get(&hash).cloned().unwrap_or_default();
+            cores.push(CatalogCore {
+                name: core_name_of_(&path).unwrap_or_default().to_string(),
+                exact_name: exact_core_name_of_(&path).unwrap_or_default().to_string(),
+                date_code: date_code_of_(&path),
+                category: path
+                    .parent()
+                    .and_then(Path::file_name)
+                    .and_then(OsStr::to_str)
+                    .and_then(|dir| dir.strip_prefix('_'))
+                    .map(str::to_string),
+                hash: hash.clone(),
+                metadata,
+                path: path.clone(),
+            });
+            files.insert(
+                path,
+                FileStamp {
+                    size,
+                    modified,
+                    hash,
+                },
+            );
+        }
+
+        // Forget about cores that were removed.
+        let hashes: BTreeSet<&String> = files.values().map(|stamp| &stamp.hash).collect();
+        self.metadata.retain(|hash, _| hashes.contains(hash));
+        self.files = files;
+
+        cores.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| b.date_code.cmp(&a.date_code))
+        });
+        self.cores = cores;
+        Ok(&self.cores)
+    }
+
+    /// The cores found in the last scan, sorted by name and most recent first.
+    pub fn cores(&self) -> &[CatalogCore] {
+        &self.cores
+    }
+
+    /// Find the most recent core with this name (without date code).
+    pub fn find(&self, name: &str) -> Option<&CatalogCore> {
+        let name = strip_version(name);
+        self.cores.iter().find(|core| core.name == name)
+    }
+
+    /// Find a core by its exact file name, without extension.
+    pub fn find_exact(&self, exact_name: &str) -> Option<&CatalogCore> {
+        self.cores.iter().find(|core| core.exact_name == exact_name)
+    }
+
+    /// The metadata of a core file, as indexed in the last scan. This does
+    /// not need the cores to be listed, so it works on a catalog just loaded.
+    pub fn metadata_of(&self, path: impl AsRef<Path>) -> Option<&RbfMetadata> {
+        let stamp = self.files.get(path.as_ref())?;
+        self.metadata.get(&stamp.hash)
+    }
+}
+
+fn list_cores_(path: &Path, cores: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    for entry in path.read_dir()? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            let file_name = path.file_name().and_then(OsStr::to_str);
+            if file_name.is_some_and(|name| name.starts_with('_')) {
+                list_cores_(&path, cores)?;
+            }
+        } else if exact_core_name_of_(&path).is_some() {
+            cores.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn rbf(program: &[u8], metadata: &[u8]) -> Vec<u8> {
+    let mut bytes = crate::fpga::RBF_MAGIC.to_vec();
+    bytes.extend_from_slice(&[0; 6]);
+    bytes.extend_from_slice(&(program.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(program);
+    bytes.extend_from_slice(metadata);
+    bytes
+}
+
+#[test]
+fn metadata_from_bitstream() {
+    let mut program = vec![0xFFu8, 0x00, 0x12, 0x34];
+    program.extend_from_slice(b"xx;;\0");
+    program.extend_from_slice(b"NES;;FS1,NESFDSNSF;F2,BIN,Load Palette;V,v123456\0");
+    program.extend_from_slice(&[0x80, 0x81]);
+
+    let metadata = RbfMetadata::from_bytes(&rbf(&program, &[]));
+    assert_eq!(metadata.system.as_deref(), Some("NES"));
+    assert_eq!(metadata.version.as_deref(), Some("v123456"));
+    assert_eq!(metadata.extensions, ["bin", "fds", "nes", "nsf"]);
+
+    let metadata = RbfMetadata::from_bytes(&rbf(&[0xFF; 64], b"Chess;;-;V,v230101\0"));
+    assert_eq!(metadata.system.as_deref(), Some("Chess"));
+    assert_eq!(
+        metadata.config_string.as_deref(),
+        Some("Chess;;-;V,v230101")
+    );
+
+    let metadata = RbfMetadata::from_bytes(&rbf(&[0xFF; 64], b"JTSDRAM;;-;V,v210108\0"));
+    assert_eq!(metadata.sdram_requirement(), SdramRequirement::Required);
+
+    assert_eq!(RbfMetadata::from_bytes(&[0xFF; 64]), RbfMetadata::default());
+}
+
+#[test]
+fn scan_catalog() {
+    let root_dir = tempdir::TempDir::new("mister").unwrap();
+    let root = root_dir.path();
+
+    std::fs::create_dir_all(root.join("_Console")).unwrap();
+    std::fs::create_dir_all(root.join("games")).unwrap();
+    std::fs::write(
+        root.join("_Console/NES_20240131.rbf"),
+        rbf(&[0xFF; 32], b"NES;;F1,NES;V,v240131"),
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("_Console/NES_20230101.rbf"),
+        rbf(&[0xFF; 32], &[]),
+    )
+    .unwrap();
+    std::fs::write(root.join("menu.rbf"), rbf(&[0xFF; 16], &[])).unwrap();
+    std::fs::write(root.join("games/Other.rbf"), rbf(&[0xFF; 16], &[])).unwrap();
+
+    let mut catalog = CoreCatalog::default();
+    let cores = catalog.scan(root).unwrap();
+    assert_eq!(cores.len(), 3);
+
+    let nes = catalog.find("NES").unwrap();
+    assert_eq!(nes.exact_name, "NES_20240131");
+    assert_eq!(nes.date_code.as_deref(), Some("20240131"));
+    assert_eq!(nes.category.as_deref(), Some("Console"));
+    assert_eq!(nes.metadata.extensions, ["nes"]);
+    assert!(catalog.find_exact("NES_20230101").is_some());
+
+    let menu = catalog.find("menu").unwrap();
+    assert_eq!(menu.date_code, None);
+    assert_eq!(menu.category, None);
+
+    // Survives a round trip, and forgets removed cores on the next scan.
+    let mut catalog: CoreCatalog = json5::from_str(&json5::to_string(&catalog).unwrap()).unwrap();
+    let nes_path = root.join("_Console/NES_20240131.rbf");
+    let metadata = catalog.metadata_of(&nes_path).unwrap();
+    assert_eq!(metadata.system.as_deref(), Some("NES"));
+    assert_eq!(metadata.sdram_requirement(), SdramRequirement::Required);
+    std::fs::remove_file(root.join("_Console/NES_20240131.rbf")).unwrap();
+    catalog.scan(root).unwrap();
+    assert_eq!(catalog.cores().len(), 2);
+    assert_eq!(catalog.metadata.len(), 2);
+    assert_eq!(catalog.find("NES").unwrap().exact_name, "NES_20230101");
+}
@@ -1,5 +1,5 @@
 use std::cell::UnsafeCell;
-use std::io::Read;
+use std::io::{self, Read};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -9,13 +9,22 @@ use tracing::{debug, error, info, trace};
 
 use cyclone_v::fpgamgrregs::ctrl::{FpgaCtrlCfgWidth, FpgaCtrlEn, FpgaCtrlNce};
 use cyclone_v::fpgamgrregs::stat::StatusRegisterMode;
-use cyclone_v::memory::DevMemMemoryMapper;
+use cyclone_v::memory::{DevMemMemoryMapper, TraceGuard, TraceRecorder};
+pub use diagnostics::*;
+pub use dma::*;
+use one_fpga::progress::{LoadStage, ProgressToken};
 pub use program::Program;
+pub use rbf::*;
+pub use sdram::*;
 pub use spi::*;
 
 use crate::fpga::osd_io::{OsdDisable, OsdEnable};
 
+mod diagnostics;
+mod dma;
 mod program;
+mod rbf;
+mod sdram;
 mod spi;
 
 #[derive(Debug, Copy, Clone)]
@@ -28,6 +37,8 @@ pub enum FpgaError {
     CouldNotEnterInitPhase,
     CouldNotEnterUserMode,
     IoError,
+    InvalidProgram,
+    Cancelled,
 }
 
 impl From<FpgaError> for &'static str {
@@ -46,10 +57,30 @@ impl From<&FpgaError> for &'static str {
             FpgaError::CouldNotEnterInitPhase => "Could not enter init phase",
             FpgaError::CouldNotEnterUserMode => "Could not enter user mode",
             FpgaError::IoError => "I/O Error",
+            FpgaError::InvalidProgram => "Invalid program",
+            FpgaError::Cancelled => "Cancelled",
         }
     }
 }
 
+/// The size of the chunks read from a program before writing them to the FPGA.
+const PROGRAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Fill a buffer from a reader, returning less than the buffer size only at the
+/// end of the stream.
+fn read_chunk(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
 static mut INITIALIZED: AtomicBool = AtomicBool::new(false);
 
 // TODO: Remove this when we're done re-writing fpga_io.cpp
@@ -170,6 +201,13 @@ impl MisterFpga {
         }
     }
 
+    /// Record every register access of the SoC (FPGA manager, SPI, DMA...)
+    /// until the guard is dropped. Files and memory sent to the core are not
+    /// included.
+    pub fn trace_registers(&self, recorder: &TraceRecorder) -> TraceGuard {
+        recorder.attach(&self.soc_mut().memory, cyclone_v::ranges::BASE.start)
+    }
+
     pub fn spi(&self) -> &Spi<DevMemMemoryMapper> {
         &self.spi
     }
@@ -336,19 +374,44 @@ impl MisterFpga {
     }
 
     pub fn load(&mut self, program: impl Program) -> Result<(), FpgaError> {
-        program.load(self)
-    }
-
-    pub(crate) fn load_rbf_bytes(&mut self, bytes: &[u8]) -> Result<(), FpgaError> {
+        self.load_with_progress(program, &mut ProgressToken::default())
+    }
+
+    /// Load a program, reporting the number of bytes written to the FPGA. If
+    /// the token is cancelled, the FPGA is left unconfigured and another core
+    /// (e.g. the menu) needs to be loaded.
+    pub fn load_with_progress(
+        &mut self,
+        program: impl Program,
+        progress: &mut ProgressToken,
+    ) -> Result<(), FpgaError> {
+        program.load(self, progress)
+    }
+
+    /// Load a bitstream, streaming it to the FPGA. If `size` is known, the FPGA
+    /// is not configured if the program is shorter (e.g. a truncated file).
+    pub(crate) fn load_rbf(
+        &mut self,
+        program: impl Read,
+        size: Option<usize>,
+        progress: &mut ProgressToken,
+    ) -> Result<(), FpgaError> {
         let start = Instant::now();
+        progress
+            .start(LoadStage::Core, size.unwrap_or(0))
+            .map_err(|_| FpgaError::Cancelled)?;
         self.disable_bridge();
 
         debug!("Initializing FPGA...");
         self.init_program()?;
         debug!("Writing program...");
         let now = Instant::now();
-        self.write_program(bytes)?;
+        let written = self.write_program(program, progress)?;
         trace!("Program written in {}ms", now.elapsed().as_millis());
+        if written == 0 || size.is_some_and(|size| size != written) {
+            error!(written, ?size, "Program is truncated");
+            return Err(FpgaError::InvalidProgram);
+        }
 
         debug!("Configuration and initialization.");
         self.enable_bridge();
@@ -413,33 +476,54 @@ impl MisterFpga {
         Ok(())
     }
 
-    /// Write the RBF program to the FPGA.
+    /// Write the RBF program to the FPGA, in chunks so the program does not need
+    /// to be in memory. Progress is updated after each chunk. Returns the number
+    /// of bytes written.
     #[inline(never)]
-    pub fn write_program(&mut self, program: impl Read) -> Result<(), FpgaError> {
-        let program = program
-            .bytes()
-            .collect::<Result<Vec<u8>, _>>()
-            .map_err(|_err| FpgaError::IoError)?;
-        if program.is_empty() {
-            return Ok(());
-        }
-
+    pub fn write_program(
+        &mut self,
+        mut program: impl Read,
+        progress: &mut ProgressToken,
+    ) -> Result<usize, FpgaError> {
         let data = unsafe { self.soc_mut().data_ptr_mut() } as *mut u32;
-        unsafe {
-            let (prefix, shorts, suffix) = program.align_to::<u32>();
 
-            for v in prefix {
-                std::ptr::write_volatile(data as *mut u8, *v);
+        // A buffer of words, so they can be written to FPGAMGRDATA directly.
+        let mut chunk = vec![0u32; PROGRAM_CHUNK_SIZE / 4];
+        let mut total = 0;
+        loop {
+            let len = {
+                let bytes = unsafe {
+                    std::slice::from_raw_parts_mut(
+                        chunk.as_mut_ptr() as *mut u8,
+                        PROGRAM_CHUNK_SIZE,
+                    )
+                };
+                read_chunk(&mut program, bytes).map_err(|error| {
+                    error!(?error, "Could not read the program");
+                    FpgaError::IoError
+                })?
+            };
+
+            let (words, rest) = (len / 4, len % 4);
+            unsafe {
+                for v in &chunk[..words] {
+                    std::ptr::write_volatile(data, *v);
+                }
+                let rest_ptr = chunk.as_ptr().add(words) as *const u8;
+                for i in 0..rest {
+                    std::ptr::write_volatile(data as *mut u8, *rest_ptr.add(i));
+                }
             }
-            for v in shorts {
-                std::ptr::write_volatile(data, *v);
-            }
-            for v in suffix {
-                std::ptr::write_volatile(data as *mut u8, *v);
+
+            total += len;
+            progress.update(total).map_err(|_| {
+                debug!(total, "Programming cancelled");
+                FpgaError::Cancelled
+            })?;
+            if len < PROGRAM_CHUNK_SIZE {
+                return Ok(total);
             }
         }
-
-        Ok(())
     }
 
     #[inline]
@@ -0,0 +1,68 @@
+//! Diagnostics of the DDR memory of the HPS, to find faulty boards. The
+//! memory test writes over the host memory shared with the FPGA, so it should
+//! only run while the menu core is loaded.
+use crate::fpga::MisterFpga;
+use cyclone_v::memory::{DevMemMemoryMapper, MemoryMapper};
+use cyclone_v::sdr::diagnostics::{EccStats, SdramConfig};
+use cyclone_v::sdr::memtest::{self, MemoryTest, MemoryTestError};
+use std::ops::Range;
+use tracing::error;
+
+/// The range of the host memory tested by default, as offsets from its start.
+/// The first 64 MiB hold the framebuffer of the menu core.
+pub const HOST_MEMORY_TEST_RANGE: Range<usize> = 0x0400_0000..0x2000_0000;
+
+impl MisterFpga {
+    /// The DDR configuration of the SDRAM controller.
+    pub fn sdram_config(&self) -> SdramConfig {
+        self.soc_mut().sdr().config()
+    }
+
+    /// The ECC error counters of the SDRAM controller.
+    pub fn ecc_stats(&self) -> EccStats {
+        self.soc_mut().sdr().ecc_stats()
+    }
+
+    /// Reset the ECC error counters of the SDRAM controller.
+    pub fn clear_ecc_stats(&mut self) {
+        self.soc_mut().sdr_mut().clear_ecc_stats();
+    }
+
+    /// Run a memory test over a range of the host memory, as offsets from its
+    /// start. The offsets of mismatches are physical addresses.
+    pub fn test_host_memory(
+        &mut self,
+        range: Range<usize>,
+        test: MemoryTest,
+        progress: impl FnMut(usize, usize) -> bool,
+    ) -> Result<(), MemoryTestError> {
+        let host_memory = cyclone_v::ranges::HOST_MEMORY;
+        let in_bounds = host_memory
+            .start
+            .checked_add(range.end)
+            .is_some_and(|end| end <= host_memory.end + 1);
+        if range.is_empty() || !in_bounds {
+            return Err(MemoryTestError::InvalidRange);
+        }
+
+        let address = host_memory.start + range.start;
+        let mut memory = DevMemMemoryMapper::create(address, range.len()).map_err(|e| {
+            error!("Could not map host memory at {address:#X}: {e}");
+            MemoryTestError::InvalidRange
+        })?;
+
+        let len = memory.len();
+        memtest::run(&mut memory, 0..len, test, progress).map_err(|e| match e {
+            MemoryTestError::Mismatch {
+                offset,
+                expected,
+                actual,
+            } => MemoryTestError::Mismatch {
+                offset: address + offset,
+                expected,
+                actual,
+            },
+            e => e,
+        })
+    }
+}
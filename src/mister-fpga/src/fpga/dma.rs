@@ -0,0 +1,97 @@
+//! Copy files to the DDR memory shared with the FPGA using the DMA controller
+//! of the HPS. Files are read into two bounce buffers in on-chip RAM, and the
+//! DMA controller copies one to the destination while the next chunk is read
+//! into the other.
+use crate::fpga::{read_chunk, MisterFpga};
+use cyclone_v::dmac::DmaError;
+use one_fpga::progress::ProgressToken;
+use std::io::Read;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// The DMA channel used to send files. Linux allocates channels from 0 if its
+/// own driver is enabled, so we use the last one.
+pub const DMA_CHANNEL: u8 = 7;
+
+/// The size of each of the two bounce buffers.
+pub const DMA_CHUNK_SIZE: usize = 16 * 1024;
+
+/// The offset of the bounce buffers in on-chip RAM, after the DMA programs.
+const DMA_BOUNCE_OFFSET: usize = 0x1000;
+
+/// How long a chunk can take to be copied before giving up.
+const DMA_CHUNK_TIMEOUT: Duration = Duration::from_millis(100);
+
+impl MisterFpga {
+    /// Take the DMA controller out of reset if needed, and check that our
+    /// channel can be used.
+    pub fn dma_ready(&mut self) -> bool {
+        let soc = self.soc_mut();
+        soc.dma_init();
+        soc.dmac().is_present() && soc.dmac().poll(DMA_CHANNEL) == Ok(true)
+    }
+
+    fn dma_wait(&mut self) -> Result<(), DmaError> {
+        let start = Instant::now();
+        while !self.soc_mut().dmac().poll(DMA_CHANNEL)? {
+            if start.elapsed() > DMA_CHUNK_TIMEOUT {
+                if let Err(error) = self.soc_mut().dmac_mut().kill(DMA_CHANNEL) {
+                    warn!(?error, "Could not stop the DMA channel");
+                }
+                return Err(DmaError::Timeout);
+            }
+            std::hint::spin_loop();
+        }
+        Ok(())
+    }
+
+    /// Copy `size` bytes from a reader to a physical address, which must be
+    /// aligned to 8 bytes. `progress` is updated with the number of bytes
+    /// copied after each chunk. Returns the CRC32 of the data.
+    pub fn dma_write(
+        &mut self,
+        address: u32,
+        size: usize,
+        mut reader: impl Read,
+        progress: &mut ProgressToken,
+    ) -> Result<u32, String> {
+        let mut crc = crc32fast::Hasher::new();
+        let mut chunk = vec![0u8; DMA_CHUNK_SIZE];
+        let mut copied = 0;
+        let mut buffer = 0;
+
+        while copied < size {
+            let len = DMA_CHUNK_SIZE.min(size - copied);
+            let len = read_chunk(&mut reader, &mut chunk[..len]).map_err(|e| e.to_string())?;
+            if len == 0 {
+                return Err(format!("File truncated at {copied} bytes."));
+            }
+            crc.update(&chunk[..len]);
+
+            // The DMA controller may still be copying the previous chunk, but
+            // that one is in the other buffer.
+            let offset = DMA_BOUNCE_OFFSET + buffer * DMA_CHUNK_SIZE;
+            unsafe {
+                let bounce = self.soc_mut().ocram_ptr_mut().add(offset);
+                std::ptr::copy_nonoverlapping(chunk.as_ptr(), bounce, len);
+            }
+
+            self.dma_wait().map_err(|e| e.to_string())?;
+            let src = (cyclone_v::ranges::OCRAM.start + offset) as u32;
+            self.soc_mut()
+                .dma_copy(DMA_CHANNEL, src, address + copied as u32, len)
+                .map_err(|e| e.to_string())?;
+
+            copied += len;
+            buffer ^= 1;
+            if let Err(cancelled) = progress.update(copied) {
+                // Let the last chunk complete before returning.
+                self.dma_wait().map_err(|e| e.to_string())?;
+                return Err(cancelled.into());
+            }
+        }
+
+        self.dma_wait().map_err(|e| e.to_string())?;
+        Ok(crc.finalize())
+    }
+}
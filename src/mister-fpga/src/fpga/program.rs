@@ -1,11 +1,8 @@
 use crate::fpga::{FpgaError, MisterFpga};
+use one_fpga::progress::ProgressToken;
 
+/// A program that can be loaded into the FPGA. See [`crate::fpga::Rbf`] and
+/// [`crate::fpga::RbfFile`] for RBF files, in memory or streamed.
 pub trait Program {
-    fn load(&self, fpga: &mut MisterFpga) -> Result<(), FpgaError>;
-}
-
-impl Program for &[u8] {
-    fn load(&self, fpga: &mut MisterFpga) -> Result<(), FpgaError> {
-        fpga.load_rbf_bytes(self)
-    }
+    fn load(self, fpga: &mut MisterFpga, progress: &mut ProgressToken) -> Result<(), FpgaError>;
 }
@@ -0,0 +1,418 @@
+//! RBF files, containing the FPGA program of a core. The bitstream can be raw,
+//! or wrapped in the MiSTer container which starts with a `MiSTer` magic and the
+//! size of the bitstream. Files can also be compressed with gzip, zstd or zip.
+use crate::fpga::{FpgaError, MisterFpga, Program};
+use one_fpga::progress::ProgressToken;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::error;
+
+/// The magic at the start of the MiSTer container.
+pub const RBF_MAGIC: &[u8; 6] = b"MiSTer";
+
+/// The size of the MiSTer container header.
+pub const RBF_HEADER_SIZE: usize = 16;
+
+/// The maximum size of a bitstream. The uncompressed bitstream of the Cyclone V
+/// on the DE10-Nano is about 7MB, anything much larger cannot be a program.
+pub const RBF_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RbfError {
+    #[error("The program is empty")]
+    Empty,
+
+    #[error("The program is too large ({0} bytes)")]
+    TooLarge(usize),
+
+    #[error("The MiSTer header is truncated")]
+    TruncatedHeader,
+
+    #[error("The header declares {declared} bytes, but only {available} are available")]
+    Truncated { declared: usize, available: usize },
+
+    #[error("No RBF file found in the archive")]
+    NotInArchive,
+
+    #[error("Could not read the program: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Could not read the archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+/// The header of the MiSTer container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RbfHeader {
+    /// The bytes between the magic and the size, unused by the MiSTer.
+    pub reserved: [u8; 6],
+
+    /// The size of the bitstream following the header.
+    pub size: u32,
+}
+
+impl RbfHeader {
+    /// Parse the header at the start of a file. Returns `None` if the file does
+    /// not start with the MiSTer magic, as it is a raw bitstream.
+    pub fn parse(bytes: &[u8]) -> Result<Option<Self>, RbfError> {
+        if !bytes.starts_with(RBF_MAGIC) {
+            return Ok(None);
+        }
+        if bytes.len() < RBF_HEADER_SIZE {
+            return Err(RbfError::TruncatedHeader);
+        }
+
+        let mut reserved = [0; 6];
+        reserved.copy_from_slice(&bytes[6..12]);
+        let size = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+        match size as usize {
+            0 => Err(RbfError::Empty),
+            s if s > RBF_MAX_SIZE => Err(RbfError::TooLarge(s)),
+            _ => Ok(Some(Self { reserved, size })),
+        }
+    }
+}
+
+/// An RBF file in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct Rbf<'a> {
+    header: Option<RbfHeader>,
+    program: &'a [u8],
+    metadata: &'a [u8],
+}
+
+impl<'a> Rbf<'a> {
+    /// Parse and validate an RBF file, with or without the MiSTer header.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, RbfError> {
+        let Some(header) = RbfHeader::parse(bytes)? else {
+            return match bytes.len() {
+                0 => Err(RbfError::Empty),
+                len if len > RBF_MAX_SIZE => Err(RbfError::TooLarge(len)),
+                _ => Ok(Self {
+                    header: None,
+                    program: bytes,
+                    metadata: &[],
+                }),
+            };
+        };
+
+        let (program, metadata) = bytes[RBF_HEADER_SIZE..]
+            .split_at_checked(header.size as usize)
+            .ok_or(RbfError::Truncated {
+                declared: header.size as usize,
+                available: bytes.len() - RBF_HEADER_SIZE,
+            })?;
+        Ok(Self {
+            header: Some(header),
+            program,
+            metadata,
+        })
+    }
+
+    pub fn header(&self) -> Option<&RbfHeader> {
+        self.header.as_ref()
+    }
+
+    /// The bitstream to send to the FPGA.
+    pub fn program(&self) -> &'a [u8] {
+        self.program
+    }
+
+    /// Any data after the bitstream in the MiSTer container.
+    pub fn metadata(&self) -> &'a [u8] {
+        self.metadata
+    }
+}
+
+impl Program for Rbf<'_> {
+    fn load(self, fpga: &mut MisterFpga, progress: &mut ProgressToken) -> Result<(), FpgaError> {
+        fpga.load_rbf(self.program, Some(self.program.len()), progress)
+    }
+}
+
+impl Program for &[u8] {
+    fn load(self, fpga: &mut MisterFpga, progress: &mut ProgressToken) -> Result<(), FpgaError> {
+        Rbf::parse(self)
+            .inspect_err(|error| error!(%error, "Invalid RBF"))
+            .map_err(|_| FpgaError::InvalidProgram)?
+            .load(fpga, progress)
+    }
+}
+
+/// A stream of an RBF file. The header is validated when creating the stream,
+/// and reading it only returns the bitstream.
+pub struct RbfStream<R: Read> {
+    header: Option<RbfHeader>,
+    reader: io::Take<io::Chain<Cursor<Vec<u8>>, R>>,
+}
+
+impl<R: Read> RbfStream<R> {
+    pub fn new(mut reader: R) -> Result<Self, RbfError> {
+        let mut start = Vec::with_capacity(RBF_HEADER_SIZE);
+        reader
+            .by_ref()
+            .take(RBF_HEADER_SIZE as u64)
+            .read_to_end(&mut start)?;
+        if start.is_empty() {
+            return Err(RbfError::Empty);
+        }
+
+        // Without a header, the bytes read are part of the bitstream.
+        let header = RbfHeader::parse(&start)?;
+        let limit = match header {
+            Some(header) => {
+                start.clear();
+                header.size as u64
+            }
+            None => RBF_MAX_SIZE as u64,
+        };
+
+        Ok(Self {
+            header,
+            reader: Cursor::new(start).chain(reader).take(limit),
+        })
+    }
+
+    pub fn header(&self) -> Option<&RbfHeader> {
+        self.header.as_ref()
+    }
+}
+
+impl<R: Read> Read for RbfStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+
+        // A bitstream without a header is only limited to its maximum size. If
+        // there is more, it would be truncated, so fail instead.
+        if read == 0 && !buf.is_empty() && self.header.is_none() && self.reader.limit() == 0 {
+            let mut byte = [0u8];
+            if self.reader.get_mut().read(&mut byte)? > 0 {
+                return Err(io::Error::other(RbfError::TooLarge(RBF_MAX_SIZE + 1)));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Read> Program for RbfStream<R> {
+    fn load(self, fpga: &mut MisterFpga, progress: &mut ProgressToken) -> Result<(), FpgaError> {
+        let size = self.header.map(|h| h.size as usize);
+        fpga.load_rbf(self, size, progress)
+    }
+}
+
+impl<R: Read> RbfStream<flate2::read::GzDecoder<R>> {
+    /// A stream of a gzip compressed RBF.
+    pub fn gzip(reader: R) -> Result<Self, RbfError> {
+        Self::new(flate2::read::GzDecoder::new(reader))
+    }
+}
+
+impl<R: BufRead> RbfStream<zstd::Decoder<'static, R>> {
+    /// A stream of a zstd compressed RBF.
+    pub fn zstd(reader: R) -> Result<Self, RbfError> {
+        Self::new(zstd::Decoder::with_buffer(reader)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RbfCompression {
+    None,
+    Gzip,
+    Zstd,
+    Zip,
+}
+
+impl RbfCompression {
+    /// Detect the compression from the first bytes of a file.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1F, 0x8B]) {
+            Self::Gzip
+        } else if bytes.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            Self::Zstd
+        } else if bytes.starts_with(b"PK\x03\x04") {
+            Self::Zip
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// An RBF file on disk, compressed or not. The program is streamed to the
+/// FPGA, except for zip archives which are extracted in memory first.
+pub struct RbfFile {
+    compression: RbfCompression,
+    stream: RbfStream<Box<dyn Read>>,
+}
+
+impl RbfFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RbfError> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    pub fn from_reader(reader: impl Read + Seek + 'static) -> Result<Self, RbfError> {
+        let mut reader = BufReader::new(reader);
+        let compression = RbfCompression::detect(reader.fill_buf()?);
+
+        let stream = match compression {
+            RbfCompression::None => {
+                let len = reader.seek(SeekFrom::End(0))?;
+                reader.rewind()?;
+                let stream = RbfStream::new(Box::new(reader) as Box<dyn Read>)?;
+                if let Some(header) = stream.header() {
+                    let available = len.saturating_sub(RBF_HEADER_SIZE as u64) as usize;
+                    if available < header.size as usize {
+                        return Err(RbfError::Truncated {
+                            declared: header.size as usize,
+                            available,
+                        });
+                    }
+                }
+                stream
+            }
+            RbfCompression::Gzip => {
+                RbfStream::new(Box::new(flate2::read::GzDecoder::new(reader)) as Box<dyn Read>)?
+            }
+            RbfCompression::Zstd => {
+                RbfStream::new(Box::new(zstd::Decoder::with_buffer(reader)?) as Box<dyn Read>)?
+            }
+            RbfCompression::Zip => {
+                let mut archive = zip::ZipArchive::new(reader)?;
+                let name = archive
+                    .file_names()
+                    .find(|name| name.to_lowercase().ends_with(".rbf"))
+                    .ok_or(RbfError::NotInArchive)?
+                    .to_string();
+                let mut file = archive.by_name(&name)?;
+                if file.size() > (RBF_HEADER_SIZE + RBF_MAX_SIZE) as u64 {
+                    return Err(RbfError::TooLarge(file.size() as usize));
+                }
+
+                let mut bytes = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut bytes)?;
+                Rbf::parse(&bytes)?;
+                RbfStream::new(Box::new(Cursor::new(bytes)) as Box<dyn Read>)?
+            }
+        };
+
+        Ok(Self {
+            compression,
+            stream,
+        })
+    }
+
+    pub fn compression(&self) -> RbfCompression {
+        self.compression
+    }
+
+    pub fn header(&self) -> Option<&RbfHeader> {
+        self.stream.header()
+    }
+}
+
+impl Read for RbfFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Program for RbfFile {
+    fn load(self, fpga: &mut MisterFpga, progress: &mut ProgressToken) -> Result<(), FpgaError> {
+        self.stream.load(fpga, progress)
+    }
+}
+
+#[cfg(test)]
+fn container(program: &[u8], metadata: &[u8]) -> Vec<u8> {
+    let mut bytes = RBF_MAGIC.to_vec();
+    bytes.extend_from_slice(&[0; 6]);
+    bytes.extend_from_slice(&(program.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(program);
+    bytes.extend_from_slice(metadata);
+    bytes
+}
+
+#[test]
+fn parse_rbf() {
+    let program = (0..100u8).collect::<Vec<_>>();
+
+    let bytes = container(&program, b"meta");
+    let rbf = Rbf::parse(&bytes).unwrap();
+    assert_eq!(rbf.header().map(|h| h.size), Some(100));
+    assert_eq!(rbf.program(), program.as_slice());
+    assert_eq!(rbf.metadata(), b"meta");
+
+    let rbf = Rbf::parse(&program).unwrap();
+    assert!(rbf.header().is_none());
+    assert_eq!(rbf.program(), program.as_slice());
+
+    assert!(matches!(Rbf::parse(&[]), Err(RbfError::Empty)));
+    assert!(matches!(
+        Rbf::parse(b"MiSTer\0\0"),
+        Err(RbfError::TruncatedHeader)
+    ));
+    assert!(matches!(
+        Rbf::parse(&bytes[..50]),
+        Err(RbfError::Truncated {
+            declared: 100,
+            available: 34
+        })
+    ));
+
+    let mut too_large = bytes.clone();
+    too_large[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(Rbf::parse(&too_large), Err(RbfError::TooLarge(_))));
+}
+
+#[test]
+fn stream_rbf() {
+    use std::io::Write;
+
+    let program = (0..100u8).collect::<Vec<_>>();
+    let bytes = container(&program, b"meta");
+    fn read(mut stream: RbfStream<impl Read>) -> Vec<u8> {
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).unwrap();
+        out
+    }
+
+    assert_eq!(read(RbfStream::new(bytes.as_slice()).unwrap()), program);
+    assert_eq!(
+        read(RbfStream::new(&program[..10]).unwrap()),
+        &program[..10]
+    );
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&bytes).unwrap();
+    let gzip = gzip.finish().unwrap();
+    assert_eq!(RbfCompression::detect(&gzip), RbfCompression::Gzip);
+    assert_eq!(read(RbfStream::gzip(gzip.as_slice()).unwrap()), program);
+
+    let zstd = zstd::encode_all(bytes.as_slice(), 3).unwrap();
+    assert_eq!(RbfCompression::detect(&zstd), RbfCompression::Zstd);
+    assert_eq!(read(RbfStream::zstd(zstd.as_slice()).unwrap()), program);
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("readme.txt", Default::default()).unwrap();
+    zip.start_file("core_20240101.rbf", Default::default())
+        .unwrap();
+    zip.write_all(&bytes).unwrap();
+    let zip = zip.finish().unwrap().into_inner();
+    let file = RbfFile::from_reader(Cursor::new(zip)).unwrap();
+    assert_eq!(file.compression(), RbfCompression::Zip);
+    assert_eq!(file.header().map(|h| h.size), Some(100));
+
+    let truncated = RbfFile::from_reader(Cursor::new(bytes[..50].to_vec()));
+    assert!(matches!(truncated, Err(RbfError::Truncated { .. })));
+
+    // Raw bitstreams are not silently truncated at the maximum size.
+    let raw = vec![0xFF; RBF_MAX_SIZE + 1];
+    let mut out = Vec::new();
+    let result = RbfStream::new(raw.as_slice())
+        .unwrap()
+        .read_to_end(&mut out);
+    assert!(result.is_err());
+    let raw = &raw[..RBF_MAX_SIZE];
+    assert_eq!(read(RbfStream::new(raw).unwrap()).len(), RBF_MAX_SIZE);
+}
@@ -0,0 +1,195 @@
+//! Detection of the SDRAM module, and whether a core can run without it.
+//! The size of the module is detected by the menu core and kept in a
+//! parameter in memory, which is sent to every core with `SetMemorySize`.
+use crate::fpga::user_io::SetMemorySize;
+use std::fmt;
+
+/// The size of the SDRAM module installed, as detected by the menu core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SdramSize {
+    None,
+    Mb32,
+    Mb64,
+    Mb128,
+}
+
+impl SdramSize {
+    /// Detect the SDRAM module from the parameter left by the menu core.
+    /// Returns `None` if the menu core did not detect it.
+    pub fn detect() -> Option<Self> {
+        SetMemorySize::from_fpga().ok()?.sdram_size()
+    }
+
+    /// The size of the module, in bytes.
+    pub fn bytes(&self) -> usize {
+        match self {
+            SdramSize::None => 0,
+            SdramSize::Mb32 => 32 << 20,
+            SdramSize::Mb64 => 64 << 20,
+            SdramSize::Mb128 => 128 << 20,
+        }
+    }
+
+    pub fn is_installed(&self) -> bool {
+        *self != SdramSize::None
+    }
+}
+
+impl fmt::Display for SdramSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdramSize::None => f.write_str("None"),
+            size => write!(f, "{}MB", size.bytes() >> 20),
+        }
+    }
+}
+
+impl SetMemorySize {
+    /// Whether the parameter was found in memory. The menu core also uses it
+    /// to report the phase of the SDRAM clock while debugging, which isn't
+    /// a size.
+    pub fn is_detected(&self) -> bool {
+        self.0 & 0x8000 != 0 && self.0 & 0x4000 == 0
+    }
+
+    /// The size of the SDRAM module, if it was detected.
+    pub fn sdram_size(&self) -> Option<SdramSize> {
+        if !self.is_detected() {
+            return None;
+        }
+
+        match self.0 & 0x7 {
+            0 => Some(SdramSize::None),
+            1 => Some(SdramSize::Mb32),
+            2 => Some(SdramSize::Mb64),
+            3 => Some(SdramSize::Mb128),
+            _ => None,
+        }
+    }
+}
+
+/// Cores known to need an SDRAM module, by the name in their config string.
+/// Nothing in these config strings tells, so this list is maintained by hand.
+const KNOWN_SDRAM_CORES: &[&str] = &[
+    "C64",
+    "Genesis",
+    "MegaCD",
+    "MegaDrive",
+    "MSX",
+    "N64",
+    "NEOGEO",
+    "NES",
+    "PSX",
+    "S32X",
+    "Saturn",
+    "SNES",
+    "TGFX16",
+    "X68000",
+];
+
+/// Whether a core needs an SDRAM module. Config strings have no field for
+/// this, so it is inferred from the config string, a list of known cores or
+/// the core type.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SdramRequirement {
+    /// Nothing tells whether the core uses SDRAM.
+    #[default]
+    Unknown,
+
+    /// The core can use SDRAM, but lets the user select another memory,
+    /// e.g. `Storage,Auto,SDRAM,DDR3`.
+    Optional,
+
+    /// The core needs an SDRAM module, e.g. SDRAM test cores.
+    Required,
+
+    /// The core needs two SDRAM modules. Only the first one can be detected.
+    Dual,
+}
+
+impl SdramRequirement {
+    /// The requirement of a core known to need SDRAM, from its name.
+    pub fn of_known_core(name: &str) -> Option<Self> {
+        KNOWN_SDRAM_CORES
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(name))
+            .then_some(SdramRequirement::Required)
+    }
+
+    /// Check the requirement against the module installed. Returns a warning
+    /// to show the user if the core might not run, or `None` if it can (or if
+    /// the module could not be detected).
+    pub fn check(&self, installed: Option<SdramSize>) -> Option<String> {
+        match (self, installed) {
+            (SdramRequirement::Required, Some(SdramSize::None)) => {
+                Some("This core requires an SDRAM module, but none was detected.".to_string())
+            }
+            (SdramRequirement::Dual, Some(SdramSize::None)) => {
+                Some("This core requires two SDRAM modules, but none was detected.".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn memory_size() {
+    assert_eq!(SetMemorySize::new(0).sdram_size(), None);
+    assert_eq!(
+        SetMemorySize::new(0x8000).sdram_size(),
+        Some(SdramSize::None)
+    );
+    assert_eq!(
+        SetMemorySize::new(0x8002).sdram_size(),
+        Some(SdramSize::Mb64)
+    );
+    assert_eq!(
+        SetMemorySize::new(0x8003).sdram_size(),
+        Some(SdramSize::Mb128)
+    );
+    assert_eq!(SetMemorySize::new(0xC203).sdram_size(), None);
+    assert_eq!(SdramSize::Mb32.to_string(), "32MB");
+    assert_eq!(SdramSize::Mb128.bytes(), 0x0800_0000);
+}
+
+#[test]
+fn check_requirement() {
+    let none = Some(SdramSize::None);
+    let installed = Some(SdramSize::Mb128);
+
+    assert!(SdramRequirement::Required.check(none).is_some());
+    assert!(SdramRequirement::Dual.check(none).is_some());
+    assert!(SdramRequirement::Required.check(installed).is_none());
+    assert!(SdramRequirement::Required.check(None).is_none());
+    assert!(SdramRequirement::Optional.check(none).is_none());
+    assert!(SdramRequirement::Unknown.check(none).is_none());
+}
+
+#[test]
+fn known_cores() {
+    assert_eq!(
+        SdramRequirement::of_known_core("snes"),
+        Some(SdramRequirement::Required)
+    );
+    assert_eq!(SdramRequirement::of_known_core("Chess"), None);
+}
+
+#[test]
+fn from_memory() {
+    use cyclone_v::memory::{BufferMemoryMapper, MemoryMapper};
+
+    let memory = BufferMemoryMapper::new(0x1000);
+    assert_eq!(
+        SetMemorySize::from_memory(memory).unwrap().sdram_size(),
+        None
+    );
+
+    let mut memory = BufferMemoryMapper::new(0x1000);
+    memory
+        .as_mut_range(0xF00..0xF04)
+        .copy_from_slice(&[0x12, 0x57, 0x00, 0x01]);
+    assert_eq!(
+        SetMemorySize::from_memory(memory).unwrap().sdram_size(),
+        Some(SdramSize::Mb32)
+    );
+}
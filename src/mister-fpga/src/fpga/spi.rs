@@ -4,7 +4,8 @@ use cyclone_v::SocFpga;
 use std::cell::UnsafeCell;
 use std::fmt::Debug;
 use std::sync::Arc;
-use tracing::trace;
+use std::time::{Duration, Instant};
+use tracing::{error, trace};
 
 /// SPI is a 16-bit data bus where the lowest 16 bits are the data and the highest 16-bits
 /// are the control bits.
@@ -15,6 +16,61 @@ const SSPI_STROBE: u32 = 1 << 17;
 /// This signal is received to indicate that the data was read.
 const SSPI_ACK: u32 = 1 << 17;
 
+/// How long to wait for the core to acknowledge a word before giving up. Cores
+/// normally answer within a few cycles, so this only triggers when a core is
+/// hung or not loaded properly.
+pub const SPI_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How many times the ACK bit is polled before checking the clock, so the
+/// common case does not pay for reading the time.
+const SPI_FAST_POLLS: u32 = 1000;
+
+/// An error happening while sending a command to the core.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SpiError {
+    /// The core did not acknowledge a word in time.
+    #[error("The core did not acknowledge word {word:#06X} within {timeout:?}")]
+    Timeout { word: u16, timeout: Duration },
+
+    /// The command itself failed, e.g. the core returned an invalid value.
+    #[error("{0}")]
+    Command(String),
+}
+
+impl SpiError {
+    /// Whether the core stopped responding, as opposed to a command failing.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, SpiError::Timeout { .. })
+    }
+}
+
+impl From<String> for SpiError {
+    fn from(value: String) -> Self {
+        SpiError::Command(value)
+    }
+}
+
+impl From<&str> for SpiError {
+    fn from(value: &str) -> Self {
+        SpiError::Command(value.to_string())
+    }
+}
+
+impl From<SpiError> for String {
+    fn from(value: SpiError) -> Self {
+        value.to_string()
+    }
+}
+
+impl From<SpiError> for one_fpga::core::Error {
+    fn from(value: SpiError) -> Self {
+        match value {
+            SpiError::Timeout { .. } => Self::CoreNotResponding(value.to_string()),
+            SpiError::Command(message) => Self::Message(message),
+        }
+    }
+}
+
 pub mod feature;
 pub mod file_io;
 pub mod osd_io;
@@ -39,6 +95,9 @@ pub trait SpiCommandExt: Sized {
     fn write_b(&mut self, byte: u8) -> &mut Self;
     fn enable(&mut self, feature: SpiFeatureSet) -> &mut Self;
     fn disable(&mut self, feature: SpiFeatureSet) -> &mut Self;
+
+    /// Take the timeout that happened during the current command, if any.
+    fn take_error(&mut self) -> Option<SpiError>;
 }
 
 pub trait IntoLowLevelSpiCommand {
@@ -46,7 +105,7 @@ pub trait IntoLowLevelSpiCommand {
 }
 
 pub trait SpiCommand {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String>;
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError>;
 }
 
 pub struct SpiCommandGuard<'a, S: SpiCommandExt> {
@@ -60,8 +119,14 @@ impl<'a, S: SpiCommandExt> SpiCommandGuard<'a, S> {
         Self { spi, feature }
     }
 
-    pub fn execute(&mut self, mut command: impl SpiCommand) -> Result<(), String> {
-        command.execute(self.spi)
+    /// Execute a command within this one. A timeout during the command is
+    /// returned, like [`Spi::execute`] does.
+    pub fn execute(&mut self, mut command: impl SpiCommand) -> Result<(), SpiError> {
+        let result = command.execute(self.spi);
+        match self.spi.take_error() {
+            Some(error) => Err(error),
+            None => result,
+        }
     }
 
     #[inline]
@@ -206,6 +271,10 @@ pub struct Spi<M: MemoryMapper> {
     // disable (error). We only actually enable if the refcount is 0, and disable
     // if the refcount is 1.
     features: fixed_map::Map<SpiFeature, u32>,
+
+    // The timeout that happened during the current command, if any. Once set,
+    // writes return immediately until the command is done.
+    error: Option<SpiError>,
 }
 unsafe impl<M: MemoryMapper> Send for Spi<M> {}
 unsafe impl<M: MemoryMapper> Sync for Spi<M> {}
@@ -216,6 +285,7 @@ impl<M: MemoryMapper> Clone for Spi<M> {
         Self {
             soc: self.soc.clone(),
             features: self.features,
+            error: None,
         }
     }
 }
@@ -225,6 +295,7 @@ impl<M: MemoryMapper> Spi<M> {
         Self {
             soc,
             features: Default::default(),
+            error: None,
         }
     }
 
@@ -233,9 +304,23 @@ impl<M: MemoryMapper> Spi<M> {
         unsafe { &mut *self.soc.get() }
     }
 
+    /// Execute a command. If the core stops acknowledging words during the
+    /// command, the rest of it is skipped and a [`SpiError::Timeout`] is returned.
     #[inline]
-    pub fn execute(&mut self, mut command: impl SpiCommand) -> Result<(), String> {
-        command.execute(self)
+    pub fn execute(&mut self, mut command: impl SpiCommand) -> Result<(), SpiError> {
+        self.error = None;
+        let result = command.execute(self);
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => result,
+        }
+    }
+
+    /// Take the timeout that happened since the last command, if any. This
+    /// is only needed when using [`Spi::write`] directly.
+    #[inline]
+    pub fn take_error(&mut self) -> Option<SpiError> {
+        self.error.take()
     }
 
     #[inline]
@@ -293,11 +378,10 @@ impl<M: MemoryMapper> Spi<M> {
     /// Gets the config string of the core.
     /// This method should only be called once per core, ideally when the core boot up.
     #[inline]
-    pub fn config_string(&mut self) -> String {
+    pub fn config_string(&mut self) -> Result<String, SpiError> {
         let mut str_builder = String::with_capacity(10240);
-        self.execute(user_io::UserIoGetString(&mut str_builder))
-            .unwrap();
-        str_builder
+        self.execute(user_io::UserIoGetString(&mut str_builder))?;
+        Ok(str_builder)
     }
 
     #[inline]
@@ -332,9 +416,39 @@ impl<M: MemoryMapper> Spi<M> {
         SpiCommandGuard::new(self, feature)
     }
 
+    /// Wait for the ACK bit to be set (or unset). Returns the last GPI value, or
+    /// `None` if the core did not answer within [`SPI_TIMEOUT`].
+    #[inline]
+    fn wait_for_ack(&mut self, set: bool) -> Option<u32> {
+        let regs = self.soc_mut().regs_mut();
+        let mut start = None;
+        let mut polls = 0;
+        loop {
+            let gpi = regs.gpi();
+            if (gpi & SSPI_ACK != 0) == set {
+                return Some(gpi);
+            }
+
+            polls += 1;
+            if polls >= SPI_FAST_POLLS {
+                polls = 0;
+                let start = start.get_or_insert_with(Instant::now);
+                if start.elapsed() >= SPI_TIMEOUT {
+                    return None;
+                }
+            }
+        }
+    }
+
     /// Send a 16-bit word to the core. Returns the 16-bit word received from the core.
+    /// If the core does not answer, the error is kept until the end of the command
+    /// and 0 is returned.
     #[inline]
     pub fn write(&mut self, word: u16) -> u16 {
+        if self.error.is_some() {
+            return 0;
+        }
+
         let regs = self.soc_mut().regs_mut();
 
         // Remove the strobe bit and set the data bits.
@@ -344,19 +458,23 @@ impl<M: MemoryMapper> Spi<M> {
         regs.set_gpo(gpo | SSPI_STROBE);
 
         // Wait for the ACK bit to be unset to give time to the core to get some work.
-        loop {
-            let gpi = regs.gpi();
-            if gpi & SSPI_ACK != 0 {
-                break;
-            }
-        }
-
         // Send the actual data without the strobe, then wait for the core to get done.
-        regs.set_gpo(gpo);
-        loop {
-            let gpi = regs.gpi();
-            if gpi & SSPI_ACK == 0 {
-                break gpi as u16;
+        let gpi = self.wait_for_ack(true).and_then(|_| {
+            self.soc_mut().regs_mut().set_gpo(gpo);
+            self.wait_for_ack(false)
+        });
+
+        match gpi {
+            Some(gpi) => gpi as u16,
+            None => {
+                // Leave the bus in a known state.
+                self.soc_mut().regs_mut().set_gpo(gpo);
+                error!(word, "SPI timeout, the core is not responding");
+                self.error = Some(SpiError::Timeout {
+                    word,
+                    timeout: SPI_TIMEOUT,
+                });
+                0
             }
         }
     }
@@ -480,6 +598,11 @@ impl<M: MemoryMapper> SpiCommandExt for Spi<M> {
         self.disable(feature);
         self
     }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<SpiError> {
+        self.take_error()
+    }
 }
 
 #[test]
@@ -494,3 +617,42 @@ pub fn features_refcount() {
 
     assert_eq!(spi.features.get(SpiFeature::Fpga), Some(&1));
 }
+
+#[test]
+pub fn write_timeout() {
+    let soc = SocFpga::create_for_test();
+    let mut spi = Spi::new(Arc::new(UnsafeCell::new(soc)));
+
+    // Nothing acknowledges the strobe on the test memory.
+    let mut status = crate::types::StatusBitMap::default();
+    let error = spi.execute(user_io::SetStatusBits(&status)).unwrap_err();
+    assert!(error.is_timeout());
+    assert!(matches!(
+        one_fpga::core::Error::from(error),
+        one_fpga::core::Error::CoreNotResponding(_)
+    ));
+
+    // The error does not leak into the next command.
+    status.set(0, true);
+    assert!(spi
+        .execute(user_io::SetStatusBits(&status))
+        .unwrap_err()
+        .is_timeout());
+    assert_eq!(spi.take_error(), None);
+}
+
+#[test]
+pub fn guarded_command_timeout() {
+    let soc = SocFpga::create_for_test();
+    let mut spi = Spi::new(Arc::new(UnsafeCell::new(soc)));
+
+    // A timeout within a command is returned, and not left for later writes.
+    let status = crate::types::StatusBitMap::default();
+    let mut command = spi.command(user_io::UserIoCommands::UserIoGetStatusBits);
+    assert!(command
+        .execute(user_io::SetStatusBits(&status))
+        .unwrap_err()
+        .is_timeout());
+    drop(command);
+    assert_eq!(spi.take_error(), None);
+}
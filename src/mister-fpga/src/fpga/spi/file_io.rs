@@ -1,5 +1,5 @@
 use crate::fpga::feature::SpiFeatureSet;
-use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt};
+use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt, SpiError};
 
 #[derive(Debug, Clone, Copy, PartialEq, strum::Display)]
 #[repr(u16)]
@@ -21,7 +21,7 @@ pub struct FileIndex(u8);
 
 impl SpiCommand for FileIndex {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(Commands::FileIndex).write_b(self.0);
 
         Ok(())
@@ -47,7 +47,7 @@ pub struct FileExtension<'a>(pub &'a str);
 
 impl SpiCommand for FileExtension<'_> {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let ext_bytes = self.0.as_bytes();
         // Extend to 4 characters with the dot.
         let ext: [u8; 4] = [
@@ -70,7 +70,7 @@ pub struct FileTxEnabled(pub Option<u32>);
 
 impl SpiCommand for FileTxEnabled {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(Commands::FileTx);
         command.write_b(0xff);
 
@@ -87,7 +87,7 @@ pub struct FileTxDisabled;
 
 impl SpiCommand for FileTxDisabled {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(Commands::FileTx).write_b(0);
 
         Ok(())
@@ -99,7 +99,7 @@ pub struct FileTxData8Bits<'a>(pub &'a [u8]);
 
 impl SpiCommand for FileTxData8Bits<'_> {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(Commands::FileTxDat).write_buffer_b(self.0);
         Ok(())
     }
@@ -110,7 +110,7 @@ pub struct FileTxData16Bits<'a>(pub &'a [u16]);
 
 impl SpiCommand for FileTxData16Bits<'_> {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(Commands::FileTxDat).write_buffer_w(self.0);
         Ok(())
     }
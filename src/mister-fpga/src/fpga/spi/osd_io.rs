@@ -1,5 +1,5 @@
 use crate::fpga::feature::SpiFeatureSet;
-use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt};
+use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt, SpiError};
 
 /// OSD SPI commands.
 #[derive(Debug, Clone, Copy, PartialEq, strum::Display)]
@@ -34,7 +34,7 @@ pub struct OsdIoWriteLine<'a>(pub u8, pub &'a [u8]);
 
 impl SpiCommand for OsdIoWriteLine<'_> {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(OsdCommands::WriteLine(self.0))
             .write_buffer_b(self.1);
 
@@ -45,7 +45,7 @@ impl SpiCommand for OsdIoWriteLine<'_> {
 pub struct OsdEnable;
 
 impl SpiCommand for OsdEnable {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(OsdCommands::Enable);
         Ok(())
     }
@@ -54,7 +54,7 @@ impl SpiCommand for OsdEnable {
 pub struct OsdDisable;
 
 impl SpiCommand for OsdDisable {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(OsdCommands::Disable);
         Ok(())
     }
@@ -1,8 +1,9 @@
 use crate::config::edid::CustomVideoMode;
+use crate::config::filter::{PolyphaseFilter, ShadowMask, ShadowMaskMode};
 use crate::core::buttons::ButtonMap;
 use crate::core::file::SdCard;
 use crate::fpga::feature::SpiFeatureSet;
-use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt};
+use crate::fpga::{IntoLowLevelSpiCommand, SpiCommand, SpiCommandExt, SpiError};
 use crate::keyboard::Ps2Scancode;
 use crate::types::StatusBitMap;
 use bitfield::bitfield;
@@ -74,6 +75,12 @@ pub(crate) enum UserIoCommands {
     /// Digital volume as a number of bits to shift to the right
     UserIoAudioVolume = 0x26,
 
+    /// Set the scaler polyphase filter coefficients.
+    UserIoSetFilterCoefficients = 0x2A,
+
+    /// Enable or disable the custom scaler filter.
+    UserIoSetFilter = 0x2B,
+
     UserIoGetStatusBits = 0x29,
 
     /// Set frame buffer for HPS output
@@ -84,11 +91,20 @@ pub(crate) enum UserIoCommands {
     /// Enable/disable Gamma correction
     UserIoSetGamma = 0x32,
 
+    /// Set the gamma curve.
+    UserIoSetGammaCurve = 0x33,
+
     /// Get the info line from the core to show.
     UserIoGetInfo = 0x36,
 
+    /// Set the line of the core's frame that starts the output frame.
+    UserIoSetSync = 0x38,
+
     UserIoSetArCust = 0x3A,
 
+    /// Set the shadow mask.
+    UserIoShadowMask = 0x3E,
+
     UserIoGetFbParams = 0x40,
 }
 
@@ -141,7 +157,7 @@ impl UserIoButtonSwitch {
 
 impl SpiCommand for UserIoButtonSwitch {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoButtonSwitch)
             .write(self.0);
         Ok(())
@@ -173,7 +189,7 @@ pub struct UserIoJoystick(u8, u32);
 
 impl SpiCommand for UserIoJoystick {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let command = match self.0 {
             0 => UserIoCommands::UserIoJoystick0,
             1 => UserIoCommands::UserIoJoystick1,
@@ -219,7 +235,7 @@ impl From<u32> for UserIoKeyboardKeyDown {
 
 impl SpiCommand for UserIoKeyboardKeyDown {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoKeyboard)
             .write_cond_b(self.0 & 0x080000 != 0, 0xE0)
             .write_b((self.0 & 0xFF) as u8);
@@ -244,7 +260,7 @@ impl From<u32> for UserIoKeyboardKeyUp {
 
 impl SpiCommand for UserIoKeyboardKeyUp {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoKeyboard)
             .write_b(0xF0)
             .write_b(self.0 as u8);
@@ -257,7 +273,7 @@ pub struct UserIoGetString<'a>(pub &'a mut String);
 
 impl SpiCommand for UserIoGetString<'_> {
     #[inline]
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoCommands::UserIoGetString);
 
         let mut i = 0;
@@ -289,7 +305,7 @@ impl From<SystemTime> for UserIoRtc {
 }
 
 impl SpiCommand for UserIoRtc {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         // MSM6242B layout, with 4 bits per digit of sec, min, hour, day, month, year (2 digits),
         // and the weekday.
         let rtc = [
@@ -331,7 +347,7 @@ impl From<SystemTime> for Timestamp {
 }
 
 impl SpiCommand for Timestamp {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let timestamp = self.0.and_utc().timestamp();
         spi.command(UserIoCommands::UserIoRtc)
             .write(timestamp as u16)
@@ -345,7 +361,7 @@ impl SpiCommand for Timestamp {
 pub struct GetStatusBits<'a>(pub &'a mut StatusBitMap, pub &'a mut u8);
 
 impl SpiCommand for GetStatusBits<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut stchg = 0;
         let mut command = spi.command_read(UserIoCommands::UserIoGetStatusBits, &mut stchg);
 
@@ -369,7 +385,7 @@ impl SpiCommand for GetStatusBits<'_> {
 pub struct SetStatusBits<'a>(pub &'a StatusBitMap);
 
 impl SpiCommand for SetStatusBits<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let bits16 = self.0.as_raw_slice();
 
         spi.command(UserIoCommands::UserIoSetStatus32Bits)
@@ -394,7 +410,7 @@ pub struct SetSdConf {
 }
 
 impl SpiCommand for SetSdConf {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoCommands::UserIoSetSdConf);
 
         if self.wide {
@@ -460,7 +476,7 @@ impl From<&SdCard> for SetSdInfo {
 }
 
 impl SpiCommand for SetSdInfo {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoCommands::UserIoSetSdInfo);
 
         trace!(?self, "SetSdInfo");
@@ -504,7 +520,7 @@ pub struct SetSdStat {
 }
 
 impl SpiCommand for SetSdStat {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoSetSdStat)
             .write_b((1 << self.index) | if self.writable { 0 } else { 0x80 });
         Ok(())
@@ -613,7 +629,7 @@ pub struct SdStatOutput {
 pub struct GetSdStat<'a>(pub &'a mut SdStatOutput);
 
 impl SpiCommand for GetSdStat<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut c = 0;
         let mut command = spi.command_read(UserIoCommands::UserIoGetSdStat, &mut c);
 
@@ -656,7 +672,7 @@ impl SpiCommand for GetSdStat<'_> {
             } else if c & 0x2400 != 0 {
                 (3, SdOp::from(c & 0x0400 == 0))
             } else {
-                return Err(format!("Invalid status: {:04X}", c));
+                return Err(format!("Invalid status: {:04X}", c).into());
             };
 
             self.0.ack = if c & 4 != 0 {
@@ -679,7 +695,7 @@ pub struct SdRead<'a> {
 }
 
 impl SpiCommand for SdRead<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoSectorRead::Read(self.ack));
 
         if self.wide {
@@ -704,7 +720,7 @@ pub struct SdWrite<'a> {
 }
 
 impl SpiCommand for SdWrite<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoSectorRead::Write(self.ack));
 
         if self.wide {
@@ -726,7 +742,7 @@ impl<'a> SdWrite<'a> {
 pub struct SetMemorySize(pub u16);
 
 impl SpiCommand for SetMemorySize {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoSetMemSz).write(self.0);
         Ok(())
     }
@@ -758,7 +774,7 @@ impl SetMemorySize {
 pub struct SetFramebufferToCore;
 
 impl SpiCommand for SetFramebufferToCore {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         debug!("Setting framebuffer to core");
         spi.command(UserIoCommands::UserIoSetFramebuffer).write(0);
         Ok(())
@@ -777,7 +793,7 @@ pub struct SetFramebufferToLinux {
 }
 
 impl SpiCommand for SetFramebufferToLinux {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         debug!("Setting framebuffer to Linux: {:?}", self);
 
         let mut out = 0;
@@ -812,7 +828,7 @@ impl SpiCommand for SetFramebufferToLinux {
 pub struct IsGammaSupported<'a>(pub &'a mut bool);
 
 impl SpiCommand for IsGammaSupported<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut out = 0;
         spi.command_read(UserIoCommands::UserIoSetGamma, &mut out);
         *self.0 = out != 0;
@@ -823,23 +839,90 @@ impl SpiCommand for IsGammaSupported<'_> {
 pub struct DisableGamma;
 
 impl SpiCommand for DisableGamma {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoSetGamma).write_b(0);
         Ok(())
     }
 }
 
+/// Upload a gamma curve to the core and enable it.
 pub struct EnableGamma<'a>(pub &'a [(u8, u8, u8)]);
 
 impl SpiCommand for EnableGamma<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
-        let mut command = spi.command(UserIoCommands::UserIoSetGamma);
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
+        {
+            let mut command = spi.command(UserIoCommands::UserIoSetGammaCurve);
+
+            for (i, (r, g, b)) in self.0.iter().enumerate() {
+                command
+                    .write(((i as u16) << 8) | *r as u16)
+                    .write(((i as u16) << 8) | *g as u16)
+                    .write(((i as u16) << 8) | *b as u16);
+            }
+        }
 
-        for (i, (r, g, b)) in self.0.iter().enumerate() {
-            command
-                .write(((i as u16) << 8) | *r as u16)
-                .write(((i as u16) << 8) | *g as u16)
-                .write(((i as u16) << 8) | *b as u16);
+        spi.command(UserIoCommands::UserIoSetGamma).write_b(1);
+        Ok(())
+    }
+}
+
+/// Upload a polyphase filter to the scaler, or revert to its default filter.
+pub struct SetScalerFilter<'a>(pub Option<&'a PolyphaseFilter>);
+
+impl SpiCommand for SetScalerFilter<'_> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
+        if let Some(filter) = self.0 {
+            let mut command = spi.command(UserIoCommands::UserIoSetFilterCoefficients);
+
+            // Horizontal coefficients are at addresses 0-63, vertical at 64-127.
+            let coefficients = filter
+                .horizontal
+                .iter()
+                .chain(filter.vertical.iter())
+                .flatten();
+            for (address, c) in coefficients.enumerate() {
+                command.write(((address as u16) << 9) | (*c as u16 & 0x1FF));
+            }
+        }
+
+        spi.command(UserIoCommands::UserIoSetFilter)
+            .write(self.0.is_some() as u16);
+        Ok(())
+    }
+}
+
+/// Set the shadow mask, or disable it.
+pub struct SetShadowMask<'a>(pub Option<&'a ShadowMask>, pub ShadowMaskMode);
+
+impl SpiCommand for SetShadowMask<'_> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
+        // The top 3 bits of every word select what is being set, with 0
+        // being the flags.
+        const SM_HMAX: u16 = 1 << 13;
+        const SM_VMAX: u16 = 2 << 13;
+        const SM_LUT: u16 = 3 << 13;
+        const SM_FLAG_ENABLED: u16 = 1;
+        const SM_FLAG_2X: u16 = 2;
+
+        let mut command = spi.command(UserIoCommands::UserIoShadowMask);
+        let (mask, mode) = match (self.0, self.1) {
+            (Some(mask), mode) if mode != ShadowMaskMode::None => (mask, mode),
+            _ => {
+                command.write(0);
+                return Ok(());
+            }
+        };
+
+        let flags = match mode {
+            ShadowMaskMode::Enabled2x => SM_FLAG_ENABLED | SM_FLAG_2X,
+            _ => SM_FLAG_ENABLED,
+        };
+        command
+            .write(flags)
+            .write(SM_HMAX | (mask.width as u16 - 1))
+            .write(SM_VMAX | (mask.height as u16 - 1));
+        for value in mask.data.iter() {
+            command.write(SM_LUT | (*value & 0xFFF));
         }
 
         Ok(())
@@ -861,7 +944,7 @@ impl From<(u16, u16)> for SetCustomAspectRatio {
 }
 
 impl SpiCommand for SetCustomAspectRatio {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoCommands::UserIoSetArCust);
 
         command
@@ -877,7 +960,7 @@ impl SpiCommand for SetCustomAspectRatio {
 pub struct SetVideoMode<'a>(pub &'a CustomVideoMode);
 
 impl SpiCommand for SetVideoMode<'_> {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         let mut command = spi.command(UserIoCommands::UserIoSetVideo);
         let m = self.0;
         let p = m.param;
@@ -911,11 +994,22 @@ impl SpiCommand for SetVideoMode<'_> {
     }
 }
 
+/// Set the line of the core's frame at which the scaler starts its output
+/// frame, using a single buffer for low latency. Zero uses triple buffering.
+pub struct SetVsyncLine(pub u16);
+
+impl SpiCommand for SetVsyncLine {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
+        spi.command(UserIoCommands::UserIoSetSync).write(self.0);
+        Ok(())
+    }
+}
+
 /// Set the audio volume as the number of bits to shift to the right.
 pub struct SetAudioVolume(pub u8);
 
 impl SpiCommand for SetAudioVolume {
-    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), String> {
+    fn execute<S: SpiCommandExt>(&mut self, spi: &mut S) -> Result<(), SpiError> {
         spi.command(UserIoCommands::UserIoAudioVolume)
             .write_b(self.0);
         Ok(())
@@ -4,7 +4,7 @@
 //! The OsdDisplay does not implement any Drawable, instead relying on the `send`
 //! method to send the data to the FPGA itself. It does not keep any internal
 //! buffers, and is light weigh.
-use crate::fpga::{osd_io, MisterFpga};
+use crate::fpga::{osd_io, MisterFpga, SpiError};
 use embedded_graphics::image::GetPixel;
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::*;
@@ -53,16 +53,16 @@ impl OsdDisplay {
         &self,
         fpga: &mut MisterFpga,
         buffer: &B,
-    ) {
+    ) -> Result<(), SpiError> {
         let size = buffer.size();
         // Send everything to the scaler. We could optimize by only sending differences,
         // but it isn't needed and would add complexity.
         for line in self.line_iter() {
             let line_buffer = self.get_binary_line_array(buffer, line, size);
             fpga.spi_mut()
-                .execute(osd_io::OsdIoWriteLine(line as u8, &line_buffer))
-                .unwrap();
+                .execute(osd_io::OsdIoWriteLine(line as u8, &line_buffer))?;
         }
+        Ok(())
     }
 
     fn with_offset(self, offset_y: usize) -> Self {
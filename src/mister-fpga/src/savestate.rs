@@ -5,6 +5,10 @@ use std::io::{Read, Write};
 use std::ptr::NonNull;
 use std::slice;
 
+pub mod file;
+pub mod rewind;
+
+/// Number of slots used when the core does not have a savestate slot option.
 const DEFAULT_MISTER_SAVESTATE_SLOTS: u32 = 4;
 
 pub struct SaveStateManager<M: MemoryMapper> {
@@ -15,6 +19,9 @@ pub struct SaveStateManager<M: MemoryMapper> {
     /// The number of savestate slots.
     nb_slots: u32,
 
+    /// The size of a single slot, in bytes.
+    slot_size: usize,
+
     /// The savestate slots.
     slots: Vec<SaveState>,
 }
@@ -22,7 +29,13 @@ pub struct SaveStateManager<M: MemoryMapper> {
 impl SaveStateManager<DevMemMemoryMapper> {
     pub fn from_config_string(config: &Config) -> Option<Self> {
         let (base, size) = config.settings().save_state?;
-        let nb_slots = DEFAULT_MISTER_SAVESTATE_SLOTS;
+
+        // Cores that support more than one slot have an option to select it.
+        let nb_slots = config
+            .savestate_slot_option()
+            .map(|(_, choices)| choices.len() as u32)
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_MISTER_SAVESTATE_SLOTS);
 
         // The memory setup is:
         //   0x00: u32 change detector.     A value that changes when the savestate changes.
@@ -35,12 +48,13 @@ impl SaveStateManager<DevMemMemoryMapper> {
         let slots = (0..nb_slots)
             .map(|i| {
                 let offset = (i as usize) * size;
-                SaveState::from_base(&mut memory, offset)
+                SaveState::from_base(&mut memory, offset, size)
             })
             .collect();
 
         Some(Self {
             nb_slots,
+            slot_size: size,
             _memory: memory,
             slots,
         })
@@ -62,6 +76,12 @@ impl<M: MemoryMapper> SaveStateManager<M> {
     pub fn nb_slots(&self) -> usize {
         self.nb_slots as usize
     }
+
+    /// The maximum size of a savestate in a slot, in bytes.
+    #[inline]
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
 }
 
 #[repr(C)]
@@ -89,13 +109,6 @@ impl SaveStateInner {
         }
     }
 
-    fn all_mut(&mut self) -> &mut [u8] {
-        unsafe {
-            let s = std::ptr::addr_of!(*self) as *mut u8;
-            slice::from_raw_parts_mut(s, self.size_adjusted())
-        }
-    }
-
     fn reset(&mut self) {
         self.counter = 0xFFFFFFFF
     }
@@ -107,6 +120,9 @@ pub struct SaveState {
 
     /// The last counter known, used to detect any changes to the savestate data.
     counter: u32,
+
+    /// The size of the memory available for this savestate, in bytes.
+    capacity: usize,
 }
 
 impl one_fpga::core::SaveState for SaveState {
@@ -121,7 +137,29 @@ impl one_fpga::core::SaveState for SaveState {
     }
 
     fn load(&mut self, reader: &mut dyn Read) -> Result<(), Error> {
-        reader.read_exact(self.inner_mut().all_mut())?;
+        // Validate the size in the header before writing anything to memory.
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let total = size
+            .checked_add(2)
+            .and_then(|s| s.checked_mul(4))
+            .filter(|s| *s <= self.capacity)
+            .ok_or_else(|| {
+                Error::Message(format!(
+                    "Savestate ({} words) does not fit in the slot ({} bytes).",
+                    size, self.capacity
+                ))
+            })?;
+
+        let mut data = vec![0; total];
+        data[..8].copy_from_slice(&header);
+        reader.read_exact(&mut data[8..])?;
+
+        unsafe {
+            let memory = slice::from_raw_parts_mut(self.inner.as_ptr() as *mut u8, total);
+            memory.copy_from_slice(&data);
+        }
         self.inner_mut().reset();
         self.counter = self.inner().counter;
         Ok(())
@@ -129,12 +167,13 @@ impl one_fpga::core::SaveState for SaveState {
 }
 
 impl SaveState {
-    fn from_base(memory: &mut impl MemoryMapper, offset: usize) -> Self {
+    fn from_base(memory: &mut impl MemoryMapper, offset: usize, capacity: usize) -> Self {
         let inner = unsafe { NonNull::new(memory.as_mut_ptr::<u8>().add(offset) as _).unwrap() };
 
         Self {
             inner,
             counter: unsafe { inner.as_ref() }.counter,
+            capacity,
         }
     }
 
@@ -0,0 +1,318 @@
+//! On-disk format for save states.
+//!
+//! All integers are little endian. The layout is:
+//!
+//! ```text
+//!   magic         [u8; 4]     "OFSS"
+//!   version       u16         Format version, currently 1.
+//!   core_name     str         Name of the core the state was made with.
+//!   core_version  str         Version of the core (empty if unknown).
+//!   game_crc      u32         CRC32 of the game ROM (0 if unknown).
+//!   timestamp     u64         Seconds since UNIX epoch.
+//!   thumbnail     bytes       PNG thumbnail (empty if none).
+//!   data_size     u32         Size of the uncompressed save state.
+//!   data          bytes       Zstd-compressed save state.
+//! ```
+//!
+//! Where `str` and `bytes` are prefixed by their length as a u32.
+use image::DynamicImage;
+use std::io::{Cursor, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 4] = b"OFSS";
+const FORMAT_VERSION: u16 = 1;
+
+/// Maximum size of a savestate (and any field in the file). This is the
+/// maximum size of a savestate slot.
+const MAX_FIELD_SIZE: usize = 128 * 1024 * 1024;
+
+/// Maximum dimension of the thumbnail.
+const THUMBNAIL_SIZE: u32 = 320;
+
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveStateFileError {
+    #[error("Not a savestate file")]
+    InvalidMagic,
+
+    #[error("Unsupported savestate format version {0}")]
+    UnsupportedVersion(u16),
+
+    #[error("Savestate was made with core {found:?} but {expected:?} is running")]
+    CoreMismatch { expected: String, found: String },
+
+    #[error("Savestate was made with version {found:?} of the core but {expected:?} is running")]
+    CoreVersionMismatch {
+        expected: Option<String>,
+        found: Option<String>,
+    },
+
+    #[error("Savestate is {size} bytes, larger than the {max} bytes slot")]
+    TooLarge { size: usize, max: usize },
+
+    #[error("Invalid savestate file: {0}")]
+    Invalid(&'static str),
+
+    #[error("Could not encode thumbnail: {0}")]
+    Image(#[from] image::ImageError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl From<SaveStateFileError> for one_fpga::core::Error {
+    fn from(value: SaveStateFileError) -> Self {
+        one_fpga::core::Error::Message(value.to_string())
+    }
+}
+
+/// Information about a save state file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveStateHeader {
+    pub core_name: String,
+    pub core_version: Option<String>,
+    pub game_crc: Option<u32>,
+    pub timestamp: SystemTime,
+}
+
+/// A save state with its metadata, as saved on disk.
+#[derive(Debug, Clone)]
+pub struct SaveStateFile {
+    header: SaveStateHeader,
+    thumbnail: Option<Vec<u8>>,
+    data: Vec<u8>,
+}
+
+fn write_u16(w: &mut impl Write, v: u16) -> std::io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_u32(w: &mut impl Write, v: u32) -> std::io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_bytes(w: &mut impl Write, v: &[u8]) -> std::io::Result<()> {
+    write_u32(w, v.len() as u32)?;
+    w.write_all(v)
+}
+
+fn read_u16(r: &mut impl Read) -> std::io::Result<u16> {
+    let mut buffer = [0; 2];
+    r.read_exact(&mut buffer)?;
+    Ok(u16::from_le_bytes(buffer))
+}
+
+fn read_u32(r: &mut impl Read) -> std::io::Result<u32> {
+    let mut buffer = [0; 4];
+    r.read_exact(&mut buffer)?;
+    Ok(u32::from_le_bytes(buffer))
+}
+
+fn read_u64(r: &mut impl Read) -> std::io::Result<u64> {
+    let mut buffer = [0; 8];
+    r.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+fn read_bytes(r: &mut impl Read) -> Result<Vec<u8>, SaveStateFileError> {
+    let len = read_u32(r)? as usize;
+    if len > MAX_FIELD_SIZE {
+        return Err(SaveStateFileError::Invalid("field too large"));
+    }
+
+    let mut buffer = vec![0; len];
+    r.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn read_string(r: &mut impl Read) -> Result<String, SaveStateFileError> {
+    String::from_utf8(read_bytes(r)?).map_err(|_| SaveStateFileError::Invalid("invalid string"))
+}
+
+impl SaveStateFile {
+    /// Whether the data starts like a save state file. Anything else is a raw
+    /// save state as found in the core memory.
+    pub fn is_save_state_file(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    pub fn new(
+        core_name: impl ToString,
+        core_version: Option<impl ToString>,
+        game_crc: Option<u32>,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            header: SaveStateHeader {
+                core_name: core_name.to_string(),
+                core_version: core_version.map(|v| v.to_string()),
+                game_crc,
+                timestamp: SystemTime::now(),
+            },
+            thumbnail: None,
+            data,
+        }
+    }
+
+    /// Add a thumbnail to the file, from a screenshot.
+    pub fn with_thumbnail(mut self, screenshot: &DynamicImage) -> Result<Self, SaveStateFileError> {
+        let mut png = Vec::new();
+        screenshot
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+        self.thumbnail = Some(png);
+        Ok(self)
+    }
+
+    pub fn header(&self) -> &SaveStateHeader {
+        &self.header
+    }
+
+    /// The thumbnail, decoded.
+    pub fn thumbnail(&self) -> Option<DynamicImage> {
+        self.thumbnail
+            .as_ref()
+            .and_then(|png| image::load_from_memory_with_format(png, image::ImageFormat::Png).ok())
+    }
+
+    /// The uncompressed save state.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Check that this save state can be loaded in a core, returning a clear
+    /// error if it cannot.
+    pub fn validate(
+        &self,
+        core_name: &str,
+        core_version: Option<&str>,
+        max_size: usize,
+    ) -> Result<(), SaveStateFileError> {
+        if self.header.core_name != core_name {
+            return Err(SaveStateFileError::CoreMismatch {
+                expected: core_name.to_string(),
+                found: self.header.core_name.clone(),
+            });
+        }
+        if self.header.core_version.as_deref() != core_version {
+            return Err(SaveStateFileError::CoreVersionMismatch {
+                expected: core_version.map(str::to_string),
+                found: self.header.core_version.clone(),
+            });
+        }
+        if self.data.len() > max_size {
+            return Err(SaveStateFileError::TooLarge {
+                size: self.data.len(),
+                max: max_size,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> Result<(), SaveStateFileError> {
+        let timestamp = self
+            .header
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        w.write_all(MAGIC)?;
+        write_u16(w, FORMAT_VERSION)?;
+        write_bytes(w, self.header.core_name.as_bytes())?;
+        write_bytes(
+            w,
+            self.header.core_version.as_deref().unwrap_or("").as_bytes(),
+        )?;
+        write_u32(w, self.header.game_crc.unwrap_or(0))?;
+        w.write_all(&timestamp.to_le_bytes())?;
+        write_bytes(w, self.thumbnail.as_deref().unwrap_or(&[]))?;
+        write_u32(w, self.data.len() as u32)?;
+        write_bytes(w, &zstd::encode_all(self.data.as_slice(), ZSTD_LEVEL)?)?;
+        Ok(())
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, SaveStateFileError> {
+        let mut out = Vec::new();
+        self.write(&mut out)?;
+        Ok(out)
+    }
+
+    pub fn read(r: &mut impl Read) -> Result<Self, SaveStateFileError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SaveStateFileError::InvalidMagic);
+        }
+
+        let version = read_u16(r)?;
+        if version != FORMAT_VERSION {
+            return Err(SaveStateFileError::UnsupportedVersion(version));
+        }
+
+        let core_name = read_string(r)?;
+        let core_version = Some(read_string(r)?).filter(|v| !v.is_empty());
+        let game_crc = Some(read_u32(r)?).filter(|crc| *crc != 0);
+        let timestamp = UNIX_EPOCH + Duration::from_secs(read_u64(r)?);
+        let thumbnail = Some(read_bytes(r)?).filter(|t| !t.is_empty());
+
+        let size = read_u32(r)? as usize;
+        if size > MAX_FIELD_SIZE {
+            return Err(SaveStateFileError::Invalid("savestate too large"));
+        }
+        let compressed = read_bytes(r)?;
+        let mut data = Vec::with_capacity(size);
+        zstd::Decoder::new(compressed.as_slice())?
+            .take(size as u64)
+            .read_to_end(&mut data)?;
+        if data.len() != size {
+            return Err(SaveStateFileError::Invalid("savestate size mismatch"));
+        }
+
+        Ok(Self {
+            header: SaveStateHeader {
+                core_name,
+                core_version,
+                game_crc,
+                timestamp,
+            },
+            thumbnail,
+            data,
+        })
+    }
+}
+
+#[test]
+fn round_trip() {
+    let data = (0..4096u32).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+    let file = SaveStateFile::new("NES", Some("v123456"), Some(0x1234), data.clone());
+    let bytes = file.to_vec().unwrap();
+    assert!(bytes.len() < data.len());
+
+    let file = SaveStateFile::read(&mut Cursor::new(bytes)).unwrap();
+    assert_eq!(file.data(), data.as_slice());
+    assert_eq!(file.header().core_name, "NES");
+    assert_eq!(file.header().core_version.as_deref(), Some("v123456"));
+    assert_eq!(file.header().game_crc, Some(0x1234));
+
+    assert!(file.validate("NES", Some("v123456"), 4096).is_ok());
+    assert!(matches!(
+        file.validate("SNES", Some("v123456"), 4096),
+        Err(SaveStateFileError::CoreMismatch { .. })
+    ));
+    assert!(matches!(
+        file.validate("NES", None, 4096),
+        Err(SaveStateFileError::CoreVersionMismatch { .. })
+    ));
+    assert!(matches!(
+        file.validate("NES", Some("v123456"), 1024),
+        Err(SaveStateFileError::TooLarge { .. })
+    ));
+}
+
+#[test]
+fn invalid_magic() {
+    let result = SaveStateFile::read(&mut Cursor::new(b"RIFF\x01\x00"));
+    assert!(matches!(result, Err(SaveStateFileError::InvalidMagic)));
+}
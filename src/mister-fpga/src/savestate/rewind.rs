@@ -0,0 +1,320 @@
+//! A rewind buffer for save states.
+//!
+//! The buffer keeps the most recent save state in full, and every older state as
+//! a delta (XOR) against the state that follows it. Consecutive save states are
+//! mostly identical, so deltas are mostly zeroes and are compressed by encoding
+//! runs of zeroes.
+use crate::config_string::{Config, ConfigMenu};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Default number of states kept in the rewind buffer.
+pub const DEFAULT_REWIND_DEPTH: usize = 120;
+
+/// Default interval between two captured states.
+pub const DEFAULT_REWIND_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Options for the rewind buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewindOptions {
+    /// The maximum number of states to keep.
+    pub depth: usize,
+
+    /// The interval between two captured states.
+    pub interval: Duration,
+}
+
+impl Default for RewindOptions {
+    fn default() -> Self {
+        Self {
+            depth: DEFAULT_REWIND_DEPTH,
+            interval: DEFAULT_REWIND_INTERVAL,
+        }
+    }
+}
+
+/// The menu items a core uses to save and restore states, as found in its
+/// config string (e.g. `d7rA,Save state(Alt+F1-F4);d7rB,Restore state(F1-F4);`
+/// and `oDE,Savestate Slot,1,2,3,4;` for the NES core).
+#[derive(Debug, Clone)]
+pub struct SaveStateTriggers {
+    /// Status bit to pulse to save a state.
+    pub save: u8,
+
+    /// Status bit to pulse to restore a state.
+    pub restore: u8,
+
+    /// Status bits selecting the slot, if the core has more than one slot.
+    pub slot: Option<Range<u8>>,
+}
+
+impl SaveStateTriggers {
+    /// Find the triggers of a core that declares savestate memory (`SS` in its
+    /// config string). Returns `None` for cores without savestates, or if the
+    /// save and restore items cannot be found in the menu.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config.settings().save_state.as_ref()?;
+
+        let mut save = None;
+        let mut restore = None;
+        for item in config.menu.iter() {
+            if let Some(ConfigMenu::Trigger { index, label, .. }) = item.as_trigger() {
+                // `T` triggers keep the comma separating the index from the label.
+                let label = label.trim_start_matches(',').trim_start().to_lowercase();
+                if label.starts_with("save state") || label.starts_with("savestate") {
+                    save.get_or_insert(*index);
+                } else if label.starts_with("restore state") || label.starts_with("load state") {
+                    restore.get_or_insert(*index);
+                }
+            }
+        }
+        let slot = config.savestate_slot_option().map(|(bits, _)| bits.clone());
+
+        Some(Self {
+            save: save?,
+            restore: restore?,
+            slot,
+        })
+    }
+}
+
+/// Compress a delta by encoding runs of zeroes. The output is a sequence of
+/// `(zeroes: varint, literals: varint, [literal bytes])` records.
+pub fn compress_delta(delta: &[u8]) -> Vec<u8> {
+    fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < delta.len() {
+        let zero_start = i;
+        while i < delta.len() && delta[i] == 0 {
+            i += 1;
+        }
+        let literal_start = i;
+        // Stop the literal run on at least 4 zeroes, as shorter runs cost more
+        // to encode than they save.
+        while i < delta.len() && !delta[i..].starts_with(&[0, 0, 0, 0]) {
+            if delta.len() - i < 4 && delta[i..].iter().all(|b| *b == 0) {
+                break;
+            }
+            i += 1;
+        }
+
+        write_varint(&mut out, literal_start - zero_start);
+        write_varint(&mut out, i - literal_start);
+        out.extend_from_slice(&delta[literal_start..i]);
+    }
+    out
+}
+
+/// XOR a compressed delta (see [compress_delta]) into `data`.
+pub fn apply_delta(data: &mut [u8], compressed: &[u8]) -> Result<(), String> {
+    fn read_varint(input: &[u8], pos: &mut usize) -> Result<usize, String> {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = *input.get(*pos).ok_or("Truncated rewind delta")?;
+            *pos += 1;
+            value |= ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+            if shift >= usize::BITS {
+                return Err("Invalid rewind delta".to_string());
+            }
+        }
+    }
+
+    let mut pos = 0;
+    let mut offset = 0;
+    while pos < compressed.len() {
+        offset += read_varint(compressed, &mut pos)?;
+        let len = read_varint(compressed, &mut pos)?;
+        let literals = compressed
+            .get(pos..pos + len)
+            .ok_or("Truncated rewind delta")?;
+        let target = data
+            .get_mut(offset..offset + len)
+            .ok_or("Rewind delta out of bounds")?;
+
+        for (d, l) in target.iter_mut().zip(literals) {
+            *d ^= *l;
+        }
+        pos += len;
+        offset += len;
+    }
+    Ok(())
+}
+
+/// A ring buffer of save states.
+#[derive(Debug, Default)]
+pub struct RewindBuffer {
+    depth: usize,
+
+    /// The most recent state, in full.
+    current: Option<Vec<u8>>,
+
+    /// Compressed deltas, from the oldest to the most recent. Applying the
+    /// last delta to `current` gives the state captured before it.
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl RewindBuffer {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            current: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    /// The number of states available.
+    pub fn len(&self) -> usize {
+        self.current.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current.is_none()
+    }
+
+    /// The total memory used by the buffer, in bytes.
+    pub fn memory_size(&self) -> usize {
+        self.current.as_ref().map_or(0, Vec::len) + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.deltas.clear();
+    }
+
+    /// Add a new state to the buffer, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, state: Vec<u8>) {
+        match self.current.take() {
+            Some(previous) if previous.len() == state.len() => {
+                let delta: Vec<u8> = previous.iter().zip(&state).map(|(a, b)| a ^ b).collect();
+                self.deltas.push_back(compress_delta(&delta));
+                while self.deltas.len() >= self.depth {
+                    self.deltas.pop_front();
+                }
+            }
+            // The size changed (or there was no previous state), we cannot
+            // compute a delta so start over.
+            _ => self.deltas.clear(),
+        }
+
+        self.current = Some(state);
+    }
+
+    /// Step back one state, returning it. The most recent state is removed
+    /// from the buffer. Returns `None` if there is no older state.
+    pub fn step_back(&mut self) -> Result<Option<&[u8]>, String> {
+        let delta = match self.deltas.pop_back() {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+
+        let current = self.current.as_mut().ok_or("Rewind buffer is empty")?;
+        apply_delta(current, &delta)?;
+        Ok(Some(current.as_slice()))
+    }
+}
+
+/// The state of rewinding on a running core.
+#[derive(Debug)]
+pub(crate) struct Rewind {
+    pub options: RewindOptions,
+    pub buffer: RewindBuffer,
+    pub triggers: SaveStateTriggers,
+
+    /// The savestate slot reserved for rewinding.
+    pub slot: usize,
+
+    /// When the last state was captured (or restored).
+    pub last_capture: Instant,
+
+    /// Whether a save was triggered and the core has not written it yet.
+    pub pending: bool,
+
+    /// The slot selected in the core before rewinding selected its own.
+    pub user_slot: Option<u32>,
+}
+
+impl Rewind {
+    pub fn new(options: RewindOptions, triggers: SaveStateTriggers, slot: usize) -> Self {
+        Self {
+            options,
+            buffer: RewindBuffer::new(options.depth),
+            triggers,
+            slot,
+            last_capture: Instant::now(),
+            pending: false,
+            user_slot: None,
+        }
+    }
+}
+
+#[test]
+fn delta_round_trip() {
+    let mut data = vec![0u8; 1024];
+    data[3] = 1;
+    data[500..510].copy_from_slice(&[1, 2, 3, 0, 0, 4, 5, 0, 0, 0]);
+    data[1023] = 0xFF;
+
+    let compressed = compress_delta(&data);
+    assert!(compressed.len() < 32);
+
+    let mut out = vec![0u8; 1024];
+    apply_delta(&mut out, &compressed).unwrap();
+    assert_eq!(out, data);
+}
+
+#[test]
+fn rewind_buffer_steps_back() {
+    let mut buffer = RewindBuffer::new(3);
+    buffer.push(vec![1, 1, 1, 1]);
+    buffer.push(vec![1, 2, 1, 1]);
+    buffer.push(vec![1, 2, 3, 1]);
+    buffer.push(vec![1, 2, 3, 4]);
+    assert_eq!(buffer.len(), 3);
+
+    assert_eq!(buffer.step_back().unwrap(), Some([1, 2, 3, 1].as_slice()));
+    assert_eq!(buffer.step_back().unwrap(), Some([1, 2, 1, 1].as_slice()));
+    assert_eq!(buffer.step_back().unwrap(), None);
+}
+
+#[test]
+fn triggers_from_config() {
+    use std::str::FromStr;
+
+    let nes =
+        Config::from_str(include_str!("../../tests/assets/config_string/nes/config").trim_end())
+            .unwrap();
+    let triggers = SaveStateTriggers::from_config(&nes).unwrap();
+    assert_eq!((triggers.save, triggers.restore), (42, 43));
+    assert_eq!(triggers.slot, Some(45..47));
+
+    let gba =
+        Config::from_str(include_str!("../../tests/assets/config_string/gba/config").trim_end())
+            .unwrap();
+    let triggers = SaveStateTriggers::from_config(&gba).unwrap();
+    assert_eq!((triggers.save, triggers.restore), (17, 18));
+
+    let config = Config::from_str("Test;SS3E000000:100000;T1,Save state;T2,Load state").unwrap();
+    let triggers = SaveStateTriggers::from_config(&config).unwrap();
+    assert_eq!(
+        (triggers.save, triggers.restore, triggers.slot),
+        (1, 2, None)
+    );
+
+    // Without savestate memory, the triggers are not used.
+    let config = Config::from_str("Test;;T1,Save state;T2,Load state").unwrap();
+    assert!(SaveStateTriggers::from_config(&config).is_none());
+}
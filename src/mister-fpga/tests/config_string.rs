@@ -1,4 +1,5 @@
 use mister_fpga::config_string::Config;
+use mister_fpga::fpga::SdramRequirement;
 use pretty_assertions::assert_eq;
 use rstest::rstest;
 use std::path::PathBuf;
@@ -19,3 +20,20 @@ fn load_config_string(#[files("tests/assets/config_string/*")] root: PathBuf) {
         assert_eq!(data, expected);
     }
 }
+
+#[rstest]
+#[case::sdram48("sdram48", SdramRequirement::Required)]
+#[case::gba("gba", SdramRequirement::Optional)]
+#[case::nes("nes", SdramRequirement::Required)]
+#[case::chess("chess", SdramRequirement::Unknown)]
+fn sdram_requirement(#[case] name: &str, #[case] expected: SdramRequirement) {
+    let config = std::fs::read_to_string(
+        PathBuf::from("tests/assets/config_string")
+            .join(name)
+            .join("config"),
+    )
+    .unwrap();
+    let config = Config::from_str(config.trim_end()).unwrap();
+
+    assert_eq!(config.sdram_requirement(), expected);
+}
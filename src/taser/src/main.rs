@@ -108,7 +108,7 @@ fn main() {
 
     if let Some(tas) = opts.tas {
         // Showtime!
-        core.soft_reset();
+        core.soft_reset().unwrap();
 
         let port0 = *core.gamepad(0).unwrap();
         let frames = read_frames(&tas, port0).expect("Could not read TAS file.");
@@ -172,10 +172,10 @@ fn main() {
             }
 
             if let Some(p0) = p0 {
-                core.send_gamepad(0, p0);
+                core.send_gamepad(0, p0).unwrap();
             }
             if let Some(p1) = p1 {
-                core.send_gamepad(1, p1);
+                core.send_gamepad(1, p1).unwrap();
             }
         }
     } else {